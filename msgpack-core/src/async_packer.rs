@@ -0,0 +1,107 @@
+use crate::{pack, PackError};
+
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+// the async counterpart of Packer. each call encodes into a scratch buffer first and hands it
+// to the writer with a single write_all
+#[derive(Debug)]
+pub struct AsyncPacker<W> {
+    wr: W,
+    buf: Vec<u8>,
+}
+
+impl<W> AsyncPacker<W> {
+    pub fn new(writer: W) -> AsyncPacker<W> {
+        AsyncPacker {
+            wr: writer,
+            buf: Vec::new(),
+        }
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.wr
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.wr
+    }
+
+    pub fn into_inner(self) -> W {
+        self.wr
+    }
+}
+
+macro_rules! impl_pack {
+    ($($method:ident($ty:ty) => $pack:ident;)*) => {
+        $(
+            pub async fn $method(&mut self, v: $ty) -> Result<(), PackError> {
+                self.buf.clear();
+                pack::$pack(&mut self.buf, v)?;
+                self.write_buf().await
+            }
+        )*
+    };
+}
+
+impl<W: AsyncWrite + Unpin> AsyncPacker<W> {
+    impl_pack! {
+        pack_uint(u64) => pack_from_u64;
+        pack_u8(u8) => pack_u8;
+        pack_from_u8(u8) => pack_from_u8;
+        pack_from_u16(u16) => pack_from_u16;
+        pack_u16(u16) => pack_u16;
+        pack_from_u32(u32) => pack_from_u32;
+        pack_u32(u32) => pack_u32;
+        pack_from_u64(u64) => pack_from_u64;
+        pack_u64(u64) => pack_u64;
+        pack_int(i64) => pack_from_i64;
+        pack_from_i8(i8) => pack_from_i8;
+        pack_i8(i8) => pack_i8;
+        pack_from_i16(i16) => pack_from_i16;
+        pack_i16(i16) => pack_i16;
+        pack_from_i32(i32) => pack_from_i32;
+        pack_i32(i32) => pack_i32;
+        pack_from_i64(i64) => pack_from_i64;
+        pack_i64(i64) => pack_i64;
+        pack_f32(f32) => pack_f32;
+        pack_f64(f64) => pack_f64;
+        pack_bool(bool) => pack_bool;
+        pack_str(&str) => pack_str;
+        pack_bin(&[u8]) => pack_bin;
+        pack_array_header(usize) => pack_array_header;
+        pack_map_header(usize) => pack_map_header;
+    }
+
+    pub async fn pack_nil(&mut self) -> Result<(), PackError> {
+        self.buf.clear();
+        pack::pack_nil(&mut self.buf)?;
+        self.write_buf().await
+    }
+
+    pub async fn pack_ext_header(&mut self, ext_type: i8, len: usize) -> Result<(), PackError> {
+        self.buf.clear();
+        pack::pack_ext_header(&mut self.buf, ext_type, len)?;
+        self.write_buf().await
+    }
+
+    // this method is used after pack_ext_header
+    pub async fn write_payload(&mut self, v: &[u8]) -> Result<(), PackError> {
+        self.wr.write_all(v).await.map_err(PackError::WriteError)
+    }
+
+    // writes an already encoded object as is
+    pub async fn write_raw(&mut self, v: &[u8]) -> Result<(), PackError> {
+        self.wr.write_all(v).await.map_err(PackError::WriteError)
+    }
+
+    pub async fn flush(&mut self) -> Result<(), PackError> {
+        self.wr.flush().await.map_err(PackError::WriteError)
+    }
+
+    async fn write_buf(&mut self) -> Result<(), PackError> {
+        self.wr
+            .write_all(&self.buf)
+            .await
+            .map_err(PackError::WriteError)
+    }
+}
@@ -0,0 +1,208 @@
+use crate::{unpack, FloatMode, UnpackError};
+
+use bytes::{Buf, Bytes, BytesMut};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+const READ_SIZE: usize = 8 * 1024;
+
+// the async counterpart of Unpacker. bytes are read into an internal buffer until a whole
+// object can be decoded from it, so a value is never left half consumed
+#[derive(Debug)]
+pub struct AsyncUnpacker<R> {
+    rd: R,
+    buf: BytesMut,
+    float_mode: FloatMode,
+}
+
+impl<R> AsyncUnpacker<R> {
+    pub fn new(reader: R) -> AsyncUnpacker<R> {
+        AsyncUnpacker {
+            rd: reader,
+            buf: BytesMut::new(),
+            float_mode: FloatMode::default(),
+        }
+    }
+
+    // sets which encodings unpack_f32 and unpack_f64 accept
+    pub fn with_float_mode(mut self, mode: FloatMode) -> Self {
+        self.float_mode = mode;
+        self
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.rd
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.rd
+    }
+
+    // bytes which were read from the reader but not consumed yet
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf
+    }
+
+    pub fn into_inner(self) -> R {
+        self.rd
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncUnpacker<R> {
+    // returns the buffered bytes, reading more only when none are left.
+    // an empty slice means the reader is at its end
+    pub async fn fill_buf(&mut self) -> Result<&[u8], UnpackError> {
+        if self.buf.is_empty() {
+            self.read_more().await?;
+        }
+        Ok(&self.buf)
+    }
+
+    // runs `f` against the buffered bytes, reading more and retrying from the start while it
+    // runs out of input. the bytes `f` read are consumed, even on error
+    pub async fn unpack_with<T, F>(&mut self, f: F) -> Result<T, UnpackError>
+    where
+        F: FnMut(&mut &[u8]) -> Result<T, UnpackError>,
+    {
+        let (ret, len) = self.run(f).await?;
+        self.buf.advance(len);
+        ret
+    }
+
+    // the encoded bytes of the next object, header and nested objects included
+    pub async fn unpack_raw(&mut self) -> Result<Bytes, UnpackError> {
+        let (ret, len) = self.run(|rd| unpack::skip_value(rd)).await?;
+        match ret {
+            Ok(()) => Ok(self.buf.split_to(len).freeze()),
+            Err(e) => {
+                self.buf.advance(len);
+                Err(e)
+            }
+        }
+    }
+
+    pub async fn unpack_u8(&mut self) -> Result<u8, UnpackError> {
+        self.unpack_with(|rd| unpack::unpack_uint(rd)).await
+    }
+
+    pub async fn unpack_u16(&mut self) -> Result<u16, UnpackError> {
+        self.unpack_with(|rd| unpack::unpack_uint(rd)).await
+    }
+
+    pub async fn unpack_u32(&mut self) -> Result<u32, UnpackError> {
+        self.unpack_with(|rd| unpack::unpack_uint(rd)).await
+    }
+
+    pub async fn unpack_u64(&mut self) -> Result<u64, UnpackError> {
+        self.unpack_with(|rd| unpack::unpack_uint(rd)).await
+    }
+
+    pub async fn unpack_i8(&mut self) -> Result<i8, UnpackError> {
+        self.unpack_with(|rd| unpack::unpack_int(rd)).await
+    }
+
+    pub async fn unpack_i16(&mut self) -> Result<i16, UnpackError> {
+        self.unpack_with(|rd| unpack::unpack_int(rd)).await
+    }
+
+    pub async fn unpack_i32(&mut self) -> Result<i32, UnpackError> {
+        self.unpack_with(|rd| unpack::unpack_int(rd)).await
+    }
+
+    pub async fn unpack_i64(&mut self) -> Result<i64, UnpackError> {
+        self.unpack_with(|rd| unpack::unpack_int(rd)).await
+    }
+
+    pub async fn unpack_f32(&mut self) -> Result<f32, UnpackError> {
+        let mode = self.float_mode;
+        self.unpack_with(|rd| unpack::unpack_f32_with(rd, mode))
+            .await
+    }
+
+    pub async fn unpack_f64(&mut self) -> Result<f64, UnpackError> {
+        let mode = self.float_mode;
+        self.unpack_with(|rd| unpack::unpack_f64_with(rd, mode))
+            .await
+    }
+
+    pub async fn unpack_nil<T>(&mut self) -> Result<Option<T>, UnpackError> {
+        self.unpack_with(|rd| unpack::unpack_nil(rd)).await
+    }
+
+    pub async fn unpack_bool(&mut self) -> Result<bool, UnpackError> {
+        self.unpack_with(|rd| unpack::unpack_bool(rd)).await
+    }
+
+    pub async fn unpack_string(&mut self) -> Result<String, UnpackError> {
+        self.unpack_with(|rd| unpack::unpack_str(rd)).await
+    }
+
+    pub async fn unpack_bin(&mut self) -> Result<Vec<u8>, UnpackError> {
+        self.unpack_with(|rd| unpack::unpack_bin(rd)).await
+    }
+
+    pub async fn unpack_str_header(&mut self) -> Result<usize, UnpackError> {
+        self.unpack_with(|rd| unpack::unpack_str_header(rd)).await
+    }
+
+    pub async fn unpack_array_header(&mut self) -> Result<usize, UnpackError> {
+        self.unpack_with(|rd| unpack::unpack_array_header(rd)).await
+    }
+
+    pub async fn unpack_map_header(&mut self) -> Result<usize, UnpackError> {
+        self.unpack_with(|rd| unpack::unpack_map_header(rd)).await
+    }
+
+    pub async fn unpack_bin_header(&mut self) -> Result<usize, UnpackError> {
+        self.unpack_with(|rd| unpack::unpack_bin_header(rd)).await
+    }
+
+    pub async fn unpack_fixext1(&mut self) -> Result<(i8, u8), UnpackError> {
+        self.unpack_with(|rd| unpack::unpack_fixext1(rd)).await
+    }
+
+    pub async fn unpack_fixext2(&mut self) -> Result<(i8, [u8; 2]), UnpackError> {
+        self.unpack_with(|rd| unpack::unpack_fixext2(rd)).await
+    }
+
+    pub async fn unpack_fixext4(&mut self) -> Result<(i8, [u8; 4]), UnpackError> {
+        self.unpack_with(|rd| unpack::unpack_fixext4(rd)).await
+    }
+
+    pub async fn unpack_fixext8(&mut self) -> Result<(i8, [u8; 8]), UnpackError> {
+        self.unpack_with(|rd| unpack::unpack_fixext8(rd)).await
+    }
+
+    pub async fn unpack_fixext16(&mut self) -> Result<(i8, [u8; 16]), UnpackError> {
+        self.unpack_with(|rd| unpack::unpack_fixext16(rd)).await
+    }
+
+    // returns what `f` returned along with how many bytes it read
+    async fn run<T, F>(&mut self, mut f: F) -> Result<(Result<T, UnpackError>, usize), UnpackError>
+    where
+        F: FnMut(&mut &[u8]) -> Result<T, UnpackError>,
+    {
+        loop {
+            let mut rest = &self.buf[..];
+            match f(&mut rest) {
+                Err(ref e) if matches!(e.root(), UnpackError::UnexpectedEof) => {
+                    if self.read_more().await? == 0 {
+                        return Err(UnpackError::UnexpectedEof);
+                    }
+                }
+                ret => {
+                    let len = self.buf.len() - rest.len();
+                    return Ok((ret, len));
+                }
+            }
+        }
+    }
+
+    // the read size doubles with the buffer so a large object isn't rescanned once per chunk
+    async fn read_more(&mut self) -> Result<usize, UnpackError> {
+        self.buf.reserve(std::cmp::max(READ_SIZE, self.buf.len()));
+        self.rd
+            .read_buf(&mut self.buf)
+            .await
+            .map_err(UnpackError::from)
+    }
+}
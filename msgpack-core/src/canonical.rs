@@ -0,0 +1,267 @@
+use crate::code::{Code, CodeSize};
+use crate::pack::primitive::*;
+use crate::unpack::{check_minimal_int, check_minimal_len};
+use crate::{pack, PackError, TIMESTAMP_TYPE};
+use std::io;
+
+const F32_NAN: u32 = 0x7fc0_0000;
+const F64_NAN: u64 = 0x7ff8_0000_0000_0000;
+
+// how canonical encoding treats floats. everything else about it is fixed: the smallest
+// integer and length encodings, map keys sorted by their encoded bytes and no duplicate keys
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CanonicalOptions {
+    // writes an f64 as f32 when that loses nothing
+    pub shrink_floats: bool,
+}
+
+// NaN is always written as the quiet NaN with an empty payload
+pub fn pack_f32_canonical<W: io::Write>(writer: &mut W, v: f32) -> Result<(), PackError> {
+    if v.is_nan() {
+        pack::pack_f32(writer, f32::from_bits(F32_NAN))
+    } else {
+        pack::pack_f32(writer, v)
+    }
+}
+
+pub fn pack_f64_canonical<W: io::Write>(
+    writer: &mut W,
+    v: f64,
+    opts: CanonicalOptions,
+) -> Result<(), PackError> {
+    if opts.shrink_floats && (v.is_nan() || fits_f32(v)) {
+        pack_f32_canonical(writer, v as f32)
+    } else if v.is_nan() {
+        pack::pack_f64(writer, f64::from_bits(F64_NAN))
+    } else {
+        pack::pack_f64(writer, v)
+    }
+}
+
+// writes a map from entries encoded separately, in the order of their encoded keys
+pub fn pack_sorted_map<W: io::Write>(
+    writer: &mut W,
+    mut entries: Vec<(Vec<u8>, Vec<u8>)>,
+) -> Result<(), PackError> {
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    if entries.windows(2).any(|w| w[0].0 == w[1].0) {
+        return Err(PackError::DuplicateMapKey);
+    }
+
+    pack::pack_map_header(writer, entries.len())?;
+    for (k, v) in entries {
+        write_all(writer, &k)?;
+        write_all(writer, &v)?;
+    }
+    Ok(())
+}
+
+// whether `buf` is exactly one object in canonical encoding with default options
+pub fn is_canonical(buf: &[u8]) -> bool {
+    is_canonical_with(buf, CanonicalOptions::default())
+}
+
+pub fn is_canonical_with(buf: &[u8], opts: CanonicalOptions) -> bool {
+    Checker { buf, pos: 0, opts }.check()
+}
+
+fn fits_f32(v: f64) -> bool {
+    ((v as f32) as f64).to_bits() == v.to_bits()
+}
+
+struct Frame {
+    // where the container starts, for handing its range to the parent once it ends
+    start: usize,
+    items: u64,
+    done: u64,
+    is_map: bool,
+    prev_key: Option<(usize, usize)>,
+}
+
+struct Checker<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    opts: CanonicalOptions,
+}
+
+impl Checker<'_> {
+    // walks the input without recursion, so nesting depth doesn't matter
+    fn check(&mut self) -> bool {
+        let mut stack = vec![Frame {
+            start: 0,
+            items: 1,
+            done: 0,
+            is_map: false,
+            prev_key: None,
+        }];
+
+        while let Some(top) = stack.last() {
+            if top.done == top.items {
+                let frame = stack.pop().unwrap();
+                match stack.last_mut() {
+                    Some(parent) => {
+                        if !self.item_done(parent, frame.start) {
+                            return false;
+                        }
+                    }
+                    None => break,
+                }
+                continue;
+            }
+
+            let start = self.pos;
+            match self.object() {
+                Some(None) => {
+                    if !self.item_done(stack.last_mut().unwrap(), start) {
+                        return false;
+                    }
+                }
+                Some(Some((len, is_map))) => stack.push(Frame {
+                    start,
+                    items: if is_map { len * 2 } else { len },
+                    done: 0,
+                    is_map,
+                    prev_key: None,
+                }),
+                None => return false,
+            }
+        }
+
+        self.pos == self.buf.len()
+    }
+
+    // keys have to be strictly ascending, which also rules out duplicates
+    fn item_done(&self, frame: &mut Frame, start: usize) -> bool {
+        if frame.is_map && frame.done.is_multiple_of(2) {
+            let key = &self.buf[start..self.pos];
+            if let Some((s, e)) = frame.prev_key {
+                if self.buf[s..e] >= *key {
+                    return false;
+                }
+            }
+            frame.prev_key = Some((start, self.pos));
+        }
+        frame.done += 1;
+        true
+    }
+
+    // checks one header and skips its body. returns the length of a container, which the
+    // caller walks itself
+    fn object(&mut self) -> Option<Option<(u64, bool)>> {
+        let code = Code::from(self.take_uint(1)? as u8);
+        let ok = match code {
+            Code::Reserved => false,
+            Code::Nil | Code::True | Code::False | Code::PosInt(_) | Code::NegInt(_) => true,
+            Code::Uint8 | Code::Uint16 | Code::Uint32 | Code::Uint64 => {
+                let v = self.take_uint(self.fix_len(&code))?;
+                check_minimal_int(&code, i128::from(v)).is_ok()
+            }
+            Code::Int8 | Code::Int16 | Code::Int32 | Code::Int64 => {
+                // sign-extends the big-endian body
+                let n = self.fix_len(&code);
+                let v = (self.take_uint(n)? << (64 - 8 * n)) as i64 >> (64 - 8 * n);
+                check_minimal_int(&code, i128::from(v)).is_ok()
+            }
+            Code::Float32 => {
+                let v = f32::from_bits(self.take_uint(4)? as u32);
+                !v.is_nan() || v.to_bits() == F32_NAN
+            }
+            Code::Float64 => {
+                let v = f64::from_bits(self.take_uint(8)?);
+                if self.opts.shrink_floats {
+                    !v.is_nan() && !fits_f32(v)
+                } else {
+                    !v.is_nan() || v.to_bits() == F64_NAN
+                }
+            }
+            Code::FixArray(len) => return Some(Some((len as u64, false))),
+            Code::Array16 | Code::Array32 => return Some(Some((self.length(&code)?, false))),
+            Code::FixMap(len) => return Some(Some((len as u64, true))),
+            Code::Map16 | Code::Map32 => return Some(Some((self.length(&code)?, true))),
+            Code::FixStr(len) => self.skip(len as u64),
+            Code::Str8 | Code::Str16 | Code::Str32 => {
+                let len = self.length(&code)?;
+                self.skip(len)
+            }
+            Code::Bin8 | Code::Bin16 | Code::Bin32 => {
+                let len = self.length(&code)?;
+                self.skip(len)
+            }
+            Code::FixExt1 | Code::FixExt2 | Code::FixExt4 | Code::FixExt8 | Code::FixExt16 => {
+                let len = self.length(&code)?;
+                self.ext(len, false)
+            }
+            Code::Ext8 | Code::Ext16 | Code::Ext32 => {
+                let len = self.length(&code)?;
+                self.ext(len, true)
+            }
+        };
+        if ok {
+            Some(None)
+        } else {
+            None
+        }
+    }
+
+    // the length of a str, bin, ext or container, which has to need every byte of its header
+    fn length(&mut self, code: &Code) -> Option<u64> {
+        let len = match code.body_size().ok()? {
+            CodeSize::FixLen(len) => return Some(u64::from(len)),
+            CodeSize::Var1 => self.take_uint(1)?,
+            CodeSize::Var2 => self.take_uint(2)?,
+            CodeSize::Var4 => self.take_uint(4)?,
+        };
+        check_minimal_len(code, usize::try_from(len).ok()?).ok()?;
+        Some(len)
+    }
+
+    fn fix_len(&self, code: &Code) -> usize {
+        match code.body_size() {
+            Ok(CodeSize::FixLen(len)) => usize::from(len),
+            _ => unreachable!(),
+        }
+    }
+
+    fn ext(&mut self, len: u64, var: bool) -> bool {
+        let ty = match self.take_uint(1) {
+            Some(ty) => ty as i8,
+            None => return false,
+        };
+        if ty != TIMESTAMP_TYPE {
+            return self.skip(len);
+        }
+
+        // the smallest of the three timestamp formats which holds the value
+        match (len, var) {
+            (4, false) => self.skip(4),
+            (8, false) => match self.take_uint(8) {
+                Some(v) => {
+                    let (nsec, sec) = (v >> 34, v & 0x3_ffff_ffff);
+                    nsec < 1_000_000_000 && (nsec != 0 || sec >> 32 != 0)
+                }
+                None => false,
+            },
+            (12, true) => match (self.take_uint(4), self.take_uint(8)) {
+                (Some(nsec), Some(sec)) => nsec < 1_000_000_000 && (sec as i64) >> 34 != 0,
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    fn take_uint(&mut self, n: usize) -> Option<u64> {
+        let bytes = self.buf.get(self.pos..self.pos + n)?;
+        self.pos += n;
+        Some(bytes.iter().fold(0, |acc, &b| (acc << 8) | b as u64))
+    }
+
+    fn skip(&mut self, len: u64) -> bool {
+        match usize::try_from(len) {
+            Ok(len) if len <= self.buf.len() - self.pos => {
+                self.pos += len;
+                true
+            }
+            _ => false,
+        }
+    }
+}
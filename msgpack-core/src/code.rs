@@ -1,3 +1,5 @@
+use crate::error::UnpackError;
+
 pub const POS_INT: u8 = 0x00;
 pub const NIL: u8 = 0xc0;
 pub const FALSE: u8 = 0xc2;
@@ -34,8 +36,9 @@ pub const FIXEXT16: u8 = 0xd8;
 pub const EXT8: u8 = 0xc7;
 pub const EXT16: u8 = 0xc8;
 pub const EXT32: u8 = 0xc9;
+pub const RESERVED: u8 = 0xc1;
 
-#[derive(Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Code {
     Nil,
     True,
@@ -84,8 +87,9 @@ pub enum CodeSize {
 }
 
 impl Code {
-    pub fn body_size(&self) -> CodeSize {
-        match self {
+    /// Fails for the reserved code 0xc1, which has no body to size.
+    pub fn body_size(&self) -> Result<CodeSize, UnpackError> {
+        let size = match self {
             Code::Nil => CodeSize::FixLen(0),
             Code::True => CodeSize::FixLen(0),
             Code::False => CodeSize::FixLen(0),
@@ -122,8 +126,9 @@ impl Code {
             Code::Ext8 => CodeSize::Var1,
             Code::Ext16 => CodeSize::Var2,
             Code::Ext32 => CodeSize::Var4,
-            Code::Reserved => unreachable!(), // tmp
-        }
+            Code::Reserved => return Err(UnpackError::ReservedCode(RESERVED)),
+        };
+        Ok(size)
     }
 }
 
@@ -166,7 +171,7 @@ impl From<u8> for Code {
             EXT8 => Code::Ext8,
             EXT16 => Code::Ext16,
             EXT32 => Code::Ext32,
-            _ => unreachable!(),
+            RESERVED => Code::Reserved,
         }
     }
 }
@@ -210,7 +215,7 @@ impl From<&Code> for u8 {
             Code::Ext8 => EXT8,
             Code::Ext16 => EXT16,
             Code::Ext32 => EXT32,
-            Code::Reserved => unreachable!(), // tmp
+            Code::Reserved => RESERVED,
         }
     }
 }
@@ -0,0 +1,139 @@
+use crate::unpack::FrameScan;
+use crate::{DecodeLimits, Limit, PackError, UnpackError};
+
+use bytes::{BufMut, Bytes, BytesMut};
+use std::{fmt, io, marker::PhantomData};
+use tokio_util::codec::{Decoder, Encoder};
+
+const DEFAULT_MAX_FRAME_LEN: usize = 8 * 1024 * 1024;
+
+// builds a codec item from the bytes of exactly one complete object
+pub trait FrameDecode {
+    type Item;
+    type Error: From<UnpackError> + From<io::Error>;
+
+    fn decode_frame(frame: Bytes, limits: DecodeLimits) -> Result<Self::Item, Self::Error>;
+}
+
+// writes `item` as one object
+pub trait FrameEncode<I> {
+    type Error: From<io::Error>;
+
+    fn encode_frame<W: io::Write>(item: I, wr: &mut W) -> Result<(), Self::Error>;
+}
+
+// frames a stream of concatenated objects by walking their headers, so no length prefix is
+// needed. `T` picks what a frame turns into, Bytes passes the raw frame through
+pub struct MsgPackCodec<T> {
+    limits: DecodeLimits,
+    max_frame_len: usize,
+    scan: FrameScan,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> MsgPackCodec<T> {
+    pub fn new() -> Self {
+        MsgPackCodec {
+            limits: DecodeLimits::default(),
+            max_frame_len: DEFAULT_MAX_FRAME_LEN,
+            scan: FrameScan::default(),
+            _marker: PhantomData,
+        }
+    }
+
+    // limits applied while decoding each frame
+    pub fn with_limits(mut self, limits: DecodeLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    // frames which are longer than this fail with Limit::FrameLength instead of being buffered
+    pub fn with_max_frame_len(mut self, len: usize) -> Self {
+        self.max_frame_len = len;
+        self
+    }
+
+    // the length of the complete frame at the front of `src`, or None while it is incomplete.
+    // progress is kept across calls so each byte is walked only once
+    fn frame_len(&mut self, src: &BytesMut) -> Result<Option<usize>, UnpackError> {
+        let len = match self.scan.frame_len(src)? {
+            Some(len) => len,
+            None if src.len() >= self.max_frame_len => {
+                self.scan.reset();
+                return Err(UnpackError::LimitExceeded(
+                    Limit::FrameLength,
+                    src.len() as u64,
+                ));
+            }
+            None => return Ok(None),
+        };
+        if len > self.max_frame_len {
+            return Err(UnpackError::LimitExceeded(Limit::FrameLength, len as u64));
+        }
+        Ok(Some(len))
+    }
+}
+
+impl<T> Default for MsgPackCodec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> fmt::Debug for MsgPackCodec<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MsgPackCodec")
+            .field("limits", &self.limits)
+            .field("max_frame_len", &self.max_frame_len)
+            .finish()
+    }
+}
+
+impl<T: FrameDecode> Decoder for MsgPackCodec<T> {
+    type Item = T::Item;
+    type Error = T::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        match self.frame_len(src)? {
+            Some(len) => {
+                let frame = src.split_to(len).freeze();
+                T::decode_frame(frame, self.limits).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+impl<T: FrameEncode<I>, I> Encoder<I> for MsgPackCodec<T> {
+    type Error = T::Error;
+
+    fn encode(&mut self, item: I, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        T::encode_frame(item, &mut dst.writer())
+    }
+}
+
+impl FrameDecode for Bytes {
+    type Item = Bytes;
+    type Error = UnpackError;
+
+    fn decode_frame(frame: Bytes, _limits: DecodeLimits) -> Result<Bytes, UnpackError> {
+        Ok(frame)
+    }
+}
+
+// raw frames are written as they are, they must already hold exactly one object
+impl FrameEncode<Bytes> for Bytes {
+    type Error = PackError;
+
+    fn encode_frame<W: io::Write>(item: Bytes, wr: &mut W) -> Result<(), PackError> {
+        wr.write_all(&item).map_err(PackError::WriteError)
+    }
+}
+
+impl<'a> FrameEncode<&'a [u8]> for Bytes {
+    type Error = PackError;
+
+    fn encode_frame<W: io::Write>(item: &'a [u8], wr: &mut W) -> Result<(), PackError> {
+        wr.write_all(item).map_err(PackError::WriteError)
+    }
+}
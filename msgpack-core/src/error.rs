@@ -1,11 +1,13 @@
 use crate::code;
 use std::error;
 use std::fmt::{self, Display, Formatter};
-use std::io;
+use std::{io, str};
 
 #[derive(Debug)]
 pub enum PackError {
     WriteError(io::Error),
+    // two map keys with the same encoding, which canonical mode can't order
+    DuplicateMapKey,
 }
 
 impl From<io::Error> for PackError {
@@ -16,7 +18,10 @@ impl From<io::Error> for PackError {
 
 impl Display for PackError {
     fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
-        self.to_string().fmt(f)
+        match *self {
+            PackError::WriteError(ref e) => write!(f, "failed to write data: {}", e),
+            PackError::DuplicateMapKey => f.write_str("duplicate map key"),
+        }
     }
 }
 
@@ -24,12 +29,14 @@ impl error::Error for PackError {
     fn description(&self) -> &str {
         match *self {
             PackError::WriteError(..) => "failed to write data",
+            PackError::DuplicateMapKey => "duplicate map key",
         }
     }
 
     fn cause(&self) -> Option<&dyn error::Error> {
         match *self {
             PackError::WriteError(ref e) => Some(e),
+            PackError::DuplicateMapKey => None,
         }
     }
 }
@@ -37,27 +44,219 @@ impl error::Error for PackError {
 #[derive(Debug)]
 pub enum UnpackError {
     InvalidData(io::Error),
+    UnexpectedEof,
+    InvalidUtf8(str::Utf8Error),
     TypeMismatch(code::Code, String),
+    OutOfRange(code::Code, String),
+    // a length header which can't be represented on this platform
+    LengthOverflow(u64),
+    DepthExceeded(usize),
+    // a length or the total allocation went over a DecodeLimits bound
+    LimitExceeded(Limit, u64),
+    InvalidExtTimestamp(String),
+    // 0xc1, which is never used by the format
+    ReservedCode(u8),
+    // an integer or length in a larger encoding than it needs, rejected in strict mode
+    NotMinimal(code::Code),
+    // rejected in strict mode
+    DuplicateMapKey,
+    // an array of a fixed size, like a tuple, which holds another number of elements:
+    // (expected, actual)
+    LengthMismatch(usize, usize),
+    // a struct field which is neither in the input nor has a default
+    MissingField(String),
+    UnknownVariant(String),
+    // an ext of another type than the one a type maps to: (expected, actual)
+    ExtTypeMismatch(i8, i8),
+    // wraps an error with where it happened in the input
+    Located(Box<UnpackError>, Location),
+}
+
+impl UnpackError {
+    // the error without its location
+    pub fn root(&self) -> &UnpackError {
+        match *self {
+            UnpackError::Located(ref e, _) => e.root(),
+            ref e => e,
+        }
+    }
+
+    pub fn location(&self) -> Option<&Location> {
+        match *self {
+            UnpackError::Located(_, ref loc) => Some(loc),
+            _ => None,
+        }
+    }
+
+    pub fn offset(&self) -> Option<u64> {
+        self.location().and_then(|loc| loc.offset)
+    }
+
+    pub fn path(&self) -> Option<String> {
+        self.location().map(|loc| loc.path())
+    }
+
+    // records that the error happened inside `segment`. called from the innermost container outwards
+    pub fn within(self, segment: PathSegment) -> Self {
+        match self {
+            UnpackError::Located(e, mut loc) => {
+                loc.segments.insert(0, segment);
+                UnpackError::Located(e, loc)
+            }
+            e => UnpackError::Located(
+                Box::new(e),
+                Location {
+                    offset: None,
+                    segments: vec![segment],
+                },
+            ),
+        }
+    }
+
+    // sets the byte offset unless an inner reader already did
+    pub fn at_offset(self, offset: u64) -> Self {
+        match self {
+            UnpackError::Located(e, mut loc) => {
+                loc.offset.get_or_insert(offset);
+                UnpackError::Located(e, loc)
+            }
+            e => UnpackError::Located(
+                Box::new(e),
+                Location {
+                    offset: Some(offset),
+                    segments: vec![],
+                },
+            ),
+        }
+    }
+}
+
+impl From<io::Error> for UnpackError {
+    fn from(err: io::Error) -> UnpackError {
+        match err.kind() {
+            io::ErrorKind::UnexpectedEof => UnpackError::UnexpectedEof,
+            _ => UnpackError::InvalidData(err),
+        }
+    }
 }
 
 impl Display for UnpackError {
     fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
-        self.to_string().fmt(f)
+        match *self {
+            UnpackError::InvalidData(ref e) => write!(f, "failed to read data: {}", e),
+            UnpackError::UnexpectedEof => f.write_str("unexpected end of input"),
+            UnpackError::InvalidUtf8(ref e) => write!(f, "invalid utf-8 string: {}", e),
+            UnpackError::TypeMismatch(ref code, ref expected) => {
+                write!(f, "type mismatch: expected {} but got {:?}", expected, code)
+            }
+            UnpackError::OutOfRange(ref code, ref expected) => {
+                write!(f, "value of {:?} is out of range for {}", code, expected)
+            }
+            UnpackError::LengthOverflow(len) => write!(f, "length {} is too large", len),
+            UnpackError::DepthExceeded(depth) => {
+                write!(f, "nesting is deeper than the limit of {}", depth)
+            }
+            UnpackError::LimitExceeded(limit, v) => {
+                write!(f, "{} of {} exceeds the decode limit", limit, v)
+            }
+            UnpackError::InvalidExtTimestamp(ref msg) => write!(f, "invalid timestamp: {}", msg),
+            UnpackError::ReservedCode(code) => write!(f, "reserved code 0x{:x}", code),
+            UnpackError::NotMinimal(ref code) => {
+                write!(f, "{:?} is not the smallest encoding of its value", code)
+            }
+            UnpackError::DuplicateMapKey => f.write_str("duplicate map key"),
+            UnpackError::LengthMismatch(expected, actual) => {
+                write!(f, "expected {} elements but got {}", expected, actual)
+            }
+            UnpackError::MissingField(ref name) => write!(f, "missing field {}", name),
+            UnpackError::UnknownVariant(ref name) => write!(f, "unknown variant {}", name),
+            UnpackError::ExtTypeMismatch(expected, actual) => {
+                write!(f, "expected ext type {} but got {}", expected, actual)
+            }
+            UnpackError::Located(ref e, ref loc) => write!(f, "{} {}", e, loc),
+        }
     }
 }
 
 impl error::Error for UnpackError {
-    fn description(&self) -> &str {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match *self {
-            UnpackError::InvalidData(..) => "failed to read data",
-            UnpackError::TypeMismatch(..) => "type isn't match with the expected one",
+            UnpackError::InvalidData(ref e) => Some(e),
+            UnpackError::InvalidUtf8(ref e) => Some(e),
+            UnpackError::Located(ref e, _) => e.source(),
+            _ => None,
         }
     }
+}
 
-    fn cause(&self) -> Option<&dyn error::Error> {
+// a Timestamp which doesn't fit the time type it is converted from or to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimestampRangeError;
+
+impl Display for TimestampRangeError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        f.write_str("timestamp is out of range")
+    }
+}
+
+impl error::Error for TimestampRangeError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Limit {
+    ContainerLength,
+    DataLength,
+    Allocation,
+    // a frame which is still incomplete once a codec has buffered this many bytes
+    FrameLength,
+}
+
+impl Display for Limit {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
         match *self {
-            UnpackError::InvalidData(ref err) => Some(err),
-            UnpackError::TypeMismatch(..) => None,
+            Limit::ContainerLength => f.write_str("container length"),
+            Limit::DataLength => f.write_str("data length"),
+            Limit::Allocation => f.write_str("total allocation"),
+            Limit::FrameLength => f.write_str("frame length"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PathSegment {
+    Index(usize),
+    Key(String),
+}
+
+// where in the input an error happened. `offset` counts bytes from where decoding started
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Location {
+    pub offset: Option<u64>,
+    pub segments: Vec<PathSegment>,
+}
+
+impl Location {
+    // formats the segments like `$.items[3].name`
+    pub fn path(&self) -> String {
+        let mut path = String::from("$");
+        for segment in &self.segments {
+            match segment {
+                PathSegment::Index(i) => path.push_str(&format!("[{}]", i)),
+                PathSegment::Key(k) => {
+                    path.push('.');
+                    path.push_str(k);
+                }
+            }
+        }
+        path
+    }
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        write!(f, "at {}", self.path())?;
+        if let Some(offset) = self.offset {
+            write!(f, " (offset {})", offset)?;
         }
+        Ok(())
     }
 }
@@ -1,17 +1,46 @@
+#[cfg(feature = "async")]
+mod async_packer;
+#[cfg(feature = "async")]
+mod async_unpacker;
+mod canonical;
 pub mod code;
+#[cfg(feature = "codec")]
+mod codec;
 mod error;
+mod limits;
 pub mod pack;
 mod packer;
+mod timestamp;
+mod traits;
 pub mod unpack;
 mod unpacker;
 
+#[cfg(feature = "async")]
+pub use async_packer::AsyncPacker;
+#[cfg(feature = "async")]
+pub use async_unpacker::AsyncUnpacker;
 pub use byteorder::ReadBytesExt;
-pub use error::{PackError, UnpackError};
+pub use canonical::{
+    is_canonical, is_canonical_with, pack_f32_canonical, pack_f64_canonical, pack_sorted_map,
+    CanonicalOptions,
+};
+#[cfg(feature = "codec")]
+pub use codec::{FrameDecode, FrameEncode, MsgPackCodec};
+pub use error::{Limit, Location, PackError, PathSegment, TimestampRangeError, UnpackError};
+pub use limits::{DecodeLimits, LimitTracker};
+#[cfg(feature = "derive")]
+pub use msgpack_derive::MsgPack;
 pub use pack::*;
 pub use packer::Packer;
+pub use timestamp::{Timestamp, TIMESTAMP_TYPE};
+pub use traits::{Pack, Unpack};
 pub use unpack::*;
+// pack and unpack both have a primitive module. the one at the root is unpack's, whose
+// readers are re-exported along with it, while pack's stays at pack::primitive
+pub use unpack::primitive;
 pub use unpacker::{InnerBuf, Unpacker};
 
+use std::cmp;
 use std::io::{self, Cursor, Read};
 
 pub trait BufferedRead<'a>: Read {
@@ -76,6 +105,11 @@ pub trait MessagePacker {
     fn flush(&mut self) -> Result<(), PackError>;
 }
 
+// integer methods accept any integer encoding and fail only when the value overflows.
+//
+// peek_code and read_payload_into are required although they came after the first release,
+// so unpackers written before them have to add them. the other methods added since then have
+// default bodies built on those two
 pub trait MessageUnpacker {
     fn unpack_u8(&mut self) -> Result<u8, UnpackError>;
     fn unpack_u16(&mut self) -> Result<u16, UnpackError>;
@@ -85,6 +119,19 @@ pub trait MessageUnpacker {
     fn unpack_i16(&mut self) -> Result<i16, UnpackError>;
     fn unpack_i32(&mut self) -> Result<i32, UnpackError>;
     fn unpack_i64(&mut self) -> Result<i64, UnpackError>;
+    // floats in the default FloatMode
+    fn unpack_f32(&mut self) -> Result<f32, UnpackError> {
+        let mut buf = [0; 9];
+        let len = read_float(self, &mut buf)?;
+        unpack::unpack_f32(&mut &buf[..len])
+    }
+    fn unpack_f64(&mut self) -> Result<f64, UnpackError> {
+        let mut buf = [0; 9];
+        let len = read_float(self, &mut buf)?;
+        unpack::unpack_f64(&mut &buf[..len])
+    }
+    // the code of the next value, which is still read by the next call
+    fn peek_code(&mut self) -> Result<code::Code, UnpackError>;
     fn unpack_nil<T>(&mut self) -> Result<Option<T>, UnpackError>;
     fn unpack_bool(&mut self) -> Result<bool, UnpackError>;
     fn unpack_string(&mut self) -> Result<String, UnpackError>;
@@ -97,4 +144,107 @@ pub trait MessageUnpacker {
     fn unpack_fixext4(&mut self) -> Result<(i8, [u8; 4]), UnpackError>;
     fn unpack_fixext8(&mut self) -> Result<(i8, [u8; 8]), UnpackError>;
     fn unpack_fixext16(&mut self) -> Result<(i8, [u8; 16]), UnpackError>;
+    fn unpack_ext_header(&mut self) -> Result<(u32, i8), UnpackError> {
+        let (code, len) = read_header(self)?;
+        let len = match code {
+            code::Code::FixExt1
+            | code::Code::FixExt2
+            | code::Code::FixExt4
+            | code::Code::FixExt8
+            | code::Code::FixExt16
+            | code::Code::Ext8
+            | code::Code::Ext16
+            | code::Code::Ext32 => len,
+            e => return Err(UnpackError::TypeMismatch(e, "ext header".to_string())),
+        };
+        let mut ty = [0; 1];
+        self.read_payload_into(&mut ty)?;
+        Ok((len, ty[0] as i8))
+    }
+    // fills `buf` with the next bytes of the input as they are, starting with a peeked code
+    fn read_payload_into(&mut self, buf: &mut [u8]) -> Result<(), UnpackError>;
+    // reads the `len` bytes of data after a bin, str or ext header
+    fn read_payload(&mut self, len: usize) -> Result<Vec<u8>, UnpackError> {
+        // grows as the data arrives, so a bogus length fails before allocating all of it
+        let mut buf = Vec::with_capacity(unpack::capacity_hint::<u8>(len));
+        while buf.len() < len {
+            let start = buf.len();
+            buf.resize(start + cmp::min(len - start, SKIP_CHUNK), 0);
+            self.read_payload_into(&mut buf[start..])?;
+        }
+        Ok(buf)
+    }
+    fn skip_value(&mut self) -> Result<(), UnpackError> {
+        use code::Code;
+
+        let mut pending: u64 = 1;
+        while pending > 0 {
+            pending -= 1;
+            let (code, len) = read_header(self)?;
+            let len = u64::from(len);
+            match code {
+                Code::FixArray(_) | Code::Array16 | Code::Array32 => pending += len,
+                Code::FixMap(_) | Code::Map16 | Code::Map32 => pending += len * 2,
+                // ext bodies are preceded by a type byte
+                Code::FixExt1
+                | Code::FixExt2
+                | Code::FixExt4
+                | Code::FixExt8
+                | Code::FixExt16
+                | Code::Ext8
+                | Code::Ext16
+                | Code::Ext32 => skip_payload(self, len + 1)?,
+                _ => skip_payload(self, len)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+const SKIP_CHUNK: usize = 256;
+
+// reads a float's code and body into `buf`, or only the code when it isn't a float
+fn read_float<U: MessageUnpacker + ?Sized>(
+    u: &mut U,
+    buf: &mut [u8; 9],
+) -> Result<usize, UnpackError> {
+    let len = match u.peek_code()? {
+        code::Code::Float32 => 5,
+        code::Code::Float64 => 9,
+        _ => 1,
+    };
+    u.read_payload_into(&mut buf[..len])?;
+    Ok(len)
+}
+
+// reads a header and returns its code with the length that follows, in entries for containers
+// and in bytes for everything else
+fn read_header<U: MessageUnpacker + ?Sized>(u: &mut U) -> Result<(code::Code, u32), UnpackError> {
+    let code = u.peek_code()?;
+    let size = code.body_size()?;
+    let mut buf = [0; 5];
+    let head = match size {
+        code::CodeSize::FixLen(_) => 1,
+        code::CodeSize::Var1 => 2,
+        code::CodeSize::Var2 => 3,
+        code::CodeSize::Var4 => 5,
+    };
+    u.read_payload_into(&mut buf[..head])?;
+    let len = match size {
+        code::CodeSize::FixLen(v) => u32::from(v),
+        _ => buf[1..head]
+            .iter()
+            .fold(0, |len, b| len << 8 | u32::from(*b)),
+    };
+    Ok((code, len))
+}
+
+fn skip_payload<U: MessageUnpacker + ?Sized>(u: &mut U, mut len: u64) -> Result<(), UnpackError> {
+    let mut chunk = [0; SKIP_CHUNK];
+    while len > 0 {
+        let n = cmp::min(len, SKIP_CHUNK as u64) as usize;
+        u.read_payload_into(&mut chunk[..n])?;
+        len -= n as u64;
+    }
+    Ok(())
 }
@@ -0,0 +1,140 @@
+use crate::code::Code;
+use crate::error::{Limit, UnpackError};
+use crate::unpack;
+
+// bounds on what a single decode may read, so hostile input can't exhaust memory or the stack
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DecodeLimits {
+    // arrays and maps nested in each other
+    pub max_depth: usize,
+    // entries in a single array or map
+    pub max_container_len: usize,
+    // bytes in a single str, bin or ext body
+    pub max_data_len: usize,
+    // bytes a decoder may allocate in total for bodies and container storage
+    pub max_alloc: usize,
+    // rejects what lenient decoding lets through: integers and lengths which aren't in their
+    // smallest encoding, duplicate map keys and str which isn't valid utf-8
+    pub strict: bool,
+}
+
+impl DecodeLimits {
+    // no limits at all. deeply nested input can overflow the stack
+    pub fn unlimited() -> Self {
+        DecodeLimits {
+            max_depth: usize::MAX,
+            max_container_len: usize::MAX,
+            max_data_len: usize::MAX,
+            max_alloc: usize::MAX,
+            strict: false,
+        }
+    }
+}
+
+impl Default for DecodeLimits {
+    // only the depth is limited by default, lengths are bounded by the format itself.
+    // 128 levels stay within a 2 MiB thread stack even in debug builds
+    fn default() -> Self {
+        DecodeLimits {
+            max_depth: 128,
+            ..DecodeLimits::unlimited()
+        }
+    }
+}
+
+// tracks one decode against its limits
+#[derive(Debug)]
+pub struct LimitTracker {
+    limits: DecodeLimits,
+    depth: usize,
+    alloc: usize,
+}
+
+impl LimitTracker {
+    pub fn new(limits: DecodeLimits) -> Self {
+        LimitTracker {
+            limits,
+            depth: 0,
+            alloc: 0,
+        }
+    }
+
+    pub fn limits(&self) -> &DecodeLimits {
+        &self.limits
+    }
+
+    // called before decoding the entries of a container. pair with `leave`
+    pub fn enter(&mut self) -> Result<(), UnpackError> {
+        if self.depth >= self.limits.max_depth {
+            return Err(UnpackError::DepthExceeded(self.limits.max_depth));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    pub fn leave(&mut self) {
+        self.depth -= 1;
+    }
+
+    pub fn strict(&self) -> bool {
+        self.limits.strict
+    }
+
+    // the checks below only fail in strict mode
+    pub fn check_int(&self, code: &Code, v: i128) -> Result<(), UnpackError> {
+        if self.limits.strict {
+            unpack::check_minimal_int(code, v)?;
+        }
+        Ok(())
+    }
+
+    pub fn check_len(&self, code: &Code, len: usize) -> Result<(), UnpackError> {
+        if self.limits.strict {
+            unpack::check_minimal_len(code, len)?;
+        }
+        Ok(())
+    }
+
+    pub fn check_utf8(&self, buf: &[u8]) -> Result<(), UnpackError> {
+        if self.limits.strict {
+            std::str::from_utf8(buf).map_err(UnpackError::InvalidUtf8)?;
+        }
+        Ok(())
+    }
+
+    // `entry_size` is what the decoder allocates per entry, 0 if it allocates nothing
+    pub fn container(&mut self, len: usize, entry_size: usize) -> Result<(), UnpackError> {
+        if len > self.limits.max_container_len {
+            return Err(UnpackError::LimitExceeded(
+                Limit::ContainerLength,
+                len as u64,
+            ));
+        }
+        self.alloc(len.saturating_mul(entry_size))
+    }
+
+    // a str, bin or ext body which is copied out of the input
+    pub fn data(&mut self, len: usize) -> Result<(), UnpackError> {
+        self.data_ref(len)?;
+        self.alloc(len)
+    }
+
+    // a str, bin or ext body which is borrowed from the input
+    pub fn data_ref(&mut self, len: usize) -> Result<(), UnpackError> {
+        if len > self.limits.max_data_len {
+            return Err(UnpackError::LimitExceeded(Limit::DataLength, len as u64));
+        }
+        Ok(())
+    }
+
+    fn alloc(&mut self, size: usize) -> Result<(), UnpackError> {
+        self.alloc = self.alloc.saturating_add(size);
+        if self.alloc > self.limits.max_alloc {
+            return Err(UnpackError::LimitExceeded(
+                Limit::Allocation,
+                self.alloc as u64,
+            ));
+        }
+        Ok(())
+    }
+}
@@ -33,11 +33,10 @@ pub fn pack_pos_fixint<W: io::Write>(writer: &mut W, v: u8) -> Result<(), PackEr
     if v < UINT8_MIN {
         write_data_u8(writer, code::POS_INT | v)
     } else {
-        use std::io::ErrorKind;
-        Err(PackError::WriteError(io::Error::new(
-            ErrorKind::Other,
-            format!("Value is out of range for positive fix int: {:?}", v),
-        )))
+        Err(PackError::WriteError(io::Error::other(format!(
+            "Value is out of range for positive fix int: {:?}",
+            v
+        ))))
     }
 }
 
@@ -97,11 +96,10 @@ pub fn pack_neg_fix_int<W: io::Write>(writer: &mut W, v: i8) -> Result<(), PackE
     if v > INT8_MAX && v <= FIX_INT_MAX {
         write_data_i8(writer, v)
     } else {
-        use std::io::ErrorKind;
-        Err(PackError::WriteError(io::Error::new(
-            ErrorKind::Other,
-            format!("Value is out of range for negative fix int : {:?}", v),
-        )))
+        Err(PackError::WriteError(io::Error::other(format!(
+            "Value is out of range for negative fix int : {:?}",
+            v
+        ))))
     }
 }
 
@@ -136,7 +134,9 @@ pub fn pack_from_i8<W: io::Write>(writer: &mut W, v: i8) -> Result<(), PackError
 }
 
 pub fn pack_from_i16<W: io::Write>(writer: &mut W, v: i16) -> Result<(), PackError> {
-    if v > INT16_MAX {
+    if v >= 0 {
+        pack_from_u16(writer, v as u16)
+    } else if v > INT16_MAX {
         pack_from_i8(writer, v as i8)
     } else {
         pack_i16(writer, v)
@@ -144,7 +144,9 @@ pub fn pack_from_i16<W: io::Write>(writer: &mut W, v: i16) -> Result<(), PackErr
 }
 
 pub fn pack_from_i32<W: io::Write>(writer: &mut W, v: i32) -> Result<(), PackError> {
-    if v > INT32_MAX {
+    if v >= 0 {
+        pack_from_u32(writer, v as u32)
+    } else if v > INT32_MAX {
         pack_from_i16(writer, v as i16)
     } else {
         pack_i32(writer, v)
@@ -152,7 +154,9 @@ pub fn pack_from_i32<W: io::Write>(writer: &mut W, v: i32) -> Result<(), PackErr
 }
 
 pub fn pack_from_i64<W: io::Write>(writer: &mut W, v: i64) -> Result<(), PackError> {
-    if v > INT64_MAX {
+    if v >= 0 {
+        pack_from_u64(writer, v as u64)
+    } else if v > INT64_MAX {
         pack_from_i32(writer, v as i32)
     } else {
         pack_i64(writer, v)
@@ -181,19 +185,19 @@ pub fn pack_str<W: io::Write>(writer: &mut W, v: &str) -> Result<(), PackError>
     let len = v.len();
     if len < FIXSTR_LIMIT {
         write_data_u8(writer, code::FIX_STR | len as u8)?;
-        write_all(writer, v[..len].as_bytes())
+        write_all(writer, v.as_bytes())
     } else if len < STR8_LIMIT {
         write_data_u8(writer, code::STR8)?;
         write_data_u8(writer, len as u8)?;
-        write_all(writer, &v[..len].as_bytes())
+        write_all(writer, v.as_bytes())
     } else if len < STR16_LIMIT {
         write_data_u8(writer, code::STR16)?;
         write_data_u16(writer, len as u16)?;
-        write_all(writer, v[..len].as_bytes())
+        write_all(writer, v.as_bytes())
     } else if len < STR32_LIMIT {
         write_data_u8(writer, code::STR32)?;
         write_data_u32(writer, len as u32)?;
-        write_all(writer, v[..len].as_bytes())
+        write_all(writer, v.as_bytes())
     } else {
         unreachable!()
     }
@@ -278,10 +282,8 @@ pub fn pack_ext_header<W: io::Write>(
     ext_type: i8,
     len: usize,
 ) -> Result<(), PackError> {
-    use std::io::ErrorKind;
     if ext_type < 0 {
-        return Err(PackError::WriteError(io::Error::new(
-            ErrorKind::Other,
+        return Err(PackError::WriteError(io::Error::other(
             "type < 0 is reserved for future extension",
         )));
     }
@@ -291,11 +293,11 @@ pub fn pack_ext_header<W: io::Write>(
         4 => write_data_u8(writer, code::FIXEXT4)?,
         8 => write_data_u8(writer, code::FIXEXT8)?,
         16 => write_data_u8(writer, code::FIXEXT16)?,
-        17..=256 => {
+        0..=0xff => {
             write_data_u8(writer, code::EXT8)?;
             write_data_u8(writer, len as u8)?
         }
-        256..=65536 => {
+        0x100..=0xffff => {
             write_data_u8(writer, code::EXT16)?;
             write_data_u16(writer, len as u16)?
         }
@@ -3,12 +3,10 @@ use crate::{code, PackError};
 use std::io;
 
 pub fn pack_timestamp<W: io::Write>(writer: &mut W, sec: i64, nsec: u32) -> Result<(), PackError> {
-    if sec >> 34 == 0 {
-        if nsec == 0 {
-            pack_timestamp32(writer, sec as u32)
-        } else {
-            pack_timestamp64(writer, sec as u64, nsec)
-        }
+    if sec >> 32 == 0 && nsec == 0 {
+        pack_timestamp32(writer, sec as u32)
+    } else if sec >> 34 == 0 {
+        pack_timestamp64(writer, sec as u64, nsec)
     } else {
         pack_timestamp96(writer, sec, nsec)
     }
@@ -26,9 +24,7 @@ pub fn pack_timestamp64<W: io::Write>(
     nsec: u32,
 ) -> Result<(), PackError> {
     if sec >> 34 != 0 || nsec >> 30 != 0 {
-        use std::io::ErrorKind;
-        return Err(PackError::WriteError(io::Error::new(
-            ErrorKind::Other,
+        return Err(PackError::WriteError(io::Error::other(
             "Value is out of range for timestamp64",
         )));
     }
@@ -0,0 +1,136 @@
+use crate::error::TimestampRangeError;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub const TIMESTAMP_TYPE: i8 = -1;
+
+const NANOS_PER_SEC: u32 = 1_000_000_000;
+
+// seconds and nanoseconds since the unix epoch, as carried by the ext type -1
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp {
+    sec: i64,
+    nsec: u32,
+}
+
+impl Timestamp {
+    pub const UNIX_EPOCH: Timestamp = Timestamp { sec: 0, nsec: 0 };
+
+    // None if `nsec` is not below one second
+    pub fn new(sec: i64, nsec: u32) -> Option<Self> {
+        if nsec < NANOS_PER_SEC {
+            Some(Timestamp { sec, nsec })
+        } else {
+            None
+        }
+    }
+
+    pub fn sec(&self) -> i64 {
+        self.sec
+    }
+
+    pub fn nsec(&self) -> u32 {
+        self.nsec
+    }
+}
+
+// the time elapsed since the unix epoch
+impl TryFrom<Duration> for Timestamp {
+    type Error = TimestampRangeError;
+
+    fn try_from(d: Duration) -> Result<Self, Self::Error> {
+        let sec = i64::try_from(d.as_secs()).map_err(|_| TimestampRangeError)?;
+        Ok(Timestamp {
+            sec,
+            nsec: d.subsec_nanos(),
+        })
+    }
+}
+
+impl TryFrom<Timestamp> for Duration {
+    type Error = TimestampRangeError;
+
+    fn try_from(t: Timestamp) -> Result<Self, Self::Error> {
+        let sec = u64::try_from(t.sec).map_err(|_| TimestampRangeError)?;
+        Ok(Duration::new(sec, t.nsec))
+    }
+}
+
+impl TryFrom<SystemTime> for Timestamp {
+    type Error = TimestampRangeError;
+
+    fn try_from(t: SystemTime) -> Result<Self, Self::Error> {
+        match t.duration_since(UNIX_EPOCH) {
+            Ok(d) => Timestamp::try_from(d),
+            Err(e) => {
+                // before the epoch. nsec still counts forward from `sec`
+                let d = e.duration();
+                let sec = i64::try_from(d.as_secs()).map_err(|_| TimestampRangeError)?;
+                if d.subsec_nanos() == 0 {
+                    Ok(Timestamp { sec: -sec, nsec: 0 })
+                } else {
+                    Ok(Timestamp {
+                        sec: -sec - 1,
+                        nsec: NANOS_PER_SEC - d.subsec_nanos(),
+                    })
+                }
+            }
+        }
+    }
+}
+
+impl TryFrom<Timestamp> for SystemTime {
+    type Error = TimestampRangeError;
+
+    fn try_from(t: Timestamp) -> Result<Self, Self::Error> {
+        let nsec = Duration::from_nanos(u64::from(t.nsec));
+        let time = if t.sec >= 0 {
+            UNIX_EPOCH.checked_add(Duration::from_secs(t.sec as u64))
+        } else {
+            UNIX_EPOCH.checked_sub(Duration::from_secs(t.sec.unsigned_abs()))
+        };
+        time.and_then(|v| v.checked_add(nsec))
+            .ok_or(TimestampRangeError)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<chrono::DateTime<chrono::Utc>> for Timestamp {
+    fn from(t: chrono::DateTime<chrono::Utc>) -> Self {
+        // chrono puts a leap second in nsec, carry it into the next second
+        let nsec = t.timestamp_subsec_nanos();
+        Timestamp {
+            sec: t.timestamp() + i64::from(nsec / NANOS_PER_SEC),
+            nsec: nsec % NANOS_PER_SEC,
+        }
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl TryFrom<Timestamp> for chrono::DateTime<chrono::Utc> {
+    type Error = TimestampRangeError;
+
+    fn try_from(t: Timestamp) -> Result<Self, Self::Error> {
+        chrono::DateTime::from_timestamp(t.sec, t.nsec).ok_or(TimestampRangeError)
+    }
+}
+
+#[cfg(feature = "time")]
+impl From<time::OffsetDateTime> for Timestamp {
+    fn from(t: time::OffsetDateTime) -> Self {
+        Timestamp {
+            sec: t.unix_timestamp(),
+            nsec: t.nanosecond(),
+        }
+    }
+}
+
+#[cfg(feature = "time")]
+impl TryFrom<Timestamp> for time::OffsetDateTime {
+    type Error = TimestampRangeError;
+
+    fn try_from(t: Timestamp) -> Result<Self, Self::Error> {
+        time::OffsetDateTime::from_unix_timestamp(t.sec)
+            .and_then(|v| v.replace_nanosecond(t.nsec))
+            .map_err(|_| TimestampRangeError)
+    }
+}
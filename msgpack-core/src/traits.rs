@@ -0,0 +1,302 @@
+use crate::code::Code;
+use crate::{unpack, MessagePacker, MessageUnpacker, PackError, PathSegment, UnpackError};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{BuildHasher, Hash};
+use std::rc::Rc;
+use std::sync::Arc;
+
+// types which write themselves through a MessagePacker, without going through serde.
+// integers take their smallest encoding and sequences become arrays
+pub trait Pack {
+    fn pack<P: MessagePacker>(&self, packer: &mut P) -> Result<(), PackError>;
+}
+
+// types which read themselves back through a MessageUnpacker. integers accept any encoding
+// their value fits in
+pub trait Unpack: Sized {
+    fn unpack<U: MessageUnpacker>(unpacker: &mut U) -> Result<Self, UnpackError>;
+}
+
+macro_rules! impl_int {
+    ($($ty:ty, $pack:ident, $unpack:ident;)*) => {
+        $(
+            impl Pack for $ty {
+                fn pack<P: MessagePacker>(&self, packer: &mut P) -> Result<(), PackError> {
+                    packer.$pack(*self)
+                }
+            }
+
+            impl Unpack for $ty {
+                fn unpack<U: MessageUnpacker>(unpacker: &mut U) -> Result<Self, UnpackError> {
+                    unpacker.$unpack()
+                }
+            }
+        )*
+    };
+}
+
+impl_int! {
+    u8, pack_from_u8, unpack_u8;
+    u16, pack_from_u16, unpack_u16;
+    u32, pack_from_u32, unpack_u32;
+    u64, pack_from_u64, unpack_u64;
+    i8, pack_from_i8, unpack_i8;
+    i16, pack_from_i16, unpack_i16;
+    i32, pack_from_i32, unpack_i32;
+    i64, pack_from_i64, unpack_i64;
+    f32, pack_f32, unpack_f32;
+    f64, pack_f64, unpack_f64;
+    bool, pack_bool, unpack_bool;
+}
+
+impl Pack for usize {
+    fn pack<P: MessagePacker>(&self, packer: &mut P) -> Result<(), PackError> {
+        packer.pack_from_u64(*self as u64)
+    }
+}
+
+impl Unpack for usize {
+    fn unpack<U: MessageUnpacker>(unpacker: &mut U) -> Result<Self, UnpackError> {
+        let v = unpacker.unpack_u64()?;
+        usize::try_from(v).map_err(|_| UnpackError::OutOfRange(Code::Uint64, "usize".to_string()))
+    }
+}
+
+impl Pack for isize {
+    fn pack<P: MessagePacker>(&self, packer: &mut P) -> Result<(), PackError> {
+        packer.pack_from_i64(*self as i64)
+    }
+}
+
+impl Unpack for isize {
+    fn unpack<U: MessageUnpacker>(unpacker: &mut U) -> Result<Self, UnpackError> {
+        let v = unpacker.unpack_i64()?;
+        isize::try_from(v).map_err(|_| UnpackError::OutOfRange(Code::Int64, "isize".to_string()))
+    }
+}
+
+impl Pack for str {
+    fn pack<P: MessagePacker>(&self, packer: &mut P) -> Result<(), PackError> {
+        packer.pack_str(self)
+    }
+}
+
+impl Pack for String {
+    fn pack<P: MessagePacker>(&self, packer: &mut P) -> Result<(), PackError> {
+        packer.pack_str(self)
+    }
+}
+
+impl Unpack for String {
+    fn unpack<U: MessageUnpacker>(unpacker: &mut U) -> Result<Self, UnpackError> {
+        unpacker.unpack_string()
+    }
+}
+
+impl<T: Pack> Pack for [T] {
+    fn pack<P: MessagePacker>(&self, packer: &mut P) -> Result<(), PackError> {
+        packer.pack_array_header(self.len())?;
+        for v in self {
+            v.pack(packer)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Pack> Pack for Vec<T> {
+    fn pack<P: MessagePacker>(&self, packer: &mut P) -> Result<(), PackError> {
+        self[..].pack(packer)
+    }
+}
+
+impl<T: Unpack> Unpack for Vec<T> {
+    fn unpack<U: MessageUnpacker>(unpacker: &mut U) -> Result<Self, UnpackError> {
+        let len = unpacker.unpack_array_header()?;
+        unpack_elements(unpacker, len)
+    }
+}
+
+impl<T: Pack, const N: usize> Pack for [T; N] {
+    fn pack<P: MessagePacker>(&self, packer: &mut P) -> Result<(), PackError> {
+        self[..].pack(packer)
+    }
+}
+
+impl<T: Unpack, const N: usize> Unpack for [T; N] {
+    fn unpack<U: MessageUnpacker>(unpacker: &mut U) -> Result<Self, UnpackError> {
+        let len = unpacker.unpack_array_header()?;
+        if len != N {
+            return Err(UnpackError::LengthMismatch(N, len));
+        }
+        let vs = unpack_elements(unpacker, len)?;
+        Ok(vs.try_into().unwrap_or_else(|_| unreachable!()))
+    }
+}
+
+fn unpack_elements<T: Unpack, U: MessageUnpacker>(
+    unpacker: &mut U,
+    len: usize,
+) -> Result<Vec<T>, UnpackError> {
+    let mut vs = Vec::with_capacity(unpack::capacity_hint::<T>(len));
+    for i in 0..len {
+        vs.push(T::unpack(unpacker).map_err(|e| e.within(PathSegment::Index(i)))?);
+    }
+    Ok(vs)
+}
+
+macro_rules! impl_tuple {
+    ($($len:expr => ($($n:tt $ty:ident)+))*) => {
+        $(
+            impl<$($ty: Pack),+> Pack for ($($ty,)+) {
+                fn pack<P: MessagePacker>(&self, packer: &mut P) -> Result<(), PackError> {
+                    packer.pack_array_header($len)?;
+                    $(self.$n.pack(packer)?;)+
+                    Ok(())
+                }
+            }
+
+            impl<$($ty: Unpack),+> Unpack for ($($ty,)+) {
+                fn unpack<U: MessageUnpacker>(unpacker: &mut U) -> Result<Self, UnpackError> {
+                    let len = unpacker.unpack_array_header()?;
+                    if len != $len {
+                        return Err(UnpackError::LengthMismatch($len, len));
+                    }
+                    Ok(($(
+                        $ty::unpack(unpacker).map_err(|e| e.within(PathSegment::Index($n)))?,
+                    )+))
+                }
+            }
+        )*
+    };
+}
+
+impl_tuple! {
+    1 => (0 T0)
+    2 => (0 T0 1 T1)
+    3 => (0 T0 1 T1 2 T2)
+    4 => (0 T0 1 T1 2 T2 3 T3)
+    5 => (0 T0 1 T1 2 T2 3 T3 4 T4)
+    6 => (0 T0 1 T1 2 T2 3 T3 4 T4 5 T5)
+    7 => (0 T0 1 T1 2 T2 3 T3 4 T4 5 T5 6 T6)
+    8 => (0 T0 1 T1 2 T2 3 T3 4 T4 5 T5 6 T6 7 T7)
+}
+
+// None is nil
+impl<T: Pack> Pack for Option<T> {
+    fn pack<P: MessagePacker>(&self, packer: &mut P) -> Result<(), PackError> {
+        match *self {
+            Some(ref v) => v.pack(packer),
+            None => packer.pack_nil(),
+        }
+    }
+}
+
+impl<T: Unpack> Unpack for Option<T> {
+    fn unpack<U: MessageUnpacker>(unpacker: &mut U) -> Result<Self, UnpackError> {
+        match unpacker.peek_code()? {
+            Code::Nil => unpacker.unpack_nil(),
+            _ => T::unpack(unpacker).map(Some),
+        }
+    }
+}
+
+impl<K: Pack, V: Pack, S> Pack for HashMap<K, V, S> {
+    fn pack<P: MessagePacker>(&self, packer: &mut P) -> Result<(), PackError> {
+        pack_entries(packer, self.len(), self)
+    }
+}
+
+impl<K, V, S> Unpack for HashMap<K, V, S>
+where
+    K: Unpack + Eq + Hash,
+    V: Unpack,
+    S: BuildHasher + Default,
+{
+    fn unpack<U: MessageUnpacker>(unpacker: &mut U) -> Result<Self, UnpackError> {
+        let len = unpacker.unpack_map_header()?;
+        let mut map =
+            HashMap::with_capacity_and_hasher(unpack::capacity_hint::<(K, V)>(len), S::default());
+        for _ in 0..len {
+            let k = K::unpack(unpacker)?;
+            map.insert(k, V::unpack(unpacker)?);
+        }
+        Ok(map)
+    }
+}
+
+impl<K: Pack, V: Pack> Pack for BTreeMap<K, V> {
+    fn pack<P: MessagePacker>(&self, packer: &mut P) -> Result<(), PackError> {
+        pack_entries(packer, self.len(), self)
+    }
+}
+
+impl<K: Unpack + Ord, V: Unpack> Unpack for BTreeMap<K, V> {
+    fn unpack<U: MessageUnpacker>(unpacker: &mut U) -> Result<Self, UnpackError> {
+        let len = unpacker.unpack_map_header()?;
+        let mut map = BTreeMap::new();
+        for _ in 0..len {
+            let k = K::unpack(unpacker)?;
+            map.insert(k, V::unpack(unpacker)?);
+        }
+        Ok(map)
+    }
+}
+
+fn pack_entries<'a, K, V, P, I>(packer: &mut P, len: usize, entries: I) -> Result<(), PackError>
+where
+    K: Pack + 'a,
+    V: Pack + 'a,
+    P: MessagePacker,
+    I: IntoIterator<Item = (&'a K, &'a V)>,
+{
+    packer.pack_map_header(len)?;
+    for (k, v) in entries {
+        k.pack(packer)?;
+        v.pack(packer)?;
+    }
+    Ok(())
+}
+
+impl<T: Pack + ?Sized> Pack for &T {
+    fn pack<P: MessagePacker>(&self, packer: &mut P) -> Result<(), PackError> {
+        (**self).pack(packer)
+    }
+}
+
+macro_rules! impl_pointer {
+    ($($ty:ident)*) => {
+        $(
+            impl<T: Pack + ?Sized> Pack for $ty<T> {
+                fn pack<P: MessagePacker>(&self, packer: &mut P) -> Result<(), PackError> {
+                    (**self).pack(packer)
+                }
+            }
+
+            impl<T: Unpack> Unpack for $ty<T> {
+                fn unpack<U: MessageUnpacker>(unpacker: &mut U) -> Result<Self, UnpackError> {
+                    T::unpack(unpacker).map($ty::new)
+                }
+            }
+        )*
+    };
+}
+
+impl_pointer! {Box Rc Arc}
+
+impl<B: Pack + ToOwned + ?Sized> Pack for Cow<'_, B> {
+    fn pack<P: MessagePacker>(&self, packer: &mut P) -> Result<(), PackError> {
+        (**self).pack(packer)
+    }
+}
+
+// always owned, since a MessageUnpacker has nothing to lend out
+impl<B> Unpack for Cow<'_, B>
+where
+    B: ToOwned + ?Sized,
+    B::Owned: Unpack,
+{
+    fn unpack<U: MessageUnpacker>(unpacker: &mut U) -> Result<Self, UnpackError> {
+        B::Owned::unpack(unpacker).map(Cow::Owned)
+    }
+}
@@ -1,16 +1,40 @@
 pub mod primitive;
 
+use crate::code::{Code, CodeSize};
+use crate::error::UnpackError;
+use crate::limits::LimitTracker;
+use crate::timestamp::{Timestamp, TIMESTAMP_TYPE};
 use crate::BufferedRead;
-use crate::{code::Code, error::UnpackError};
-use std::io::{self, ErrorKind};
+use std::any::type_name;
+use std::cmp;
+use std::io::{self, Read};
 use std::{str, string};
 
 pub use primitive::*;
 
+// upper bound in bytes on what is reserved up front for a length which comes from the input
+const MAX_PREALLOC: usize = 64 * 1024;
+
+// how many `T`s to reserve up front for a length read from the input or a size hint. the
+// rest grows as entries arrive, so a bogus length can't make a decoder allocate more than the
+// input holds
+pub fn capacity_hint<T>(len: usize) -> usize {
+    cmp::min(len, MAX_PREALLOC / cmp::max(std::mem::size_of::<T>(), 1))
+}
+
+// length headers are u32 at most, which only overflows usize on 16-bit targets
+pub fn len_to_usize(len: u32) -> Result<usize, UnpackError> {
+    usize::try_from(len).map_err(|_| UnpackError::LengthOverflow(u64::from(len)))
+}
+
+// the buffer grows as bytes arrive instead of trusting `len` up front, so a bogus header
+// can't make it allocate more than the input holds
 pub fn unpack_data<R: io::Read>(reader: &mut R, len: usize) -> Result<Vec<u8>, UnpackError> {
-    let mut buf = Vec::with_capacity(len);
-    buf.resize(len as usize, 0u8);
-    read_data(reader, &mut buf[..])?;
+    let mut buf = Vec::with_capacity(capacity_hint::<u8>(len));
+    io::Read::take(&mut *reader, len as u64).read_to_end(&mut buf)?;
+    if buf.len() < len {
+        return Err(UnpackError::UnexpectedEof);
+    }
     Ok(buf)
 }
 
@@ -18,12 +42,9 @@ pub fn unpack_data_ref<'a, R>(reader: &mut R, len: usize) -> Result<&'a [u8], Un
 where
     R: BufferedRead<'a>,
 {
-    let buf = reader.fill_buf().map_err(UnpackError::InvalidData)?;
+    let buf = reader.fill_buf().map_err(UnpackError::from)?;
     if len > buf.len() {
-        return Err(UnpackError::InvalidData(io::Error::new(
-            ErrorKind::UnexpectedEof,
-            "Unexpected EOF",
-        )));
+        return Err(UnpackError::UnexpectedEof);
     }
 
     let buf = &buf[..len];
@@ -110,17 +131,206 @@ pub fn unpack_i64<R: io::Read>(reader: &mut R) -> Result<i64, UnpackError> {
     }
 }
 
-pub fn unpack_f32<R: io::Read>(reader: &mut R) -> Result<f32, UnpackError> {
-    match read_code(reader)? {
-        Code::Int32 => read_data_f32(reader),
-        e => Err(UnpackError::TypeMismatch(e, "f32".to_string())),
+// reads the body following an integer code, or returns None when the code isn't an integer
+fn read_int_body<R: io::Read>(reader: &mut R, code: &Code) -> Result<Option<i128>, UnpackError> {
+    let v = match *code {
+        Code::PosInt(v) => i128::from(v),
+        Code::NegInt(v) => i128::from(v),
+        Code::Uint8 => i128::from(read_data_u8(reader)?),
+        Code::Uint16 => i128::from(read_data_u16(reader)?),
+        Code::Uint32 => i128::from(read_data_u32(reader)?),
+        Code::Uint64 => i128::from(read_data_u64(reader)?),
+        Code::Int8 => i128::from(read_data_i8(reader)?),
+        Code::Int16 => i128::from(read_data_i16(reader)?),
+        Code::Int32 => i128::from(read_data_i32(reader)?),
+        Code::Int64 => i128::from(read_data_i64(reader)?),
+        _ => return Ok(None),
+    };
+    Ok(Some(v))
+}
+
+/// Reads an integer of any encoding (fixint, uint8..64, int8..64) and converts it
+/// to `T`, failing only when the value does not fit in `T`.
+pub fn unpack_uint<T, R>(reader: &mut R) -> Result<T, UnpackError>
+where
+    T: TryFrom<u64>,
+    R: io::Read,
+{
+    unpack_uint_with(reader, false)
+}
+
+/// [`unpack_uint`] which, when `strict` is set, also rejects an integer that isn't in its
+/// smallest encoding.
+pub fn unpack_uint_with<T, R>(reader: &mut R, strict: bool) -> Result<T, UnpackError>
+where
+    T: TryFrom<u64>,
+    R: io::Read,
+{
+    let code = read_code(reader)?;
+    let v = match read_int_body(reader, &code)? {
+        Some(v) => v,
+        None => {
+            return Err(UnpackError::TypeMismatch(
+                code,
+                type_name::<T>().to_string(),
+            ))
+        }
+    };
+    if strict {
+        check_minimal_int(&code, v)?;
     }
+    u64::try_from(v)
+        .ok()
+        .and_then(|v| T::try_from(v).ok())
+        .ok_or_else(|| UnpackError::OutOfRange(code, type_name::<T>().to_string()))
+}
+
+/// Signed counterpart of [`unpack_uint`].
+pub fn unpack_int<T, R>(reader: &mut R) -> Result<T, UnpackError>
+where
+    T: TryFrom<i64>,
+    R: io::Read,
+{
+    unpack_int_with(reader, false)
+}
+
+/// Signed counterpart of [`unpack_uint_with`].
+pub fn unpack_int_with<T, R>(reader: &mut R, strict: bool) -> Result<T, UnpackError>
+where
+    T: TryFrom<i64>,
+    R: io::Read,
+{
+    let code = read_code(reader)?;
+    let v = match read_int_body(reader, &code)? {
+        Some(v) => v,
+        None => {
+            return Err(UnpackError::TypeMismatch(
+                code,
+                type_name::<T>().to_string(),
+            ))
+        }
+    };
+    if strict {
+        check_minimal_int(&code, v)?;
+    }
+    i64::try_from(v)
+        .ok()
+        .and_then(|v| T::try_from(v).ok())
+        .ok_or_else(|| UnpackError::OutOfRange(code, type_name::<T>().to_string()))
+}
+
+// a non-negative integer is never smaller in the int family than in the uint one, so only
+// negative values may use int8..64
+pub fn check_minimal_int(code: &Code, v: i128) -> Result<(), UnpackError> {
+    let minimal = match *code {
+        Code::Uint8 => v > 0x7f,
+        Code::Uint16 => v > 0xff,
+        Code::Uint32 => v > 0xffff,
+        Code::Uint64 => v > 0xffff_ffff,
+        Code::Int8 => v < -32,
+        Code::Int16 => v < i128::from(i8::MIN),
+        Code::Int32 => v < i128::from(i16::MIN),
+        Code::Int64 => v < i128::from(i32::MIN),
+        _ => true,
+    };
+    if minimal {
+        Ok(())
+    } else {
+        Err(UnpackError::NotMinimal(code.clone()))
+    }
+}
+
+// the length of a str, bin, ext or container against the header it was read from
+pub fn check_minimal_len(code: &Code, len: usize) -> Result<(), UnpackError> {
+    let minimal = match *code {
+        Code::Str8 => len > 31,
+        Code::Array16 | Code::Map16 => len > 15,
+        Code::Str16 | Code::Bin16 | Code::Ext16 => len > 0xff,
+        Code::Str32 | Code::Bin32 | Code::Ext32 | Code::Array32 | Code::Map32 => len > 0xffff,
+        Code::Ext8 => !matches!(len, 1 | 2 | 4 | 8 | 16),
+        _ => true,
+    };
+    if minimal {
+        Ok(())
+    } else {
+        Err(UnpackError::NotMinimal(code.clone()))
+    }
+}
+
+/// Controls which encodings the float readers accept.
+///
+/// The default only accepts float32 and float64 and rejects any conversion which
+/// would lose precision: a float64 narrowed to `f32`, or an integer beyond what the
+/// float holds exactly, which starts above 2^24 for `f32` and above 2^53 for `f64`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FloatMode {
+    /// accept integer codes as well as float codes
+    pub accept_int: bool,
+    /// round values which can't be represented exactly instead of failing
+    pub allow_precision_loss: bool,
+    /// with `accept_int`, reject an integer that isn't in its smallest encoding
+    pub strict: bool,
+}
+
+pub fn unpack_f32<R: io::Read>(reader: &mut R) -> Result<f32, UnpackError> {
+    unpack_f32_with(reader, FloatMode::default())
 }
 
 pub fn unpack_f64<R: io::Read>(reader: &mut R) -> Result<f64, UnpackError> {
-    match read_code(reader)? {
-        Code::Int64 => read_data_f64(reader),
-        e => Err(UnpackError::TypeMismatch(e, "f64".to_string())),
+    unpack_f64_with(reader, FloatMode::default())
+}
+
+pub fn unpack_f32_with<R: io::Read>(reader: &mut R, mode: FloatMode) -> Result<f32, UnpackError> {
+    let code = read_code(reader)?;
+    let (f, exact) = match code {
+        Code::Float32 => return read_data_f32(reader),
+        Code::Float64 => {
+            let v = read_data_f64(reader)?;
+            let f = v as f32;
+            (f, v.is_nan() || f64::from(f) == v)
+        }
+        _ if mode.accept_int => match read_int_body(reader, &code)? {
+            Some(v) => {
+                if mode.strict {
+                    check_minimal_int(&code, v)?;
+                }
+                let f = v as f32;
+                (f, f as i128 == v)
+            }
+            None => return Err(UnpackError::TypeMismatch(code, "f32".to_string())),
+        },
+        e => return Err(UnpackError::TypeMismatch(e, "f32".to_string())),
+    };
+
+    if exact || mode.allow_precision_loss {
+        Ok(f)
+    } else {
+        Err(UnpackError::OutOfRange(code, "f32".to_string()))
+    }
+}
+
+pub fn unpack_f64_with<R: io::Read>(reader: &mut R, mode: FloatMode) -> Result<f64, UnpackError> {
+    let code = read_code(reader)?;
+    let (f, exact) = match code {
+        Code::Float32 => return read_data_f32(reader).map(f64::from),
+        Code::Float64 => return read_data_f64(reader),
+        _ if mode.accept_int => match read_int_body(reader, &code)? {
+            Some(v) => {
+                if mode.strict {
+                    check_minimal_int(&code, v)?;
+                }
+                let f = v as f64;
+                (f, f as i128 == v)
+            }
+            None => return Err(UnpackError::TypeMismatch(code, "f64".to_string())),
+        },
+        e => return Err(UnpackError::TypeMismatch(e, "f64".to_string())),
+    };
+
+    if exact || mode.allow_precision_loss {
+        Ok(f)
+    } else {
+        Err(UnpackError::OutOfRange(code, "f64".to_string()))
     }
 }
 
@@ -140,12 +350,26 @@ pub fn unpack_nil<T, R: io::Read>(reader: &mut R) -> Result<Option<T>, UnpackErr
 }
 
 pub fn unpack_bin_header<R: io::Read>(reader: &mut R) -> Result<usize, UnpackError> {
-    match read_code(reader)? {
-        Code::Bin8 => read_data_u8(reader).map(usize::from),
-        Code::Bin16 => read_data_u16(reader).map(usize::from),
-        Code::Bin32 => read_data_u32(reader).map(|v| v as usize),
-        e => Err(UnpackError::TypeMismatch(e, "binary header".to_string())),
+    unpack_bin_header_with(reader, false)
+}
+
+/// [`unpack_bin_header`] which, when `strict` is set, also rejects a length that isn't in its
+/// smallest encoding.
+pub fn unpack_bin_header_with<R: io::Read>(
+    reader: &mut R,
+    strict: bool,
+) -> Result<usize, UnpackError> {
+    let code = read_code(reader)?;
+    let len = match code {
+        Code::Bin8 => read_data_u8(reader).map(usize::from)?,
+        Code::Bin16 => read_data_u16(reader).map(usize::from)?,
+        Code::Bin32 => read_data_u32(reader).and_then(len_to_usize)?,
+        e => return Err(UnpackError::TypeMismatch(e, "binary header".to_string())),
+    };
+    if strict {
+        check_minimal_len(&code, len)?;
     }
+    Ok(len)
 }
 
 pub fn unpack_bin_ref<'a, R>(reader: &mut R) -> Result<&'a [u8], UnpackError>
@@ -166,9 +390,7 @@ where
 
 pub fn unpack_str<R: io::Read>(reader: &mut R) -> Result<String, UnpackError> {
     let len = unpack_str_header(reader)?;
-    let buf = unpack_data(reader, len)?;
-    string::String::from_utf8(buf)
-        .map_err(|e| UnpackError::InvalidData(io::Error::new(ErrorKind::Other, e.to_string())))
+    unpack_str_data(reader, len)
 }
 
 pub fn unpack_str_ref<'a, R>(reader: &mut R) -> Result<&'a str, UnpackError>
@@ -176,37 +398,90 @@ where
     R: BufferedRead<'a>,
 {
     let len = unpack_str_header(reader)?;
+    unpack_str_data_ref(reader, len)
+}
+
+pub fn unpack_str_data<R: io::Read>(reader: &mut R, len: usize) -> Result<String, UnpackError> {
+    let buf = unpack_data(reader, len)?;
+    string::String::from_utf8(buf).map_err(|e| UnpackError::InvalidUtf8(e.utf8_error()))
+}
+
+pub fn unpack_str_data_ref<'a, R>(reader: &mut R, len: usize) -> Result<&'a str, UnpackError>
+where
+    R: BufferedRead<'a>,
+{
     let buf = unpack_data_ref(reader, len)?;
-    str::from_utf8(buf)
-        .map_err(|e| UnpackError::InvalidData(io::Error::new(ErrorKind::Other, e.to_string())))
+    str::from_utf8(buf).map_err(UnpackError::InvalidUtf8)
 }
 
 pub fn unpack_str_header<R: io::Read>(reader: &mut R) -> Result<usize, UnpackError> {
-    match read_code(reader)? {
-        Code::FixStr(v) => Ok(usize::from(v)),
-        Code::Str8 => read_data_u8(reader).map(usize::from),
-        Code::Str16 => read_data_u16(reader).map(usize::from),
-        Code::Str32 => read_data_u32(reader).map(|v| v as usize),
-        e => Err(UnpackError::TypeMismatch(e, "str header".to_string())),
+    unpack_str_header_with(reader, false)
+}
+
+/// [`unpack_str_header`] which, when `strict` is set, also rejects a length that isn't in its
+/// smallest encoding.
+pub fn unpack_str_header_with<R: io::Read>(
+    reader: &mut R,
+    strict: bool,
+) -> Result<usize, UnpackError> {
+    let code = read_code(reader)?;
+    let len = match code {
+        Code::FixStr(v) => usize::from(v),
+        Code::Str8 => read_data_u8(reader).map(usize::from)?,
+        Code::Str16 => read_data_u16(reader).map(usize::from)?,
+        Code::Str32 => read_data_u32(reader).and_then(len_to_usize)?,
+        e => return Err(UnpackError::TypeMismatch(e, "str header".to_string())),
+    };
+    if strict {
+        check_minimal_len(&code, len)?;
     }
+    Ok(len)
 }
 
 pub fn unpack_array_header<R: io::Read>(reader: &mut R) -> Result<usize, UnpackError> {
-    match read_code(reader)? {
-        Code::FixArray(v) => Ok(usize::from(v)),
-        Code::Array16 => read_data_u16(reader).map(usize::from),
-        Code::Array32 => read_data_u32(reader).map(|v| v as usize),
-        e => Err(UnpackError::TypeMismatch(e, "array header".to_string())),
+    unpack_array_header_with(reader, false)
+}
+
+/// [`unpack_array_header`] which, when `strict` is set, also rejects a length that isn't in its
+/// smallest encoding.
+pub fn unpack_array_header_with<R: io::Read>(
+    reader: &mut R,
+    strict: bool,
+) -> Result<usize, UnpackError> {
+    let code = read_code(reader)?;
+    let len = match code {
+        Code::FixArray(v) => usize::from(v),
+        Code::Array16 => read_data_u16(reader).map(usize::from)?,
+        Code::Array32 => read_data_u32(reader).and_then(len_to_usize)?,
+        e => return Err(UnpackError::TypeMismatch(e, "array header".to_string())),
+    };
+    if strict {
+        check_minimal_len(&code, len)?;
     }
+    Ok(len)
 }
 
 pub fn unpack_map_header<R: io::Read>(reader: &mut R) -> Result<usize, UnpackError> {
-    match read_code(reader)? {
-        Code::FixMap(v) => Ok(usize::from(v)),
-        Code::Map16 => read_data_u16(reader).map(usize::from),
-        Code::Map32 => read_data_u32(reader).map(|v| v as usize),
-        e => Err(UnpackError::TypeMismatch(e, "map header".to_string())),
+    unpack_map_header_with(reader, false)
+}
+
+/// [`unpack_map_header`] which, when `strict` is set, also rejects a length that isn't in its
+/// smallest encoding.
+pub fn unpack_map_header_with<R: io::Read>(
+    reader: &mut R,
+    strict: bool,
+) -> Result<usize, UnpackError> {
+    let code = read_code(reader)?;
+    let len = match code {
+        Code::FixMap(v) => usize::from(v),
+        Code::Map16 => read_data_u16(reader).map(usize::from)?,
+        Code::Map32 => read_data_u32(reader).and_then(len_to_usize)?,
+        e => return Err(UnpackError::TypeMismatch(e, "map header".to_string())),
+    };
+    if strict {
+        check_minimal_len(&code, len)?;
     }
+    Ok(len)
 }
 
 pub fn unpack_fixext1<R: io::Read>(reader: &mut R) -> Result<(i8, u8), UnpackError> {
@@ -273,7 +548,17 @@ pub fn unpack_fixext16<R: io::Read>(reader: &mut R) -> Result<(i8, [u8; 16]), Un
 }
 
 pub fn unpack_ext_header<R: io::Read>(reader: &mut R) -> Result<(u32, i8), UnpackError> {
-    let len = match read_code(reader)? {
+    unpack_ext_header_with(reader, false)
+}
+
+/// [`unpack_ext_header`] which, when `strict` is set, also rejects a length that isn't in its
+/// smallest encoding.
+pub fn unpack_ext_header_with<R: io::Read>(
+    reader: &mut R,
+    strict: bool,
+) -> Result<(u32, i8), UnpackError> {
+    let code = read_code(reader)?;
+    let len = match code {
         Code::FixExt1 => 1,
         Code::FixExt2 => 2,
         Code::FixExt4 => 4,
@@ -284,6 +569,9 @@ pub fn unpack_ext_header<R: io::Read>(reader: &mut R) -> Result<(u32, i8), Unpac
         Code::Ext32 => read_data_u32(reader)?,
         e => return Err(UnpackError::TypeMismatch(e, "ext header".to_string())),
     };
+    if strict {
+        check_minimal_len(&code, len_to_usize(len)?)?;
+    }
     let ty = read_data_i8(reader)?;
     Ok((len, ty))
 }
@@ -292,12 +580,12 @@ pub fn unpack_timestamp32<R: io::Read>(reader: &mut R) -> Result<u32, UnpackErro
     match read_code(reader)? {
         Code::FixExt4 => {
             let type_tag = read_data_i8(reader)?;
-            if type_tag == -1 {
+            if type_tag == TIMESTAMP_TYPE {
                 read_data_u32(reader)
             } else {
-                Err(UnpackError::InvalidData(io::Error::new(
-                    ErrorKind::Other,
-                    format!("Timestamp32 expects -1 but {:?}", type_tag),
+                Err(UnpackError::InvalidExtTimestamp(format!(
+                    "timestamp32 expects -1 but {:?}",
+                    type_tag
                 )))
             }
         }
@@ -308,22 +596,19 @@ pub fn unpack_timestamp32<R: io::Read>(reader: &mut R) -> Result<u32, UnpackErro
     }
 }
 
-const TIMESTAMP64_SEC_MASK: u64 = (1 << 35) - 1;
-const TIMESTAMP64_NSEC_MASK: u32 = (1 << 31) - 1;
+const TIMESTAMP64_SEC_MASK: u64 = (1 << 34) - 1;
 
 pub fn unpack_timestamp64<R: io::Read>(reader: &mut R) -> Result<(u64, u32), UnpackError> {
     match read_code(reader)? {
         Code::FixExt8 => {
             let type_tag = read_data_i8(reader)?;
-            if type_tag == -1 {
-                let v = read_data_u64(reader)?;
-                let sec = v | TIMESTAMP64_SEC_MASK;
-                let nsec = (v >> 34) as u32 | TIMESTAMP64_NSEC_MASK;
-                Ok((sec, nsec))
+            if type_tag == TIMESTAMP_TYPE {
+                let t = unpack_timestamp_data(reader, 8)?;
+                Ok((t.sec() as u64, t.nsec()))
             } else {
-                Err(UnpackError::InvalidData(io::Error::new(
-                    ErrorKind::Other,
-                    format!("Timestamp64 expects -1 but {:?}", type_tag),
+                Err(UnpackError::InvalidExtTimestamp(format!(
+                    "timestamp64 expects -1 but {:?}",
+                    type_tag
                 )))
             }
         }
@@ -337,16 +622,15 @@ pub fn unpack_timestamp64<R: io::Read>(reader: &mut R) -> Result<(u64, u32), Unp
 pub fn unpack_timestamp96<R: io::Read>(reader: &mut R) -> Result<(i64, u32), UnpackError> {
     match read_code(reader)? {
         Code::Ext8 => {
-            let type_tag = read_data_i8(reader)?;
             let size = read_data_u8(reader)?;
-            if type_tag == -1 && size == 12 {
-                let nsec = read_data_u32(reader)?;
-                let sec = read_data_i64(reader)?;
-                Ok((sec, nsec))
+            let type_tag = read_data_i8(reader)?;
+            if type_tag == TIMESTAMP_TYPE && size == 12 {
+                let t = unpack_timestamp_data(reader, 12)?;
+                Ok((t.sec(), t.nsec()))
             } else {
-                Err(UnpackError::InvalidData(io::Error::new(
-                    ErrorKind::Other,
-                    format!("Timestamp96 expects -1 but {:?}", type_tag),
+                Err(UnpackError::InvalidExtTimestamp(format!(
+                    "timestamp96 expects -1 with size 12 but {:?} with size {:?}",
+                    type_tag, size
                 )))
             }
         }
@@ -356,3 +640,217 @@ pub fn unpack_timestamp96<R: io::Read>(reader: &mut R) -> Result<(i64, u32), Unp
         )),
     }
 }
+
+// any of timestamp32, 64 and 96
+pub fn unpack_timestamp<R: io::Read>(reader: &mut R) -> Result<Timestamp, UnpackError> {
+    let (len, type_tag) = unpack_ext_header(reader)?;
+    if type_tag != TIMESTAMP_TYPE {
+        return Err(UnpackError::InvalidExtTimestamp(format!(
+            "timestamp expects -1 but {:?}",
+            type_tag
+        )));
+    }
+    unpack_timestamp_data(reader, len_to_usize(len)?)
+}
+
+// the body of an ext -1 value whose header has already been read
+pub fn unpack_timestamp_data<R: io::Read>(
+    reader: &mut R,
+    len: usize,
+) -> Result<Timestamp, UnpackError> {
+    let (sec, nsec) = match len {
+        4 => (i64::from(read_data_u32(reader)?), 0),
+        8 => {
+            let v = read_data_u64(reader)?;
+            ((v & TIMESTAMP64_SEC_MASK) as i64, (v >> 34) as u32)
+        }
+        12 => {
+            let nsec = read_data_u32(reader)?;
+            let sec = read_data_i64(reader)?;
+            (sec, nsec)
+        }
+        _ => {
+            return Err(UnpackError::InvalidExtTimestamp(format!(
+                "unexpected body size {}",
+                len
+            )))
+        }
+    };
+    Timestamp::new(sec, nsec).ok_or_else(|| {
+        UnpackError::InvalidExtTimestamp(format!("nsec {} is not below one second", nsec))
+    })
+}
+
+// reads one complete object, including everything nested in it, and discards it.
+// nesting is tracked with a counter instead of recursion so deep input can't overflow the stack
+pub fn skip_value<R: io::Read>(reader: &mut R) -> Result<(), UnpackError> {
+    let mut pending: u64 = 1;
+    while pending > 0 {
+        pending = pending - 1 + skip_header_and_body(reader)?;
+    }
+
+    Ok(())
+}
+
+// like `skip_value`, but checks the depth, container lengths and body lengths against `limits`
+// before skipping past them, so it can gate input that is about to be buffered
+pub fn skip_value_with_limits<R: io::Read>(
+    reader: &mut R,
+    limits: &mut LimitTracker,
+) -> Result<(), UnpackError> {
+    // objects left in each open container, innermost last
+    let mut pending: Vec<u64> = vec![1];
+    while let Some(left) = pending.last_mut() {
+        if *left == 0 {
+            pending.pop();
+            if !pending.is_empty() {
+                limits.leave();
+            }
+            continue;
+        }
+        *left -= 1;
+
+        let (code, len) = read_skip_header(reader)?;
+        match code {
+            Code::FixArray(_) | Code::Array16 | Code::Array32 => {
+                limits.container(len_to_usize(len)?, 0)?;
+                limits.enter()?;
+                pending.push(u64::from(len));
+            }
+            Code::FixMap(_) | Code::Map16 | Code::Map32 => {
+                limits.container(len_to_usize(len)?, 0)?;
+                limits.enter()?;
+                pending.push(u64::from(len) * 2);
+            }
+            Code::FixExt1
+            | Code::FixExt2
+            | Code::FixExt4
+            | Code::FixExt8
+            | Code::FixExt16
+            | Code::Ext8
+            | Code::Ext16
+            | Code::Ext32 => {
+                limits.data_ref(len_to_usize(len)?)?;
+                skip_data(reader, u64::from(len) + 1)?;
+            }
+            Code::FixStr(_)
+            | Code::Str8
+            | Code::Str16
+            | Code::Str32
+            | Code::Bin8
+            | Code::Bin16
+            | Code::Bin32 => {
+                limits.data_ref(len_to_usize(len)?)?;
+                skip_data(reader, u64::from(len))?;
+            }
+            _ => skip_data(reader, u64::from(len))?,
+        }
+    }
+
+    Ok(())
+}
+
+// reads a header and the length of what follows it, in entries for containers
+fn read_skip_header<R: io::Read>(reader: &mut R) -> Result<(Code, u32), UnpackError> {
+    let code = read_code(reader)?;
+    let len = match code.body_size()? {
+        CodeSize::FixLen(v) => u32::from(v),
+        CodeSize::Var1 => u32::from(read_data_u8(reader)?),
+        CodeSize::Var2 => u32::from(read_data_u16(reader)?),
+        CodeSize::Var4 => read_data_u32(reader)?,
+    };
+    Ok((code, len))
+}
+
+// skips a single header along with its body and returns how many nested objects follow it
+pub(crate) fn skip_header_and_body<R: io::Read>(reader: &mut R) -> Result<u64, UnpackError> {
+    let (code, len) = read_skip_header(reader)?;
+    let len = u64::from(len);
+
+    match code {
+        Code::FixArray(_) | Code::Array16 | Code::Array32 => Ok(len),
+        Code::FixMap(_) | Code::Map16 | Code::Map32 => Ok(len * 2),
+        // ext bodies are preceded by a type byte
+        Code::FixExt1
+        | Code::FixExt2
+        | Code::FixExt4
+        | Code::FixExt8
+        | Code::FixExt16
+        | Code::Ext8
+        | Code::Ext16
+        | Code::Ext32 => skip_data(reader, len + 1).map(|_| 0),
+        _ => skip_data(reader, len).map(|_| 0),
+    }
+}
+
+// walks the headers of the object at the front of a buffer which is still arriving. progress
+// is kept across calls so each byte is walked only once, however the object is split up
+#[derive(Debug, Default)]
+pub(crate) struct FrameScan {
+    // how far into the object the headers were already walked,
+    // and how many objects are still missing from it
+    scanned: usize,
+    pending: u64,
+}
+
+impl FrameScan {
+    // the length of the complete object at the front of `buf`, or None while it is incomplete.
+    // `buf` has to start at the same object on every call until this returns something else
+    pub(crate) fn frame_len(&mut self, buf: &[u8]) -> Result<Option<usize>, UnpackError> {
+        if self.pending == 0 {
+            if buf.is_empty() {
+                return Ok(None);
+            }
+            self.pending = 1;
+        }
+
+        while self.pending > 0 {
+            let mut rd = &buf[self.scanned..];
+            match skip_header_and_body(&mut rd) {
+                Ok(nested) => {
+                    self.scanned = buf.len() - rd.len();
+                    self.pending = self.pending - 1 + nested;
+                }
+                Err(ref e) if matches!(e.root(), UnpackError::UnexpectedEof) => return Ok(None),
+                Err(e) => {
+                    self.reset();
+                    return Err(e);
+                }
+            }
+        }
+
+        let len = self.scanned;
+        self.reset();
+        Ok(Some(len))
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.scanned = 0;
+        self.pending = 0;
+    }
+}
+
+// returns the encoded bytes of the next object, header and nested objects included,
+// without decoding it
+pub fn unpack_raw_ref<'a, R>(reader: &mut R) -> Result<&'a [u8], UnpackError>
+where
+    R: BufferedRead<'a>,
+{
+    let buf = reader.fill_buf().map_err(UnpackError::from)?;
+    let mut rest = buf;
+    skip_value(&mut rest)?;
+
+    let len = buf.len() - rest.len();
+    reader.consume(len);
+    Ok(&buf[..len])
+}
+
+fn skip_data<R: io::Read>(reader: &mut R, len: u64) -> Result<(), UnpackError> {
+    let skipped = io::copy(&mut io::Read::take(&mut *reader, len), &mut io::sink())
+        .map_err(UnpackError::from)?;
+    if skipped < len {
+        return Err(UnpackError::UnexpectedEof);
+    }
+
+    Ok(())
+}
@@ -1,11 +1,15 @@
-use crate::{unpack, MessageUnpacker, UnpackError};
+use crate::unpack::FrameScan;
+use crate::{code, unpack, FloatMode, MessageUnpacker, UnpackError};
 
-use bytes::{Buf, BytesMut};
+use bytes::{Buf, Bytes, BytesMut};
 use std::{cmp, io};
 
 #[derive(Debug)]
 pub struct Unpacker<R> {
     inner: R,
+    float_mode: FloatMode,
+    // the code byte read by peek_code, which the next read starts with
+    peeked: Option<u8>,
 }
 
 impl<T> Unpacker<T>
@@ -13,12 +17,26 @@ where
     T: io::Read,
 {
     pub fn from_reader(inner: T) -> Self {
-        Unpacker { inner }
+        Unpacker {
+            inner,
+            float_mode: FloatMode::default(),
+            peeked: None,
+        }
+    }
+}
+
+impl<R> Unpacker<R> {
+    // sets which encodings unpack_f32 and unpack_f64 accept
+    pub fn with_float_mode(mut self, mode: FloatMode) -> Self {
+        self.float_mode = mode;
+        self
     }
 }
 
+// the scan only stays valid while the read position doesn't move, so everything which moves
+// it resets the scan
 #[derive(Debug)]
-pub struct InnerBuf(io::Cursor<bytes::BytesMut>);
+pub struct InnerBuf(io::Cursor<bytes::BytesMut>, FrameScan);
 
 impl io::Write for InnerBuf {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
@@ -33,7 +51,7 @@ impl io::Write for InnerBuf {
 
 impl io::Read for InnerBuf {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        if self.0.get_ref().len() == 0 {
+        if !self.0.has_remaining() {
             use std::io::ErrorKind;
             return Err(io::Error::new(
                 ErrorKind::UnexpectedEof,
@@ -41,18 +59,110 @@ impl io::Read for InnerBuf {
             ));
         }
 
-        let len = cmp::min(self.0.get_ref().len(), buf.len());
+        let len = cmp::min(self.0.remaining(), buf.len());
         Buf::copy_to_slice(&mut self.0, &mut buf[0..len]);
+        self.1.reset();
         Ok(len)
     }
 }
 
 impl InnerBuf {
     pub fn new() -> Self {
-        Self(io::Cursor::new(BytesMut::new()))
+        Self(io::Cursor::new(BytesMut::new()), FrameScan::default())
+    }
+
+    pub fn feed(&mut self, buf: &[u8]) {
+        self.0.get_mut().extend_from_slice(buf);
+    }
+
+    // number of buffered bytes which have not been consumed yet
+    pub fn remaining(&self) -> usize {
+        self.0.remaining()
+    }
+
+    // runs `f` against the buffered bytes. returns None and rewinds when the buffer ends
+    // before `f` finishes, so the same message can be read again after more bytes are fed.
+    // otherwise the bytes `f` read are dropped from the buffer, even on error.
+    pub fn try_read<T, F>(&mut self, f: F) -> Option<Result<T, UnpackError>>
+    where
+        F: FnOnce(&mut Self) -> Result<T, UnpackError>,
+    {
+        let pos = self.0.position();
+        let ret = f(self);
+        self.settle(pos, ret)
+    }
+
+    // like try_read, but `f` only runs once a whole object is buffered. how far the object's
+    // headers were walked is kept between calls, so an object fed in many pieces is walked
+    // once instead of being decoded again after every piece
+    pub fn try_read_object<T, F>(&mut self, f: F) -> Option<Result<T, UnpackError>>
+    where
+        F: FnOnce(&mut Self) -> Result<T, UnpackError>,
+    {
+        match self.object_len() {
+            Ok(None) => None,
+            // a malformed header fails `f` as well, which drops the bytes it read
+            Ok(Some(_)) | Err(_) => self.try_read(f),
+        }
+    }
+
+    // length of the object at the read position, or None while it isn't fully buffered
+    fn object_len(&mut self) -> Result<Option<usize>, UnpackError> {
+        let pos = self.0.position() as usize;
+        self.1.frame_len(&self.0.get_ref()[pos..])
+    }
+
+    // drops everything buffered, for when the stream can't be framed past a malformed header
+    fn clear(&mut self) {
+        self.0.get_mut().clear();
+        self.0.set_position(0);
+        self.1.reset();
     }
 
-    pub fn as_ref(&self) -> &io::Cursor<bytes::BytesMut> {
+    // puts back the byte which was read last, for an unpacker handing back its peeked code
+    fn unread(&mut self, byte: u8) {
+        let pos = self.0.position();
+        if pos > 0 {
+            self.0.set_position(pos - 1);
+        } else {
+            // the byte was already dropped along with a completed read
+            let mut buf = BytesMut::with_capacity(self.0.get_ref().len() + 1);
+            buf.extend_from_slice(&[byte]);
+            buf.extend_from_slice(self.0.get_ref());
+            *self.0.get_mut() = buf;
+        }
+        self.1.reset();
+    }
+
+    fn settle<T>(
+        &mut self,
+        pos: u64,
+        ret: Result<T, UnpackError>,
+    ) -> Option<Result<T, UnpackError>> {
+        match ret {
+            Err(ref e) if matches!(e.root(), UnpackError::UnexpectedEof) => {
+                self.0.set_position(pos);
+                None
+            }
+            ret => {
+                let consumed = self.0.position() as usize;
+                self.0.get_mut().advance(consumed);
+                self.0.set_position(0);
+                self.1.reset();
+                Some(ret)
+            }
+        }
+    }
+}
+
+impl Default for InnerBuf {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AsRef<io::Cursor<bytes::BytesMut>> for InnerBuf {
+    fn as_ref(&self) -> &io::Cursor<bytes::BytesMut> {
         &self.0
     }
 }
@@ -61,10 +171,61 @@ impl Unpacker<InnerBuf> {
     pub fn new() -> Self {
         Unpacker {
             inner: InnerBuf::new(),
+            float_mode: FloatMode::default(),
+            peeked: None,
         }
     }
 }
 
+impl Unpacker<InnerBuf> {
+    pub fn feed(&mut self, buf: &[u8]) {
+        self.inner.feed(buf)
+    }
+
+    // like InnerBuf::try_read, but `f` reads through this unpacker
+    pub fn try_unpack<T, F>(&mut self, f: F) -> Option<Result<T, UnpackError>>
+    where
+        F: FnOnce(&mut Self) -> Result<T, UnpackError>,
+    {
+        let pos = self.inner.0.position();
+        let peeked = self.peeked;
+        let ret = f(self);
+        let ret = self.inner.settle(pos, ret);
+        if ret.is_none() {
+            self.peeked = peeked;
+        }
+        ret
+    }
+}
+
+// yields each complete object as its encoded bytes and None while more bytes are needed, so
+// unlike most iterators it goes on once more bytes are fed
+impl Iterator for Unpacker<InnerBuf> {
+    type Item = Result<Bytes, UnpackError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(code) = self.peeked.take() {
+            self.inner.unread(code);
+        }
+        match self.inner.object_len() {
+            Ok(None) => None,
+            Ok(Some(len)) => self
+                .inner
+                .try_read(|rd| unpack::unpack_data(rd, len).map(Bytes::from)),
+            Err(e) => {
+                self.inner.clear();
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+impl Default for Unpacker<InnerBuf> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<T> io::Write for Unpacker<T>
 where
     T: io::Write,
@@ -83,114 +244,153 @@ where
     T: io::Read,
 {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        self.inner.read(buf)
-    }
-}
-
-// impl<'a, R> Unpacker<R>
-// where
-//     R: io::Write,
-// {
-//     pub fn feed_slice(&mut self, slice: &[u8]) -> io::Result<usize> {
-//         self.inner.write(slice)
-//     }
-// }
-
-// impl<'a, R> Unpacker<R>
-// where
-//     R: io::Write + io::Read,
-// {
-//     pub fn feed_each<F>(&mut self, slice: &[u8], f: F) -> io::Result<usize>
-//     where
-//         F: Fn(Value) -> (),
-//     {
-//         let r = self.feed_slice(slice)?;
-//         for val in self.iter() {
-//             f(val)
-//         }
-
-//         Ok(r)
-//     }
-// }
+        self.reader().read(buf)
+    }
+}
+
+impl<R> Unpacker<R> {
+    fn reader(&mut self) -> Peeked<'_, R> {
+        Peeked {
+            code: &mut self.peeked,
+            inner: &mut self.inner,
+        }
+    }
+}
+
+// the inner reader with the peeked code put back in front of it
+struct Peeked<'a, R> {
+    code: &'a mut Option<u8>,
+    inner: &'a mut R,
+}
+
+impl<R: io::Read> io::Read for Peeked<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        match self.code.take() {
+            Some(code) => {
+                buf[0] = code;
+                Ok(1)
+            }
+            None => self.inner.read(buf),
+        }
+    }
+}
 
 impl<R: io::Read> MessageUnpacker for Unpacker<R> {
     fn unpack_u8(&mut self) -> Result<u8, UnpackError> {
-        unpack::unpack_u8(&mut self.inner)
+        unpack::unpack_uint(&mut self.reader())
     }
 
     fn unpack_u16(&mut self) -> Result<u16, UnpackError> {
-        unpack::unpack_u16(&mut self.inner)
+        unpack::unpack_uint(&mut self.reader())
     }
 
     fn unpack_u32(&mut self) -> Result<u32, UnpackError> {
-        unpack::unpack_u32(&mut self.inner)
+        unpack::unpack_uint(&mut self.reader())
     }
 
     fn unpack_u64(&mut self) -> Result<u64, UnpackError> {
-        unpack::unpack_u64(&mut self.inner)
+        unpack::unpack_uint(&mut self.reader())
     }
 
     fn unpack_i8(&mut self) -> Result<i8, UnpackError> {
-        unpack::unpack_i8(&mut self.inner)
+        unpack::unpack_int(&mut self.reader())
     }
 
     fn unpack_i16(&mut self) -> Result<i16, UnpackError> {
-        unpack::unpack_i16(&mut self.inner)
+        unpack::unpack_int(&mut self.reader())
     }
 
     fn unpack_i32(&mut self) -> Result<i32, UnpackError> {
-        unpack::unpack_i32(&mut self.inner)
+        unpack::unpack_int(&mut self.reader())
     }
 
     fn unpack_i64(&mut self) -> Result<i64, UnpackError> {
-        unpack::unpack_i64(&mut self.inner)
+        unpack::unpack_int(&mut self.reader())
+    }
+
+    fn unpack_f32(&mut self) -> Result<f32, UnpackError> {
+        let mode = self.float_mode;
+        unpack::unpack_f32_with(&mut self.reader(), mode)
+    }
+
+    fn unpack_f64(&mut self) -> Result<f64, UnpackError> {
+        let mode = self.float_mode;
+        unpack::unpack_f64_with(&mut self.reader(), mode)
+    }
+
+    fn peek_code(&mut self) -> Result<code::Code, UnpackError> {
+        let code = match self.peeked {
+            Some(code) => code,
+            None => unpack::primitive::read_data_u8(&mut self.inner)?,
+        };
+        self.peeked = Some(code);
+        Ok(code::Code::from(code))
     }
 
     fn unpack_nil<T>(&mut self) -> Result<Option<T>, UnpackError> {
-        unpack::unpack_nil(&mut self.inner)
+        unpack::unpack_nil(&mut self.reader())
     }
 
     fn unpack_bool(&mut self) -> Result<bool, UnpackError> {
-        unpack::unpack_bool(&mut self.inner)
+        unpack::unpack_bool(&mut self.reader())
     }
 
     fn unpack_string(&mut self) -> Result<String, UnpackError> {
-        unpack::unpack_str(&mut self.inner)
+        unpack::unpack_str(&mut self.reader())
     }
 
     fn unpack_str_header(&mut self) -> Result<usize, UnpackError> {
-        unpack::unpack_str_header(&mut self.inner)
+        unpack::unpack_str_header(&mut self.reader())
     }
 
     fn unpack_array_header(&mut self) -> Result<usize, UnpackError> {
-        unpack::unpack_array_header(&mut self.inner)
+        unpack::unpack_array_header(&mut self.reader())
     }
 
     fn unpack_map_header(&mut self) -> Result<usize, UnpackError> {
-        unpack::unpack_map_header(&mut self.inner)
+        unpack::unpack_map_header(&mut self.reader())
     }
 
     fn unpack_bin_header(&mut self) -> Result<usize, UnpackError> {
-        unpack::unpack_bin_header(&mut self.inner)
+        unpack::unpack_bin_header(&mut self.reader())
     }
 
     fn unpack_fixext1(&mut self) -> Result<(i8, u8), UnpackError> {
-        unpack::unpack_fixext1(&mut self.inner)
+        unpack::unpack_fixext1(&mut self.reader())
     }
 
     fn unpack_fixext2(&mut self) -> Result<(i8, [u8; 2]), UnpackError> {
-        unpack::unpack_fixext2(&mut self.inner)
+        unpack::unpack_fixext2(&mut self.reader())
     }
 
     fn unpack_fixext4(&mut self) -> Result<(i8, [u8; 4]), UnpackError> {
-        unpack::unpack_fixext4(&mut self.inner)
+        unpack::unpack_fixext4(&mut self.reader())
     }
 
     fn unpack_fixext8(&mut self) -> Result<(i8, [u8; 8]), UnpackError> {
-        unpack::unpack_fixext8(&mut self.inner)
+        unpack::unpack_fixext8(&mut self.reader())
     }
 
     fn unpack_fixext16(&mut self) -> Result<(i8, [u8; 16]), UnpackError> {
-        unpack::unpack_fixext16(&mut self.inner)
+        unpack::unpack_fixext16(&mut self.reader())
+    }
+
+    fn unpack_ext_header(&mut self) -> Result<(u32, i8), UnpackError> {
+        unpack::unpack_ext_header(&mut self.reader())
+    }
+
+    fn read_payload_into(&mut self, buf: &mut [u8]) -> Result<(), UnpackError> {
+        io::Read::read_exact(&mut self.reader(), buf).map_err(UnpackError::from)
+    }
+
+    fn read_payload(&mut self, len: usize) -> Result<Vec<u8>, UnpackError> {
+        unpack::unpack_data(&mut self.reader(), len)
+    }
+
+    fn skip_value(&mut self) -> Result<(), UnpackError> {
+        unpack::skip_value(&mut self.reader())
     }
 }
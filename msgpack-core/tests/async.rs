@@ -0,0 +1,86 @@
+#![cfg(feature = "async")]
+
+use msgpack::{AsyncPacker, AsyncUnpacker, UnpackError};
+
+#[tokio::test]
+async fn async_pack_unpack() {
+    // a tiny pipe so every object is split across several reads
+    let (wr, rd) = tokio::io::duplex(3);
+
+    let write = async move {
+        let mut packer = AsyncPacker::new(wr);
+        packer.pack_nil().await.unwrap();
+        packer.pack_bool(true).await.unwrap();
+        packer.pack_uint(70000).await.unwrap();
+        packer.pack_int(-1).await.unwrap();
+        packer.pack_f64(0.5).await.unwrap();
+        packer.pack_str("hello world").await.unwrap();
+        packer.pack_bin(&[1, 2, 3]).await.unwrap();
+        packer.pack_array_header(2).await.unwrap();
+        packer.pack_u8(1).await.unwrap();
+        packer.pack_map_header(1).await.unwrap();
+        packer.pack_str("k").await.unwrap();
+        packer.pack_i16(-300).await.unwrap();
+        packer.pack_ext_header(5, 1).await.unwrap();
+        packer.write_payload(&[9]).await.unwrap();
+        packer.flush().await.unwrap();
+    };
+
+    let read = async move {
+        let mut unpacker = AsyncUnpacker::new(rd);
+        assert_eq!(unpacker.unpack_nil::<u8>().await.unwrap(), None);
+        assert!(unpacker.unpack_bool().await.unwrap());
+        assert_eq!(unpacker.unpack_u32().await.unwrap(), 70000);
+        assert_eq!(unpacker.unpack_i8().await.unwrap(), -1);
+        assert_eq!(unpacker.unpack_f64().await.unwrap(), 0.5);
+        assert_eq!(unpacker.unpack_string().await.unwrap(), "hello world");
+        assert_eq!(unpacker.unpack_bin().await.unwrap(), vec![1, 2, 3]);
+        assert_eq!(unpacker.unpack_array_header().await.unwrap(), 2);
+        assert_eq!(unpacker.unpack_u8().await.unwrap(), 1);
+        assert_eq!(unpacker.unpack_map_header().await.unwrap(), 1);
+        assert_eq!(unpacker.unpack_string().await.unwrap(), "k");
+        assert_eq!(unpacker.unpack_i16().await.unwrap(), -300);
+        assert_eq!(unpacker.unpack_fixext1().await.unwrap(), (5, 9));
+        assert!(unpacker.fill_buf().await.unwrap().is_empty());
+    };
+
+    tokio::join!(write, read);
+}
+
+#[tokio::test]
+async fn async_unpack_raw() {
+    let (wr, rd) = tokio::io::duplex(2);
+
+    let write = async move {
+        let mut packer = AsyncPacker::new(wr);
+        packer.pack_array_header(2).await.unwrap();
+        packer.pack_str("ab").await.unwrap();
+        packer.pack_nil().await.unwrap();
+        packer.pack_u8(7).await.unwrap();
+    };
+
+    let read = async move {
+        let mut unpacker = AsyncUnpacker::new(rd);
+        assert_eq!(
+            &unpacker.unpack_raw().await.unwrap()[..],
+            &[0x92, 0xa2, 0x61, 0x62, 0xc0]
+        );
+        assert_eq!(unpacker.unpack_u8().await.unwrap(), 7);
+    };
+
+    tokio::join!(write, read);
+}
+
+#[tokio::test]
+async fn async_unpack_errors() {
+    // a type mismatch consumes the object, truncated input is UnexpectedEof
+    let mut unpacker = AsyncUnpacker::new(&[0xc3, 0xa3, 0x61][..]);
+    match unpacker.unpack_u8().await {
+        Err(UnpackError::TypeMismatch(..)) => {}
+        v => panic!("unexpected {:?}", v),
+    }
+    match unpacker.unpack_string().await {
+        Err(UnpackError::UnexpectedEof) => {}
+        v => panic!("unexpected {:?}", v),
+    }
+}
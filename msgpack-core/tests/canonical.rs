@@ -0,0 +1,149 @@
+use msgpack::{is_canonical, is_canonical_with, CanonicalOptions, PackError};
+
+const SHRINK: CanonicalOptions = CanonicalOptions {
+    shrink_floats: true,
+};
+
+#[test]
+fn canonical_scalars() {
+    assert!(is_canonical(&[0xc0]));
+    assert!(is_canonical(&[0x7f]));
+    assert!(is_canonical(&[0xcc, 0x80]));
+    assert!(is_canonical(&[0xe0]));
+    assert!(is_canonical(&[0xd0, 0xdf]));
+    assert!(is_canonical(&[0xa1, 0x61]));
+    assert!(is_canonical(&[0xc4, 0x00]));
+    assert!(is_canonical(&[0xd4, 0x01, 0x00]));
+    assert!(is_canonical(&[0xc7, 0x03, 0x01, 0x00, 0x00, 0x00]));
+}
+
+#[test]
+fn non_minimal_encodings() {
+    // 1 as uint8, 127 as uint16, -1 as int8, 1 as int8
+    assert!(!is_canonical(&[0xcc, 0x01]));
+    assert!(!is_canonical(&[0xcd, 0x00, 0x7f]));
+    assert!(!is_canonical(&[0xd0, 0xff]));
+    assert!(!is_canonical(&[0xd0, 0x01]));
+    // "a" as str8, an empty array as array16, an empty map as map32
+    assert!(!is_canonical(&[0xd9, 0x01, 0x61]));
+    assert!(!is_canonical(&[0xdc, 0x00, 0x00]));
+    assert!(!is_canonical(&[0xdf, 0x00, 0x00, 0x00, 0x00]));
+    // bin8 of 1 byte as bin16
+    assert!(!is_canonical(&[0xc5, 0x00, 0x01, 0x00]));
+    // ext of 1 byte as ext8 instead of fixext1
+    assert!(!is_canonical(&[0xc7, 0x01, 0x01, 0x00]));
+}
+
+#[test]
+fn canonical_timestamps() {
+    let mut buf = vec![];
+    msgpack::pack_timestamp(&mut buf, 1, 0).unwrap();
+    assert!(is_canonical(&buf));
+
+    for (sec, nsec) in [(1 << 32, 0), (1, 1), (-1, 0), (1 << 34, 5)] {
+        let mut buf = vec![];
+        msgpack::pack_timestamp(&mut buf, sec, nsec).unwrap();
+        assert!(is_canonical(&buf), "{} {}", sec, nsec);
+    }
+
+    // 1 second as timestamp64 and as timestamp96
+    let mut buf = vec![];
+    msgpack::pack_timestamp64(&mut buf, 1, 0).unwrap();
+    assert!(!is_canonical(&buf));
+    let mut buf = vec![];
+    msgpack::pack_timestamp96(&mut buf, 1, 0).unwrap();
+    assert!(!is_canonical(&buf));
+}
+
+#[test]
+fn pack_timestamp_over_u32_seconds() {
+    let mut buf = vec![];
+    msgpack::pack_timestamp(&mut buf, 1 << 32, 0).unwrap();
+    assert_eq!(
+        buf,
+        &[0xd7, 0xff, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00]
+    );
+}
+
+#[test]
+fn canonical_floats() {
+    let mut buf = vec![];
+    msgpack::pack_f64_canonical(&mut buf, f64::NAN.copysign(-1.0), Default::default()).unwrap();
+    assert_eq!(buf, &[0xcb, 0x7f, 0xf8, 0, 0, 0, 0, 0, 0]);
+    assert!(is_canonical(&buf));
+    assert!(!is_canonical_with(&buf, SHRINK));
+
+    let mut buf = vec![];
+    msgpack::pack_f64_canonical(&mut buf, 1.5, SHRINK).unwrap();
+    assert_eq!(buf, &[0xca, 0x3f, 0xc0, 0x00, 0x00]);
+    assert!(is_canonical_with(&buf, SHRINK));
+
+    let mut buf = vec![];
+    msgpack::pack_f64_canonical(&mut buf, 0.1, SHRINK).unwrap();
+    assert_eq!(buf[0], 0xcb);
+    assert!(is_canonical_with(&buf, SHRINK));
+
+    // -0.0 keeps its sign when shrunk
+    let mut buf = vec![];
+    msgpack::pack_f64_canonical(&mut buf, -0.0, SHRINK).unwrap();
+    assert_eq!(buf, &[0xca, 0x80, 0x00, 0x00, 0x00]);
+
+    let mut buf = vec![];
+    msgpack::pack_f32_canonical(&mut buf, f32::from_bits(0x7fc0_0001)).unwrap();
+    assert_eq!(buf, &[0xca, 0x7f, 0xc0, 0x00, 0x00]);
+    assert!(!is_canonical(&[0xca, 0x7f, 0xc0, 0x00, 0x01]));
+    assert!(!is_canonical_with(
+        &[0xcb, 0x3f, 0xf8, 0, 0, 0, 0, 0, 0],
+        SHRINK
+    ));
+}
+
+#[test]
+fn map_key_order() {
+    // {1: nil, "a": nil}
+    assert!(is_canonical(&[0x82, 0x01, 0xc0, 0xa1, 0x61, 0xc0]));
+    assert!(!is_canonical(&[0x82, 0xa1, 0x61, 0xc0, 0x01, 0xc0]));
+    // duplicate keys
+    assert!(!is_canonical(&[0x82, 0x01, 0xc0, 0x01, 0xc0]));
+    // nested maps are checked on their own
+    assert!(is_canonical(&[0x81, 0x01, 0x82, 0x01, 0xc0, 0x02, 0xc0]));
+    assert!(!is_canonical(&[0x81, 0x01, 0x82, 0x02, 0xc0, 0x01, 0xc0]));
+    // map keys which are containers compare by all of their bytes
+    assert!(is_canonical(&[0x82, 0x91, 0x01, 0xc0, 0x91, 0x02, 0xc0]));
+    assert!(!is_canonical(&[0x82, 0x91, 0x02, 0xc0, 0x91, 0x01, 0xc0]));
+}
+
+#[test]
+fn exactly_one_object() {
+    assert!(!is_canonical(&[]));
+    assert!(!is_canonical(&[0xc0, 0xc0]));
+    assert!(!is_canonical(&[0x92, 0x01]));
+    assert!(!is_canonical(&[0xa2, 0x61]));
+    assert!(!is_canonical(&[0xc1]));
+    assert!(!is_canonical(&[0xdd, 0xff, 0xff, 0xff, 0xff]));
+}
+
+#[test]
+fn deep_nesting() {
+    let mut buf = vec![0x91; 100_000];
+    buf.push(0xc0);
+    assert!(is_canonical(&buf));
+}
+
+#[test]
+fn pack_sorted_map() {
+    let mut buf = vec![];
+    let entries = vec![
+        (vec![0xa1, 0x62], vec![0x02]),
+        (vec![0xa1, 0x61], vec![0x01]),
+    ];
+    msgpack::pack_sorted_map(&mut buf, entries).unwrap();
+    assert_eq!(buf, &[0x82, 0xa1, 0x61, 0x01, 0xa1, 0x62, 0x02]);
+    assert!(is_canonical(&buf));
+
+    let entries = vec![(vec![0x01], vec![0x01]), (vec![0x01], vec![0x02])];
+    match msgpack::pack_sorted_map(&mut vec![], entries) {
+        Err(PackError::DuplicateMapKey) => {}
+        v => panic!("unexpected {:?}", v),
+    }
+}
@@ -0,0 +1,64 @@
+#![cfg(feature = "codec")]
+
+use bytes::{Bytes, BytesMut};
+use msgpack::{DecodeLimits, Limit, MsgPackCodec, UnpackError};
+use tokio_util::codec::{Decoder, Encoder};
+
+// [1, {"a": nil}] followed by "bc"
+const FRAMES: [u8; 9] = [0x92, 0x01, 0x81, 0xa1, 0x61, 0xc0, 0xa2, 0x62, 0x63];
+
+#[test]
+fn codec_frames() {
+    let mut codec = MsgPackCodec::<Bytes>::new();
+    let mut buf = BytesMut::new();
+    let mut frames = vec![];
+
+    // fed one byte at a time, frames come out only once complete
+    for b in FRAMES.iter() {
+        buf.extend_from_slice(&[*b]);
+        while let Some(frame) = codec.decode(&mut buf).unwrap() {
+            frames.push(frame);
+        }
+    }
+
+    assert_eq!(frames, vec![&FRAMES[..6], &FRAMES[6..]]);
+    assert!(buf.is_empty());
+    assert!(codec.decode_eof(&mut buf).unwrap().is_none());
+
+    // a truncated frame at the end of the stream is an error
+    buf.extend_from_slice(&[0x92, 0x01]);
+    assert!(codec.decode_eof(&mut buf).is_err());
+}
+
+#[test]
+fn codec_encode_raw() {
+    let mut codec = MsgPackCodec::<Bytes>::new();
+    let mut buf = BytesMut::new();
+    codec.encode(&FRAMES[..6], &mut buf).unwrap();
+    codec
+        .encode(Bytes::from_static(&FRAMES[6..]), &mut buf)
+        .unwrap();
+    assert_eq!(&buf[..], &FRAMES[..]);
+}
+
+#[test]
+fn codec_errors() {
+    let mut codec = MsgPackCodec::<Bytes>::new();
+    let mut buf = BytesMut::from(&[0xc1][..]);
+    match codec.decode(&mut buf) {
+        Err(UnpackError::ReservedCode(0xc1)) => {}
+        v => panic!("unexpected {:?}", v),
+    }
+
+    // str32 claiming 4 GiB is cut off once the buffer reaches the frame limit
+    let mut codec = MsgPackCodec::<Bytes>::new()
+        .with_limits(DecodeLimits::default())
+        .with_max_frame_len(16);
+    let mut buf = BytesMut::from(&[0xdb, 0xff, 0xff, 0xff, 0xff][..]);
+    assert!(codec.decode(&mut buf).unwrap().is_none());
+    buf.extend_from_slice(&[0; 11]);
+    match codec.decode(&mut buf) {
+        Err(UnpackError::LimitExceeded(Limit::FrameLength, 16)) => {}
+        v => panic!("unexpected {:?}", v),
+    }
+}
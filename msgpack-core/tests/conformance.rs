@@ -0,0 +1,88 @@
+use serde_json::Value as Json;
+use std::io;
+
+#[path = "../../testdata/conformance.rs"]
+mod suite;
+
+use suite::from_hex;
+
+fn check<R: io::Read>(ctx: &str, reader: &mut R, expected: &Json) {
+    match expected {
+        Json::Null => assert_eq!(
+            msgpack::unpack_nil::<u8, _>(reader).expect(ctx),
+            None,
+            "{}",
+            ctx
+        ),
+        Json::Bool(v) => assert_eq!(msgpack::unpack_bool(reader).expect(ctx), *v, "{}", ctx),
+        Json::Number(v) => {
+            if let Some(v) = v.as_u64() {
+                let actual = msgpack::unpack_uint::<u64, _>(reader).expect(ctx);
+                assert_eq!(actual, v, "{}", ctx);
+            } else if let Some(v) = v.as_i64() {
+                let actual = msgpack::unpack_int::<i64, _>(reader).expect(ctx);
+                assert_eq!(actual, v, "{}", ctx);
+            } else {
+                let mode = msgpack::FloatMode {
+                    accept_int: false,
+                    allow_precision_loss: false,
+                    strict: false,
+                };
+                let actual = msgpack::unpack_f64_with(reader, mode).expect(ctx);
+                assert_eq!(actual, v.as_f64().unwrap(), "{}", ctx);
+            }
+        }
+        Json::String(v) => assert_eq!(&msgpack::unpack_str(reader).expect(ctx), v, "{}", ctx),
+        Json::Array(v) => {
+            let len = msgpack::unpack_array_header(reader).expect(ctx);
+            assert_eq!(len, v.len(), "{}", ctx);
+            for item in v {
+                check(ctx, reader, item);
+            }
+        }
+        Json::Object(v) => {
+            let len = msgpack::unpack_map_header(reader).expect(ctx);
+            assert_eq!(len, v.len(), "{}", ctx);
+            for (key, value) in v {
+                assert_eq!(&msgpack::unpack_str(reader).expect(ctx), key, "{}", ctx);
+                check(ctx, reader, value);
+            }
+        }
+    }
+}
+
+fn check_case(ctx: &str, case: &Json, bytes: &[u8]) {
+    let mut reader = io::Cursor::new(bytes);
+
+    if let Some(v) = case.get("binary") {
+        let expected = from_hex(v.as_str().unwrap());
+        let actual = msgpack::unpack_bin(&mut reader).expect(ctx);
+        assert_eq!(actual, expected, "{}", ctx);
+    } else if let Some(v) = case.get("ext") {
+        let ty = v[0].as_i64().unwrap() as i8;
+        let data = from_hex(v[1].as_str().unwrap());
+        let (len, actual_ty) = msgpack::unpack_ext_header(&mut reader).expect(ctx);
+        assert_eq!(actual_ty, ty, "{}", ctx);
+        assert_eq!(len as usize, data.len(), "{}", ctx);
+        let actual = msgpack::unpack_data(&mut reader, len as usize).expect(ctx);
+        assert_eq!(actual, data, "{}", ctx);
+    } else if let Some(v) = case.get("timestamp") {
+        let expected =
+            msgpack::Timestamp::new(v[0].as_i64().unwrap(), v[1].as_u64().unwrap() as u32);
+        let actual = msgpack::unpack_timestamp(&mut reader).ok();
+        assert_eq!(actual, expected, "{}", ctx);
+    } else {
+        let expected = ["nil", "bool", "number", "string", "array", "map"]
+            .iter()
+            .find_map(|k| case.get(k))
+            .unwrap();
+        check(ctx, &mut reader, expected);
+    }
+
+    assert_eq!(reader.position() as usize, bytes.len(), "{}", ctx);
+}
+
+#[test]
+fn conformance() {
+    suite::for_each_case(check_case);
+}
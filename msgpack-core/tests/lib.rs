@@ -1,3 +1,5 @@
+#![allow(clippy::bool_assert_comparison)]
+
 use msgpack::{self, MessagePacker, MessageUnpacker};
 use std::io;
 
@@ -37,3 +39,48 @@ fn packer_unpacker() {
     assert_eq!(unpacker.unpack_u8().unwrap(), 1);
     assert_eq!(unpacker.unpack_string().unwrap(), "s".to_owned());
 }
+
+#[test]
+fn packer_unpacker_any_width() {
+    let mut buf = vec![];
+    let mut packer = msgpack::Packer::new(&mut buf);
+
+    packer.pack_from_u64(1).unwrap();
+    packer.pack_from_u64(300).unwrap();
+    packer.pack_from_i64(-1).unwrap();
+    packer.pack_from_i64(-200).unwrap();
+    packer.pack_from_u32(u32::MAX).unwrap();
+
+    let mut reader = io::Cursor::new(buf);
+    let mut unpacker = msgpack::Unpacker::from_reader(&mut reader);
+
+    assert_eq!(unpacker.unpack_u64().unwrap(), 1);
+    assert_eq!(unpacker.unpack_u32().unwrap(), 300);
+    assert_eq!(unpacker.unpack_i64().unwrap(), -1);
+    assert_eq!(unpacker.unpack_i16().unwrap(), -200);
+    assert!(unpacker.unpack_u16().is_err());
+}
+
+#[test]
+fn packer_unpacker_floats() {
+    let mut buf = vec![];
+    let mut packer = msgpack::Packer::new(&mut buf);
+
+    packer.pack_f32(1.5).unwrap();
+    packer.pack_f64(0.25).unwrap();
+    packer.pack_from_u64(3).unwrap();
+    packer.pack_from_i64(-7).unwrap();
+
+    let mut reader = io::Cursor::new(buf);
+    let mut unpacker =
+        msgpack::Unpacker::from_reader(&mut reader).with_float_mode(msgpack::FloatMode {
+            accept_int: true,
+            allow_precision_loss: false,
+            strict: false,
+        });
+
+    assert_eq!(unpacker.unpack_f64().unwrap(), 1.5);
+    assert_eq!(unpacker.unpack_f32().unwrap(), 0.25);
+    assert_eq!(unpacker.unpack_f32().unwrap(), 3.0);
+    assert_eq!(unpacker.unpack_f64().unwrap(), -7.0);
+}
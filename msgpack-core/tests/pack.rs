@@ -1,3 +1,5 @@
+#![allow(clippy::single_component_path_imports, clippy::unnecessary_cast)]
+
 use msgpack;
 
 #[test]
@@ -440,3 +442,20 @@ fn pack_timestamp() {
         ]
     );
 }
+
+#[test]
+fn pack_from_positive_signed() {
+    let mut writer = vec![];
+    msgpack::pack_from_i16(&mut writer, 300).unwrap();
+    msgpack::pack_from_i32(&mut writer, 70000).unwrap();
+    msgpack::pack_from_i64(&mut writer, i64::MAX).unwrap();
+
+    assert_eq!(
+        writer,
+        &[
+            0xcd, 0x01, 0x2c, // 300
+            0xce, 0x00, 0x01, 0x11, 0x70, // 70000
+            0xcf, 0x7f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, // i64::MAX
+        ]
+    );
+}
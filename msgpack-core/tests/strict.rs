@@ -0,0 +1,119 @@
+use msgpack::code::Code;
+use msgpack::UnpackError;
+
+#[test]
+fn strict_ints() {
+    // 1 as uint8, -1 as int8 and 5 as int16
+    let err = msgpack::unpack_uint_with::<u8, _>(&mut &[0xcc, 0x01][..], true).unwrap_err();
+    assert!(matches!(err, UnpackError::NotMinimal(Code::Uint8)));
+    let err = msgpack::unpack_int_with::<i8, _>(&mut &[0xd0, 0xff][..], true).unwrap_err();
+    assert!(matches!(err, UnpackError::NotMinimal(Code::Int8)));
+    let err = msgpack::unpack_int_with::<i64, _>(&mut &[0xd1, 0x00, 0x05][..], true).unwrap_err();
+    assert!(matches!(err, UnpackError::NotMinimal(Code::Int16)));
+
+    // the lenient readers still take them
+    assert_eq!(
+        msgpack::unpack_uint::<u8, _>(&mut &[0xcc, 0x01][..]).unwrap(),
+        1
+    );
+    assert_eq!(
+        msgpack::unpack_int::<i8, _>(&mut &[0xd0, 0xff][..]).unwrap(),
+        -1
+    );
+
+    assert_eq!(
+        msgpack::unpack_uint_with::<u8, _>(&mut &[0xcc, 0x80][..], true).unwrap(),
+        128
+    );
+    assert_eq!(
+        msgpack::unpack_int_with::<i64, _>(&mut &[0xd1, 0xff, 0x7f][..], true).unwrap(),
+        -129
+    );
+    assert_eq!(
+        msgpack::unpack_int_with::<i64, _>(&mut &[0xe0][..], true).unwrap(),
+        -32
+    );
+}
+
+#[test]
+fn strict_headers() {
+    let err = msgpack::unpack_str_header_with(&mut &[0xd9, 0x01][..], true).unwrap_err();
+    assert!(matches!(err, UnpackError::NotMinimal(Code::Str8)));
+    assert_eq!(
+        msgpack::unpack_str_header(&mut &[0xd9, 0x01][..]).unwrap(),
+        1
+    );
+    assert_eq!(
+        msgpack::unpack_str_header_with(&mut &[0xd9, 0x20][..], true).unwrap(),
+        32
+    );
+
+    let err = msgpack::unpack_bin_header_with(&mut &[0xc5, 0x00, 0xff][..], true).unwrap_err();
+    assert!(matches!(err, UnpackError::NotMinimal(Code::Bin16)));
+    assert_eq!(
+        msgpack::unpack_bin_header_with(&mut &[0xc4, 0x00][..], true).unwrap(),
+        0
+    );
+
+    let err = msgpack::unpack_array_header_with(&mut &[0xdc, 0x00, 0x0f][..], true).unwrap_err();
+    assert!(matches!(err, UnpackError::NotMinimal(Code::Array16)));
+    let err = msgpack::unpack_map_header_with(&mut &[0xdf, 0x00, 0x00, 0xff, 0xff][..], true)
+        .unwrap_err();
+    assert!(matches!(err, UnpackError::NotMinimal(Code::Map32)));
+    assert_eq!(
+        msgpack::unpack_map_header_with(&mut &[0xde, 0x00, 0x10][..], true).unwrap(),
+        16
+    );
+
+    // a 4 byte body fits fixext4
+    let err = msgpack::unpack_ext_header_with(&mut &[0xc7, 0x04, 0x01][..], true).unwrap_err();
+    assert!(matches!(err, UnpackError::NotMinimal(Code::Ext8)));
+    assert_eq!(
+        msgpack::unpack_ext_header_with(&mut &[0xc7, 0x03, 0x01][..], true).unwrap(),
+        (3, 1)
+    );
+}
+
+#[test]
+fn strict_floats() {
+    let mode = msgpack::FloatMode {
+        accept_int: true,
+        strict: true,
+        ..msgpack::FloatMode::default()
+    };
+    let err = msgpack::unpack_f64_with(&mut &[0xcc, 0x01][..], mode).unwrap_err();
+    assert!(matches!(err, UnpackError::NotMinimal(Code::Uint8)));
+    let err = msgpack::unpack_f32_with(&mut &[0xd0, 0xff][..], mode).unwrap_err();
+    assert!(matches!(err, UnpackError::NotMinimal(Code::Int8)));
+    assert_eq!(
+        msgpack::unpack_f64_with(&mut &[0xcc, 0x80][..], mode).unwrap(),
+        128.0
+    );
+}
+
+#[test]
+fn limit_tracker_checks_only_in_strict_mode() {
+    let lenient = msgpack::LimitTracker::new(msgpack::DecodeLimits::default());
+    assert!(!lenient.strict());
+    lenient.check_int(&Code::Uint16, 1).unwrap();
+    lenient.check_len(&Code::Str16, 1).unwrap();
+    lenient.check_utf8(&[0xff]).unwrap();
+
+    let strict = msgpack::LimitTracker::new(msgpack::DecodeLimits {
+        strict: true,
+        ..msgpack::DecodeLimits::default()
+    });
+    assert!(matches!(
+        strict.check_int(&Code::Uint16, 1),
+        Err(UnpackError::NotMinimal(Code::Uint16))
+    ));
+    assert!(matches!(
+        strict.check_len(&Code::Str16, 1),
+        Err(UnpackError::NotMinimal(Code::Str16))
+    ));
+    assert!(matches!(
+        strict.check_utf8(&[0xff]),
+        Err(UnpackError::InvalidUtf8(_))
+    ));
+    strict.check_int(&Code::Uint16, 256).unwrap();
+}
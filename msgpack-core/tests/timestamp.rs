@@ -0,0 +1,76 @@
+use msgpack::{Timestamp, TimestampRangeError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[test]
+fn timestamp_new() {
+    assert_eq!(Timestamp::new(0, 0), Some(Timestamp::UNIX_EPOCH));
+    let t = Timestamp::new(-1, 999_999_999).unwrap();
+    assert_eq!((t.sec(), t.nsec()), (-1, 999_999_999));
+    assert_eq!(Timestamp::new(0, 1_000_000_000), None);
+}
+
+#[test]
+fn timestamp_duration() {
+    let t = Timestamp::try_from(Duration::new(1514862245, 678901234)).unwrap();
+    assert_eq!(t, Timestamp::new(1514862245, 678901234).unwrap());
+    assert_eq!(
+        Duration::try_from(t).unwrap(),
+        Duration::new(1514862245, 678901234)
+    );
+
+    assert_eq!(
+        Duration::try_from(Timestamp::new(-1, 0).unwrap()),
+        Err(TimestampRangeError)
+    );
+    assert_eq!(
+        Timestamp::try_from(Duration::new(u64::MAX, 0)),
+        Err(TimestampRangeError)
+    );
+}
+
+#[test]
+fn timestamp_system_time() {
+    let time = UNIX_EPOCH + Duration::new(1514862245, 678901234);
+    let t = Timestamp::try_from(time).unwrap();
+    assert_eq!(t, Timestamp::new(1514862245, 678901234).unwrap());
+    assert_eq!(SystemTime::try_from(t).unwrap(), time);
+
+    // before the epoch nsec still counts forward
+    let time = UNIX_EPOCH - Duration::from_nanos(1);
+    let t = Timestamp::try_from(time).unwrap();
+    assert_eq!(t, Timestamp::new(-1, 999_999_999).unwrap());
+    assert_eq!(SystemTime::try_from(t).unwrap(), time);
+
+    let time = UNIX_EPOCH - Duration::from_secs(2);
+    let t = Timestamp::try_from(time).unwrap();
+    assert_eq!(t, Timestamp::new(-2, 0).unwrap());
+    assert_eq!(SystemTime::try_from(t).unwrap(), time);
+}
+
+#[cfg(feature = "chrono")]
+#[test]
+fn timestamp_chrono() {
+    use chrono::{DateTime, TimeZone, Utc};
+
+    let time = Utc.timestamp_opt(1514862245, 678901234).unwrap();
+    let t = Timestamp::from(time);
+    assert_eq!(t, Timestamp::new(1514862245, 678901234).unwrap());
+    assert_eq!(DateTime::<Utc>::try_from(t).unwrap(), time);
+
+    let t = Timestamp::new(i64::MAX, 0).unwrap();
+    assert_eq!(DateTime::<Utc>::try_from(t), Err(TimestampRangeError));
+}
+
+#[cfg(feature = "time")]
+#[test]
+fn timestamp_time() {
+    use time::OffsetDateTime;
+
+    let time = OffsetDateTime::from_unix_timestamp_nanos(-1).unwrap();
+    let t = Timestamp::from(time);
+    assert_eq!(t, Timestamp::new(-1, 999_999_999).unwrap());
+    assert_eq!(OffsetDateTime::try_from(t).unwrap(), time);
+
+    let t = Timestamp::new(i64::MAX, 0).unwrap();
+    assert_eq!(OffsetDateTime::try_from(t), Err(TimestampRangeError));
+}
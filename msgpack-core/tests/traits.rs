@@ -0,0 +1,242 @@
+use msgpack::{MessageUnpacker, Pack, Packer, Unpack, UnpackError, Unpacker};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Debug;
+use std::io::Read;
+use std::rc::Rc;
+use std::sync::Arc;
+
+fn packed<T: Pack + ?Sized>(v: &T) -> Vec<u8> {
+    let mut packer = Packer::new(vec![]);
+    v.pack(&mut packer).unwrap();
+    packer.into_inner()
+}
+
+fn unpacked<T: Unpack>(buf: &[u8]) -> Result<T, UnpackError> {
+    T::unpack(&mut Unpacker::from_reader(buf))
+}
+
+fn round_trip<T: Pack + Unpack + PartialEq + Debug>(v: T) {
+    assert_eq!(unpacked::<T>(&packed(&v)).unwrap(), v);
+}
+
+#[test]
+fn primitives() {
+    round_trip(1u8);
+    round_trip(u16::MAX);
+    round_trip(u32::MAX);
+    round_trip(u64::MAX);
+    round_trip(i8::MIN);
+    round_trip(-300i16);
+    round_trip(i32::MIN);
+    round_trip(i64::MIN);
+    round_trip(usize::MAX);
+    round_trip(isize::MIN);
+    round_trip(1.5f32);
+    round_trip(0.1f64);
+    round_trip(true);
+    round_trip("s".to_owned());
+
+    // integers take their smallest encoding and read back at any width
+    assert_eq!(packed(&300u64), [0xcd, 0x01, 0x2c]);
+    assert_eq!(packed(&-1i64), [0xff]);
+    assert_eq!(unpacked::<u16>(&packed(&300u64)).unwrap(), 300);
+    assert!(unpacked::<u8>(&packed(&300u64)).is_err());
+    assert_eq!(packed("s"), [0xa1, b's']);
+}
+
+#[test]
+fn sequences() {
+    round_trip(vec![1u8, 2, 3]);
+    round_trip(Vec::<String>::new());
+    round_trip([[1i8, -1], [2, -2]]);
+    round_trip((1u8,));
+    round_trip((1u8, "a".to_owned(), -1i32, 0.5f64));
+    round_trip((1u8, 2u8, 3u8, 4u8, 5u8, 6u8, 7u8, 8u8));
+
+    assert_eq!(packed(&(1u8, true)), [0x92, 0x01, 0xc3]);
+    assert_eq!(packed(&[1u8, 2]), packed(&vec![1u8, 2]));
+    assert_eq!(
+        packed(&["a", "b"][..]),
+        packed(&vec!["a".to_owned(), "b".to_owned()])
+    );
+}
+
+#[test]
+fn fixed_length() {
+    let e = unpacked::<(u8, u8)>(&packed(&[1u8, 2, 3])).unwrap_err();
+    assert!(matches!(e, UnpackError::LengthMismatch(2, 3)));
+    let e = unpacked::<[u8; 4]>(&packed(&[1u8, 2, 3])).unwrap_err();
+    assert!(matches!(e, UnpackError::LengthMismatch(4, 3)));
+}
+
+#[test]
+fn element_path() {
+    let e = unpacked::<Vec<(u8, u8)>>(&packed(&vec![(1u16, 2u16), (3, 300)])).unwrap_err();
+    assert_eq!(e.path().unwrap(), "$[1][1]");
+    assert!(matches!(e.root(), UnpackError::OutOfRange(..)));
+}
+
+#[test]
+fn options() {
+    round_trip(Some(1u8));
+    round_trip(None::<u8>);
+    round_trip(vec![Some("a".to_owned()), None, Some("b".to_owned())]);
+    round_trip(Some(Some(1u8)));
+
+    assert_eq!(packed(&None::<u8>), [0xc0]);
+    assert!(unpacked::<Option<u8>>(&packed(&"a")).is_err());
+}
+
+#[test]
+fn peek_code_is_read_again() {
+    let buf = packed(&(Some(1u8), 2u8));
+    let mut unpacker = Unpacker::from_reader(&buf[..]);
+    assert_eq!(unpacker.unpack_array_header().unwrap(), 2);
+    assert_eq!(
+        unpacker.peek_code().unwrap(),
+        msgpack::code::Code::PosInt(1)
+    );
+    assert_eq!(
+        unpacker.peek_code().unwrap(),
+        msgpack::code::Code::PosInt(1)
+    );
+
+    // reading the unpacker directly starts at the peeked code too
+    let mut rest = vec![];
+    unpacker.read_to_end(&mut rest).unwrap();
+    assert_eq!(rest, [0x01, 0x02]);
+}
+
+#[test]
+fn peek_code_is_rewound() {
+    let buf = packed(&vec![Some("abc".to_owned())]);
+    let mut unpacker = Unpacker::new();
+    unpacker.feed(&buf[..3]);
+    assert!(unpacker.try_unpack(Vec::<Option<String>>::unpack).is_none());
+    unpacker.feed(&buf[3..]);
+    assert_eq!(
+        unpacker
+            .try_unpack(Vec::<Option<String>>::unpack)
+            .unwrap()
+            .unwrap(),
+        [Some("abc".to_owned())]
+    );
+}
+
+#[test]
+fn maps() {
+    let map: BTreeMap<String, Vec<u8>> =
+        [("a".to_owned(), vec![1]), ("b".to_owned(), vec![])].into();
+    round_trip(map.clone());
+    assert_eq!(
+        packed(&map),
+        [0x82, 0xa1, b'a', 0x91, 0x01, 0xa1, b'b', 0x90]
+    );
+
+    let hash: HashMap<u8, bool> = [(1, true), (2, false)].into();
+    round_trip(hash.clone());
+    let back: BTreeMap<u8, bool> = unpacked(&packed(&hash)).unwrap();
+    assert_eq!(back, [(1, true), (2, false)].into());
+}
+
+#[test]
+fn pointers() {
+    round_trip(Box::new(1u8));
+    round_trip(Rc::new("a".to_owned()));
+    round_trip(Arc::new(vec![1u8]));
+
+    let boxed: Box<str> = "abc".into();
+    assert_eq!(packed(&boxed), packed("abc"));
+    assert_eq!(packed(&&&1u8), packed(&1u8));
+}
+
+#[test]
+fn cows() {
+    let borrowed: Cow<str> = Cow::Borrowed("abc");
+    assert_eq!(packed(&borrowed), packed("abc"));
+    let back: Cow<str> = unpacked(&packed(&borrowed)).unwrap();
+    assert_eq!(back, "abc");
+
+    let slice: Cow<[u8]> = Cow::Borrowed(&[1, 2]);
+    assert_eq!(packed(&slice), packed(&vec![1u8, 2]));
+    let back: Cow<[u8]> = unpacked(&packed(&slice)).unwrap();
+    assert_eq!(back, &[1, 2][..]);
+}
+
+// unpackers from outside the crate only have to provide the required methods
+macro_rules! delegate {
+    ($($name:ident -> $ty:ty),*) => {
+        $(
+            fn $name(&mut self) -> Result<$ty, UnpackError> {
+                self.0.$name()
+            }
+        )*
+
+        fn unpack_nil<T>(&mut self) -> Result<Option<T>, UnpackError> {
+            self.0.unpack_nil()
+        }
+
+        fn read_payload_into(&mut self, buf: &mut [u8]) -> Result<(), UnpackError> {
+            self.0.read_payload_into(buf)
+        }
+    };
+}
+
+struct Minimal<'a>(Unpacker<&'a [u8]>);
+
+impl MessageUnpacker for Minimal<'_> {
+    delegate! {
+        unpack_u8 -> u8, unpack_u16 -> u16, unpack_u32 -> u32, unpack_u64 -> u64,
+        unpack_i8 -> i8, unpack_i16 -> i16, unpack_i32 -> i32, unpack_i64 -> i64,
+        peek_code -> msgpack::code::Code, unpack_bool -> bool, unpack_string -> String,
+        unpack_str_header -> usize, unpack_array_header -> usize,
+        unpack_map_header -> usize, unpack_bin_header -> usize,
+        unpack_fixext1 -> (i8, u8), unpack_fixext2 -> (i8, [u8; 2]),
+        unpack_fixext4 -> (i8, [u8; 4]), unpack_fixext8 -> (i8, [u8; 8]),
+        unpack_fixext16 -> (i8, [u8; 16])
+    }
+}
+
+#[test]
+fn default_methods() {
+    let mut map = BTreeMap::new();
+    map.insert("a".to_owned(), vec![Some(1.5f64), None]);
+    map.insert("b".to_owned(), vec![Some(-1.0)]);
+    let mut buf = packed(&(map, -300i16, "s", true, u64::MAX));
+    buf.extend_from_slice(&[0xc7, 0x03, 0x05, 1, 2, 3, 0xd4, 0x05, 1, 0xc4, 0x01, 0]);
+    buf.extend(packed(&7u8));
+
+    // skip_value reads through peek_code and read_payload_into
+    let mut minimal = Minimal(Unpacker::from_reader(&buf[..]));
+    for _ in 0..4 {
+        minimal.skip_value().unwrap();
+    }
+    assert_eq!(minimal.unpack_u8().unwrap(), 7);
+    assert!(minimal.skip_value().is_err());
+
+    let mut minimal = Minimal(Unpacker::from_reader(&buf[..]));
+    minimal.unpack_array_header().unwrap();
+    minimal.skip_value().unwrap();
+    minimal.skip_value().unwrap();
+    minimal.unpack_string().unwrap();
+    minimal.skip_value().unwrap();
+    minimal.skip_value().unwrap();
+    assert_eq!(minimal.unpack_ext_header().unwrap(), (3, 5));
+    assert_eq!(minimal.read_payload(3).unwrap(), [1, 2, 3]);
+    assert_eq!(minimal.unpack_ext_header().unwrap(), (1, 5));
+    assert_eq!(minimal.read_payload(1).unwrap(), [1]);
+    assert!(matches!(
+        minimal.unpack_ext_header(),
+        Err(UnpackError::TypeMismatch(msgpack::code::Code::Bin8, _))
+    ));
+
+    let buf = [packed(&1.5f32), packed(&0.1f64), packed(&1u8)].concat();
+    let mut minimal = Minimal(Unpacker::from_reader(&buf[..]));
+    assert_eq!(minimal.unpack_f32().unwrap(), 1.5);
+    assert_eq!(minimal.unpack_f64().unwrap(), 0.1);
+    assert!(matches!(
+        minimal.unpack_f64(),
+        Err(UnpackError::TypeMismatch(msgpack::code::Code::PosInt(1), _))
+    ));
+}
@@ -1,3 +1,5 @@
+#![allow(clippy::bool_assert_comparison)]
+
 use std::io;
 
 #[test]
@@ -191,3 +193,371 @@ fn unpack_fixext4() {
     );
     assert_eq!(msgpack::unpack_u8(&mut reader).unwrap(), 0x01);
 }
+
+#[test]
+fn unpack_uint() {
+    let mut reader = io::Cursor::new(&[
+        0x01, // 1
+        0xcc, 0xff, // 255
+        0xcd, 0x01, 0x00, // 256
+        0xd0, 0x7f, // 127 as int8
+        0xcf, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, // 2 as uint64
+    ]);
+    assert_eq!(msgpack::unpack_uint::<u64, _>(&mut reader).unwrap(), 1);
+    assert_eq!(msgpack::unpack_uint::<u16, _>(&mut reader).unwrap(), 255);
+    assert_eq!(msgpack::unpack_uint::<u32, _>(&mut reader).unwrap(), 256);
+    assert_eq!(msgpack::unpack_uint::<u8, _>(&mut reader).unwrap(), 127);
+    assert_eq!(msgpack::unpack_uint::<u8, _>(&mut reader).unwrap(), 2);
+}
+
+#[test]
+fn unpack_uint_out_of_range() {
+    let mut reader = io::Cursor::new(&[0xcd, 0x01, 0x00]);
+    match msgpack::unpack_uint::<u8, _>(&mut reader) {
+        Err(msgpack::UnpackError::OutOfRange(..)) => {}
+        v => panic!("unexpected result: {:?}", v),
+    }
+
+    let mut reader = io::Cursor::new(&[0xff]);
+    match msgpack::unpack_uint::<u64, _>(&mut reader) {
+        Err(msgpack::UnpackError::OutOfRange(..)) => {}
+        v => panic!("unexpected result: {:?}", v),
+    }
+
+    let mut reader = io::Cursor::new(&[0xc0]);
+    match msgpack::unpack_uint::<u64, _>(&mut reader) {
+        Err(msgpack::UnpackError::TypeMismatch(..)) => {}
+        v => panic!("unexpected result: {:?}", v),
+    }
+}
+
+#[test]
+fn unpack_int() {
+    let mut reader = io::Cursor::new(&[
+        0x01, // 1
+        0xff, // -1
+        0xcc, 0xff, // 255
+        0xd1, 0xff, 0x7f, // -129
+        0xd3, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe, // -2 as int64
+    ]);
+    assert_eq!(msgpack::unpack_int::<i8, _>(&mut reader).unwrap(), 1);
+    assert_eq!(msgpack::unpack_int::<i64, _>(&mut reader).unwrap(), -1);
+    assert_eq!(msgpack::unpack_int::<i16, _>(&mut reader).unwrap(), 255);
+    assert_eq!(msgpack::unpack_int::<i32, _>(&mut reader).unwrap(), -129);
+    assert_eq!(msgpack::unpack_int::<i8, _>(&mut reader).unwrap(), -2);
+}
+
+#[test]
+fn unpack_int_out_of_range() {
+    let mut reader = io::Cursor::new(&[0xcc, 0x80]);
+    match msgpack::unpack_int::<i8, _>(&mut reader) {
+        Err(msgpack::UnpackError::OutOfRange(..)) => {}
+        v => panic!("unexpected result: {:?}", v),
+    }
+
+    let mut reader = io::Cursor::new(&[0xcf, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]);
+    match msgpack::unpack_int::<i64, _>(&mut reader) {
+        Err(msgpack::UnpackError::OutOfRange(..)) => {}
+        v => panic!("unexpected result: {:?}", v),
+    }
+}
+
+#[test]
+fn unpack_floats() {
+    let mut reader = io::Cursor::new(&[
+        0xca, 0x3f, 0xc0, 0x00, 0x00, // 1.5f32
+        0xcb, 0x3f, 0xd0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // 0.25f64
+        0xca, 0x3f, 0xc0, 0x00, 0x00, // 1.5f32
+        0xcb, 0x3f, 0xd0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // 0.25f64
+    ]);
+    assert_eq!(msgpack::unpack_f32(&mut reader).unwrap(), 1.5);
+    assert_eq!(msgpack::unpack_f32(&mut reader).unwrap(), 0.25);
+    assert_eq!(msgpack::unpack_f64(&mut reader).unwrap(), 1.5);
+    assert_eq!(msgpack::unpack_f64(&mut reader).unwrap(), 0.25);
+
+    let mut reader = io::Cursor::new(&[0x01]);
+    match msgpack::unpack_f64(&mut reader) {
+        Err(msgpack::UnpackError::TypeMismatch(..)) => {}
+        v => panic!("unexpected result: {:?}", v),
+    }
+}
+
+#[test]
+fn unpack_floats_from_int() {
+    let mode = msgpack::FloatMode {
+        accept_int: true,
+        allow_precision_loss: false,
+        strict: false,
+    };
+    let mut reader = io::Cursor::new(&[
+        0x01, // 1
+        0xff, // -1
+        0xcd, 0x01, 0x00, // 256
+        0xd3, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00, // -256
+    ]);
+    assert_eq!(msgpack::unpack_f32_with(&mut reader, mode).unwrap(), 1.0);
+    assert_eq!(msgpack::unpack_f32_with(&mut reader, mode).unwrap(), -1.0);
+    assert_eq!(msgpack::unpack_f64_with(&mut reader, mode).unwrap(), 256.0);
+    assert_eq!(msgpack::unpack_f64_with(&mut reader, mode).unwrap(), -256.0);
+
+    let mut reader = io::Cursor::new(&[0xc0]);
+    match msgpack::unpack_f64_with(&mut reader, mode) {
+        Err(msgpack::UnpackError::TypeMismatch(..)) => {}
+        v => panic!("unexpected result: {:?}", v),
+    }
+}
+
+#[test]
+fn unpack_floats_precision_loss() {
+    // 2^24 + 1 doesn't fit in f32, 2^53 + 1 doesn't fit in f64
+    let int_f32 = [0xce, 0x01, 0x00, 0x00, 0x01];
+    let int_f64 = [0xcf, 0x00, 0x20, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01];
+    // 0.1f64
+    let f64_f32 = [0xcb, 0x3f, 0xb9, 0x99, 0x99, 0x99, 0x99, 0x99, 0x9a];
+
+    let strict = msgpack::FloatMode {
+        accept_int: true,
+        allow_precision_loss: false,
+        strict: false,
+    };
+    let lossy = msgpack::FloatMode {
+        accept_int: true,
+        allow_precision_loss: true,
+        strict: false,
+    };
+
+    match msgpack::unpack_f32_with(&mut io::Cursor::new(&int_f32), strict) {
+        Err(msgpack::UnpackError::OutOfRange(..)) => {}
+        v => panic!("unexpected result: {:?}", v),
+    }
+    match msgpack::unpack_f64_with(&mut io::Cursor::new(&int_f64), strict) {
+        Err(msgpack::UnpackError::OutOfRange(..)) => {}
+        v => panic!("unexpected result: {:?}", v),
+    }
+    match msgpack::unpack_f32(&mut io::Cursor::new(&f64_f32)) {
+        Err(msgpack::UnpackError::OutOfRange(..)) => {}
+        v => panic!("unexpected result: {:?}", v),
+    }
+
+    assert_eq!(
+        msgpack::unpack_f32_with(&mut io::Cursor::new(&int_f32), lossy).unwrap(),
+        16_777_216.0
+    );
+    assert_eq!(
+        msgpack::unpack_f64_with(&mut io::Cursor::new(&int_f64), lossy).unwrap(),
+        9_007_199_254_740_992.0
+    );
+    assert_eq!(
+        msgpack::unpack_f32_with(&mut io::Cursor::new(&f64_f32), lossy).unwrap(),
+        0.1
+    );
+}
+
+#[test]
+fn skip_value() {
+    let mut reader = io::Cursor::new(vec![
+        0x01, // 1
+        0xa3, 0x61, 0x62, 0x63, // "abc"
+        0xc4, 0x02, 0x00, 0x01, // bin8 [0, 1]
+        0x92, 0x01, 0x91, 0xc0, // [1, [nil]]
+        0x82, 0xa1, 0x61, 0x81, 0x01, 0x02, 0x01, 0xcb, 0, 0, 0, 0, 0, 0, 0,
+        0, // {"a": {1: 2}, 1: 0.0}
+        0xd5, 0x01, 0x00, 0x01, // fixext2
+        0xc7, 0x03, 0x01, 0x00, 0x01, 0x02, // ext8
+        0xdc, 0x00, 0x02, 0xc2, 0xc3, // array16 [false, true]
+        0x07,
+    ]);
+
+    for _ in 0..8 {
+        msgpack::skip_value(&mut reader).unwrap();
+    }
+    assert_eq!(msgpack::unpack_u8(&mut reader).unwrap(), 7);
+}
+
+#[test]
+fn skip_value_eof() {
+    let mut reader = io::Cursor::new(&[0x92, 0x01]);
+    assert!(msgpack::skip_value(&mut reader).is_err());
+
+    let mut reader = io::Cursor::new(&[0xa3, 0x61]);
+    assert!(msgpack::skip_value(&mut reader).is_err());
+}
+
+#[test]
+fn skip_value_reserved() {
+    let mut reader = io::Cursor::new(&[0x91, 0xc1]);
+    assert!(matches!(
+        msgpack::skip_value(&mut reader),
+        Err(msgpack::UnpackError::ReservedCode(0xc1))
+    ));
+}
+
+#[test]
+fn unpack_raw_ref() {
+    let buf = [
+        0xcd, 0x00, 0x01, // 1 encoded as uint16
+        0x92, 0xa1, 0x61, 0x81, 0x01, 0xc0, // ["a", {1: nil}]
+        0xc3,
+    ];
+    let mut reader = &buf[..];
+
+    assert_eq!(
+        msgpack::unpack_raw_ref(&mut reader).unwrap(),
+        &[0xcd, 0x00, 0x01]
+    );
+    assert_eq!(
+        msgpack::unpack_raw_ref(&mut reader).unwrap(),
+        &[0x92, 0xa1, 0x61, 0x81, 0x01, 0xc0]
+    );
+    assert!(msgpack::unpack_bool(&mut reader).unwrap());
+
+    let mut reader = io::Cursor::new(&[0x92, 0x01][..]);
+    assert!(msgpack::unpack_raw_ref(&mut reader).is_err());
+    assert_eq!(reader.position(), 0);
+}
+
+#[test]
+fn unpack_errors() {
+    let mut reader = io::Cursor::new(&[0xcd, 0x01]);
+    match msgpack::unpack_u16(&mut reader) {
+        Err(msgpack::UnpackError::UnexpectedEof) => {}
+        v => panic!("unexpected result: {:?}", v),
+    }
+
+    let mut reader = io::Cursor::new(&[0xa2, 0xc3, 0x28]);
+    match msgpack::unpack_str(&mut reader) {
+        Err(msgpack::UnpackError::InvalidUtf8(_)) => {}
+        v => panic!("unexpected result: {:?}", v),
+    }
+
+    let mut reader = &[0xa2, 0xc3, 0x28][..];
+    match msgpack::unpack_str_ref(&mut reader) {
+        Err(msgpack::UnpackError::InvalidUtf8(_)) => {}
+        v => panic!("unexpected result: {:?}", v),
+    }
+
+    let mut reader = &[0xa3, 0x61][..];
+    match msgpack::unpack_str_ref(&mut reader) {
+        Err(msgpack::UnpackError::UnexpectedEof) => {}
+        v => panic!("unexpected result: {:?}", v),
+    }
+
+    let mut reader = io::Cursor::new(&[0xc1]);
+    match msgpack::unpack_nil::<u8, _>(&mut reader) {
+        Err(msgpack::UnpackError::ReservedCode(0xc1)) => {}
+        v => panic!("unexpected result: {:?}", v),
+    }
+
+    let mut reader = io::Cursor::new(&[0xd6, 0x01, 0x00, 0x00, 0x00, 0x01]);
+    match msgpack::unpack_timestamp32(&mut reader) {
+        Err(msgpack::UnpackError::InvalidExtTimestamp(_)) => {}
+        v => panic!("unexpected result: {:?}", v),
+    }
+}
+
+#[test]
+fn unpack_error_location() {
+    let err = msgpack::UnpackError::UnexpectedEof
+        .within(msgpack::PathSegment::Key("name".to_string()))
+        .within(msgpack::PathSegment::Index(3))
+        .within(msgpack::PathSegment::Key("items".to_string()))
+        .at_offset(12)
+        .at_offset(20);
+
+    assert!(matches!(err.root(), msgpack::UnpackError::UnexpectedEof));
+    assert_eq!(err.path().unwrap(), "$.items[3].name");
+    assert_eq!(err.offset(), Some(12));
+    assert_eq!(
+        err.to_string(),
+        "unexpected end of input at $.items[3].name (offset 12)"
+    );
+}
+
+#[test]
+fn unpack_data_hostile_length() {
+    // a bin32 header claiming 4 GiB with nothing behind it
+    let mut reader = io::Cursor::new(&[0xc6, 0xff, 0xff, 0xff, 0xff]);
+    match msgpack::unpack_bin(&mut reader) {
+        Err(msgpack::UnpackError::UnexpectedEof) => {}
+        v => panic!("unexpected result: {:?}", v),
+    }
+}
+
+#[test]
+fn limit_tracker() {
+    let limits = msgpack::DecodeLimits {
+        max_depth: 1,
+        max_container_len: 2,
+        max_data_len: 4,
+        max_alloc: 6,
+        strict: false,
+    };
+    let mut tracker = msgpack::LimitTracker::new(limits);
+
+    tracker.enter().unwrap();
+    match tracker.enter() {
+        Err(msgpack::UnpackError::DepthExceeded(1)) => {}
+        v => panic!("unexpected result: {:?}", v),
+    }
+    tracker.leave();
+
+    tracker.container(2, 0).unwrap();
+    match tracker.container(3, 0) {
+        Err(msgpack::UnpackError::LimitExceeded(msgpack::Limit::ContainerLength, 3)) => {}
+        v => panic!("unexpected result: {:?}", v),
+    }
+
+    tracker.data_ref(4).unwrap();
+    match tracker.data_ref(5) {
+        Err(msgpack::UnpackError::LimitExceeded(msgpack::Limit::DataLength, 5)) => {}
+        v => panic!("unexpected result: {:?}", v),
+    }
+
+    tracker.data(4).unwrap();
+    match tracker.data(4) {
+        Err(msgpack::UnpackError::LimitExceeded(msgpack::Limit::Allocation, 8)) => {}
+        v => panic!("unexpected result: {:?}", v),
+    }
+}
+
+#[test]
+fn unpack_timestamps() {
+    let mut reader = io::Cursor::new(&[0xd7, 0xff, 0x07, 0xff, 0xff, 0xfc, 0x5d, 0x87, 0x3d, 0x44]);
+    assert_eq!(
+        msgpack::unpack_timestamp64(&mut reader).unwrap(),
+        (1569144132, 33554431)
+    );
+
+    let buf = [
+        0xc7, 0x0c, 0xff, 0x3b, 0x9a, 0xc9, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    ];
+    assert_eq!(
+        msgpack::unpack_timestamp96(&mut io::Cursor::new(&buf)).unwrap(),
+        (-1, 999_999_999)
+    );
+    assert_eq!(
+        msgpack::unpack_timestamp(&mut io::Cursor::new(&buf)).unwrap(),
+        msgpack::Timestamp::new(-1, 999_999_999).unwrap()
+    );
+
+    // nsec must be below one second
+    let buf = [
+        0xc7, 0x0c, 0xff, 0x3b, 0x9a, 0xca, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+    match msgpack::unpack_timestamp(&mut io::Cursor::new(&buf)) {
+        Err(msgpack::UnpackError::InvalidExtTimestamp(_)) => {}
+        v => panic!("unexpected result: {:?}", v),
+    }
+    let buf = [0xd7, 0xff, 0xee, 0x6b, 0x28, 0x00, 0x00, 0x00, 0x00, 0x00];
+    match msgpack::unpack_timestamp64(&mut io::Cursor::new(&buf)) {
+        Err(msgpack::UnpackError::InvalidExtTimestamp(_)) => {}
+        v => panic!("unexpected result: {:?}", v),
+    }
+
+    // ext -1 bodies other than 4, 8 or 12 bytes
+    let mut reader = io::Cursor::new(&[0xd5, 0xff, 0x00, 0x00]);
+    match msgpack::unpack_timestamp(&mut reader) {
+        Err(msgpack::UnpackError::InvalidExtTimestamp(_)) => {}
+        v => panic!("unexpected result: {:?}", v),
+    }
+}
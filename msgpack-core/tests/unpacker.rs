@@ -1,3 +1,5 @@
+#![allow(clippy::bool_assert_comparison)]
+
 use msgpack::{self, MessageUnpacker};
 use std::io;
 
@@ -24,3 +26,85 @@ fn unpacker() {
     assert_eq!(unpacker.unpack_u8().unwrap(), 1);
     assert_eq!(unpacker.unpack_string().unwrap(), "s".to_owned());
 }
+
+#[test]
+fn unpacker_feed_partial() {
+    let mut unpacker = msgpack::Unpacker::new();
+
+    // "abc" split across three feeds
+    unpacker.feed(&[0xa3, 0x61]);
+    assert!(unpacker.try_unpack(|u| u.unpack_string()).is_none());
+    unpacker.feed(&[0x62]);
+    assert!(unpacker.try_unpack(|u| u.unpack_string()).is_none());
+    unpacker.feed(&[0x63, 0xcd, 0x01]);
+    assert_eq!(
+        unpacker.try_unpack(|u| u.unpack_string()).unwrap().unwrap(),
+        "abc".to_owned()
+    );
+
+    assert!(unpacker.try_unpack(|u| u.unpack_u16()).is_none());
+    unpacker.feed(&[0x00]);
+    assert_eq!(
+        unpacker.try_unpack(|u| u.unpack_u16()).unwrap().unwrap(),
+        256
+    );
+    assert!(unpacker.try_unpack(|u| u.unpack_u16()).is_none());
+}
+
+#[test]
+fn unpacker_next() {
+    let val = [0x92, 0x01, 0xa1, 0x73, 0xcd, 0x01, 0x00, 0xc0];
+    let mut unpacker = msgpack::Unpacker::new();
+
+    // objects come out whole, as their encoded bytes
+    let mut objects = vec![];
+    for b in val {
+        unpacker.feed(&[b]);
+        for object in &mut unpacker {
+            objects.push(object.unwrap());
+        }
+    }
+    assert_eq!(objects, [&val[..4], &val[4..7], &val[7..]]);
+    assert!(unpacker.next().is_none());
+
+    // a peeked code is handed back with its object
+    unpacker.feed(&[0xa1]);
+    assert_eq!(
+        unpacker.peek_code().unwrap(),
+        msgpack::code::Code::FixStr(1)
+    );
+    assert!(unpacker.next().is_none());
+    unpacker.feed(&[0x73]);
+    assert_eq!(unpacker.next().unwrap().unwrap(), &[0xa1, 0x73][..]);
+
+    // even once the read which peeked it dropped it from the buffer
+    unpacker.feed(&[0xa1, 0x73]);
+    unpacker.try_unpack(|u| u.peek_code()).unwrap().unwrap();
+    assert_eq!(unpacker.next().unwrap().unwrap(), &[0xa1, 0x73][..]);
+
+    unpacker.feed(&[0xc1, 0xc0]);
+    assert!(unpacker.next().unwrap().is_err());
+    // nothing after a malformed header can be framed, so it's dropped
+    assert!(unpacker.next().is_none());
+    unpacker.feed(&[0xc0]);
+    assert_eq!(unpacker.next().unwrap().unwrap(), &[0xc0][..]);
+}
+
+#[test]
+fn unpacker_feed_error() {
+    let mut unpacker = msgpack::Unpacker::new();
+
+    unpacker.feed(&[0xc0, 0x01]);
+    assert!(unpacker.try_unpack(|u| u.unpack_bool()).unwrap().is_err());
+    assert_eq!(unpacker.try_unpack(|u| u.unpack_u8()).unwrap().unwrap(), 1);
+}
+
+#[test]
+fn inner_buf_read_past_end() {
+    let mut buf = msgpack::InnerBuf::new();
+    buf.feed(&[0x01, 0x02]);
+
+    let mut out = [0; 4];
+    assert!(io::Read::read_exact(&mut buf, &mut out).is_err());
+    assert_eq!(buf.remaining(), 0);
+}
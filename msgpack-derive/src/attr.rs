@@ -0,0 +1,109 @@
+use syn::{Attribute, LitInt, LitStr, Result};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    // named fields as a map from their keys, like msgpack-serde's default
+    Map,
+    // named fields as an array in declaration order
+    Array,
+}
+
+// #[msgpack(..)] on a struct or enum
+pub struct Container {
+    pub layout: Layout,
+    // packs the whole value as the data of an ext of this type
+    pub ext: Option<i8>,
+}
+
+impl Container {
+    pub fn parse(attrs: &[Attribute]) -> Result<Self> {
+        let mut container = Container {
+            layout: Layout::Map,
+            ext: None,
+        };
+        for attr in msgpack_attrs(attrs) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("array") {
+                    container.layout = Layout::Array;
+                } else if meta.path.is_ident("map") {
+                    container.layout = Layout::Map;
+                } else if meta.path.is_ident("ext") {
+                    container.ext = Some(meta.value()?.parse::<LitInt>()?.base10_parse()?);
+                } else {
+                    return Err(meta.error("expected `array`, `map` or `ext = <type>`"));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(container)
+    }
+}
+
+// what a field is called in a map
+#[derive(Clone)]
+pub enum Key {
+    Str(String),
+    Int(i64),
+}
+
+// #[msgpack(..)] on a field
+#[derive(Default)]
+pub struct Field {
+    pub rename: Option<String>,
+    pub key: Option<i64>,
+    // Default::default() when the field is missing from the input
+    pub default: bool,
+    // never packed, and always Default::default() when unpacked
+    pub skip: bool,
+}
+
+impl Field {
+    pub fn parse(attrs: &[Attribute]) -> Result<Self> {
+        let mut field = Field::default();
+        for attr in msgpack_attrs(attrs) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    field.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("key") {
+                    field.key = Some(meta.value()?.parse::<LitInt>()?.base10_parse()?);
+                } else if meta.path.is_ident("default") {
+                    field.default = true;
+                } else if meta.path.is_ident("skip") {
+                    field.skip = true;
+                } else {
+                    return Err(meta
+                        .error("expected `rename = \"..\"`, `key = <int>`, `default` or `skip`"));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(field)
+    }
+}
+
+// #[msgpack(..)] on an enum variant
+#[derive(Default)]
+pub struct Variant {
+    pub rename: Option<String>,
+}
+
+impl Variant {
+    pub fn parse(attrs: &[Attribute]) -> Result<Self> {
+        let mut variant = Variant::default();
+        for attr in msgpack_attrs(attrs) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    variant.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `rename = \"..\"`"))
+                }
+            })?;
+        }
+        Ok(variant)
+    }
+}
+
+fn msgpack_attrs(attrs: &[Attribute]) -> impl Iterator<Item = &Attribute> {
+    attrs.iter().filter(|attr| attr.path().is_ident("msgpack"))
+}
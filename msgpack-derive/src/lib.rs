@@ -0,0 +1,495 @@
+extern crate proc_macro;
+
+mod attr;
+
+use attr::{Container, Key, Layout};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, parse_quote, Data, DeriveInput, Error, Fields, Generics, Ident, Result,
+};
+
+// implements msgpack::Pack and msgpack::Unpack. structs are laid out like msgpack-serde does
+// by default: named fields as a map, tuple structs as an array, newtypes as what they wrap and
+// unit structs as an empty array. enum variants are a map of one entry from the variant name
+// to its payload, and unit variants can also be just their name
+#[proc_macro_derive(MsgPack, attributes(msgpack))]
+pub fn derive_msgpack(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: &DeriveInput) -> Result<TokenStream> {
+    let container = Container::parse(&input.attrs)?;
+    let (pack, unpack) = match input.data {
+        Data::Struct(ref data) => {
+            let body = Body::parse(&data.fields)?;
+            let access = |f: &Field| {
+                let member = &f.member;
+                quote!(&self.#member)
+            };
+            (
+                body.pack(container.layout, access),
+                body.unpack(container.layout, &quote!(Self)),
+            )
+        }
+        Data::Enum(ref data) => {
+            let variants = data
+                .variants
+                .iter()
+                .map(Variant::parse)
+                .collect::<Result<Vec<_>>>()?;
+            (
+                pack_enum(&variants, container.layout),
+                unpack_enum(&variants, container.layout),
+            )
+        }
+        Data::Union(ref data) => {
+            return Err(Error::new(
+                data.union_token.span,
+                "MsgPack can't be derived for unions",
+            ))
+        }
+    };
+    let (pack, unpack) = match container.ext {
+        Some(ty) => (pack_ext(ty, pack), unpack_ext(ty, unpack)),
+        None => (pack, unpack),
+    };
+
+    let ident = &input.ident;
+    let generics = bounded(&input.generics, quote!(::msgpack::Pack));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let pack_impl = quote! {
+        impl #impl_generics ::msgpack::Pack for #ident #ty_generics #where_clause {
+            fn pack<__P: ::msgpack::MessagePacker>(
+                &self,
+                __packer: &mut __P,
+            ) -> ::core::result::Result<(), ::msgpack::PackError> {
+                #pack
+                ::core::result::Result::Ok(())
+            }
+        }
+    };
+    let generics = bounded(&input.generics, quote!(::msgpack::Unpack));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let unpack_impl = quote! {
+        impl #impl_generics ::msgpack::Unpack for #ident #ty_generics #where_clause {
+            fn unpack<__U: ::msgpack::MessageUnpacker>(
+                __unpacker: &mut __U,
+            ) -> ::core::result::Result<Self, ::msgpack::UnpackError> {
+                #unpack
+            }
+        }
+    };
+    Ok(quote!(#pack_impl #unpack_impl))
+}
+
+fn bounded(generics: &Generics, bound: TokenStream) -> Generics {
+    let mut generics = generics.clone();
+    for param in generics.type_params_mut() {
+        param.bounds.push(parse_quote!(#bound));
+    }
+    generics
+}
+
+struct Field<'a> {
+    attr: attr::Field,
+    ty: &'a syn::Type,
+    member: syn::Member,
+    key: Key,
+    // the local it's bound to while packing an enum or unpacking
+    var: Ident,
+}
+
+impl Field<'_> {
+    fn name(&self) -> String {
+        match self.key {
+            Key::Str(ref s) => s.clone(),
+            Key::Int(i) => i.to_string(),
+        }
+    }
+
+    // what a missing field is unpacked as
+    fn missing(&self, error: TokenStream) -> TokenStream {
+        if self.attr.default {
+            quote!(::core::default::Default::default())
+        } else {
+            quote!(return ::core::result::Result::Err(#error))
+        }
+    }
+}
+
+#[derive(PartialEq, Eq)]
+enum Shape {
+    Named,
+    Unnamed,
+    Unit,
+}
+
+// the fields of a struct or variant
+struct Body<'a> {
+    shape: Shape,
+    fields: Vec<Field<'a>>,
+}
+
+impl<'a> Body<'a> {
+    fn parse(fields: &'a Fields) -> Result<Self> {
+        let shape = match *fields {
+            Fields::Named(_) => Shape::Named,
+            Fields::Unnamed(_) => Shape::Unnamed,
+            Fields::Unit => Shape::Unit,
+        };
+        let fields = fields
+            .iter()
+            .enumerate()
+            .map(|(i, field)| {
+                let attr = attr::Field::parse(&field.attrs)?;
+                let (member, key) = match field.ident {
+                    Some(ref ident) => {
+                        let key = match (attr.key, &attr.rename) {
+                            (Some(key), _) => Key::Int(key),
+                            (None, Some(name)) => Key::Str(name.clone()),
+                            (None, None) => Key::Str(ident.to_string()),
+                        };
+                        (syn::Member::Named(ident.clone()), key)
+                    }
+                    None => {
+                        if attr.rename.is_some() || attr.key.is_some() || attr.skip {
+                            return Err(Error::new_spanned(
+                                field,
+                                "tuple fields can't be renamed, keyed or skipped",
+                            ));
+                        }
+                        (syn::Member::Unnamed(i.into()), Key::Int(i as i64))
+                    }
+                };
+                Ok(Field {
+                    attr,
+                    ty: &field.ty,
+                    member,
+                    key,
+                    var: format_ident!("__f{}", i),
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Body { shape, fields })
+    }
+
+    fn packed(&self) -> impl Iterator<Item = &Field<'a>> {
+        self.fields.iter().filter(|f| !f.attr.skip)
+    }
+
+    fn is_newtype(&self) -> bool {
+        self.shape == Shape::Unnamed && self.fields.len() == 1
+    }
+
+    fn pack(&self, layout: Layout, access: impl Fn(&Field) -> TokenStream) -> TokenStream {
+        if self.is_newtype() {
+            let v = access(&self.fields[0]);
+            return quote!(::msgpack::Pack::pack(#v, __packer)?;);
+        }
+        let len = self.packed().count();
+        let values = self.packed().map(|f| {
+            let v = access(f);
+            quote!(::msgpack::Pack::pack(#v, __packer)?;)
+        });
+        if self.shape != Shape::Named || layout == Layout::Array {
+            return quote! {
+                ::msgpack::MessagePacker::pack_array_header(__packer, #len)?;
+                #(#values)*
+            };
+        }
+        let keys = self.packed().map(|f| match f.key {
+            Key::Str(ref s) => quote!(::msgpack::MessagePacker::pack_str(__packer, #s)?;),
+            Key::Int(i) => quote!(::msgpack::MessagePacker::pack_int(__packer, #i)?;),
+        });
+        quote! {
+            ::msgpack::MessagePacker::pack_map_header(__packer, #len)?;
+            #(#keys #values)*
+        }
+    }
+
+    // an expression which unpacks the fields and builds `path` from them
+    fn unpack(&self, layout: Layout, path: &TokenStream) -> TokenStream {
+        let vars = self.fields.iter().map(|f| &f.var);
+        let build = match self.shape {
+            Shape::Named => {
+                let members = self.fields.iter().map(|f| &f.member);
+                quote!(#path { #(#members: #vars),* })
+            }
+            Shape::Unnamed => quote!(#path(#(#vars),*)),
+            Shape::Unit => quote!(#path),
+        };
+        if self.is_newtype() {
+            let var = &self.fields[0].var;
+            return quote! {{
+                let #var = ::msgpack::Unpack::unpack(__unpacker)?;
+                ::core::result::Result::Ok(#build)
+            }};
+        }
+        let skipped = self.fields.iter().filter(|f| f.attr.skip).map(|f| {
+            let var = &f.var;
+            quote!(let #var = ::core::default::Default::default();)
+        });
+        let fields = if self.shape != Shape::Named || layout == Layout::Array {
+            self.unpack_array()
+        } else {
+            self.unpack_map()
+        };
+        quote! {{
+            #fields
+            #(#skipped)*
+            ::core::result::Result::Ok(#build)
+        }}
+    }
+
+    // fields past the end of the array take their default, and extra elements are skipped so
+    // fields can be appended
+    fn unpack_array(&self) -> TokenStream {
+        let len = self.packed().count();
+        let fields = self.packed().enumerate().map(|(i, f)| {
+            let var = &f.var;
+            let missing = f.missing(quote!(::msgpack::UnpackError::LengthMismatch(#len, __len)));
+            quote! {
+                let #var = if #i < __len {
+                    ::msgpack::Unpack::unpack(__unpacker)
+                        .map_err(|e| e.within(::msgpack::PathSegment::Index(#i)))?
+                } else {
+                    #missing
+                };
+            }
+        });
+        quote! {
+            let __len = ::msgpack::MessageUnpacker::unpack_array_header(__unpacker)?;
+            #(#fields)*
+            for _ in #len..__len {
+                ::msgpack::MessageUnpacker::skip_value(__unpacker)?;
+            }
+        }
+    }
+
+    // entries with unknown keys are skipped
+    fn unpack_map(&self) -> TokenStream {
+        let slot = |f: &Field| {
+            let var = &f.var;
+            let name = f.name();
+            quote! {
+                #var = ::core::option::Option::Some(
+                    ::msgpack::Unpack::unpack(__unpacker).map_err(|e| {
+                        e.within(::msgpack::PathSegment::Key(#name.to_string()))
+                    })?,
+                )
+            }
+        };
+        let str_arms = self.packed().filter_map(|f| match f.key {
+            Key::Str(ref s) => {
+                let slot = slot(f);
+                Some(quote!(#s => #slot,))
+            }
+            Key::Int(_) => None,
+        });
+        let int_arms = self
+            .packed()
+            .filter_map(|f| match f.key {
+                Key::Int(i) => {
+                    let slot = slot(f);
+                    Some(quote!(#i => #slot,))
+                }
+                Key::Str(_) => None,
+            })
+            .collect::<Vec<_>>();
+        let int_keys = if int_arms.is_empty() {
+            quote!()
+        } else {
+            quote! {
+                ::msgpack::code::Code::PosInt(_)
+                | ::msgpack::code::Code::NegInt(_)
+                | ::msgpack::code::Code::Uint8
+                | ::msgpack::code::Code::Uint16
+                | ::msgpack::code::Code::Uint32
+                | ::msgpack::code::Code::Uint64
+                | ::msgpack::code::Code::Int8
+                | ::msgpack::code::Code::Int16
+                | ::msgpack::code::Code::Int32
+                | ::msgpack::code::Code::Int64 => {
+                    match ::msgpack::MessageUnpacker::unpack_i64(__unpacker)? {
+                        #(#int_arms)*
+                        _ => ::msgpack::MessageUnpacker::skip_value(__unpacker)?,
+                    }
+                }
+            }
+        };
+
+        let slots = self.packed().map(|f| {
+            let var = &f.var;
+            let ty = f.ty;
+            quote!(let mut #var: ::core::option::Option<#ty> = ::core::option::Option::None;)
+        });
+        let fields = self.packed().map(|f| {
+            let var = &f.var;
+            let name = f.name();
+            let missing =
+                f.missing(quote!(::msgpack::UnpackError::MissingField(#name.to_string())));
+            quote! {
+                let #var = match #var {
+                    ::core::option::Option::Some(v) => v,
+                    ::core::option::Option::None => #missing,
+                };
+            }
+        });
+        quote! {
+            #(#slots)*
+            for _ in 0..::msgpack::MessageUnpacker::unpack_map_header(__unpacker)? {
+                match ::msgpack::MessageUnpacker::peek_code(__unpacker)? {
+                    ::msgpack::code::Code::FixStr(_)
+                    | ::msgpack::code::Code::Str8
+                    | ::msgpack::code::Code::Str16
+                    | ::msgpack::code::Code::Str32 => {
+                        match ::msgpack::MessageUnpacker::unpack_string(__unpacker)?.as_str() {
+                            #(#str_arms)*
+                            _ => ::msgpack::MessageUnpacker::skip_value(__unpacker)?,
+                        }
+                    }
+                    #int_keys
+                    _ => {
+                        ::msgpack::MessageUnpacker::skip_value(__unpacker)?;
+                        ::msgpack::MessageUnpacker::skip_value(__unpacker)?;
+                    }
+                }
+            }
+            #(#fields)*
+        }
+    }
+}
+
+struct Variant<'a> {
+    ident: &'a Ident,
+    name: String,
+    body: Body<'a>,
+}
+
+impl<'a> Variant<'a> {
+    fn parse(variant: &'a syn::Variant) -> Result<Self> {
+        let attr = attr::Variant::parse(&variant.attrs)?;
+        Ok(Variant {
+            ident: &variant.ident,
+            name: attr.rename.unwrap_or_else(|| variant.ident.to_string()),
+            body: Body::parse(&variant.fields)?,
+        })
+    }
+}
+
+fn pack_enum(variants: &[Variant], layout: Layout) -> TokenStream {
+    let arms = variants.iter().map(|v| {
+        let ident = v.ident;
+        let name = &v.name;
+        let vars = v.body.packed().map(|f| &f.var);
+        let (pattern, payload) = match v.body.shape {
+            Shape::Named => {
+                let members = v.body.packed().map(|f| &f.member);
+                (
+                    quote!(Self::#ident { #(#members: #vars,)* .. }),
+                    v.body.pack(layout, |f| {
+                        let var = &f.var;
+                        quote!(#var)
+                    }),
+                )
+            }
+            Shape::Unnamed => (
+                quote!(Self::#ident(#(#vars),*)),
+                v.body.pack(layout, |f| {
+                    let var = &f.var;
+                    quote!(#var)
+                }),
+            ),
+            Shape::Unit => (
+                quote!(Self::#ident),
+                quote!(::msgpack::MessagePacker::pack_nil(__packer)?;),
+            ),
+        };
+        quote! {
+            #pattern => {
+                ::msgpack::MessagePacker::pack_map_header(__packer, 1)?;
+                ::msgpack::MessagePacker::pack_str(__packer, #name)?;
+                #payload
+            }
+        }
+    });
+    if variants.is_empty() {
+        return quote!(match *self {});
+    }
+    quote! {
+        match self {
+            #(#arms)*
+        }
+    }
+}
+
+fn unpack_enum(variants: &[Variant], layout: Layout) -> TokenStream {
+    let arms = variants.iter().map(|v| {
+        let ident = v.ident;
+        let name = &v.name;
+        if v.body.shape == Shape::Unit {
+            return quote! {
+                #name => {
+                    if __tagged {
+                        ::msgpack::MessageUnpacker::unpack_nil::<()>(__unpacker)?;
+                    }
+                    ::core::result::Result::Ok(Self::#ident)
+                }
+            };
+        }
+        let payload = v.body.unpack(layout, &quote!(Self::#ident));
+        quote!(#name if __tagged => #payload,)
+    });
+    quote! {
+        let __tagged = match ::msgpack::MessageUnpacker::peek_code(__unpacker)? {
+            ::msgpack::code::Code::FixMap(_)
+            | ::msgpack::code::Code::Map16
+            | ::msgpack::code::Code::Map32 => {
+                let __len = ::msgpack::MessageUnpacker::unpack_map_header(__unpacker)?;
+                if __len != 1 {
+                    return ::core::result::Result::Err(
+                        ::msgpack::UnpackError::LengthMismatch(1, __len),
+                    );
+                }
+                true
+            }
+            _ => false,
+        };
+        let __name = ::msgpack::MessageUnpacker::unpack_string(__unpacker)?;
+        match __name.as_str() {
+            #(#arms)*
+            _ => ::core::result::Result::Err(::msgpack::UnpackError::UnknownVariant(__name)),
+        }
+    }
+}
+
+// the data of the ext is the value packed as it would be without it
+fn pack_ext(ty: i8, pack: TokenStream) -> TokenStream {
+    quote! {
+        let mut __data = ::msgpack::Packer::new(::std::vec::Vec::new());
+        {
+            let __packer = &mut __data;
+            #pack
+        }
+        let __data = __data.into_inner();
+        ::msgpack::MessagePacker::pack_ext_header(__packer, #ty, __data.len())?;
+        ::msgpack::MessagePacker::write_payload(__packer, &__data)?;
+    }
+}
+
+fn unpack_ext(ty: i8, unpack: TokenStream) -> TokenStream {
+    quote! {
+        let (__len, __ty) = ::msgpack::MessageUnpacker::unpack_ext_header(__unpacker)?;
+        if __ty != #ty {
+            return ::core::result::Result::Err(::msgpack::UnpackError::ExtTypeMismatch(#ty, __ty));
+        }
+        let __len = ::msgpack::unpack::len_to_usize(__len)?;
+        let __data = ::msgpack::MessageUnpacker::read_payload(__unpacker, __len)?;
+        let __unpacker = &mut ::msgpack::Unpacker::from_reader(&__data[..]);
+        #unpack
+    }
+}
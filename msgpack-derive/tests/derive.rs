@@ -0,0 +1,238 @@
+use msgpack::{MsgPack, Pack, Packer, Unpack, UnpackError, Unpacker};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+
+#[derive(MsgPack, Serialize, Deserialize, Debug, PartialEq, Clone)]
+struct Config {
+    name: String,
+    retries: u8,
+    timeout: Option<f64>,
+    tags: Vec<String>,
+    labels: BTreeMap<String, i32>,
+    outputs: Vec<Output>,
+    point: Point,
+    id: Id,
+    marker: Marker,
+}
+
+#[derive(MsgPack, Serialize, Deserialize, Debug, PartialEq, Clone)]
+enum Output {
+    Stdout,
+    File(String),
+    Forward { host: String, port: u16 },
+    Pair(u8, u8),
+}
+
+#[derive(MsgPack, Serialize, Deserialize, Debug, PartialEq, Clone)]
+struct Point(i32, i32);
+
+#[derive(MsgPack, Serialize, Deserialize, Debug, PartialEq, Clone)]
+struct Id(u64);
+
+#[derive(MsgPack, Serialize, Deserialize, Debug, PartialEq, Clone)]
+struct Marker;
+
+#[derive(MsgPack, Debug, PartialEq)]
+#[msgpack(array)]
+struct Row {
+    a: u8,
+    #[msgpack(skip)]
+    cached: Option<String>,
+    b: String,
+    #[msgpack(default)]
+    c: Vec<u8>,
+}
+
+#[derive(MsgPack, Debug, PartialEq)]
+struct Renamed {
+    #[msgpack(rename = "n")]
+    name: String,
+    #[msgpack(key = 1)]
+    count: u32,
+    #[msgpack(key = -2, default)]
+    flag: bool,
+}
+
+#[derive(MsgPack, Debug, PartialEq)]
+enum Event {
+    #[msgpack(rename = "start")]
+    Start,
+    #[msgpack(rename = "stop")]
+    Stop { code: i32 },
+}
+
+#[derive(MsgPack, Debug, PartialEq)]
+#[msgpack(ext = 5, array)]
+struct Version {
+    major: u8,
+    minor: u8,
+}
+
+#[derive(MsgPack, Debug, PartialEq)]
+struct Wrapper<T> {
+    inner: Vec<T>,
+}
+
+fn packed<T: Pack>(v: &T) -> Vec<u8> {
+    let mut packer = Packer::new(vec![]);
+    v.pack(&mut packer).unwrap();
+    packer.into_inner()
+}
+
+fn unpacked<T: Unpack>(buf: &[u8]) -> Result<T, UnpackError> {
+    T::unpack(&mut Unpacker::from_reader(buf))
+}
+
+fn round_trip<T: Pack + Unpack + PartialEq + Debug>(v: T) {
+    assert_eq!(unpacked::<T>(&packed(&v)).unwrap(), v);
+}
+
+fn config() -> Config {
+    Config {
+        name: "plugin".to_owned(),
+        retries: 3,
+        timeout: Some(1.5),
+        tags: vec!["a".to_owned(), "b".to_owned()],
+        labels: [("x".to_owned(), -1), ("y".to_owned(), 2)].into(),
+        outputs: vec![
+            Output::Stdout,
+            Output::File("out.log".to_owned()),
+            Output::Forward {
+                host: "localhost".to_owned(),
+                port: 24224,
+            },
+            Output::Pair(1, 2),
+        ],
+        point: Point(-1, 1),
+        id: Id(7),
+        marker: Marker,
+    }
+}
+
+#[test]
+fn same_as_msgpack_serde() {
+    let buf = packed(&config());
+    assert_eq!(buf, msgpack_serde::pack(&config()).unwrap());
+    assert_eq!(unpacked::<Config>(&buf).unwrap(), config());
+    assert_eq!(msgpack_serde::unpack::<Config>(&buf).unwrap(), config());
+}
+
+#[test]
+fn unknown_keys_are_skipped() {
+    let mut buf = vec![];
+    let mut packer = Packer::new(&mut buf);
+    msgpack::MessagePacker::pack_map_header(&mut packer, 3).unwrap();
+    msgpack::MessagePacker::pack_str(&mut packer, "n").unwrap();
+    msgpack::MessagePacker::pack_str(&mut packer, "x").unwrap();
+    msgpack::MessagePacker::pack_str(&mut packer, "other").unwrap();
+    vec![1u8, 2].pack(&mut packer).unwrap();
+    msgpack::MessagePacker::pack_uint(&mut packer, 1).unwrap();
+    msgpack::MessagePacker::pack_uint(&mut packer, 5).unwrap();
+
+    let v: Renamed = unpacked(&buf).unwrap();
+    assert_eq!(
+        v,
+        Renamed {
+            name: "x".to_owned(),
+            count: 5,
+            flag: false,
+        }
+    );
+}
+
+#[test]
+fn renamed_and_keyed() {
+    let v = Renamed {
+        name: "x".to_owned(),
+        count: 2,
+        flag: true,
+    };
+    assert_eq!(
+        packed(&v),
+        [0x83, 0xa1, b'n', 0xa1, b'x', 0x01, 0x02, 0xfe, 0xc3]
+    );
+    round_trip(v);
+
+    let e = unpacked::<Renamed>(&[0x81, 0xa1, b'n', 0xa1, b'x']).unwrap_err();
+    assert!(matches!(e, UnpackError::MissingField(ref name) if name == "1"));
+}
+
+#[test]
+fn array_layout() {
+    let row = Row {
+        a: 1,
+        cached: Some("ignored".to_owned()),
+        b: "b".to_owned(),
+        c: vec![2],
+    };
+    let buf = packed(&row);
+    assert_eq!(buf, [0x93, 0x01, 0xa1, b'b', 0x91, 0x02]);
+    assert_eq!(
+        unpacked::<Row>(&buf).unwrap(),
+        Row {
+            cached: None,
+            ..row
+        }
+    );
+
+    // trailing defaults may be left out, and extra elements are skipped
+    let short: Row = unpacked(&[0x92, 0x01, 0xa1, b'b']).unwrap();
+    assert_eq!(short.c, Vec::<u8>::new());
+    let long: Row = unpacked(&[0x94, 0x01, 0xa1, b'b', 0x90, 0xc0]).unwrap();
+    assert_eq!(long.b, "b");
+    let e = unpacked::<Row>(&[0x91, 0x01]).unwrap_err();
+    assert!(matches!(e, UnpackError::LengthMismatch(3, 1)));
+}
+
+#[test]
+fn enums() {
+    round_trip(Event::Start);
+    round_trip(Event::Stop { code: -1 });
+    assert_eq!(
+        packed(&Event::Start),
+        [0x81, 0xa5, b's', b't', b'a', b'r', b't', 0xc0]
+    );
+
+    // a unit variant can be just its name
+    let v: Event = unpacked(&[0xa5, b's', b't', b'a', b'r', b't']).unwrap();
+    assert_eq!(v, Event::Start);
+    let e = unpacked::<Event>(&[0xa4, b's', b't', b'o', b'p']).unwrap_err();
+    assert!(matches!(e, UnpackError::UnknownVariant(ref name) if name == "stop"));
+    let e = unpacked::<Event>(&[0xa1, b'x']).unwrap_err();
+    assert!(matches!(e, UnpackError::UnknownVariant(_)));
+}
+
+#[test]
+fn ext() {
+    let v = Version { major: 1, minor: 2 };
+    assert_eq!(packed(&v), [0xc7, 0x03, 0x05, 0x92, 0x01, 0x02]);
+    round_trip(v);
+
+    let e = unpacked::<Version>(&[0xc7, 0x03, 0x06, 0x92, 0x01, 0x02]).unwrap_err();
+    assert!(matches!(e, UnpackError::ExtTypeMismatch(5, 6)));
+}
+
+#[test]
+fn generics() {
+    round_trip(Wrapper {
+        inner: vec![Point(1, 2)],
+    });
+}
+
+#[test]
+fn error_path() {
+    #[derive(MsgPack, Debug)]
+    struct Outer {
+        point: Point,
+    }
+
+    let mut buf = vec![];
+    let mut packer = Packer::new(&mut buf);
+    msgpack::MessagePacker::pack_map_header(&mut packer, 1).unwrap();
+    msgpack::MessagePacker::pack_str(&mut packer, "point").unwrap();
+    (1i32, i64::MAX).pack(&mut packer).unwrap();
+
+    let e = unpacked::<Outer>(&buf).unwrap_err();
+    assert_eq!(e.path().unwrap(), "$.point[1]");
+}
@@ -0,0 +1,62 @@
+use msgpack::{PackError, UnpackError};
+use std::error;
+use std::fmt::{self, Display, Formatter};
+use std::io;
+
+#[derive(Debug)]
+pub enum ForwardError {
+    PackError(PackError),
+    UnpackError(UnpackError),
+    // failures of the transport or of gzip
+    Io(io::Error),
+    // a well formed object which doesn't follow the forward protocol
+    InvalidMessage(String),
+    // an ack whose chunk id isn't the one which was sent
+    AckMismatch(String),
+    // compressed entries which inflate past this many bytes
+    DecompressedTooLong(usize),
+}
+
+impl From<PackError> for ForwardError {
+    fn from(err: PackError) -> ForwardError {
+        ForwardError::PackError(err)
+    }
+}
+
+impl From<UnpackError> for ForwardError {
+    fn from(err: UnpackError) -> ForwardError {
+        ForwardError::UnpackError(err)
+    }
+}
+
+impl From<io::Error> for ForwardError {
+    fn from(err: io::Error) -> ForwardError {
+        ForwardError::Io(err)
+    }
+}
+
+impl Display for ForwardError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            ForwardError::PackError(ref e) => e.fmt(f),
+            ForwardError::UnpackError(ref e) => e.fmt(f),
+            ForwardError::Io(ref e) => e.fmt(f),
+            ForwardError::InvalidMessage(ref s) => write!(f, "invalid message: {}", s),
+            ForwardError::AckMismatch(ref s) => write!(f, "unexpected ack {}", s),
+            ForwardError::DecompressedTooLong(max) => {
+                write!(f, "compressed entries inflate past {} bytes", max)
+            }
+        }
+    }
+}
+
+impl error::Error for ForwardError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            ForwardError::PackError(ref e) => Some(e),
+            ForwardError::UnpackError(ref e) => Some(e),
+            ForwardError::Io(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
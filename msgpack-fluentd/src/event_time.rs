@@ -0,0 +1,73 @@
+use crate::ForwardError;
+
+use msgpack_value::{Integer, IntegerNumber, Value};
+
+// the ext type fluentd uses for EventTime
+pub const EVENT_TIME_TYPE: i8 = 0;
+
+// seconds and nanoseconds since the unix epoch, as carried by fluentd's EventTime ext.
+// both are packed as big-endian u32
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct EventTime {
+    pub sec: u32,
+    pub nsec: u32,
+}
+
+impl EventTime {
+    pub fn new(sec: u32, nsec: u32) -> Self {
+        EventTime { sec, nsec }
+    }
+
+    pub fn to_value(self) -> Value {
+        let mut data = Vec::with_capacity(8);
+        data.extend_from_slice(&self.sec.to_be_bytes());
+        data.extend_from_slice(&self.nsec.to_be_bytes());
+        Value::Extension(EVENT_TIME_TYPE, data)
+    }
+
+    // accepts an EventTime ext, the timestamp ext or plain integer seconds
+    pub fn from_value(v: &Value) -> Result<Self, ForwardError> {
+        match *v {
+            Value::Extension(EVENT_TIME_TYPE, ref data) if data.len() == 8 => {
+                let sec = u32::from_be_bytes([data[0], data[1], data[2], data[3]]);
+                let nsec = u32::from_be_bytes([data[4], data[5], data[6], data[7]]);
+                Ok(EventTime { sec, nsec })
+            }
+            Value::Timestamp(sec, nsec) => match u32::try_from(sec) {
+                Ok(sec) => Ok(EventTime { sec, nsec }),
+                Err(_) => Err(invalid_time(v)),
+            },
+            Value::Integer(Integer {
+                n: IntegerNumber::PosInt(sec),
+            }) => match u32::try_from(sec) {
+                Ok(sec) => Ok(EventTime { sec, nsec: 0 }),
+                Err(_) => Err(invalid_time(v)),
+            },
+            _ => Err(invalid_time(v)),
+        }
+    }
+}
+
+fn invalid_time(v: &Value) -> ForwardError {
+    ForwardError::InvalidMessage(format!("invalid time {:?}", v))
+}
+
+impl From<EventTime> for msgpack::Timestamp {
+    fn from(t: EventTime) -> Self {
+        // EventTime allows nsec up to u32::MAX, carry the excess into sec
+        let sec = i64::from(t.sec) + i64::from(t.nsec / 1_000_000_000);
+        msgpack::Timestamp::new(sec, t.nsec % 1_000_000_000).unwrap()
+    }
+}
+
+impl TryFrom<msgpack::Timestamp> for EventTime {
+    type Error = msgpack::TimestampRangeError;
+
+    fn try_from(t: msgpack::Timestamp) -> Result<Self, Self::Error> {
+        let sec = u32::try_from(t.sec()).map_err(|_| msgpack::TimestampRangeError)?;
+        Ok(EventTime {
+            sec,
+            nsec: t.nsec(),
+        })
+    }
+}
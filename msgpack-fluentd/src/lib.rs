@@ -0,0 +1,9 @@
+mod error;
+mod event_time;
+mod message;
+mod server;
+
+pub use error::ForwardError;
+pub use event_time::{EventTime, EVENT_TIME_TYPE};
+pub use message::{ack_value, read_ack, Entry, ForwardLimits, ForwardMessage, Mode, Options};
+pub use server::{Event, EventReader};
@@ -0,0 +1,353 @@
+use crate::{EventTime, ForwardError};
+
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use msgpack::DecodeLimits;
+use msgpack_value::{pack_value, unpack_value_with_limits, Integer, IntegerNumber, Value};
+use std::io::{self, Read, Write};
+
+const GZIP: &str = "gzip";
+
+const DEFAULT_MAX_DECOMPRESSED_LEN: usize = 64 * 1024 * 1024;
+
+// an ack is one small map, so a reply which is anything more fails early
+const ACK_LIMITS: DecodeLimits = DecodeLimits {
+    max_depth: 2,
+    max_container_len: 16,
+    max_data_len: 1024,
+    max_alloc: 64 * 1024,
+    strict: false,
+};
+
+// what decoding a message from an untrusted peer may take on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ForwardLimits {
+    // applied to the message, and again to each entry of a packed message
+    pub decode: DecodeLimits,
+    // the most bytes the entries of a CompressedPackedForward message may inflate to
+    pub max_decompressed_len: usize,
+}
+
+impl Default for ForwardLimits {
+    fn default() -> Self {
+        ForwardLimits {
+            decode: DecodeLimits::default(),
+            max_decompressed_len: DEFAULT_MAX_DECOMPRESSED_LEN,
+        }
+    }
+}
+
+// how the entries of a message are laid out on the wire
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    // [tag, time, record, option?], exactly one entry
+    Message,
+    // [tag, [[time, record], ...], option?]
+    Forward,
+    // [tag, bin of concatenated [time, record], option?]
+    PackedForward,
+    // PackedForward with the entries gzipped and option "compressed" set to "gzip"
+    CompressedPackedForward,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    pub time: EventTime,
+    pub record: Value,
+}
+
+impl Entry {
+    pub fn new(time: EventTime, record: Value) -> Self {
+        Entry { time, record }
+    }
+
+    fn to_value(&self) -> Value {
+        Value::Array(vec![self.time.to_value(), self.record.clone()])
+    }
+
+    fn from_value(v: Value) -> Result<Self, ForwardError> {
+        match v {
+            Value::Array(v) if v.len() == 2 => {
+                let mut v = v.into_iter();
+                let time = EventTime::from_value(&v.next().unwrap())?;
+                let record = v.next().unwrap();
+                Ok(Entry { time, record })
+            }
+            v => Err(invalid(format!("invalid entry {:?}", v))),
+        }
+    }
+}
+
+// the option map which may end any message. other keys are ignored
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Options {
+    // the number of entries, for the packed modes
+    pub size: Option<u64>,
+    // a chunk id the server acknowledges with {"ack": chunk}
+    pub chunk: Option<String>,
+    pub compressed: Option<String>,
+}
+
+impl Options {
+    fn is_empty(&self) -> bool {
+        self.size.is_none() && self.chunk.is_none() && self.compressed.is_none()
+    }
+
+    fn to_value(&self) -> Value {
+        let mut map = vec![];
+        if let Some(size) = self.size {
+            map.push((str_value("size"), Value::from(size)));
+        }
+        if let Some(ref chunk) = self.chunk {
+            map.push((str_value("chunk"), Value::from(chunk.clone())));
+        }
+        if let Some(ref compressed) = self.compressed {
+            map.push((str_value("compressed"), Value::from(compressed.clone())));
+        }
+        Value::Map(map)
+    }
+
+    fn from_value(v: Value) -> Result<Self, ForwardError> {
+        let map = match v {
+            Value::Map(map) => map,
+            Value::Nil => return Ok(Options::default()),
+            v => return Err(invalid(format!("invalid option {:?}", v))),
+        };
+
+        let mut options = Options::default();
+        for (k, v) in map {
+            match as_str(&k) {
+                Some("size") => options.size = as_u64(&v),
+                Some("chunk") => options.chunk = into_string(v),
+                Some("compressed") => options.compressed = into_string(v),
+                _ => {}
+            }
+        }
+        Ok(options)
+    }
+}
+
+// one message of the forward protocol, whichever mode it was sent in
+#[derive(Clone, Debug, PartialEq)]
+pub struct ForwardMessage {
+    pub tag: String,
+    pub entries: Vec<Entry>,
+    pub options: Options,
+    pub mode: Mode,
+}
+
+impl ForwardMessage {
+    pub fn new(tag: &str, entries: Vec<Entry>, mode: Mode) -> Self {
+        ForwardMessage {
+            tag: tag.to_owned(),
+            entries,
+            options: Options::default(),
+            mode,
+        }
+    }
+
+    // asks the server to acknowledge this message
+    pub fn with_chunk(mut self, chunk: &str) -> Self {
+        self.options.chunk = Some(chunk.to_owned());
+        self
+    }
+
+    // size and compressed are filled in from the mode
+    pub fn to_value(&self) -> Result<Value, ForwardError> {
+        let mut options = self.options.clone();
+        let mut fields = vec![Value::from(self.tag.clone())];
+        match self.mode {
+            Mode::Message => match self.entries.as_slice() {
+                [entry] => {
+                    fields.push(entry.time.to_value());
+                    fields.push(entry.record.clone());
+                }
+                entries => {
+                    return Err(invalid(format!(
+                        "message mode carries exactly one entry, got {}",
+                        entries.len()
+                    )))
+                }
+            },
+            Mode::Forward => {
+                fields.push(Value::Array(
+                    self.entries.iter().map(Entry::to_value).collect(),
+                ));
+            }
+            Mode::PackedForward | Mode::CompressedPackedForward => {
+                let mut packed = vec![];
+                for entry in &self.entries {
+                    pack_value(&mut packed, entry.to_value())?;
+                }
+                if self.mode == Mode::CompressedPackedForward {
+                    let mut gz = GzEncoder::new(vec![], Compression::default());
+                    gz.write_all(&packed)?;
+                    packed = gz.finish()?;
+                    options.compressed = Some(GZIP.to_owned());
+                }
+                options.size = Some(self.entries.len() as u64);
+                fields.push(Value::Binary(packed));
+            }
+        }
+
+        if !options.is_empty() {
+            fields.push(options.to_value());
+        }
+        Ok(Value::Array(fields))
+    }
+
+    pub fn from_value(v: Value) -> Result<Self, ForwardError> {
+        ForwardMessage::from_value_with_limits(v, ForwardLimits::default())
+    }
+
+    // the limits apply to the entries packed into a bin, which `v` holds still encoded
+    pub fn from_value_with_limits(v: Value, limits: ForwardLimits) -> Result<Self, ForwardError> {
+        let mut fields = match v {
+            Value::Array(fields) if (2..=4).contains(&fields.len()) => fields.into_iter(),
+            v => return Err(invalid(format!("invalid message {:?}", v))),
+        };
+
+        let tag = fields
+            .next()
+            .and_then(into_string)
+            .ok_or_else(|| invalid("tag is not a string".to_owned()))?;
+        let (mode, entries, options) = match fields.next().unwrap() {
+            Value::Array(entries) => {
+                let entries = entries
+                    .into_iter()
+                    .map(Entry::from_value)
+                    .collect::<Result<_, _>>()?;
+                (Mode::Forward, entries, last_options(fields)?)
+            }
+            Value::Binary(packed) => packed_forward(packed, last_options(fields)?, limits)?,
+            Value::String(s) => {
+                packed_forward(s.as_slice().to_vec(), last_options(fields)?, limits)?
+            }
+            time => {
+                let time = EventTime::from_value(&time)?;
+                let record = fields
+                    .next()
+                    .ok_or_else(|| invalid("record is missing".to_owned()))?;
+                let entries = vec![Entry { time, record }];
+                (Mode::Message, entries, last_options(fields)?)
+            }
+        };
+
+        Ok(ForwardMessage {
+            tag,
+            entries,
+            options,
+            mode,
+        })
+    }
+
+    pub fn pack<W: io::Write>(&self, writer: &mut W) -> Result<(), ForwardError> {
+        pack_value(writer, self.to_value()?).map_err(ForwardError::from)
+    }
+
+    pub fn unpack<R: io::Read>(reader: &mut R) -> Result<Self, ForwardError> {
+        ForwardMessage::unpack_with_limits(reader, ForwardLimits::default())
+    }
+
+    pub fn unpack_with_limits<R: io::Read>(
+        reader: &mut R,
+        limits: ForwardLimits,
+    ) -> Result<Self, ForwardError> {
+        let v = unpack_value_with_limits(reader, limits.decode)?;
+        ForwardMessage::from_value_with_limits(v, limits)
+    }
+}
+
+// {"ack": chunk}, sent back for a message whose options carry a chunk
+pub fn ack_value(chunk: &str) -> Value {
+    Value::Map(vec![(str_value("ack"), Value::from(chunk.to_owned()))])
+}
+
+// reads the server's ack and checks it is for `chunk`
+pub fn read_ack<R: io::Read>(reader: &mut R, chunk: &str) -> Result<(), ForwardError> {
+    let ack = match unpack_value_with_limits(reader, ACK_LIMITS)? {
+        Value::Map(map) => map
+            .into_iter()
+            .find(|(k, _)| as_str(k) == Some("ack"))
+            .and_then(|(_, v)| into_string(v)),
+        _ => None,
+    };
+    match ack {
+        Some(ref ack) if ack == chunk => Ok(()),
+        Some(ack) => Err(ForwardError::AckMismatch(ack)),
+        None => Err(invalid("response has no ack".to_owned())),
+    }
+}
+
+fn packed_forward(
+    packed: Vec<u8>,
+    options: Options,
+    limits: ForwardLimits,
+) -> Result<(Mode, Vec<Entry>, Options), ForwardError> {
+    let (mode, packed) = match options.compressed.as_deref() {
+        None => (Mode::PackedForward, packed),
+        Some(GZIP) => {
+            // concatenated gzip members are allowed, one per appended chunk. one byte past the
+            // limit is enough to tell it was hit
+            let max = limits.max_decompressed_len;
+            let mut buf = vec![];
+            MultiGzDecoder::new(&packed[..])
+                .take(max as u64 + 1)
+                .read_to_end(&mut buf)?;
+            if buf.len() > max {
+                return Err(ForwardError::DecompressedTooLong(max));
+            }
+            (Mode::CompressedPackedForward, buf)
+        }
+        Some(v) => return Err(invalid(format!("unknown compression {}", v))),
+    };
+
+    let mut entries = vec![];
+    let mut rd = &packed[..];
+    while !rd.is_empty() {
+        entries.push(Entry::from_value(unpack_value_with_limits(
+            &mut rd,
+            limits.decode,
+        )?)?);
+    }
+    Ok((mode, entries, options))
+}
+
+fn last_options<I: Iterator<Item = Value>>(mut fields: I) -> Result<Options, ForwardError> {
+    match fields.next() {
+        Some(v) => Options::from_value(v),
+        None => Ok(Options::default()),
+    }
+}
+
+fn invalid(msg: String) -> ForwardError {
+    ForwardError::InvalidMessage(msg)
+}
+
+fn str_value(s: &str) -> Value {
+    Value::from(s.to_owned())
+}
+
+fn as_str(v: &Value) -> Option<&str> {
+    match *v {
+        Value::String(ref s) => s.as_str(),
+        _ => None,
+    }
+}
+
+fn into_string(v: Value) -> Option<String> {
+    match v {
+        Value::String(s) => s.into_string(),
+        _ => None,
+    }
+}
+
+fn as_u64(v: &Value) -> Option<u64> {
+    match *v {
+        Value::Integer(Integer {
+            n: IntegerNumber::PosInt(v),
+        }) => Some(v),
+        _ => None,
+    }
+}
@@ -0,0 +1,76 @@
+use crate::{ack_value, EventTime, ForwardError, ForwardLimits, ForwardMessage};
+
+use msgpack_value::{pack_value, Value};
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader};
+
+// one record as a server sees it, however it was batched on the wire
+#[derive(Clone, Debug, PartialEq)]
+pub struct Event {
+    pub tag: String,
+    pub time: EventTime,
+    pub record: Value,
+}
+
+// reads forward messages from a connection and yields their events one at a time.
+// messages carrying a chunk id are acknowledged on `writer` once they are fully decoded
+#[derive(Debug)]
+pub struct EventReader<R, W> {
+    rd: BufReader<R>,
+    wr: W,
+    events: VecDeque<Event>,
+    limits: ForwardLimits,
+}
+
+impl<R: io::Read, W: io::Write> EventReader<R, W> {
+    pub fn new(reader: R, writer: W) -> Self {
+        EventReader {
+            rd: BufReader::new(reader),
+            wr: writer,
+            events: VecDeque::new(),
+            limits: ForwardLimits::default(),
+        }
+    }
+
+    // limits applied to every message read from the connection
+    pub fn with_limits(mut self, limits: ForwardLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    // the next message as it was sent, without splitting it into events. it is acked first
+    pub fn read_message(&mut self) -> Result<Option<ForwardMessage>, ForwardError> {
+        if self.rd.fill_buf()?.is_empty() {
+            return Ok(None);
+        }
+
+        let msg = ForwardMessage::unpack_with_limits(&mut self.rd, self.limits)?;
+        if let Some(ref chunk) = msg.options.chunk {
+            pack_value(&mut self.wr, ack_value(chunk))?;
+            self.wr.flush()?;
+        }
+        Ok(Some(msg))
+    }
+}
+
+impl<R: io::Read, W: io::Write> Iterator for EventReader<R, W> {
+    type Item = Result<Event, ForwardError>;
+
+    // None once the connection ends between messages
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.events.is_empty() {
+            let msg = match self.read_message() {
+                Ok(Some(msg)) => msg,
+                Ok(None) => return None,
+                Err(e) => return Some(Err(e)),
+            };
+            let tag = msg.tag;
+            self.events.extend(msg.entries.into_iter().map(|e| Event {
+                tag: tag.clone(),
+                time: e.time,
+                record: e.record,
+            }));
+        }
+        self.events.pop_front().map(Ok)
+    }
+}
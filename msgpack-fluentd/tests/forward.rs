@@ -0,0 +1,210 @@
+use msgpack::{DecodeLimits, Limit, UnpackError};
+use msgpack_fluentd::{
+    read_ack, Entry, Event, EventReader, EventTime, ForwardError, ForwardLimits, ForwardMessage,
+    Mode,
+};
+use msgpack_value::{pack_value, Value};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::thread;
+
+fn record(n: u64) -> Value {
+    Value::Map(vec![(Value::from("n".to_owned()), Value::from(n))])
+}
+
+fn entries() -> Vec<Entry> {
+    (0..3)
+        .map(|n| Entry::new(EventTime::new(1_500_000_000 + n as u32, 7), record(n)))
+        .collect()
+}
+
+#[test]
+fn event_time() {
+    let t = EventTime::new(1, 2);
+    let mut buf = vec![];
+    pack_value(&mut buf, t.to_value()).unwrap();
+    assert_eq!(
+        buf,
+        vec![0xd7, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x02]
+    );
+    assert_eq!(EventTime::from_value(&t.to_value()).unwrap(), t);
+
+    // integer seconds and the timestamp ext are accepted too
+    assert_eq!(
+        EventTime::from_value(&Value::from(5u64)).unwrap(),
+        EventTime::new(5, 0)
+    );
+    assert_eq!(
+        EventTime::from_value(&Value::Timestamp(5, 6)).unwrap(),
+        EventTime::new(5, 6)
+    );
+    assert!(EventTime::from_value(&Value::Timestamp(-1, 0)).is_err());
+    assert_eq!(
+        msgpack::Timestamp::from(t),
+        msgpack::Timestamp::new(1, 2).unwrap()
+    );
+}
+
+#[test]
+fn modes() {
+    for mode in [
+        Mode::Forward,
+        Mode::PackedForward,
+        Mode::CompressedPackedForward,
+    ] {
+        let msg = ForwardMessage::new("app.log", entries(), mode).with_chunk("c1");
+        let mut buf = vec![];
+        msg.pack(&mut buf).unwrap();
+        let actual = ForwardMessage::unpack(&mut &buf[..]).unwrap();
+        assert_eq!(actual.mode, mode);
+        assert_eq!(actual.tag, "app.log");
+        assert_eq!(actual.entries, entries());
+        assert_eq!(actual.options.chunk.as_deref(), Some("c1"));
+    }
+
+    let one = vec![entries().remove(0)];
+    let msg = ForwardMessage::new("app.log", one.clone(), Mode::Message);
+    let v = msg.to_value().unwrap();
+    assert_eq!(
+        v,
+        Value::Array(vec![
+            Value::from("app.log".to_owned()),
+            one[0].time.to_value(),
+            one[0].record.clone(),
+        ])
+    );
+    assert_eq!(ForwardMessage::from_value(v).unwrap(), msg);
+
+    // message mode can't carry several entries
+    let msg = ForwardMessage::new("app.log", entries(), Mode::Message);
+    assert!(matches!(
+        msg.to_value(),
+        Err(ForwardError::InvalidMessage(_))
+    ));
+}
+
+#[test]
+fn compressed_members() {
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+
+    // two gzip members appended to each other decode as one stream of entries
+    let mut packed = vec![];
+    for entry in entries() {
+        let mut raw = vec![];
+        pack_value(
+            &mut raw,
+            Value::Array(vec![entry.time.to_value(), entry.record]),
+        )
+        .unwrap();
+        let mut gz = GzEncoder::new(vec![], Compression::fast());
+        gz.write_all(&raw).unwrap();
+        packed.extend(gz.finish().unwrap());
+    }
+
+    let v = Value::Array(vec![
+        Value::from("t".to_owned()),
+        Value::Binary(packed),
+        Value::Map(vec![(
+            Value::from("compressed".to_owned()),
+            Value::from("gzip".to_owned()),
+        )]),
+    ]);
+    let msg = ForwardMessage::from_value(v).unwrap();
+    assert_eq!(msg.mode, Mode::CompressedPackedForward);
+    assert_eq!(msg.entries, entries());
+}
+
+#[test]
+fn message_limits() {
+    let mut buf = vec![];
+    ForwardMessage::new("t", entries(), Mode::CompressedPackedForward)
+        .pack(&mut buf)
+        .unwrap();
+    let v = msgpack_value::unpack_value(&mut &buf[..]).unwrap();
+
+    // the entries inflate to 3 * 15 bytes
+    let limits = ForwardLimits {
+        max_decompressed_len: 45,
+        ..ForwardLimits::default()
+    };
+    assert!(ForwardMessage::from_value_with_limits(v.clone(), limits).is_ok());
+    let limits = ForwardLimits {
+        max_decompressed_len: 44,
+        ..ForwardLimits::default()
+    };
+    let err = ForwardMessage::from_value_with_limits(v.clone(), limits).unwrap_err();
+    assert!(matches!(err, ForwardError::DecompressedTooLong(44)));
+
+    // the decode limits hold for each packed entry as well as for the message
+    let limits = ForwardLimits {
+        decode: DecodeLimits {
+            max_depth: 1,
+            ..DecodeLimits::default()
+        },
+        ..ForwardLimits::default()
+    };
+    let err = ForwardMessage::from_value_with_limits(v, limits).unwrap_err();
+    assert!(matches!(
+        err,
+        ForwardError::UnpackError(ref e) if matches!(e.root(), UnpackError::DepthExceeded(1))
+    ));
+    let err = ForwardMessage::unpack_with_limits(&mut &buf[..], limits);
+    assert!(err.is_err());
+
+    let limits = ForwardLimits {
+        decode: DecodeLimits {
+            max_data_len: 0,
+            ..DecodeLimits::default()
+        },
+        ..ForwardLimits::default()
+    };
+    let err = ForwardMessage::unpack_with_limits(&mut &buf[..], limits).unwrap_err();
+    assert!(matches!(
+        err,
+        ForwardError::UnpackError(ref e)
+            if matches!(e.root(), UnpackError::LimitExceeded(Limit::DataLength, _))
+    ));
+}
+
+#[test]
+fn server_events() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let events = EventReader::new(stream.try_clone().unwrap(), stream);
+        events.collect::<Result<Vec<Event>, _>>().unwrap()
+    });
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    let modes = [
+        Mode::Forward,
+        Mode::PackedForward,
+        Mode::CompressedPackedForward,
+    ];
+    for (i, mode) in modes.iter().enumerate() {
+        let chunk = format!("chunk-{}", i);
+        let msg = ForwardMessage::new("app.log", entries(), *mode).with_chunk(&chunk);
+        msg.pack(&mut stream).unwrap();
+        read_ack(&mut stream, &chunk).unwrap();
+    }
+    // without a chunk no ack is sent
+    let one = vec![entries().remove(0)];
+    ForwardMessage::new("app.one", one, Mode::Message)
+        .pack(&mut stream)
+        .unwrap();
+    stream.shutdown(Shutdown::Write).unwrap();
+
+    let events = server.join().unwrap();
+    assert_eq!(events.len(), 10);
+    assert_eq!(
+        events[4],
+        Event {
+            tag: "app.log".to_owned(),
+            time: EventTime::new(1_500_000_001, 7),
+            record: record(1),
+        }
+    );
+    assert_eq!(events[9].tag, "app.one");
+}
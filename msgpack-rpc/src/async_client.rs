@@ -0,0 +1,101 @@
+use crate::client::Pending;
+use crate::{Message, Notification, Request, RpcError};
+
+use msgpack::AsyncPacker;
+use msgpack_value::{AsyncValueUnpacker, Value};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::oneshot;
+
+// the async counterpart of Client. calls can be awaited concurrently
+#[derive(Debug)]
+pub struct AsyncClient<W> {
+    wr: tokio::sync::Mutex<AsyncPacker<W>>,
+    next_msgid: AtomicU32,
+    pending: Pending<oneshot::Sender<Result<Value, Value>>>,
+}
+
+impl<W: AsyncWrite + Unpin> AsyncClient<W> {
+    // the returned future reads responses until `reader` ends. it has to be polled, usually by
+    // spawning it, for any call to complete
+    pub fn new<R>(reader: R, writer: W) -> (Self, impl Future<Output = ()>)
+    where
+        R: AsyncRead + Unpin,
+    {
+        let pending: Pending<_> = Arc::new(Mutex::new(Some(HashMap::new())));
+        let client = AsyncClient {
+            wr: tokio::sync::Mutex::new(AsyncPacker::new(writer)),
+            next_msgid: AtomicU32::new(0),
+            pending: Arc::clone(&pending),
+        };
+        (client, read_responses(reader, pending))
+    }
+
+    pub async fn call(&self, method: &str, params: Vec<Value>) -> Result<Value, RpcError> {
+        let msgid = self.next_msgid.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = oneshot::channel();
+        match *self.pending.lock().unwrap() {
+            Some(ref mut pending) => pending.insert(msgid, tx),
+            None => return Err(RpcError::Disconnected),
+        };
+
+        let req = Request {
+            msgid,
+            method: method.to_owned(),
+            params,
+        };
+        if let Err(e) = self.send(req.into()).await {
+            if let Some(ref mut pending) = *self.pending.lock().unwrap() {
+                pending.remove(&msgid);
+            }
+            return Err(e);
+        }
+
+        match rx.await {
+            Ok(Ok(v)) => Ok(v),
+            Ok(Err(e)) => Err(RpcError::Remote(e)),
+            Err(_) => Err(RpcError::Disconnected),
+        }
+    }
+
+    pub async fn notify(&self, method: &str, params: Vec<Value>) -> Result<(), RpcError> {
+        let n = Notification {
+            method: method.to_owned(),
+            params,
+        };
+        self.send(n.into()).await
+    }
+
+    async fn send(&self, msg: Message) -> Result<(), RpcError> {
+        let mut buf = vec![];
+        msg.pack(&mut buf)?;
+
+        let mut wr = self.wr.lock().await;
+        wr.write_raw(&buf).await?;
+        wr.flush().await?;
+        Ok(())
+    }
+}
+
+async fn read_responses<R>(reader: R, pending: Pending<oneshot::Sender<Result<Value, Value>>>)
+where
+    R: AsyncRead + Unpin,
+{
+    let mut unpacker = AsyncValueUnpacker::new(reader);
+    while let Some(Ok(v)) = unpacker.next().await {
+        if let Ok(Message::Response(res)) = Message::from_value(v) {
+            let tx = match *pending.lock().unwrap() {
+                Some(ref mut pending) => pending.remove(&res.msgid),
+                None => None,
+            };
+            if let Some(tx) = tx {
+                let _ = tx.send(res.result);
+            }
+        }
+    }
+
+    pending.lock().unwrap().take();
+}
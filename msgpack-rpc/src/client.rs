@@ -0,0 +1,109 @@
+use crate::{Message, Notification, Request, RpcError};
+
+use msgpack_value::Value;
+use std::collections::HashMap;
+use std::io::{self, BufReader};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+pub(crate) type Pending<T> = Arc<Mutex<Option<HashMap<u32, T>>>>;
+
+// calls can be made from several threads at once. a background thread reads responses and
+// hands each one to the caller waiting on its msgid
+#[derive(Debug)]
+pub struct Client<W> {
+    wr: Mutex<W>,
+    next_msgid: AtomicU32,
+    // None once the connection is closed
+    pending: Pending<mpsc::Sender<Result<Value, Value>>>,
+}
+
+impl<W: io::Write> Client<W> {
+    // for a socket, `reader` and `writer` are usually two handles of the same stream.
+    // the reader thread runs until `reader` ends or fails
+    pub fn new<R>(reader: R, writer: W) -> Self
+    where
+        R: io::Read + Send + 'static,
+    {
+        let pending: Pending<_> = Arc::new(Mutex::new(Some(HashMap::new())));
+        let shared = Arc::clone(&pending);
+        thread::spawn(move || read_responses(reader, shared));
+
+        Client {
+            wr: Mutex::new(writer),
+            next_msgid: AtomicU32::new(0),
+            pending,
+        }
+    }
+
+    // sends a request and blocks until its response arrives
+    pub fn call(&self, method: &str, params: Vec<Value>) -> Result<Value, RpcError> {
+        let msgid = self.next_msgid.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = mpsc::channel();
+        match *self.pending.lock().unwrap() {
+            Some(ref mut pending) => pending.insert(msgid, tx),
+            None => return Err(RpcError::Disconnected),
+        };
+
+        let req = Request {
+            msgid,
+            method: method.to_owned(),
+            params,
+        };
+        if let Err(e) = self.send(req.into()) {
+            if let Some(ref mut pending) = *self.pending.lock().unwrap() {
+                pending.remove(&msgid);
+            }
+            return Err(e);
+        }
+
+        match rx.recv() {
+            Ok(Ok(v)) => Ok(v),
+            Ok(Err(e)) => Err(RpcError::Remote(e)),
+            Err(_) => Err(RpcError::Disconnected),
+        }
+    }
+
+    pub fn notify(&self, method: &str, params: Vec<Value>) -> Result<(), RpcError> {
+        let n = Notification {
+            method: method.to_owned(),
+            params,
+        };
+        self.send(n.into())
+    }
+
+    fn send(&self, msg: Message) -> Result<(), RpcError> {
+        // encoded up front so the lock is held only for the write
+        let mut buf = vec![];
+        msg.pack(&mut buf)?;
+
+        let mut wr = self.wr.lock().unwrap();
+        wr.write_all(&buf).map_err(msgpack::PackError::from)?;
+        wr.flush().map_err(msgpack::PackError::from)?;
+        Ok(())
+    }
+}
+
+fn read_responses<R: io::Read>(reader: R, pending: Pending<mpsc::Sender<Result<Value, Value>>>) {
+    let mut reader = BufReader::new(reader);
+    loop {
+        match Message::unpack(&mut reader) {
+            Ok(Some(Message::Response(res))) => {
+                let tx = match *pending.lock().unwrap() {
+                    Some(ref mut pending) => pending.remove(&res.msgid),
+                    None => None,
+                };
+                if let Some(tx) = tx {
+                    let _ = tx.send(res.result);
+                }
+            }
+            // anything else isn't meant for a client. the stream is still in sync, so go on
+            Ok(Some(_)) | Err(RpcError::InvalidMessage(_)) => {}
+            Ok(None) | Err(_) => break,
+        }
+    }
+
+    // dropping the senders wakes every caller still waiting
+    pending.lock().unwrap().take();
+}
@@ -0,0 +1,50 @@
+use msgpack::{PackError, UnpackError};
+use msgpack_value::Value;
+use std::error;
+use std::fmt::{self, Display, Formatter};
+
+#[derive(Debug)]
+pub enum RpcError {
+    PackError(PackError),
+    UnpackError(UnpackError),
+    // a well formed object which isn't a request, response or notification
+    InvalidMessage(String),
+    // the error a server returned for a call
+    Remote(Value),
+    // the connection closed before a response arrived
+    Disconnected,
+}
+
+impl From<PackError> for RpcError {
+    fn from(err: PackError) -> RpcError {
+        RpcError::PackError(err)
+    }
+}
+
+impl From<UnpackError> for RpcError {
+    fn from(err: UnpackError) -> RpcError {
+        RpcError::UnpackError(err)
+    }
+}
+
+impl Display for RpcError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            RpcError::PackError(ref e) => e.fmt(f),
+            RpcError::UnpackError(ref e) => e.fmt(f),
+            RpcError::InvalidMessage(ref s) => write!(f, "invalid message: {}", s),
+            RpcError::Remote(ref v) => write!(f, "remote error: {:?}", v),
+            RpcError::Disconnected => f.write_str("connection closed"),
+        }
+    }
+}
+
+impl error::Error for RpcError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            RpcError::PackError(ref e) => Some(e),
+            RpcError::UnpackError(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,13 @@
+#[cfg(feature = "async")]
+mod async_client;
+mod client;
+mod error;
+mod message;
+mod server;
+
+#[cfg(feature = "async")]
+pub use async_client::AsyncClient;
+pub use client::Client;
+pub use error::RpcError;
+pub use message::{Message, Notification, Request, Response};
+pub use server::Dispatcher;
@@ -0,0 +1,175 @@
+use crate::RpcError;
+
+use msgpack::UnpackError;
+use msgpack_value::{pack_value, unpack_value, Integer, IntegerNumber, Value};
+use std::io;
+
+const REQUEST: u64 = 0;
+const RESPONSE: u64 = 1;
+const NOTIFICATION: u64 = 2;
+
+// [0, msgid, method, params]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Request {
+    pub msgid: u32,
+    pub method: String,
+    pub params: Vec<Value>,
+}
+
+// [1, msgid, error, result]. the error is nil exactly when the call succeeded
+#[derive(Clone, Debug, PartialEq)]
+pub struct Response {
+    pub msgid: u32,
+    pub result: Result<Value, Value>,
+}
+
+// [2, method, params]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Notification {
+    pub method: String,
+    pub params: Vec<Value>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Message {
+    Request(Request),
+    Response(Response),
+    Notification(Notification),
+}
+
+impl Message {
+    pub fn to_value(self) -> Value {
+        match self {
+            Message::Request(r) => Value::Array(vec![
+                Value::from(REQUEST),
+                Value::from(r.msgid),
+                Value::from(r.method),
+                Value::Array(r.params),
+            ]),
+            Message::Response(r) => {
+                let (error, result) = match r.result {
+                    Ok(v) => (Value::Nil, v),
+                    Err(e) => (e, Value::Nil),
+                };
+                Value::Array(vec![
+                    Value::from(RESPONSE),
+                    Value::from(r.msgid),
+                    error,
+                    result,
+                ])
+            }
+            Message::Notification(n) => Value::Array(vec![
+                Value::from(NOTIFICATION),
+                Value::from(n.method),
+                Value::Array(n.params),
+            ]),
+        }
+    }
+
+    pub fn from_value(v: Value) -> Result<Message, RpcError> {
+        let mut fields = match v {
+            Value::Array(fields) => fields.into_iter(),
+            v => return Err(invalid(format!("expected an array, got {:?}", v))),
+        };
+
+        let ty = fields.next().and_then(|v| uint(&v));
+        let msg = match (ty, fields.len()) {
+            (Some(REQUEST), 3) => Message::Request(Request {
+                msgid: msgid(fields.next())?,
+                method: method(fields.next())?,
+                params: params(fields.next())?,
+            }),
+            (Some(RESPONSE), 3) => {
+                let msgid = msgid(fields.next())?;
+                let error = fields.next().unwrap_or(Value::Nil);
+                let result = fields.next().unwrap_or(Value::Nil);
+                Message::Response(Response {
+                    msgid,
+                    result: match error {
+                        Value::Nil => Ok(result),
+                        e => Err(e),
+                    },
+                })
+            }
+            (Some(NOTIFICATION), 2) => Message::Notification(Notification {
+                method: method(fields.next())?,
+                params: params(fields.next())?,
+            }),
+            (ty, len) => {
+                return Err(invalid(format!(
+                    "unknown type {:?} with {} fields",
+                    ty,
+                    len + 1
+                )))
+            }
+        };
+        Ok(msg)
+    }
+
+    pub fn pack<W: io::Write>(self, writer: &mut W) -> Result<(), RpcError> {
+        pack_value(writer, self.to_value()).map_err(RpcError::from)
+    }
+
+    // reads the next message. Ok(None) means the reader ended cleanly between messages
+    pub fn unpack<R: io::BufRead>(reader: &mut R) -> Result<Option<Message>, RpcError> {
+        if reader.fill_buf().map_err(UnpackError::from)?.is_empty() {
+            return Ok(None);
+        }
+        let v = unpack_value(reader)?;
+        Message::from_value(v).map(Some)
+    }
+}
+
+impl From<Request> for Message {
+    fn from(v: Request) -> Self {
+        Message::Request(v)
+    }
+}
+
+impl From<Response> for Message {
+    fn from(v: Response) -> Self {
+        Message::Response(v)
+    }
+}
+
+impl From<Notification> for Message {
+    fn from(v: Notification) -> Self {
+        Message::Notification(v)
+    }
+}
+
+fn invalid(msg: String) -> RpcError {
+    RpcError::InvalidMessage(msg)
+}
+
+fn uint(v: &Value) -> Option<u64> {
+    match *v {
+        Value::Integer(Integer {
+            n: IntegerNumber::PosInt(v),
+        }) => Some(v),
+        _ => None,
+    }
+}
+
+fn msgid(v: Option<Value>) -> Result<u32, RpcError> {
+    v.as_ref()
+        .and_then(uint)
+        .and_then(|v| u32::try_from(v).ok())
+        .ok_or_else(|| invalid(format!("invalid msgid {:?}", v)))
+}
+
+fn method(v: Option<Value>) -> Result<String, RpcError> {
+    match v {
+        Some(Value::String(s)) => s
+            .into_string()
+            .ok_or_else(|| invalid("method is not valid utf-8".to_owned())),
+        v => Err(invalid(format!("invalid method {:?}", v))),
+    }
+}
+
+fn params(v: Option<Value>) -> Result<Vec<Value>, RpcError> {
+    match v {
+        Some(Value::Array(v)) => Ok(v),
+        v => Err(invalid(format!("invalid params {:?}", v))),
+    }
+}
@@ -0,0 +1,109 @@
+use crate::{Message, Response, RpcError};
+
+use msgpack_value::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, BufReader};
+
+type Handler = Box<dyn Fn(Vec<Value>) -> Result<Value, Value> + Send + Sync>;
+
+// routes requests and notifications to the handler registered for their method
+#[derive(Default)]
+pub struct Dispatcher {
+    methods: HashMap<String, Handler>,
+}
+
+impl Dispatcher {
+    pub fn new() -> Self {
+        Dispatcher::default()
+    }
+
+    // the handler's Err is sent back as the response's error. a later registration of the same
+    // method replaces the earlier one
+    pub fn register<F>(&mut self, method: &str, handler: F)
+    where
+        F: Fn(Vec<Value>) -> Result<Value, Value> + Send + Sync + 'static,
+    {
+        self.methods.insert(method.to_owned(), Box::new(handler));
+    }
+
+    // runs the handler for a request or notification. only requests get a response
+    pub fn dispatch(&self, msg: Message) -> Option<Response> {
+        match msg {
+            Message::Request(req) => Some(Response {
+                msgid: req.msgid,
+                result: self.call(&req.method, req.params),
+            }),
+            Message::Notification(n) => {
+                let _ = self.call(&n.method, n.params);
+                None
+            }
+            Message::Response(_) => None,
+        }
+    }
+
+    // handles messages one at a time until `reader` ends
+    pub fn serve<R, W>(&self, reader: R, mut writer: W) -> Result<(), RpcError>
+    where
+        R: io::Read,
+        W: io::Write,
+    {
+        let mut reader = BufReader::new(reader);
+        loop {
+            let msg = match Message::unpack(&mut reader) {
+                Ok(Some(msg)) => msg,
+                Ok(None) => return Ok(()),
+                Err(RpcError::InvalidMessage(_)) => continue,
+                Err(e) => return Err(e),
+            };
+
+            if let Some(res) = self.dispatch(msg) {
+                let mut buf = vec![];
+                Message::from(res).pack(&mut buf)?;
+                writer
+                    .write_all(&buf)
+                    .and_then(|_| writer.flush())
+                    .map_err(msgpack::PackError::from)?;
+            }
+        }
+    }
+
+    #[cfg(feature = "async")]
+    pub async fn serve_async<R, W>(&self, reader: R, writer: W) -> Result<(), RpcError>
+    where
+        R: tokio::io::AsyncRead + Unpin,
+        W: tokio::io::AsyncWrite + Unpin,
+    {
+        let mut unpacker = msgpack_value::AsyncValueUnpacker::new(reader);
+        let mut packer = msgpack::AsyncPacker::new(writer);
+        while let Some(v) = unpacker.next().await {
+            let msg = match Message::from_value(v?) {
+                Ok(msg) => msg,
+                Err(_) => continue,
+            };
+
+            if let Some(res) = self.dispatch(msg) {
+                let mut buf = vec![];
+                Message::from(res).pack(&mut buf)?;
+                packer.write_raw(&buf).await?;
+                packer.flush().await?;
+            }
+        }
+        Ok(())
+    }
+
+    fn call(&self, method: &str, params: Vec<Value>) -> Result<Value, Value> {
+        match self.methods.get(method) {
+            Some(handler) => handler(params),
+            None => Err(Value::from(format!("method not found: {}", method))),
+        }
+    }
+}
+
+impl fmt::Debug for Dispatcher {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Dispatcher")
+            .field("methods", &self.methods.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
@@ -0,0 +1,37 @@
+#![cfg(feature = "async")]
+
+use msgpack_rpc::{AsyncClient, Dispatcher, RpcError};
+use msgpack_value::Value;
+
+#[tokio::test]
+async fn async_client_server() {
+    // one pipe per direction so dropping a writer ends the other side's reader
+    let (client_wr, server_rd) = tokio::io::duplex(64);
+    let (server_wr, client_rd) = tokio::io::duplex(64);
+
+    let mut d = Dispatcher::new();
+    d.register("echo", |params| Ok(Value::Array(params)));
+    d.register("fail", |_| Err(Value::from(1u64)));
+
+    let (client, driver) = AsyncClient::new(client_rd, client_wr);
+    let calls = async move {
+        // several calls awaited at once, answered in order by the server
+        let (a, b, c) = tokio::join!(
+            client.call("echo", vec![Value::from(1u64)]),
+            client.call("echo", vec![Value::from("x".to_owned())]),
+            client.call("fail", vec![]),
+        );
+        assert_eq!(a.unwrap(), Value::Array(vec![Value::from(1u64)]));
+        assert_eq!(b.unwrap(), Value::Array(vec![Value::from("x".to_owned())]));
+        match c {
+            Err(RpcError::Remote(v)) => assert_eq!(v, Value::from(1u64)),
+            v => panic!("unexpected {:?}", v),
+        }
+        client.notify("echo", vec![]).await.unwrap();
+        // dropping the client closes its writer, which ends the server
+    };
+
+    let server = d.serve_async(server_rd, server_wr);
+    let (_, res, _) = tokio::join!(calls, server, driver);
+    res.unwrap();
+}
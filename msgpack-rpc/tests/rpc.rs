@@ -0,0 +1,153 @@
+use msgpack_rpc::{Client, Dispatcher, Message, Notification, Request, Response, RpcError};
+use msgpack_value::{Integer, IntegerNumber, Value};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+fn roundtrip(msg: Message) -> Message {
+    let mut buf = vec![];
+    msg.pack(&mut buf).unwrap();
+    Message::unpack(&mut &buf[..]).unwrap().unwrap()
+}
+
+#[test]
+fn messages() {
+    let req = Message::from(Request {
+        msgid: 7,
+        method: "add".to_owned(),
+        params: vec![Value::from(1u64), Value::from(2u64)],
+    });
+    assert_eq!(roundtrip(req.clone()), req);
+
+    let ok = Message::from(Response {
+        msgid: 7,
+        result: Ok(Value::from(3u64)),
+    });
+    assert_eq!(roundtrip(ok.clone()), ok);
+
+    let err = Message::from(Response {
+        msgid: 8,
+        result: Err(Value::from("boom".to_owned())),
+    });
+    assert_eq!(roundtrip(err.clone()), err);
+
+    let n = Message::from(Notification {
+        method: "log".to_owned(),
+        params: vec![],
+    });
+    assert_eq!(roundtrip(n.clone()), n);
+
+    // [0, 1, "m", [nil]]
+    let buf = [0x94, 0x00, 0x01, 0xa1, 0x6d, 0x91, 0xc0];
+    assert_eq!(
+        Message::unpack(&mut &buf[..]).unwrap().unwrap(),
+        Message::Request(Request {
+            msgid: 1,
+            method: "m".to_owned(),
+            params: vec![Value::Nil],
+        })
+    );
+
+    // [3, 1] and [0, 1, "m"] aren't messages, an empty reader has none left
+    for buf in [&[0x92, 0x03, 0x01][..], &[0x93, 0x00, 0x01, 0xa1, 0x6d][..]] {
+        match Message::unpack(&mut &buf[..]) {
+            Err(RpcError::InvalidMessage(_)) => {}
+            v => panic!("unexpected {:?}", v),
+        }
+    }
+    assert!(Message::unpack(&mut &[][..]).unwrap().is_none());
+}
+
+fn to_u64(v: &Value) -> u64 {
+    match *v {
+        Value::Integer(Integer {
+            n: IntegerNumber::PosInt(v),
+        }) => v,
+        ref v => panic!("not an unsigned integer {:?}", v),
+    }
+}
+
+fn dispatcher(notified: mpsc::Sender<Vec<Value>>) -> Dispatcher {
+    let notified = Mutex::new(notified);
+    let mut d = Dispatcher::new();
+    d.register("add", |params| {
+        Ok(Value::from(params.iter().map(to_u64).sum::<u64>()))
+    });
+    d.register("fail", |_| Err(Value::from("failed".to_owned())));
+    d.register("log", move |params| {
+        notified.lock().unwrap().send(params).unwrap();
+        Ok(Value::Nil)
+    });
+    d
+}
+
+#[test]
+fn client_server_tcp() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tx, rx) = mpsc::channel();
+
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let d = dispatcher(tx);
+        d.serve(stream.try_clone().unwrap(), stream).unwrap();
+    });
+
+    let stream = TcpStream::connect(addr).unwrap();
+    let closer = stream.try_clone().unwrap();
+    let client = Arc::new(Client::new(stream.try_clone().unwrap(), stream));
+
+    // calls from several threads are in flight at the same time
+    let callers: Vec<_> = (0..8u64)
+        .map(|i| {
+            let client = Arc::clone(&client);
+            thread::spawn(move || {
+                for j in 0..20u64 {
+                    let params = vec![Value::from(i), Value::from(j)];
+                    let v = client.call("add", params).unwrap();
+                    assert_eq!(to_u64(&v), i + j);
+                }
+            })
+        })
+        .collect();
+    for c in callers {
+        c.join().unwrap();
+    }
+
+    match client.call("fail", vec![]) {
+        Err(RpcError::Remote(v)) => assert_eq!(v, Value::from("failed".to_owned())),
+        v => panic!("unexpected {:?}", v),
+    }
+    match client.call("missing", vec![]) {
+        Err(RpcError::Remote(_)) => {}
+        v => panic!("unexpected {:?}", v),
+    }
+
+    client.notify("log", vec![Value::from(true)]).unwrap();
+    assert_eq!(rx.recv().unwrap(), vec![Value::from(true)]);
+
+    // the server returns once the client's side is shut down
+    closer.shutdown(Shutdown::Write).unwrap();
+    server.join().unwrap();
+}
+
+#[test]
+fn client_disconnected() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    // a server which reads the request and hangs up without answering
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = std::io::BufReader::new(stream);
+        Message::unpack(&mut reader).unwrap();
+    });
+
+    let stream = TcpStream::connect(addr).unwrap();
+    let client = Client::new(stream.try_clone().unwrap(), stream);
+    match client.call("add", vec![]) {
+        Err(RpcError::Disconnected) => {}
+        v => panic!("unexpected {:?}", v),
+    }
+    server.join().unwrap();
+}
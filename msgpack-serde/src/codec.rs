@@ -0,0 +1,37 @@
+use crate::{de, ser, unpack_with_limits};
+
+use bytes::Bytes;
+use msgpack::{DecodeLimits, FrameDecode, FrameEncode};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::{io, marker::PhantomData};
+
+// picks serde for a codec's frames, as in MsgPackCodec<Serde<T>>, which decodes into T and
+// encodes T or &T
+#[derive(Debug)]
+pub struct Serde<T>(PhantomData<fn() -> T>);
+
+impl<T: DeserializeOwned> FrameDecode for Serde<T> {
+    type Item = T;
+    type Error = de::DeError;
+
+    fn decode_frame(frame: Bytes, limits: DecodeLimits) -> Result<T, de::DeError> {
+        unpack_with_limits(&frame, limits)
+    }
+}
+
+impl<T: Serialize> FrameEncode<T> for Serde<T> {
+    type Error = ser::SerError;
+
+    fn encode_frame<W: io::Write>(item: T, wr: &mut W) -> Result<(), ser::SerError> {
+        item.serialize(&mut ser::Serializer::new(wr))
+    }
+}
+
+impl<'a, T: Serialize> FrameEncode<&'a T> for Serde<T> {
+    type Error = ser::SerError;
+
+    fn encode_frame<W: io::Write>(item: &'a T, wr: &mut W) -> Result<(), ser::SerError> {
+        item.serialize(&mut ser::Serializer::new(wr))
+    }
+}
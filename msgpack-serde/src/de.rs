@@ -1,12 +1,16 @@
 mod error;
+mod stream;
 
+use crate::ext::EXT_TOKEN;
 pub use error::DeError;
-use msgpack::{code, pack, unpack};
+use msgpack::{code, unpack, BufferedRead, DecodeLimits, FloatMode, LimitTracker, PathSegment};
+pub(crate) use stream::read_raw;
+pub use stream::StreamDeserializer;
 
-use serde;
-use serde::de::{self, Deserialize, DeserializeOwned, DeserializeSeed, Visitor};
+use serde::de::{self, DeserializeSeed, Visitor};
 use serde::forward_to_deserialize_any;
-use std::io;
+use std::collections::HashSet;
+use std::{cmp, io};
 
 #[derive(Clone)]
 enum ESize {
@@ -15,21 +19,34 @@ enum ESize {
     Var1(u8),
 }
 
+impl From<ESize> for u32 {
+    fn from(v: ESize) -> Self {
+        match v {
+            ESize::Var4(v) => v,
+            ESize::Var2(v) => u32::from(v),
+            ESize::Var1(v) => u32::from(v),
+        }
+    }
+}
+
+// header bytes read by peek_code and peek_size are kept in `peeked` and handed out again
+// by io::Read, so whatever reads the value afterwards still sees the whole object
 struct PeekReader<R> {
     code: Option<code::Code>,
     len: Option<ESize>,
+    peeked: Vec<u8>,
     reader: R,
 }
 
 impl<R: io::Read> PeekReader<R> {
     pub fn peek_code(&mut self) -> Result<&code::Code, msgpack::UnpackError> {
-        if let Some(ref v) = self.code {
-            Ok(v)
-        } else {
-            let code = unpack::read_code(&mut self.reader)?;
-            self.code = Some(code);
-            Ok(self.code.as_ref().unwrap())
+        if self.code.is_none() {
+            let v = unpack::read_data_u8(&mut self.reader)?;
+            self.peeked.push(v);
+            self.code = Some(code::Code::from(v));
         }
+
+        Ok(self.code.as_ref().unwrap())
     }
 
     pub fn peek_size(&mut self) -> Result<ESize, msgpack::UnpackError> {
@@ -37,81 +54,44 @@ impl<R: io::Read> PeekReader<R> {
             return Ok(v.clone());
         }
 
-        let ret = if let Some(ref v) = self.code {
-            v
-        } else {
-            self.peek_code()?
-        };
-
         use code::CodeSize;
-        let esize = match ret.body_size() {
+        let esize = match self.peek_code()?.body_size()? {
             CodeSize::Var1 => ESize::Var1(unpack::read_data_u8(&mut self.reader)?),
             CodeSize::Var2 => ESize::Var2(unpack::read_data_u16(&mut self.reader)?),
             CodeSize::Var4 => ESize::Var4(unpack::read_data_u32(&mut self.reader)?),
             CodeSize::FixLen(_) => unreachable!(),
         };
 
-        self.len = Some(esize);
-        Ok(self.len.clone().unwrap())
+        match esize {
+            ESize::Var1(v) => self.peeked.extend_from_slice(&v.to_be_bytes()),
+            ESize::Var2(v) => self.peeked.extend_from_slice(&v.to_be_bytes()),
+            ESize::Var4(v) => self.peeked.extend_from_slice(&v.to_be_bytes()),
+        }
+
+        self.len = Some(esize.clone());
+        Ok(esize)
     }
 
+    // drops the peeked header
     pub fn consume_code(&mut self) -> Option<code::Code> {
+        self.peeked.clear();
+        self.len = None;
         self.code.take()
     }
 }
 
 impl<R: io::Read> io::Read for PeekReader<R> {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        if let Some(ref v) = self.code.take() {
-            buf[0] = u8::from(v);
-            let buf_len = buf.len();
-
-            if buf_len <= 1 {
-                return Ok(1);
-            }
-
-            if let Some(ref es) = self.len.take() {
-                match es {
-                    ESize::Var1(v) => {
-                        if buf_len <= 5 {
-                            unreachable!()
-                        } else {
-                            use pack::primitive::write_data_u8;
-                            let mut cur = io::Cursor::new(&mut buf[0..4]);
-                            write_data_u8(&mut cur, *v);
-                        }
-                    } // ESize::Var2(v) =>
-                    // ESize::Var4(v) =>
-                    _ => unreachable!(),
-                }
-
-                self.reader.read(&mut buf[4..])
-            } else {
-                self.reader.read(&mut buf[1..])
-            }
-
-        // if buf.len()  1 {
-
-        // if let Some(ref es) = self.code {
-        //     match es {
-        //         ESize::Var1(v) => {
-
-        //             buf[]
-        //         }
-        //         ESize::Var2(v) =>
-        //         ESize::Var4(v) =>
-        //     }
-        // } else {
-        //
-        //     if buf.len() > 1 {
-        //         self.reader.read(&mut buf[1..])
-        //     } else {
-        //         Ok(1)
-        //     }
-        // }
-        } else {
-            self.reader.read(buf)
+        if self.peeked.is_empty() {
+            return self.reader.read(buf);
         }
+
+        self.code = None;
+        self.len = None;
+        let len = cmp::min(buf.len(), self.peeked.len());
+        buf[..len].copy_from_slice(&self.peeked[..len]);
+        self.peeked.drain(..len);
+        Ok(len)
     }
 }
 
@@ -128,6 +108,13 @@ impl<'a, R: msgpack::BufferedRead<'a>> msgpack::BufferedRead<'a> for PeekReader<
 struct SeqAccess<'a, R: io::Read + 'a> {
     de: &'a mut Deserializer<R>,
     len: usize,
+    index: usize,
+}
+
+impl<'a, R: io::Read + 'a> SeqAccess<'a, R> {
+    fn new(de: &'a mut Deserializer<R>, len: usize) -> Self {
+        SeqAccess { de, len, index: 0 }
+    }
 }
 
 impl<'de, 'a, R> serde::de::SeqAccess<'de> for SeqAccess<'a, R>
@@ -142,7 +129,13 @@ where
     {
         if self.len > 0 {
             self.len -= 1;
-            Ok(Some(seed.deserialize(&mut *self.de)?))
+            let index = self.index;
+            self.index += 1;
+            let v = self
+                .de
+                .item(|de| seed.deserialize(de))
+                .map_err(|e| e.within(PathSegment::Index(index)))?;
+            Ok(Some(v))
         } else {
             Ok(None)
         }
@@ -153,12 +146,35 @@ where
     }
 }
 
-struct MapAccess<'a, R: 'a> {
+struct MapAccess<'de, 'a, R: 'a> {
     de: &'a mut Deserializer<R>,
     len: usize,
+    // the encoded key of the current entry, decoded only to name it in errors
+    key: &'de [u8],
+    // the encoded keys so far, only kept in strict mode
+    keys: HashSet<&'de [u8]>,
+}
+
+impl<'de, 'a, R: 'a> MapAccess<'de, 'a, R> {
+    fn new(de: &'a mut Deserializer<R>, len: usize) -> Self {
+        MapAccess {
+            de,
+            len,
+            key: &[],
+            keys: HashSet::new(),
+        }
+    }
+}
+
+fn key_segment(key: &[u8]) -> PathSegment {
+    let name = unpack::unpack_str_ref(&mut &*key)
+        .map(str::to_string)
+        .or_else(|_| unpack::unpack_int::<i64, _>(&mut &*key).map(|v| v.to_string()))
+        .unwrap_or_else(|_| "?".to_string());
+    PathSegment::Key(name)
 }
 
-impl<'de, 'a, R> de::MapAccess<'de> for MapAccess<'a, R>
+impl<'de, 'a, R> de::MapAccess<'de> for MapAccess<'de, 'a, R>
 where
     R: msgpack::BufferedRead<'de> + 'a,
 {
@@ -170,7 +186,19 @@ where
     {
         if self.len > 0 {
             self.len -= 1;
-            Ok(Some(seed.deserialize(&mut *self.de)?))
+            let start = self.de.offset();
+            self.key = self.de.unread_slice();
+            let k = self.de.item(|de| seed.deserialize(de))?;
+            if self.de.limits.strict() {
+                let rest = self.de.unread_slice().len();
+                self.key = &self.key[..self.key.len().saturating_sub(rest)];
+                if !self.keys.insert(self.key) {
+                    return Err(DeError::from(msgpack::UnpackError::DuplicateMapKey)
+                        .within(key_segment(self.key))
+                        .at_offset(start));
+                }
+            }
+            Ok(Some(k))
         } else {
             Ok(None)
         }
@@ -180,7 +208,9 @@ where
     where
         V: DeserializeSeed<'de>,
     {
-        Ok(seed.deserialize(&mut *self.de)?)
+        self.de
+            .item(|de| seed.deserialize(de))
+            .map_err(|e| e.within(key_segment(self.key)))
     }
 
     fn size_hint(&self) -> Option<usize> {
@@ -188,97 +218,187 @@ where
     }
 }
 
-// struct BytesAccess<'a, R: io::Read + 'a> {
-//     de: &'a mut Deserializer<R>,
-//     len: usize,
-// }
-
-// impl<'de, 'a, R> serde::de::SeqAccess<'de> for BytesAccess<'a, R>
-// where
-//     R: BufferedRead<'de> + 'a,
-// {
-//     type Error = DeError;
-
-//     fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
-//     where
-//         T: serde::de::DeserializeSeed<'de>,
-//     {
-//         if self.len > 0 {
-//             self.len -= 1;
-//       // seed.deserialize(MapKey { de: &mut *self.de }).map(Some),
-//             Ok(Some(seed.deserialize(&mut *self.de)?))
-//         } else {
-//             Ok(None)
-//         }
-//     }
-
-//     fn size_hint(&self) -> Option<usize> {
-//         Some(self.len)
-//     }
-// }
-
+// deserializes objects from a slice one after another, borrowing strs and bytes from it.
+// offsets in errors count from the start of the slice
 pub struct Deserializer<R> {
     reader: PeekReader<R>,
+    limits: LimitTracker,
+    float_mode: FloatMode,
+    // the length of the input when decoding started, which offsets are counted from
+    start: usize,
 }
 
-impl<R> Deserializer<R> {
+impl<'de, R: msgpack::BufferedRead<'de>> Deserializer<R> {
     pub fn new(r: R) -> Self {
+        let start = r.fill_buf().map_or(0, |buf| buf.len());
         Deserializer {
             reader: PeekReader {
                 code: None,
                 len: None,
+                peeked: Vec::new(),
                 reader: r,
             },
+            limits: LimitTracker::new(DecodeLimits::default()),
+            float_mode: FloatMode::default(),
+            start,
         }
     }
 }
 
+impl<R> Deserializer<R> {
+    pub fn with_limits(mut self, limits: DecodeLimits) -> Self {
+        self.limits = LimitTracker::new(limits);
+        self
+    }
+
+    pub fn with_float_mode(mut self, mode: FloatMode) -> Self {
+        self.float_mode = mode;
+        self
+    }
+}
+
 impl<'de, R> Deserializer<R>
 where
     R: msgpack::BufferedRead<'de>,
 {
-    fn read_ext<V>(&mut self, len: u32, visitor: V) -> Result<V::Value, DeError>
+    // the input which hasn't been read yet. empty while a peeked header is pending since
+    // it would not include the header bytes
+    fn unread_slice(&self) -> &'de [u8] {
+        if !self.reader.peeked.is_empty() {
+            return &[];
+        }
+        self.reader.fill_buf().unwrap_or(&[])
+    }
+
+    // how far into the input the next unread byte is. peeked header bytes count as unread
+    fn offset(&self) -> u64 {
+        let rest = self.reader.peeked.len() + self.reader.fill_buf().map_or(0, |buf| buf.len());
+        (self.start - rest) as u64
+    }
+
+    // deserializes one value with `f`. errors point at where the innermost value that failed
+    // starts
+    pub(crate) fn item<T>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<T, DeError>,
+    ) -> Result<T, DeError> {
+        let start = self.offset();
+        f(self).map_err(|e| e.at_offset(start))
+    }
+
+    // the strict header reader when the limits ask for strict decoding
+    fn read_header(
+        &mut self,
+        read: fn(&mut PeekReader<R>, bool) -> Result<usize, msgpack::UnpackError>,
+    ) -> Result<usize, DeError> {
+        read(&mut self.reader, self.limits.strict()).map_err(DeError::from)
+    }
+
+    // visitors size their own storage, so containers only count against the length and
+    // depth limits
+    fn visit_array<V>(&mut self, len: usize, visitor: V) -> Result<V::Value, DeError>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.limits.container(len, 0)?;
+        self.limits.enter()?;
+        let v = visitor.visit_seq(SeqAccess::new(self, len))?;
+        self.limits.leave();
+        Ok(v)
+    }
+
+    fn visit_map<V>(&mut self, len: usize, visitor: V) -> Result<V::Value, DeError>
     where
         V: de::Visitor<'de>,
     {
-        let ext_de = ExtDeserializer {
+        self.limits.container(len, 0)?;
+        self.limits.enter()?;
+        let v = visitor.visit_map(MapAccess::new(self, len))?;
+        self.limits.leave();
+        Ok(v)
+    }
+
+    // the ext whose code has been peeked
+    fn ext_access(&mut self) -> Result<ExtDeserializer<'_, PeekReader<R>>, DeError> {
+        use code::Code;
+
+        let code = self.reader.peek_code()?.clone();
+        let len = match code {
+            Code::FixExt1 => 1,
+            Code::FixExt2 => 2,
+            Code::FixExt4 => 4,
+            Code::FixExt8 => 8,
+            Code::FixExt16 => 16,
+            _ => {
+                let len = u32::from(self.reader.peek_size()?);
+                self.limits.check_len(&code, len as usize)?;
+                len
+            }
+        };
+        self.limits.data_ref(len as usize)?;
+        let code = u8::from(&code);
+        Ok(ExtDeserializer {
             reader: &mut self.reader,
+            code,
             len: len as usize,
-            rest: 10, //
-        };
-        visitor.visit_seq(ext_de)
+            field: ExtField::Code,
+        })
+    }
+
+    fn read_ext<V>(&mut self, visitor: V) -> Result<V::Value, DeError>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_seq(self.ext_access()?)
     }
 }
 
-macro_rules! impl_nums {
+macro_rules! impl_ints {
+    ($dser_method:ident, $visitor_method:ident, $unpack_method:ident, $typ:ty) => {
+        #[inline]
+        fn $dser_method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where
+            V: serde::de::Visitor<'de>,
+        {
+            let v = unpack::$unpack_method::<$typ, _>(&mut self.reader, self.limits.strict())?;
+            visitor.$visitor_method(v)
+        }
+    };
+}
+
+macro_rules! impl_floats {
     ($dser_method:ident, $visitor_method:ident, $unpack_method:ident) => {
         #[inline]
         fn $dser_method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
         where
             V: serde::de::Visitor<'de>,
         {
-            let v = unpack::$unpack_method(&mut self.reader)?;
+            let mode = FloatMode {
+                strict: self.limits.strict(),
+                ..self.float_mode
+            };
+            let v = unpack::$unpack_method(&mut self.reader, mode)?;
             visitor.$visitor_method(v)
         }
-    }
+    };
 }
 
-impl<'de, 'a, R> serde::Deserializer<'de> for &mut Deserializer<R>
+impl<'de, R> serde::Deserializer<'de> for &mut Deserializer<R>
 where
     R: msgpack::BufferedRead<'de>,
 {
     type Error = DeError;
 
-    impl_nums!(deserialize_u8, visit_u8, unpack_u8);
-    impl_nums!(deserialize_u16, visit_u16, unpack_u16);
-    impl_nums!(deserialize_u32, visit_u32, unpack_u32);
-    impl_nums!(deserialize_u64, visit_u64, unpack_u64);
-    impl_nums!(deserialize_i8, visit_i8, unpack_i8);
-    impl_nums!(deserialize_i16, visit_i16, unpack_i16);
-    impl_nums!(deserialize_i32, visit_i32, unpack_i32);
-    impl_nums!(deserialize_i64, visit_i64, unpack_i64);
-    impl_nums!(deserialize_f32, visit_f32, unpack_f32);
-    impl_nums!(deserialize_f64, visit_f64, unpack_f64);
+    impl_ints!(deserialize_u8, visit_u8, unpack_uint_with, u8);
+    impl_ints!(deserialize_u16, visit_u16, unpack_uint_with, u16);
+    impl_ints!(deserialize_u32, visit_u32, unpack_uint_with, u32);
+    impl_ints!(deserialize_u64, visit_u64, unpack_uint_with, u64);
+    impl_ints!(deserialize_i8, visit_i8, unpack_int_with, i8);
+    impl_ints!(deserialize_i16, visit_i16, unpack_int_with, i16);
+    impl_ints!(deserialize_i32, visit_i32, unpack_int_with, i32);
+    impl_ints!(deserialize_i64, visit_i64, unpack_int_with, i64);
+    impl_floats!(deserialize_f32, visit_f32, unpack_f32_with);
+    impl_floats!(deserialize_f64, visit_f64, unpack_f64_with);
 
     fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
@@ -286,8 +406,6 @@ where
     {
         use code::Code;
 
-        println!("{:?}", "deserialize_any");
-
         match self.reader.peek_code()? {
             Code::Nil => self.deserialize_unit(visitor),
             Code::True | Code::False => self.deserialize_bool(visitor),
@@ -302,27 +420,21 @@ where
             Code::Float32 => self.deserialize_f32(visitor),
             Code::Float64 => self.deserialize_f64(visitor),
             Code::FixStr(_) | Code::Str8 | Code::Str16 | Code::Str32 => {
-                self.deserialize_string(visitor)
+                self.deserialize_str(visitor)
             }
             Code::Bin8 | Code::Bin16 | Code::Bin32 => self.deserialize_bytes(visitor),
             Code::FixArray(_) | Code::Array16 | Code::Array32 => self.deserialize_seq(visitor),
             Code::FixMap(_) | Code::Map16 | Code::Map32 => self.deserialize_map(visitor),
-            Code::FixExt1 => self.read_ext(1, visitor),
-            Code::FixExt2 => self.read_ext(2, visitor),
-            Code::FixExt4 => self.read_ext(4, visitor),
-            Code::FixExt8 => self.read_ext(8, visitor),
-            Code::FixExt16 => self.read_ext(16, visitor),
-            Code::Ext8 => {
-                let s = self.reader.peek_size()?;
-                match s {
-                    ESize::Var1(v) => self.read_ext(v as u32, visitor),
-                    _ => unreachable!(),
-                }
-            }
-            // Code::Ext16 => EXT16,
-            // Code::Ext32 => EXT32,
-            Code::Reserved => unreachable!(), // tmp
-            _ => unreachable!(),
+            // a newtype, so visitors of any value can tell an ext from an array
+            Code::FixExt1
+            | Code::FixExt2
+            | Code::FixExt4
+            | Code::FixExt8
+            | Code::FixExt16
+            | Code::Ext8
+            | Code::Ext16
+            | Code::Ext32 => visitor.visit_newtype_struct(ExtNewtype(self.ext_access()?)),
+            Code::Reserved => Err(msgpack::UnpackError::ReservedCode(code::RESERVED).into()),
         }
     }
 
@@ -339,6 +451,7 @@ where
         }
     }
 
+    // takes every EnumRepr, and a bare name or index as a unit variant
     fn deserialize_enum<V>(
         self,
         _name: &str,
@@ -348,18 +461,46 @@ where
     where
         V: Visitor<'de>,
     {
-        visitor.visit_none() // TODO
+        use code::Code;
+
+        let form = match self.reader.peek_code()? {
+            Code::FixMap(_) | Code::Map16 | Code::Map32 => {
+                let len = self.read_header(unpack::unpack_map_header_with)?;
+                if len != 1 {
+                    return Err(DeError::InvalidSize);
+                }
+                EnumForm::Tagged
+            }
+            Code::FixArray(_) | Code::Array16 | Code::Array32 => {
+                let len = self.read_header(unpack::unpack_array_header_with)?;
+                if len != 2 {
+                    return Err(DeError::InvalidSize);
+                }
+                EnumForm::Array
+            }
+            _ => return visitor.visit_enum(EnumAccess::new(self, EnumForm::Bare)),
+        };
+
+        self.limits.enter()?;
+        let v = visitor.visit_enum(EnumAccess::new(self, form))?;
+        self.limits.leave();
+        Ok(v)
     }
 
+    // only ext and Timestamp ask for an ext's fields. other newtypes around an ext, like one of
+    // msgpack_value::Value, see it the way deserialize_any hands it out
     fn deserialize_newtype_struct<V>(
         self,
-        _name: &'static str,
+        name: &'static str,
         visitor: V,
     ) -> Result<V::Value, Self::Error>
     where
         V: Visitor<'de>,
     {
         use code::Code;
+        if name != EXT_TOKEN {
+            return visitor.visit_newtype_struct(self);
+        }
         match self.reader.peek_code()? {
             Code::FixExt1
             | Code::FixExt2
@@ -368,7 +509,7 @@ where
             | Code::FixExt16
             | Code::Ext8
             | Code::Ext16
-            | Code::Ext32 => self.deserialize_any(visitor),
+            | Code::Ext32 => self.read_ext(visitor),
             _ => visitor.visit_newtype_struct(self),
         }
     }
@@ -398,27 +539,20 @@ where
     where
         V: serde::de::Visitor<'de>,
     {
-        let size = unpack::unpack_array_header(&mut self.reader)?;
-
-        visitor.visit_seq(SeqAccess {
-            de: self,
-            len: size,
-        })
+        let size = self.read_header(unpack::unpack_array_header_with)?;
+        self.visit_array(size, visitor)
     }
 
     fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        let size = unpack::unpack_array_header(&mut self.reader)?;
+        let size = self.read_header(unpack::unpack_array_header_with)?;
         if size != len {
             return Err(Self::Error::InvalidSize);
         }
 
-        visitor.visit_seq(SeqAccess {
-            de: self,
-            len: size,
-        })
+        self.visit_array(size, visitor)
     }
 
     fn deserialize_tuple_struct<V>(
@@ -437,6 +571,7 @@ where
     where
         V: serde::de::Visitor<'de>,
     {
+        unpack::unpack_nil::<(), _>(&mut self.reader)?;
         visitor.visit_unit()
     }
 
@@ -444,7 +579,9 @@ where
     where
         V: serde::de::Visitor<'de>,
     {
-        let body = unpack::unpack_str(&mut self.reader)?;
+        let len = self.read_header(unpack::unpack_str_header_with)?;
+        self.limits.data(len)?;
+        let body = unpack::unpack_str_data(&mut self.reader, len)?;
         // TODO: bytes
         visitor.visit_string(body)
     }
@@ -453,59 +590,70 @@ where
     where
         V: serde::de::Visitor<'de>,
     {
-        let body = unpack::unpack_str_ref(&mut self.reader)?;
-        // TODO: bytes_ref
-        visitor.visit_str(body)
+        let len = self.read_header(unpack::unpack_str_header_with)?;
+        self.limits.data_ref(len)?;
+        let body = unpack::unpack_str_data_ref(&mut self.reader, len)?;
+        visitor.visit_borrowed_str(body)
     }
 
     fn deserialize_struct<V>(
         self,
         _name: &str,
-        fields: &'static [&'static str],
+        _fields: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        // TODO
-        let _size = unpack::unpack_map_header(&mut self.reader)?;
-        visitor.visit_map(MapAccess {
-            de: self,
-            len: fields.len(),
-        })
+        use code::Code;
+
+        // either StructRepr. unknown fields of a map are consumed through
+        // deserialize_ignored_any, and the visitor checks the length of an array
+        match self.reader.peek_code()? {
+            Code::FixArray(_) | Code::Array16 | Code::Array32 => self.deserialize_seq(visitor),
+            _ => self.deserialize_map(visitor),
+        }
     }
 
     fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        let size = unpack::unpack_map_header(&mut self.reader)?;
-        visitor.visit_map(MapAccess {
-            de: self,
-            len: size,
-        })
+        let size = self.read_header(unpack::unpack_map_header_with)?;
+        self.visit_map(size, visitor)
     }
 
+    // variants may be named by their index
     fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        self.deserialize_str(visitor)
+        use code::Code;
+        match self.reader.peek_code()? {
+            Code::PosInt(_) | Code::Uint8 | Code::Uint16 | Code::Uint32 | Code::Uint64 => {
+                self.deserialize_u64(visitor)
+            }
+            _ => self.deserialize_str(visitor),
+        }
     }
 
     fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        let body = unpack::unpack_bin_ref(&mut self.reader)?;
-        visitor.visit_bytes(body)
+        let len = self.read_header(unpack::unpack_bin_header_with)?;
+        self.limits.data_ref(len)?;
+        let body = unpack::unpack_data_ref(&mut self.reader, len)?;
+        visitor.visit_borrowed_bytes(body)
     }
 
     fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: serde::de::Visitor<'de>,
     {
-        let body = unpack::unpack_bin(&mut self.reader)?;
+        let len = self.read_header(unpack::unpack_bin_header_with)?;
+        self.limits.data(len)?;
+        let body = unpack::unpack_data(&mut self.reader, len)?;
         visitor.visit_byte_buf(body)
     }
 
@@ -520,15 +668,192 @@ where
     where
         V: de::Visitor<'de>,
     {
+        use code::Code;
+
+        // strict mode checks ignored values too, by decoding them. ext bodies are opaque
+        // and only need their header checked
+        match self.reader.peek_code()? {
+            Code::Ext8 | Code::Ext16 | Code::Ext32 if self.limits.strict() => {
+                let code = self.reader.peek_code()?.clone();
+                let len = u32::from(self.reader.peek_size()?);
+                self.limits.check_len(&code, len as usize)?;
+                unpack::skip_value(&mut self.reader)?;
+            }
+            Code::FixExt1 | Code::FixExt2 | Code::FixExt4 | Code::FixExt8 | Code::FixExt16 => {
+                unpack::skip_value(&mut self.reader)?;
+            }
+            _ if self.limits.strict() => {
+                de::Deserializer::deserialize_any(&mut *self, de::IgnoredAny)?;
+            }
+            _ => unpack::skip_value(&mut self.reader)?,
+        }
         visitor.visit_unit()
     }
 }
 
+// how a variant was tagged
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum EnumForm {
+    // a map of one entry from the variant to its payload
+    Tagged,
+    // `[index, [fields..]]`
+    Array,
+    // just the variant, for a unit variant
+    Bare,
+}
+
+struct EnumAccess<'a, R: 'a> {
+    de: &'a mut Deserializer<R>,
+    form: EnumForm,
+}
+
+impl<'a, R: 'a> EnumAccess<'a, R> {
+    fn new(de: &'a mut Deserializer<R>, form: EnumForm) -> Self {
+        EnumAccess { de, form }
+    }
+
+    // reads the array around the fields of an `[index, [fields..]]` variant
+    fn fields_header<'de>(&mut self, len: usize) -> Result<(), DeError>
+    where
+        R: msgpack::BufferedRead<'de>,
+    {
+        let size = self.de.read_header(unpack::unpack_array_header_with)?;
+        if size != len {
+            return Err(DeError::InvalidSize);
+        }
+        Ok(())
+    }
+
+    fn expect_payload(&self) -> Result<(), DeError> {
+        match self.form {
+            EnumForm::Bare => Err(de::Error::invalid_type(
+                de::Unexpected::UnitVariant,
+                &"a variant with data",
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<'de, 'a, R> de::EnumAccess<'de> for EnumAccess<'a, R>
+where
+    R: msgpack::BufferedRead<'de> + 'a,
+{
+    type Error = DeError;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self), Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let variant = self.de.item(|de| seed.deserialize(de))?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de, 'a, R> de::VariantAccess<'de> for EnumAccess<'a, R>
+where
+    R: msgpack::BufferedRead<'de> + 'a,
+{
+    type Error = DeError;
+
+    fn unit_variant(mut self) -> Result<(), Self::Error> {
+        match self.form {
+            EnumForm::Tagged => self.de.item(|de| de::Deserialize::deserialize(de)),
+            EnumForm::Array => self.fields_header(0),
+            EnumForm::Bare => Ok(()),
+        }
+    }
+
+    fn newtype_variant_seed<T>(mut self, seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        self.expect_payload()?;
+        if self.form == EnumForm::Array {
+            self.fields_header(1)?;
+        }
+        self.de.item(|de| seed.deserialize(de))
+    }
+
+    fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.expect_payload()?;
+        self.de
+            .item(|de| de::Deserializer::deserialize_tuple(de, len, visitor))
+    }
+
+    fn struct_variant<V>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.expect_payload()?;
+        self.de
+            .item(|de| de::Deserializer::deserialize_struct(de, "", fields, visitor))
+    }
+}
+
+// the fields of an ext as a seq: its code, the length for ext8/16/32, its type and its data
+struct ExtNewtype<'a, R: 'a>(ExtDeserializer<'a, R>);
+
+impl<'de, 'a, R> serde::Deserializer<'de> for ExtNewtype<'a, R>
+where
+    R: msgpack::BufferedRead<'de>,
+{
+    type Error = DeError;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        visitor.visit_seq(self.0)
+    }
+
+    forward_to_deserialize_any! {
+        bool u8 u16 u32 u64 i8 i16 i32 i64 f32 f64 char str string bytes unit option seq
+            byte_buf map unit_struct newtype_struct
+            tuple_struct struct identifier tuple enum ignored_any
+    }
+}
+
 #[derive(Debug)]
 struct ExtDeserializer<'a, R: 'a> {
     reader: &'a mut R,
+    code: u8,
     len: usize,
-    rest: usize,
+    // the next field to hand out
+    field: ExtField,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ExtField {
+    Code,
+    // only for ext8/16/32
+    Len,
+    Type,
+    Data,
+    End,
+}
+
+impl<R> ExtDeserializer<'_, R> {
+    fn has_len(&self) -> bool {
+        matches!(self.code, code::EXT8 | code::EXT16 | code::EXT32)
+    }
+
+    fn advance(&mut self) {
+        self.field = match self.field {
+            ExtField::Code if self.has_len() => ExtField::Len,
+            ExtField::Code | ExtField::Len => ExtField::Type,
+            ExtField::Type => ExtField::Data,
+            ExtField::Data | ExtField::End => ExtField::End,
+        };
+    }
 }
 
 impl<'de, 'a, R> serde::de::SeqAccess<'de> for ExtDeserializer<'a, R>
@@ -541,16 +866,22 @@ where
     where
         T: serde::de::DeserializeSeed<'de>,
     {
-        if self.rest > 0 {
-            self.rest -= 1;
-            Ok(Some(seed.deserialize(self)?))
-        } else {
-            Ok(None)
+        if self.field == ExtField::End {
+            return Ok(None);
         }
+        let v = seed.deserialize(&mut *self)?;
+        self.advance();
+        Ok(Some(v))
     }
 
     fn size_hint(&self) -> Option<usize> {
-        Some(self.rest)
+        Some(match self.field {
+            ExtField::Code if self.has_len() => 4,
+            ExtField::Code | ExtField::Len => 3,
+            ExtField::Type => 2,
+            ExtField::Data => 1,
+            ExtField::End => 0,
+        })
     }
 }
 
@@ -560,40 +891,27 @@ where
 {
     type Error = DeError;
 
-    impl_nums!(deserialize_u8, visit_u8, read_data_u8);
-    impl_nums!(deserialize_u16, visit_u16, read_data_u16);
-    impl_nums!(deserialize_u32, visit_u32, read_data_u32);
-    impl_nums!(deserialize_i8, visit_i8, read_data_i8);
-    // impl_nums!(deserialize_i16, visit_i16, read_data_i16);
-    // impl_nums!(deserialize_i32, visit_i32, read_data_i32);
-    // impl_nums!(deserialize_i64, visit_i64, read_data_i64);
-    // impl_nums!(deserialize_u64, visit_u64, read_data_u64);
-
-    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
-    where
-        V: de::Visitor<'de>,
-    {
-        visitor.visit_seq(self) // size(u8) + id(i8) + data([u8])
-    }
-
-    #[inline]
+    // each field is read in the width it has in the header, whatever the visitor asks for
     fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_u8(10)
-    }
-
-    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, Self::Error>
-    where
-        V: de::Visitor<'de>,
-    {
-        let body = unpack::unpack_data_ref(self.reader, self.len)?;
-        visitor.visit_borrowed_bytes(body)
+        match self.field {
+            ExtField::Code => visitor.visit_u8(unpack::read_data_u8(self.reader)?),
+            ExtField::Len => match self.code {
+                code::EXT8 => visitor.visit_u8(unpack::read_data_u8(self.reader)?),
+                code::EXT16 => visitor.visit_u16(unpack::read_data_u16(self.reader)?),
+                _ => visitor.visit_u32(unpack::read_data_u32(self.reader)?),
+            },
+            ExtField::Type => visitor.visit_i8(unpack::read_data_i8(self.reader)?),
+            ExtField::Data | ExtField::End => {
+                visitor.visit_borrowed_bytes(unpack::unpack_data_ref(self.reader, self.len)?)
+            }
+        }
     }
 
     forward_to_deserialize_any! {
-        bool u64 i16 i32 i64 f32 f64 char str string unit option
+        bool u8 u16 u32 u64 i8 i16 i32 i64 f32 f64 char str string bytes unit option seq
             byte_buf map unit_struct newtype_struct
             tuple_struct struct identifier tuple enum ignored_any
     }
@@ -1,11 +1,79 @@
+use msgpack::{Location, PathSegment};
 use std::error;
 use std::fmt::{self, Display};
+use std::io;
 
 #[derive(Debug)]
 pub enum DeError {
     InvalidSize,
     UnpackError(msgpack::UnpackError),
     Custom(String),
+    // wraps an error with where it happened in the input
+    Located(Box<DeError>, Location),
+}
+
+impl DeError {
+    // the error without its location
+    pub fn root(&self) -> &DeError {
+        match *self {
+            DeError::Located(ref e, _) => e.root(),
+            ref e => e,
+        }
+    }
+
+    pub fn location(&self) -> Option<&Location> {
+        match *self {
+            DeError::Located(_, ref loc) => Some(loc),
+            _ => None,
+        }
+    }
+
+    pub fn offset(&self) -> Option<u64> {
+        self.location().and_then(|loc| loc.offset)
+    }
+
+    pub fn path(&self) -> Option<String> {
+        self.location().map(|loc| loc.path())
+    }
+
+    pub(crate) fn within(self, segment: PathSegment) -> Self {
+        match self {
+            DeError::Located(e, mut loc) => {
+                loc.segments.insert(0, segment);
+                DeError::Located(e, loc)
+            }
+            e => DeError::Located(
+                Box::new(e),
+                Location {
+                    offset: None,
+                    segments: vec![segment],
+                },
+            ),
+        }
+    }
+
+    pub(crate) fn at_offset(self, offset: u64) -> Self {
+        match self {
+            DeError::Located(e, mut loc) => {
+                loc.offset.get_or_insert(offset);
+                DeError::Located(e, loc)
+            }
+            e => DeError::Located(
+                Box::new(e),
+                Location {
+                    offset: Some(offset),
+                    segments: vec![],
+                },
+            ),
+        }
+    }
+}
+
+// read errors from codecs and async readers
+impl From<io::Error> for DeError {
+    fn from(err: io::Error) -> DeError {
+        DeError::UnpackError(msgpack::UnpackError::from(err))
+    }
 }
 
 impl From<msgpack::UnpackError> for DeError {
@@ -22,28 +90,26 @@ impl serde::de::Error for DeError {
 
 impl Display for DeError {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        error::Error::description(self).fmt(f)
-    }
-}
-
-impl error::Error for DeError {
-    fn description(&self) -> &str {
         use DeError::*;
 
         match *self {
-            InvalidSize => "invalid size",
-            UnpackError(ref e) => e.description(),
-            Custom(ref s) => s,
+            InvalidSize => f.write_str("invalid size"),
+            UnpackError(ref e) => e.fmt(f),
+            Custom(ref s) => f.write_str(s),
+            Located(ref e, ref loc) => write!(f, "{} {}", e, loc),
         }
     }
+}
 
-    fn cause(&self) -> Option<&dyn error::Error> {
+impl error::Error for DeError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         use DeError::*;
 
         match *self {
             UnpackError(ref e) => Some(e),
             Custom(_) => None,
             InvalidSize => None,
+            Located(ref e, _) => e.source(),
         }
     }
 }
@@ -0,0 +1,143 @@
+use super::DeError;
+use msgpack::{unpack, DecodeLimits, Limit, LimitTracker, UnpackError};
+use serde::de::DeserializeOwned;
+use std::cmp;
+use std::io::{self, Read};
+use std::marker::PhantomData;
+
+// copies the encoded bytes of the next object into `scratch`, for decoding input from a reader
+// which can't lend out its buffer. lengths and depth are checked as headers come in, and no
+// more than `max_alloc` bytes are copied in total
+pub(crate) fn read_raw<R: io::Read>(
+    reader: &mut R,
+    scratch: &mut Vec<u8>,
+    limits: DecodeLimits,
+) -> Result<(), DeError> {
+    scratch.clear();
+    let mut rd = Recorder {
+        inner: reader,
+        buf: scratch,
+        max: limits.max_alloc,
+        full: false,
+    };
+    match unpack::skip_value_with_limits(&mut rd, &mut LimitTracker::new(limits)) {
+        Err(UnpackError::UnexpectedEof) if rd.full => Err(DeError::from(
+            UnpackError::LimitExceeded(Limit::Allocation, rd.max as u64 + 1),
+        )),
+        ret => ret.map_err(DeError::from),
+    }
+}
+
+struct Recorder<'a, R> {
+    inner: &'a mut R,
+    buf: &'a mut Vec<u8>,
+    max: usize,
+    // set once a read was cut short by `max`
+    full: bool,
+}
+
+impl<R: io::Read> io::Read for Recorder<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let room = self.max - self.buf.len();
+        if room == 0 && !buf.is_empty() {
+            self.full = true;
+            return Ok(0);
+        }
+        let len = cmp::min(buf.len(), room);
+        let n = self.inner.read(&mut buf[..len])?;
+        self.buf.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+}
+
+// decodes the concatenated objects of a reader one by one. it ends where the input does, and
+// after the first error since the rest of the input can't be trusted to line up anymore.
+// offsets in errors are relative to the start of the object, which is at `byte_offset`
+pub struct StreamDeserializer<R, T> {
+    reader: R,
+    scratch: Vec<u8>,
+    limits: DecodeLimits,
+    offset: u64,
+    failed: bool,
+    output: PhantomData<fn() -> T>,
+}
+
+impl<R, T> StreamDeserializer<R, T>
+where
+    R: io::Read,
+    T: DeserializeOwned,
+{
+    pub fn new(reader: R) -> Self {
+        StreamDeserializer {
+            reader,
+            scratch: vec![],
+            limits: DecodeLimits::default(),
+            offset: 0,
+            failed: false,
+            output: PhantomData,
+        }
+    }
+
+    // applies to each object on its own
+    pub fn with_limits(mut self, limits: DecodeLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    // the bytes of the objects decoded so far
+    pub fn byte_offset(&self) -> u64 {
+        self.offset
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    // the first byte of the next object, or None at the end of the input
+    fn first_byte(&mut self) -> io::Result<Option<u8>> {
+        let mut b = [0];
+        loop {
+            match self.reader.read(&mut b) {
+                Ok(0) => return Ok(None),
+                Ok(_) => return Ok(Some(b[0])),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+impl<R, T> Iterator for StreamDeserializer<R, T>
+where
+    R: io::Read,
+    T: DeserializeOwned,
+{
+    type Item = Result<T, DeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        let ret = match self.first_byte() {
+            Ok(None) => return None,
+            Ok(Some(b)) => read_raw(
+                &mut (&[b][..]).chain(&mut self.reader),
+                &mut self.scratch,
+                self.limits,
+            )
+            .and_then(|()| crate::from_slice_with_limits(&self.scratch, self.limits)),
+            Err(e) => Err(DeError::from(e)),
+        };
+        match ret {
+            Ok(v) => {
+                self.offset += self.scratch.len() as u64;
+                Some(Ok(v))
+            }
+            Err(e) => {
+                self.failed = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
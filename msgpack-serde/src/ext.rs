@@ -4,9 +4,12 @@ use serde_bytes;
 use msgpack::{self, pack, unpack};
 use serde::de;
 use serde::ser::{self, SerializeTupleStruct};
-use std::{fmt, io};
+use std::fmt;
 
-pub const EXT_TOKEN: &'static str = "$serde_json::private::Ext";
+// msgpack-value writes its extensions and timestamps under the same name. an ext is read back
+// as a newtype of this name around a seq of its fields: its code, the length for ext8/16/32, its
+// type and its data
+pub const EXT_TOKEN: &str = "$serde_json::private::Ext";
 
 #[derive(Debug, PartialEq)]
 enum ExtType {
@@ -20,66 +23,6 @@ enum ExtType {
     Ext32(u32),
 }
 
-impl<'de> de::Deserialize<'de> for ExtType {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-    where
-        D: de::Deserializer<'de>,
-    {
-        struct ExtTypeVisitor;
-
-        impl<'de> serde::de::Visitor<'de> for ExtTypeVisitor {
-            type Value = ExtType;
-
-            fn expecting(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-                formatter.write_str("valid msgpack exttype")
-            }
-
-            #[inline]
-            fn visit_seq<V>(self, mut seq: V) -> Result<Self::Value, V::Error>
-            where
-                V: de::SeqAccess<'de>,
-            {
-                let h = seq.next_element();
-                let h: u8 = h?.ok_or_else(|| de::Error::invalid_length(0, &self))?;
-
-                Ok(match h {
-                    msgpack::code::FIXEXT1 => ExtType::FixExt1,
-                    msgpack::code::FIXEXT2 => ExtType::FixExt2,
-                    msgpack::code::FIXEXT4 => ExtType::FixExt4,
-                    msgpack::code::FIXEXT8 => ExtType::FixExt8,
-                    msgpack::code::FIXEXT16 => ExtType::FixExt16,
-                    msgpack::code::EXT8 => {
-                        let v: u8 = seq
-                            .next_element()?
-                            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
-                        ExtType::Ext8(v)
-                    }
-                    msgpack::code::EXT16 => {
-                        let v: u16 = seq
-                            .next_element()?
-                            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
-                        ExtType::Ext16(v)
-                    }
-                    msgpack::code::EXT32 => {
-                        let v: u32 = seq
-                            .next_element()?
-                            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
-                        ExtType::Ext32(v)
-                    }
-                    v => {
-                        return Err(de::Error::custom(format!(
-                            "invalid code for ext type: {:}",
-                            v
-                        )));
-                    }
-                })
-            }
-        }
-
-        deserializer.deserialize_seq(ExtTypeVisitor)
-    }
-}
-
 impl From<&ExtType> for u8 {
     fn from(v: &ExtType) -> Self {
         match v {
@@ -104,48 +47,77 @@ pub struct Ext {
 
 struct ExtVisitor;
 
-impl<'de> de::Deserialize<'de> for Ext {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+impl<'de> serde::de::Visitor<'de> for ExtVisitor {
+    type Value = Ext;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        formatter.write_str("valid msgpack")
+    }
+
+    #[inline]
+    fn visit_seq<V>(self, mut seq: V) -> Result<Self::Value, V::Error>
     where
-        D: de::Deserializer<'de>,
+        V: de::SeqAccess<'de>,
     {
-        impl<'de> serde::de::Visitor<'de> for ExtVisitor {
-            type Value = Ext;
-
-            fn expecting(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-                formatter.write_str("valid msgpack")
+        let code: u8 = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let ext = match code {
+            msgpack::code::FIXEXT1 => ExtType::FixExt1,
+            msgpack::code::FIXEXT2 => ExtType::FixExt2,
+            msgpack::code::FIXEXT4 => ExtType::FixExt4,
+            msgpack::code::FIXEXT8 => ExtType::FixExt8,
+            msgpack::code::FIXEXT16 => ExtType::FixExt16,
+            msgpack::code::EXT8 => ExtType::Ext8(
+                seq.next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(1, &self))?,
+            ),
+            msgpack::code::EXT16 => ExtType::Ext16(
+                seq.next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(1, &self))?,
+            ),
+            msgpack::code::EXT32 => ExtType::Ext32(
+                seq.next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(1, &self))?,
+            ),
+            v => {
+                return Err(de::Error::custom(format!(
+                    "invalid code for ext type: {:}",
+                    v
+                )));
             }
+        };
+        let id: i8 = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(2, &self))?;
+
+        // owned, since msgpack-value hands out the data of an owned Value as a byte buf
+        let data: serde_bytes::ByteBuf = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(3, &self))?;
+
+        Ok(Ext {
+            ext_type: ext,
+            typ: id,
+            data: bytes::Bytes::from(data.into_vec()),
+        })
+    }
 
-            #[inline]
-            fn visit_seq<V>(self, mut seq: V) -> Result<Self::Value, V::Error>
-            where
-                V: de::SeqAccess<'de>,
-            {
-                println!("visit_seq");
-                let ext: ExtType = seq
-                    .next_element()?
-                    .ok_or_else(|| de::Error::invalid_length(0, &self))?;
-                println!("ext: {:?}", ext);
-                let id: i8 = seq
-                    .next_element()?
-                    .ok_or_else(|| de::Error::invalid_length(1, &self))?;
-                println!("id {:?}", id);
-
-                let data = seq.next_element();
-                println!("{:?}", data);
-                let data: &serde_bytes::Bytes =
-                    data?.ok_or_else(|| de::Error::invalid_length(2, &self))?;
-                println!("data: {:?}", data);
-
-                Ok(Ext {
-                    ext_type: ext,
-                    typ: id,
-                    data: bytes::Bytes::from(data.as_ref()),
-                })
-            }
-        }
+    // how deserialize_any hands out an ext
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(self)
+    }
+}
 
-        deserializer.deserialize_newtype_struct("Ext", ExtVisitor)
+impl<'de> de::Deserialize<'de> for Ext {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct(EXT_TOKEN, ExtVisitor)
     }
 }
 
@@ -153,7 +125,7 @@ impl Ext {
     pub fn fixext1(typ: i8, data: &[u8]) -> Self {
         Ext {
             ext_type: ExtType::FixExt1,
-            data: bytes::Bytes::from(data), // COPY?
+            data: bytes::Bytes::copy_from_slice(data), // COPY?
             typ,
         }
     }
@@ -161,7 +133,7 @@ impl Ext {
     pub fn fixext2(typ: i8, data: &[u8]) -> Self {
         Ext {
             ext_type: ExtType::FixExt2,
-            data: bytes::Bytes::from(data), // COPY?
+            data: bytes::Bytes::copy_from_slice(data), // COPY?
             typ,
         }
     }
@@ -169,7 +141,7 @@ impl Ext {
     pub fn fixext4(typ: i8, data: &[u8]) -> Self {
         Ext {
             ext_type: ExtType::FixExt4,
-            data: bytes::Bytes::from(data), // COPY?
+            data: bytes::Bytes::copy_from_slice(data), // COPY?
             typ,
         }
     }
@@ -177,7 +149,7 @@ impl Ext {
     pub fn fixext8(typ: i8, data: &[u8]) -> Self {
         Ext {
             ext_type: ExtType::FixExt8,
-            data: bytes::Bytes::from(data), // COPY?
+            data: bytes::Bytes::copy_from_slice(data), // COPY?
             typ,
         }
     }
@@ -185,7 +157,7 @@ impl Ext {
     pub fn fixext16(typ: i8, data: &[u8]) -> Self {
         Ext {
             ext_type: ExtType::FixExt16,
-            data: bytes::Bytes::from(data), // COPY?
+            data: bytes::Bytes::copy_from_slice(data), // COPY?
             typ,
         }
     }
@@ -193,7 +165,7 @@ impl Ext {
     pub fn ext8(len: u8, typ: i8, data: &[u8]) -> Self {
         Ext {
             ext_type: ExtType::Ext8(len),
-            data: bytes::Bytes::from(data), // COPY?
+            data: bytes::Bytes::copy_from_slice(data), // COPY?
             typ,
         }
     }
@@ -201,7 +173,7 @@ impl Ext {
     pub fn ext16(len: u16, typ: i8, data: &[u8]) -> Self {
         Ext {
             ext_type: ExtType::Ext16(len),
-            data: bytes::Bytes::from(data), // COPY?
+            data: bytes::Bytes::copy_from_slice(data), // COPY?
             typ,
         }
     }
@@ -209,10 +181,18 @@ impl Ext {
     pub fn ext32(len: u32, typ: i8, data: &[u8]) -> Self {
         Ext {
             ext_type: ExtType::Ext32(len),
-            data: bytes::Bytes::from(data), // COPY?
+            data: bytes::Bytes::copy_from_slice(data), // COPY?
             typ,
         }
     }
+
+    pub fn typ(&self) -> i8 {
+        self.typ
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
 }
 
 impl ser::Serialize for Ext {
@@ -238,7 +218,7 @@ impl ser::Serialize for Ext {
                 // nothing
             }
         };
-        seri.serialize_field(&(self.typ as i8))?;
+        seri.serialize_field(&self.typ)?;
         seri.serialize_field(&self.data)?; // TODO
         seri.end()
     }
@@ -268,7 +248,7 @@ impl<'de> de::Deserialize<'de> for Timestamp {
     where
         D: de::Deserializer<'de>,
     {
-        let v: Ext = deserializer.deserialize_newtype_struct("Timestamp", ExtVisitor)?;
+        let v: Ext = deserializer.deserialize_newtype_struct(EXT_TOKEN, ExtVisitor)?;
         if v.typ != -1 {
             return Err(de::Error::custom(format!(
                 "type is wrong, expectd -1 but {:?}",
@@ -277,55 +257,38 @@ impl<'de> de::Deserialize<'de> for Timestamp {
         }
 
         match v.ext_type {
-            ExtType::FixExt4 => {
-                let sec = unpack::primitive::read_data_u32(&mut io::Cursor::new(v.data)).unwrap();
-                Ok(Timestamp::new(sec as i64, 0))
-            }
-            ExtType::FixExt8 => {
-                let val = unpack::primitive::read_data_u64(&mut io::Cursor::new(v.data)).unwrap();
-                let nsec = val >> 34;
-                let sec = val & 0x00000003ffffffff;
-                Ok(Timestamp::new(sec as i64, nsec as u32))
-            }
-            ExtType::Ext8(size) => {
-                if size != 12 {
-                    return Err(de::Error::custom(format!(
-                        "expected 12 as Timestamp size but {:?}",
-                        size
-                    )));
-                };
-                let mut mm = io::Cursor::new(v.data);
-                let sec = unpack::primitive::read_data_u32(&mut mm).unwrap();
-                let nsec = unpack::primitive::read_data_u64(&mut mm).unwrap();
-                Ok(Timestamp::new(sec as i64, nsec as u32))
-            }
-            v => {
-                return Err(de::Error::custom(format!(
-                    "expected FixExt4, FixExt8 or Ext8, actual value {:?}",
-                    v
-                )))
+            ExtType::FixExt4 | ExtType::FixExt8 | ExtType::Ext8(12) => {
+                let t = unpack::unpack_timestamp_data(&mut &v.data[..], v.data.len())
+                    .map_err(de::Error::custom)?;
+                Ok(Timestamp::new(t.sec(), t.nsec()))
             }
+            ExtType::Ext8(size) => Err(de::Error::custom(format!(
+                "expected 12 as Timestamp size but {:?}",
+                size
+            ))),
+            v => Err(de::Error::custom(format!(
+                "expected FixExt4, FixExt8 or Ext8, actual value {:?}",
+                v
+            ))),
         }
     }
 }
 
-impl<'a> From<&Timestamp> for Ext {
+impl From<&Timestamp> for Ext {
     fn from(v: &Timestamp) -> Self {
         let sec = v.0;
         let nsec = v.1;
         let mut v = vec![]; // TODO
 
-        if sec >> 34 == 0 {
-            if nsec == 0 {
-                pack::primitive::write_data_u32(&mut v, sec as u32)
-                    .expect("expected u32 as timestamp sec ");
-                Ext::fixext4(-1, &v)
-            } else {
-                // TODO: range
-                let payload: u64 = ((nsec as u64) << 34) | sec as u64;
-                pack::primitive::write_data_u64(&mut v, payload).expect("expect u64");
-                Ext::fixext8(-1, &v)
-            }
+        if sec >> 32 == 0 && nsec == 0 {
+            pack::primitive::write_data_u32(&mut v, sec as u32)
+                .expect("expected u32 as timestamp sec ");
+            Ext::fixext4(-1, &v)
+        } else if sec >> 34 == 0 {
+            // TODO: range
+            let payload: u64 = ((nsec as u64) << 34) | sec as u64;
+            pack::primitive::write_data_u64(&mut v, payload).expect("expect u64");
+            Ext::fixext8(-1, &v)
         } else {
             pack::primitive::write_data_u32(&mut v, nsec).expect("u32");
             pack::primitive::write_data_i64(&mut v, sec).expect("u64");
@@ -334,6 +297,99 @@ impl<'a> From<&Timestamp> for Ext {
     }
 }
 
+impl From<msgpack::Timestamp> for Timestamp {
+    fn from(v: msgpack::Timestamp) -> Self {
+        Timestamp(v.sec(), v.nsec())
+    }
+}
+
+impl TryFrom<Timestamp> for msgpack::Timestamp {
+    type Error = msgpack::TimestampRangeError;
+
+    fn try_from(v: Timestamp) -> Result<Self, Self::Error> {
+        msgpack::Timestamp::new(v.0, v.1).ok_or(msgpack::TimestampRangeError)
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<chrono::DateTime<chrono::Utc>> for Timestamp {
+    fn from(v: chrono::DateTime<chrono::Utc>) -> Self {
+        Timestamp::from(msgpack::Timestamp::from(v))
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl TryFrom<Timestamp> for chrono::DateTime<chrono::Utc> {
+    type Error = msgpack::TimestampRangeError;
+
+    fn try_from(v: Timestamp) -> Result<Self, Self::Error> {
+        msgpack::Timestamp::try_from(v).and_then(chrono::DateTime::try_from)
+    }
+}
+
+#[cfg(feature = "time")]
+impl From<time::OffsetDateTime> for Timestamp {
+    fn from(v: time::OffsetDateTime) -> Self {
+        Timestamp::from(msgpack::Timestamp::from(v))
+    }
+}
+
+#[cfg(feature = "time")]
+impl TryFrom<Timestamp> for time::OffsetDateTime {
+    type Error = msgpack::TimestampRangeError;
+
+    fn try_from(v: Timestamp) -> Result<Self, Self::Error> {
+        msgpack::Timestamp::try_from(v).and_then(time::OffsetDateTime::try_from)
+    }
+}
+
+// for fields declared with #[serde(with = "msgpack_serde::ext::chrono_datetime")],
+// which are written as the ext -1 timestamp instead of a string
+#[cfg(feature = "chrono")]
+pub mod chrono_datetime {
+    use super::Timestamp;
+    use chrono::{DateTime, Utc};
+    use serde::{de, ser, Deserialize, Serialize};
+
+    pub fn serialize<S>(v: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        Timestamp::from(*v).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let v = Timestamp::deserialize(deserializer)?;
+        DateTime::try_from(v).map_err(de::Error::custom)
+    }
+}
+
+// the same as chrono_datetime for time::OffsetDateTime. the offset is not kept
+#[cfg(feature = "time")]
+pub mod time_datetime {
+    use super::Timestamp;
+    use serde::{de, ser, Deserialize, Serialize};
+    use time::OffsetDateTime;
+
+    pub fn serialize<S>(v: &OffsetDateTime, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        Timestamp::from(*v).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<OffsetDateTime, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let v = Timestamp::deserialize(deserializer)?;
+        OffsetDateTime::try_from(v).map_err(de::Error::custom)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -379,8 +435,7 @@ mod tests {
     fn timestamp_serizlize() {
         let mut writer = vec![];
         let t = Timestamp(1569144132, 33554431);
-        let _ = t
-            .serialize(&mut crate::ser::Serializer::new(&mut writer))
+        t.serialize(&mut crate::ser::Serializer::new(&mut writer))
             .unwrap();
 
         assert_eq!(
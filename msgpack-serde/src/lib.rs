@@ -1,26 +1,140 @@
+#[cfg(feature = "codec")]
+mod codec;
 mod de;
 pub mod ext;
 mod ser;
 
+#[cfg(feature = "codec")]
+pub use codec::Serde;
+pub use de::{DeError, Deserializer, StreamDeserializer};
 pub use ext::Timestamp;
+use msgpack::{CanonicalOptions, DecodeLimits};
+pub use ser::{Backpatch, EnumRepr, SerError, Serializer, StructRepr};
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::io;
 
 #[inline]
-pub fn pack<T: ?Sized>(value: &T) -> Result<Vec<u8>, ser::SerError>
+pub fn pack<T>(value: &T) -> Result<Vec<u8>, ser::SerError>
 where
-    T: Serialize,
+    T: ?Sized + Serialize,
 {
     let mut writer = vec![];
-    let _ = value.serialize(&mut ser::Serializer::new(&mut writer))?;
+    value.serialize(&mut ser::Serializer::new(&mut writer))?;
     Ok(writer)
 }
 
+#[inline]
+pub fn to_writer<W, T>(writer: W, value: &T) -> Result<(), ser::SerError>
+where
+    W: io::Write,
+    T: ?Sized + Serialize,
+{
+    value.serialize(&mut ser::Serializer::new(writer))
+}
+
+// see Serializer::with_canonical
+#[inline]
+pub fn pack_canonical<T>(value: &T, opts: CanonicalOptions) -> Result<Vec<u8>, ser::SerError>
+where
+    T: ?Sized + Serialize,
+{
+    let mut ser = ser::Serializer::new(vec![]).with_canonical(opts);
+    value.serialize(&mut ser)?;
+    Ok(ser.into_inner())
+}
+
 #[inline]
 pub fn unpack<T>(value: &[u8]) -> Result<T, de::DeError>
 where
     T: DeserializeOwned,
 {
-    let mut de = de::Deserializer::new(value);
-    Deserialize::deserialize(&mut de)
+    unpack_with_limits(value, DecodeLimits::default())
+}
+
+#[inline]
+pub fn unpack_with_limits<T>(value: &[u8], limits: DecodeLimits) -> Result<T, de::DeError>
+where
+    T: DeserializeOwned,
+{
+    from_slice_with_limits(value, limits)
+}
+
+// strs and bytes in `T` can borrow from `value`. bytes after the first object are ignored
+#[inline]
+pub fn from_slice<'de, T>(value: &'de [u8]) -> Result<T, de::DeError>
+where
+    T: Deserialize<'de>,
+{
+    from_slice_with_limits(value, DecodeLimits::default())
+}
+
+pub fn from_slice_with_limits<'de, T>(
+    value: &'de [u8],
+    limits: DecodeLimits,
+) -> Result<T, de::DeError>
+where
+    T: Deserialize<'de>,
+{
+    let mut de = de::Deserializer::new(value).with_limits(limits);
+    de.item(|de| Deserialize::deserialize(de))
+}
+
+// reads exactly one object, which is copied into a buffer before it's deserialized. offsets
+// in errors are relative to the start of that object
+#[inline]
+pub fn from_reader<R, T>(reader: R) -> Result<T, de::DeError>
+where
+    R: io::Read,
+    T: DeserializeOwned,
+{
+    from_reader_with_limits(reader, DecodeLimits::default())
+}
+
+pub fn from_reader_with_limits<R, T>(mut reader: R, limits: DecodeLimits) -> Result<T, de::DeError>
+where
+    R: io::Read,
+    T: DeserializeOwned,
+{
+    let mut scratch = vec![];
+    de::read_raw(&mut reader, &mut scratch, limits)?;
+    from_slice_with_limits(&scratch, limits)
+}
+
+// serializes `value` and writes it out with a single write
+#[cfg(feature = "async")]
+pub async fn pack_async<T, W>(
+    packer: &mut msgpack::AsyncPacker<W>,
+    value: &T,
+) -> Result<(), ser::SerError>
+where
+    T: ?Sized + Serialize,
+    W: tokio::io::AsyncWrite + Unpin,
+{
+    let buf = pack(value)?;
+    packer.write_raw(&buf).await.map_err(ser::SerError::from)
+}
+
+// waits for the next complete object and deserializes it. offsets in errors are relative to
+// the start of that object
+#[cfg(feature = "async")]
+pub async fn unpack_async<T, R>(unpacker: &mut msgpack::AsyncUnpacker<R>) -> Result<T, de::DeError>
+where
+    T: DeserializeOwned,
+    R: tokio::io::AsyncRead + Unpin,
+{
+    unpack_async_with_limits(unpacker, DecodeLimits::default()).await
+}
+
+#[cfg(feature = "async")]
+pub async fn unpack_async_with_limits<T, R>(
+    unpacker: &mut msgpack::AsyncUnpacker<R>,
+    limits: DecodeLimits,
+) -> Result<T, de::DeError>
+where
+    T: DeserializeOwned,
+    R: tokio::io::AsyncRead + Unpin,
+{
+    let raw = unpacker.unpack_raw().await.map_err(de::DeError::from)?;
+    unpack_with_limits(&raw, limits)
 }
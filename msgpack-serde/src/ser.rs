@@ -1,22 +1,122 @@
+mod backpatch;
 mod compound;
 mod error;
 
+pub use backpatch::Backpatch;
 pub use error::SerError;
 
 use crate::ext::EXT_TOKEN;
+use backpatch::Patcher;
 use compound::Compound;
-use msgpack::pack;
+use msgpack::{code, pack, CanonicalOptions};
 use serde::ser;
 use std::io;
 
+// how an enum variant is tagged. the deserializer reads all three
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EnumRepr {
+    // `{name: payload}`, where a unit variant's payload is nil
+    #[default]
+    Name,
+    // `{index: payload}`
+    Index,
+    // `[index, [fields..]]`, as rmp-serde writes it. unit variants have no fields and
+    // struct variants only their values
+    Array,
+}
+
+// how a struct's fields are written. the deserializer reads both
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StructRepr {
+    // `{name: value}`
+    #[default]
+    Map,
+    // `[value..]` in declaration order. fields skipped with skip_serializing_if shift the
+    // ones after them, so a struct written this way shouldn't skip any
+    Array,
+}
+
 #[derive(Debug)]
 pub struct Serializer<W> {
     pub(crate) wr: W,
+    pub(crate) canonical: Option<CanonicalOptions>,
+    pub(crate) patcher: Option<Patcher<W>>,
+    pub(crate) enum_repr: EnumRepr,
+    pub(crate) struct_repr: StructRepr,
 }
 
 impl<W> Serializer<W> {
     pub fn new(wr: W) -> Self {
-        Serializer { wr }
+        Serializer {
+            wr,
+            canonical: None,
+            patcher: None,
+            enum_repr: EnumRepr::default(),
+            struct_repr: StructRepr::default(),
+        }
+    }
+
+    pub fn with_enum_repr(mut self, repr: EnumRepr) -> Self {
+        self.enum_repr = repr;
+        self
+    }
+
+    // also applies to the payload of struct variants
+    pub fn with_struct_repr(mut self, repr: StructRepr) -> Self {
+        self.struct_repr = repr;
+        self
+    }
+
+    // a serializer into a buffer with the same options, for values encoded out of order
+    pub(crate) fn child(&self) -> Serializer<Vec<u8>> {
+        let mut ser = Serializer::new(vec![]);
+        ser.canonical = self.canonical;
+        ser.enum_repr = self.enum_repr;
+        ser.struct_repr = self.struct_repr;
+        ser
+    }
+
+    // a seq or map of unknown length is written behind an array32 or map32 header which is
+    // filled in at its end, instead of being buffered until its length is known.
+    // canonical mode always buffers since it needs the smallest header
+    pub fn with_backpatch(mut self) -> Self
+    where
+        W: Backpatch,
+    {
+        self.patcher = Some(Patcher::new());
+        self
+    }
+
+    // sorts maps and structs by their encoded keys and normalizes floats, so equal values
+    // always serialize to the same bytes. a map with two equal keys fails with DuplicateMapKey
+    pub fn with_canonical(mut self, opts: CanonicalOptions) -> Self {
+        self.canonical = Some(opts);
+        self
+    }
+
+    pub fn into_inner(self) -> W {
+        self.wr
+    }
+}
+
+impl<W: io::Write> Serializer<W> {
+    // writes what comes before a variant's payload
+    fn variant_tag(&mut self, idx: u32, variant: &str) -> Result<(), SerError> {
+        match self.enum_repr {
+            EnumRepr::Name => {
+                pack::pack_map_header(&mut self.wr, 1)?;
+                pack::pack_str(&mut self.wr, variant)?;
+            }
+            EnumRepr::Index => {
+                pack::pack_map_header(&mut self.wr, 1)?;
+                pack::pack_from_u32(&mut self.wr, idx)?;
+            }
+            EnumRepr::Array => {
+                pack::pack_array_header(&mut self.wr, 2)?;
+                pack::pack_from_u32(&mut self.wr, idx)?;
+            }
+        }
+        Ok(())
     }
 }
 
@@ -26,7 +126,7 @@ macro_rules! delegate_impl {
         fn $ser_method(self, v: $typ) -> Result<Self::Ok, Self::Error> {
             pack::$pack_method(&mut self.wr, v).map_err(Self::Error::from)
         }
-    }
+    };
 }
 
 impl<'a, W> serde::Serializer for &'a mut Serializer<W>
@@ -53,11 +153,25 @@ where
     delegate_impl!(serialize_u32, pack_from_u32, u32);
     delegate_impl!(serialize_u64, pack_from_u64, u64);
     delegate_impl!(serialize_bool, pack_bool, bool);
-    delegate_impl!(serialize_f32, pack_f32, f32);
-    delegate_impl!(serialize_f64, pack_f64, f64);
     delegate_impl!(serialize_str, pack_str, &str);
     delegate_impl!(serialize_bytes, pack_bin, &[u8]);
 
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
+        match self.canonical {
+            Some(_) => msgpack::pack_f32_canonical(&mut self.wr, v),
+            None => pack::pack_f32(&mut self.wr, v),
+        }
+        .map_err(Self::Error::from)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
+        match self.canonical {
+            Some(opts) => msgpack::pack_f64_canonical(&mut self.wr, v, opts),
+            None => pack::pack_f64(&mut self.wr, v),
+        }
+        .map_err(Self::Error::from)
+    }
+
     fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
         let mut buf = [0; 4];
         self.serialize_str(v.encode_utf8(&mut buf))
@@ -82,12 +196,14 @@ where
     fn serialize_unit_variant(
         self,
         _name: &str,
-        _idx: u32,
+        idx: u32,
         variant: &str,
     ) -> Result<Self::Ok, Self::Error> {
-        pack::pack_map_header(&mut self.wr, 1)?;
-        self.serialize_str(variant).map_err(Self::Error::from)?;
-        self.serialize_unit().map_err(Self::Error::from)
+        self.variant_tag(idx, variant)?;
+        match self.enum_repr {
+            EnumRepr::Array => pack::pack_array_header(&mut self.wr, 0).map_err(SerError::from),
+            _ => self.serialize_unit(),
+        }
     }
 
     fn serialize_newtype_struct<T: ?Sized + serde::Serialize>(
@@ -101,13 +217,14 @@ where
     fn serialize_newtype_variant<T: ?Sized + serde::Serialize>(
         self,
         _name: &'static str,
-        _idx: u32,
+        idx: u32,
         variant: &'static str,
         value: &T,
     ) -> Result<Self::Ok, Self::Error> {
-        pack::pack_map_header(&mut self.wr, 1)?;
-        // TODO
-        self.serialize_str(variant)?;
+        self.variant_tag(idx, variant)?;
+        if self.enum_repr == EnumRepr::Array {
+            pack::pack_array_header(&mut self.wr, 1)?;
+        }
         value.serialize(self)
     }
 
@@ -117,7 +234,7 @@ where
                 pack::pack_array_header(&mut self.wr, len)?;
                 Ok(self.compound())
             }
-            None => Err(error::SerError::MustHaveLength),
+            None => self.compound_unsized(code::ARRAY32),
         }
     }
 
@@ -138,24 +255,25 @@ where
 
     fn serialize_tuple_variant(
         self,
-        name: &'static str,
-        _idx: u32,
+        _name: &'static str,
+        idx: u32,
         variant: &'static str,
         len: usize,
     ) -> Result<Self::SerializeTupleVariant, Self::Error> {
-        pack::pack_map_header(&mut self.wr, 1)?;
-        // TODO
-        self.serialize_str(variant)?;
-        self.serialize_tuple_struct(name, len)
+        self.variant_tag(idx, variant)?;
+        self.serialize_tuple(len)
     }
 
     fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        if self.canonical.is_some() {
+            return Ok(self.compound_sorted());
+        }
         match len {
             Some(len) => {
                 pack::pack_map_header(&mut self.wr, len)?;
                 Ok(self.compound())
             }
-            None => Err(error::SerError::MustHaveLength),
+            None => self.compound_unsized(code::MAP32),
         }
     }
 
@@ -164,21 +282,30 @@ where
         _name: &'static str,
         len: usize,
     ) -> Result<Self::SerializeStruct, Self::Error> {
+        if self.struct_repr == StructRepr::Array {
+            pack::pack_array_header(&mut self.wr, len)?;
+            return Ok(Compound::Values(self));
+        }
+        if self.canonical.is_some() {
+            return Ok(self.compound_sorted());
+        }
         pack::pack_map_header(&mut self.wr, len)?;
         Ok(self.compound())
     }
 
     fn serialize_struct_variant(
         self,
-        _name: &'static str,
-        _id: u32,
+        name: &'static str,
+        idx: u32,
         variant: &'static str,
         len: usize,
     ) -> Result<Self::SerializeStructVariant, Self::Error> {
-        pack::pack_map_header(&mut self.wr, len)?;
-        self.serialize_str(variant)?;
-        pack::pack_map_header(&mut self.wr, 1)?;
-        Ok(self.compound())
+        self.variant_tag(idx, variant)?;
+        if self.enum_repr == EnumRepr::Array {
+            pack::pack_array_header(&mut self.wr, len)?;
+            return Ok(Compound::Values(self));
+        }
+        self.serialize_struct(name, len)
     }
 }
 
@@ -190,10 +317,10 @@ pub struct ExtSerializer<'a, W: 'a> {
 macro_rules! delegate_ext_error1 {
     ($ser_method:ident, $typ:ty) => {
         #[inline]
-        fn $ser_method(self, v: $typ) -> Result<Self::Ok, Self::Error> {
+        fn $ser_method(self, _v: $typ) -> Result<Self::Ok, Self::Error> {
             Err(SerError::InvalidSerializeMethod("serilize_byte is valid"))
         }
-    }
+    };
 }
 
 impl<'a, W: io::Write + 'a> serde::Serializer for &mut ExtSerializer<'a, W> {
@@ -265,9 +392,9 @@ impl<'a, W: io::Write + 'a> serde::Serializer for &mut ExtSerializer<'a, W> {
     }
 
     #[inline]
-    fn serialize_some<T: ?Sized>(self, _value: &T) -> Result<Self::Ok, Self::Error>
+    fn serialize_some<T>(self, _value: &T) -> Result<Self::Ok, Self::Error>
     where
-        T: ser::Serialize,
+        T: ?Sized + ser::Serialize,
     {
         Err(SerError::InvalidSerializeMethod("serilize_byte is valid"))
     }
@@ -288,18 +415,18 @@ impl<'a, W: io::Write + 'a> serde::Serializer for &mut ExtSerializer<'a, W> {
     }
 
     #[inline]
-    fn serialize_newtype_struct<T: ?Sized>(
+    fn serialize_newtype_struct<T>(
         self,
         _name: &'static str,
         _value: &T,
     ) -> Result<Self::Ok, Self::Error>
     where
-        T: ser::Serialize,
+        T: ?Sized + ser::Serialize,
     {
         Err(SerError::InvalidSerializeMethod("serilize_byte is valid"))
     }
 
-    fn serialize_newtype_variant<T: ?Sized>(
+    fn serialize_newtype_variant<T>(
         self,
         _name: &'static str,
         _idx: u32,
@@ -307,7 +434,7 @@ impl<'a, W: io::Write + 'a> serde::Serializer for &mut ExtSerializer<'a, W> {
         _value: &T,
     ) -> Result<Self::Ok, Self::Error>
     where
-        T: ser::Serialize,
+        T: ?Sized + ser::Serialize,
     {
         Err(SerError::InvalidSerializeMethod("serilize_byte is valid"))
     }
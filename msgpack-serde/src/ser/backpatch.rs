@@ -0,0 +1,101 @@
+use std::fs::File;
+use std::io::{self, Seek, SeekFrom, Write};
+
+// a writer whose earlier output can be overwritten, so a header can be written before the
+// length it holds is known
+pub trait Backpatch: Write {
+    // where the next write goes
+    fn position(&mut self) -> io::Result<u64>;
+
+    // overwrites bytes already written at `pos` without moving the position
+    fn patch(&mut self, pos: u64, buf: &[u8]) -> io::Result<()>;
+}
+
+impl Backpatch for Vec<u8> {
+    fn position(&mut self) -> io::Result<u64> {
+        Ok(self.len() as u64)
+    }
+
+    fn patch(&mut self, pos: u64, buf: &[u8]) -> io::Result<()> {
+        let start = pos as usize;
+        self[start..start + buf.len()].copy_from_slice(buf);
+        Ok(())
+    }
+}
+
+impl<T: Backpatch + ?Sized> Backpatch for &mut T {
+    fn position(&mut self) -> io::Result<u64> {
+        (**self).position()
+    }
+
+    fn patch(&mut self, pos: u64, buf: &[u8]) -> io::Result<()> {
+        (**self).patch(pos, buf)
+    }
+}
+
+impl<T: AsRef<[u8]>> Backpatch for io::Cursor<T>
+where
+    io::Cursor<T>: Write,
+{
+    fn position(&mut self) -> io::Result<u64> {
+        Ok(io::Cursor::position(self))
+    }
+
+    fn patch(&mut self, pos: u64, buf: &[u8]) -> io::Result<()> {
+        seek_patch(self, pos, buf)
+    }
+}
+
+impl Backpatch for File {
+    fn position(&mut self) -> io::Result<u64> {
+        self.stream_position()
+    }
+
+    fn patch(&mut self, pos: u64, buf: &[u8]) -> io::Result<()> {
+        seek_patch(self, pos, buf)
+    }
+}
+
+// seeking flushes the buffer first, so a patch costs a flush
+impl<W: Write + Seek> Backpatch for io::BufWriter<W> {
+    fn position(&mut self) -> io::Result<u64> {
+        self.stream_position()
+    }
+
+    fn patch(&mut self, pos: u64, buf: &[u8]) -> io::Result<()> {
+        seek_patch(self, pos, buf)
+    }
+}
+
+fn seek_patch<W: Write + Seek>(wr: &mut W, pos: u64, buf: &[u8]) -> io::Result<()> {
+    let end = wr.stream_position()?;
+    wr.seek(SeekFrom::Start(pos))?;
+    wr.write_all(buf)?;
+    wr.seek(SeekFrom::Start(end))?;
+    Ok(())
+}
+
+// the Backpatch methods of a writer, kept by a Serializer whose writer type isn't bound by
+// the trait itself
+#[derive(Debug)]
+pub(crate) struct Patcher<W> {
+    pub(crate) position: fn(&mut W) -> io::Result<u64>,
+    pub(crate) patch: fn(&mut W, u64, &[u8]) -> io::Result<()>,
+}
+
+impl<W: Backpatch> Patcher<W> {
+    pub(crate) fn new() -> Self {
+        Patcher {
+            position: <W as Backpatch>::position,
+            patch: <W as Backpatch>::patch,
+        }
+    }
+}
+
+impl<W> Clone for Patcher<W> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<W> Copy for Patcher<W> {}
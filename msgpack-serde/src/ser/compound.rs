@@ -1,24 +1,158 @@
 use std::io;
 
 use crate::ser::{error::SerError, ExtSerializer, Serializer};
+use msgpack::{code, pack};
 use serde::ser::{self, Serialize};
 
 #[derive(Debug)]
 pub enum Compound<'a, W: 'a> {
     Normal(&'a mut Serializer<W>),
+    // struct fields written without their names
+    Values(&'a mut Serializer<W>),
     Ext(ExtSerializer<'a, W>),
+    // a map or struct in canonical mode. entries are encoded on their own and written in
+    // order of their keys once the map ends
+    Sorted {
+        ser: &'a mut Serializer<W>,
+        entries: Vec<(Vec<u8>, Vec<u8>)>,
+        key: Option<Vec<u8>>,
+    },
+    // a seq or map of unknown length, encoded into `buf` until its length is known.
+    // `header` is ARRAY32 or MAP32
+    Buffered {
+        ser: &'a mut Serializer<W>,
+        buf: Serializer<Vec<u8>>,
+        len: usize,
+        header: u8,
+    },
+    // a seq or map of unknown length behind a 32-bit header at `pos`, patched at the end
+    Patched {
+        ser: &'a mut Serializer<W>,
+        pos: u64,
+        len: usize,
+    },
 }
 
-impl<'a, W: io::Write> Serializer<W> {
+impl<W: io::Write> Serializer<W> {
     #[inline]
-    pub fn compound(&mut self) -> Compound<W> {
+    pub fn compound(&mut self) -> Compound<'_, W> {
         Compound::Normal(self)
     }
 
-    pub fn compound_ext(&mut self) -> Compound<W> {
+    pub fn compound_ext(&mut self) -> Compound<'_, W> {
         let ext_se = ExtSerializer { wr: &mut self.wr };
         Compound::Ext(ext_se)
     }
+
+    // `header` is ARRAY32 or MAP32
+    pub(crate) fn compound_unsized(&mut self, header: u8) -> Result<Compound<'_, W>, SerError> {
+        match self.patcher {
+            Some(patcher) if self.canonical.is_none() => {
+                let pos = (patcher.position)(&mut self.wr)?;
+                pack::primitive::write_data_u8(&mut self.wr, header)?;
+                pack::primitive::write_data_u32(&mut self.wr, 0)?;
+                Ok(Compound::Patched {
+                    ser: self,
+                    pos,
+                    len: 0,
+                })
+            }
+            _ => {
+                let buf = self.child();
+                Ok(Compound::Buffered {
+                    ser: self,
+                    buf,
+                    len: 0,
+                    header,
+                })
+            }
+        }
+    }
+
+    pub(crate) fn compound_sorted(&mut self) -> Compound<'_, W> {
+        Compound::Sorted {
+            ser: self,
+            entries: vec![],
+            key: None,
+        }
+    }
+}
+
+impl<'a, W: io::Write + 'a> Compound<'a, W> {
+    fn push_entry<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerError> {
+        match self {
+            Compound::Normal(ref mut ser) => {
+                key.serialize(&mut **ser)?;
+                value.serialize(&mut **ser)
+            }
+            Compound::Values(ref mut ser) => value.serialize(&mut **ser),
+            Compound::Sorted {
+                ref ser,
+                ref mut entries,
+                ..
+            } => {
+                entries.push((encode(ser, key)?, encode(ser, value)?));
+                Ok(())
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    // an element of a seq, or a key or value of a map
+    fn write_item<T: ?Sized + Serialize>(&mut self, v: &T) -> Result<(), SerError> {
+        match self {
+            Compound::Normal(ref mut ser) | Compound::Patched { ref mut ser, .. } => {
+                v.serialize(&mut **ser)
+            }
+            Compound::Buffered { ref mut buf, .. } => v.serialize(buf),
+            _ => unreachable!(),
+        }
+    }
+
+    fn count_item(&mut self) {
+        if let Compound::Buffered { ref mut len, .. } | Compound::Patched { ref mut len, .. } = self
+        {
+            *len += 1;
+        }
+    }
+
+    fn finish(self) -> Result<(), SerError> {
+        match self {
+            Compound::Sorted { ser, entries, .. } => {
+                msgpack::pack_sorted_map(&mut ser.wr, entries).map_err(SerError::from)
+            }
+            Compound::Buffered {
+                ser,
+                buf,
+                len,
+                header,
+            } => {
+                if header == code::MAP32 {
+                    pack::pack_map_header(&mut ser.wr, len)?;
+                } else {
+                    pack::pack_array_header(&mut ser.wr, len)?;
+                }
+                pack::write_all(&mut ser.wr, &buf.into_inner()).map_err(SerError::from)
+            }
+            Compound::Patched { ser, pos, len } => {
+                let len = u32::try_from(len)
+                    .map_err(|_| io::Error::other("too many entries for a 32-bit length"))?;
+                let patch = ser.patcher.expect("patched without a patcher").patch;
+                patch(&mut ser.wr, pos + 1, &len.to_be_bytes()).map_err(SerError::from)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+fn encode<W, T: ?Sized + Serialize>(ser: &Serializer<W>, v: &T) -> Result<Vec<u8>, SerError> {
+    let mut ser = ser.child();
+    v.serialize(&mut ser)?;
+    Ok(ser.into_inner())
 }
 
 impl<'a, W: io::Write + 'a> ser::SerializeMap for Compound<'a, W> {
@@ -27,8 +161,15 @@ impl<'a, W: io::Write + 'a> ser::SerializeMap for Compound<'a, W> {
 
     fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<Self::Ok, Self::Error> {
         match *self {
-            Compound::Normal(ref mut ser) => key.serialize(&mut **ser),
-            Compound::Ext(_) => unreachable!(),
+            Compound::Sorted {
+                ref ser,
+                key: ref mut k,
+                ..
+            } => {
+                *k = Some(encode(ser, key)?);
+                Ok(())
+            }
+            _ => self.write_item(key),
         }
     }
 
@@ -37,13 +178,27 @@ impl<'a, W: io::Write + 'a> ser::SerializeMap for Compound<'a, W> {
         value: &T,
     ) -> Result<Self::Ok, Self::Error> {
         match self {
-            Compound::Normal(ref mut ser) => value.serialize(&mut **ser),
-            Compound::Ext(_) => unreachable!(),
+            Compound::Sorted {
+                ref ser,
+                ref mut entries,
+                ref mut key,
+            } => {
+                let key = key
+                    .take()
+                    .expect("serialize_value is called after serialize_key");
+                entries.push((key, encode(ser, value)?));
+                Ok(())
+            }
+            _ => {
+                self.write_item(value)?;
+                self.count_item();
+                Ok(())
+            }
         }
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        Ok(())
+        self.finish()
     }
 }
 
@@ -55,14 +210,13 @@ impl<'a, W: io::Write + 'a> ser::SerializeSeq for Compound<'a, W> {
         &mut self,
         value: &T,
     ) -> Result<Self::Ok, Self::Error> {
-        match self {
-            Compound::Normal(ref mut ser) => value.serialize(&mut **ser),
-            Compound::Ext(_) => unreachable!(),
-        }
+        self.write_item(value)?;
+        self.count_item();
+        Ok(())
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        Ok(())
+        self.finish()
     }
 }
 
@@ -76,7 +230,7 @@ impl<'a, W: io::Write + 'a> ser::SerializeTuple for Compound<'a, W> {
     ) -> Result<Self::Ok, Self::Error> {
         match self {
             Compound::Normal(ref mut ser) => value.serialize(&mut **ser),
-            Compound::Ext(_) => unreachable!(),
+            _ => unreachable!(),
         }
     }
 
@@ -96,6 +250,7 @@ impl<'a, W: io::Write + 'a> ser::SerializeTupleStruct for Compound<'a, W> {
         match self {
             Compound::Normal(ref mut ser) => value.serialize(&mut **ser),
             Compound::Ext(ref mut ser) => value.serialize(&mut *ser),
+            _ => unreachable!(),
         }
     }
 
@@ -114,7 +269,7 @@ impl<'a, W: io::Write + 'a> ser::SerializeTupleVariant for Compound<'a, W> {
     ) -> Result<Self::Ok, Self::Error> {
         match self {
             Compound::Normal(ref mut ser) => value.serialize(&mut **ser),
-            Compound::Ext(_) => unreachable!(),
+            _ => unreachable!(),
         }
     }
 
@@ -132,17 +287,11 @@ impl<'a, W: io::Write + 'a> ser::SerializeStruct for Compound<'a, W> {
         key: &'static str,
         value: &T,
     ) -> Result<Self::Ok, Self::Error> {
-        match self {
-            Compound::Normal(ref mut ser) => {
-                key.serialize(&mut **ser)?;
-                value.serialize(&mut **ser)
-            }
-            Compound::Ext(_) => unreachable!(),
-        }
+        self.push_entry(key, value)
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        Ok(())
+        self.finish()
     }
 }
 
@@ -155,16 +304,10 @@ impl<'a, W: io::Write + 'a> ser::SerializeStructVariant for Compound<'a, W> {
         key: &'static str,
         value: &T,
     ) -> Result<Self::Ok, Self::Error> {
-        match self {
-            Compound::Normal(ref mut ser) => {
-                key.serialize(&mut **ser)?;
-                value.serialize(&mut **ser)
-            }
-            Compound::Ext(_) => unreachable!(),
-        }
+        self.push_entry(key, value)
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
-        Ok(())
+        self.finish()
     }
 }
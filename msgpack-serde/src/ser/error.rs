@@ -3,8 +3,7 @@ use msgpack::PackError;
 
 use std::error;
 use std::fmt::{self, Display};
-
-use serde;
+use std::io;
 
 #[derive(Debug)]
 pub enum SerError {
@@ -18,6 +17,12 @@ pub enum SerError {
     // Syntax(String)
 }
 
+impl From<io::Error> for SerError {
+    fn from(err: io::Error) -> SerError {
+        SerError::PackError(PackError::WriteError(err))
+    }
+}
+
 impl From<PackError> for SerError {
     fn from(err: PackError) -> SerError {
         SerError::PackError(err)
@@ -25,24 +30,7 @@ impl From<PackError> for SerError {
 }
 
 impl error::Error for SerError {
-    fn description(&self) -> &str {
-        use SerError::*;
-
-        match *self {
-            MustHaveLength => "must have lenght",
-            Custom(ref s) => s,
-            PackError(ref e) => e.description(),
-            InvalidSerializeMethod(_) => "invalid seriaze method",
-            // Error::InvalidValueWrite(..) => "invalid value write",
-            // Error::UnknownLength => {
-            //     "attempt to serialize struct, sequence or map with unknown length"
-            // }
-            // Error::DepthLimitExceeded => "depth limit exceeded",
-            // Error::Syntax(..) => "syntax error",
-        }
-    }
-
-    fn cause(&self) -> Option<&dyn error::Error> {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         use SerError::*;
 
         match *self {
@@ -60,7 +48,20 @@ impl error::Error for SerError {
 
 impl Display for SerError {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        error::Error::description(self).fmt(f)
+        use SerError::*;
+
+        match *self {
+            MustHaveLength => f.write_str("must have length"),
+            Custom(ref s) => f.write_str(s),
+            PackError(ref e) => e.fmt(f),
+            InvalidSerializeMethod(s) => write!(f, "invalid serialize method: {}", s),
+            // Error::InvalidValueWrite(..) => "invalid value write",
+            // Error::UnknownLength => {
+            //     "attempt to serialize struct, sequence or map with unknown length"
+            // }
+            // Error::DepthLimitExceeded => "depth limit exceeded",
+            // Error::Syntax(..) => "syntax error",
+        }
     }
 }
 
@@ -0,0 +1,42 @@
+#![cfg(feature = "async")]
+
+use msgpack::{AsyncPacker, AsyncUnpacker};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+struct Message {
+    id: u32,
+    name: String,
+    tags: Vec<String>,
+}
+
+#[tokio::test]
+async fn async_serde() {
+    let (wr, rd) = tokio::io::duplex(5);
+    let messages: Vec<_> = (0..3)
+        .map(|id| Message {
+            id,
+            name: format!("message {}", id),
+            tags: vec!["a".to_owned(); id as usize],
+        })
+        .collect();
+
+    let expected = messages.clone();
+    let write = async move {
+        let mut packer = AsyncPacker::new(wr);
+        for m in &messages {
+            msgpack_serde::pack_async(&mut packer, m).await.unwrap();
+        }
+    };
+
+    let read = async move {
+        let mut unpacker = AsyncUnpacker::new(rd);
+        for m in expected {
+            let actual: Message = msgpack_serde::unpack_async(&mut unpacker).await.unwrap();
+            assert_eq!(actual, m);
+        }
+        assert!(unpacker.fill_buf().await.unwrap().is_empty());
+    };
+
+    tokio::join!(write, read);
+}
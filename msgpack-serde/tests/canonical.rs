@@ -0,0 +1,90 @@
+use msgpack::{is_canonical, is_canonical_with, CanonicalOptions, PackError};
+use msgpack_serde::SerError;
+use serde::Serialize;
+use std::collections::HashMap;
+
+#[derive(Serialize)]
+struct Point {
+    y: i32,
+    x: i32,
+    label: &'static str,
+}
+
+#[derive(Serialize)]
+enum Shape {
+    Rect { w: u8, h: u8 },
+}
+
+#[test]
+fn sorts_struct_fields() {
+    let v = Point {
+        y: 2,
+        x: 1,
+        label: "p",
+    };
+    let buf = msgpack_serde::pack_canonical(&v, CanonicalOptions::default()).unwrap();
+    assert!(is_canonical(&buf));
+    assert_eq!(
+        buf,
+        &[
+            0x83, 0xa1, 0x78, 0x01, 0xa1, 0x79, 0x02, 0xa5, 0x6c, 0x61, 0x62, 0x65, 0x6c, 0xa1,
+            0x70
+        ]
+    );
+}
+
+#[test]
+fn sorts_maps() {
+    let v: HashMap<u32, &str> = (0..100).map(|i| (i * 7, "v")).collect();
+    let buf = msgpack_serde::pack_canonical(&v, CanonicalOptions::default()).unwrap();
+    assert!(is_canonical(&buf));
+    assert_eq!(
+        msgpack_serde::unpack::<HashMap<u32, String>>(&buf)
+            .unwrap()
+            .len(),
+        100
+    );
+}
+
+#[test]
+fn struct_variant() {
+    let buf =
+        msgpack_serde::pack_canonical(&Shape::Rect { w: 1, h: 2 }, Default::default()).unwrap();
+    assert!(is_canonical(&buf));
+    assert_eq!(
+        buf,
+        &[0x81, 0xa4, 0x52, 0x65, 0x63, 0x74, 0x82, 0xa1, 0x68, 0x02, 0xa1, 0x77, 0x01]
+    );
+}
+
+#[test]
+fn floats() {
+    let opts = CanonicalOptions {
+        shrink_floats: true,
+    };
+    let buf = msgpack_serde::pack_canonical(&(1.0_f64, 0.1_f64, f64::NAN), opts).unwrap();
+    assert!(is_canonical_with(&buf, opts));
+    assert_eq!(&buf[..6], &[0x93, 0xca, 0x3f, 0x80, 0x00, 0x00]);
+    assert_eq!(&buf[buf.len() - 5..], &[0xca, 0x7f, 0xc0, 0x00, 0x00]);
+}
+
+#[test]
+fn duplicate_keys() {
+    struct Dup;
+
+    impl Serialize for Dup {
+        fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+            use serde::ser::SerializeMap;
+            let mut map = s.serialize_map(Some(2))?;
+            map.serialize_entry("a", &1)?;
+            map.serialize_entry("a", &2)?;
+            map.end()
+        }
+    }
+
+    match msgpack_serde::pack_canonical(&Dup, CanonicalOptions::default()) {
+        Err(SerError::PackError(PackError::DuplicateMapKey)) => {}
+        v => panic!("unexpected {:?}", v),
+    }
+    assert!(msgpack_serde::pack(&Dup).is_ok());
+}
@@ -0,0 +1,51 @@
+#![cfg(feature = "codec")]
+
+use bytes::BytesMut;
+use msgpack::MsgPackCodec;
+use msgpack_serde::Serde;
+use serde::{Deserialize, Serialize};
+use tokio_util::codec::{Decoder, Encoder};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Message {
+    id: u32,
+    body: String,
+}
+
+#[test]
+fn codec_serde() {
+    let mut codec = MsgPackCodec::<Serde<Message>>::new();
+    let mut encoded = BytesMut::new();
+    let first = Message {
+        id: 1,
+        body: "hello".to_owned(),
+    };
+    codec.encode(&first, &mut encoded).unwrap();
+    codec
+        .encode(
+            Message {
+                id: 2,
+                body: "world".to_owned(),
+            },
+            &mut encoded,
+        )
+        .unwrap();
+
+    let mut buf = BytesMut::new();
+    let mut actual = vec![];
+    for b in encoded.iter() {
+        buf.extend_from_slice(&[*b]);
+        if let Some(m) = codec.decode(&mut buf).unwrap() {
+            actual.push(m);
+        }
+    }
+    assert_eq!(actual[0], first);
+    assert_eq!(actual[1].id, 2);
+    assert_eq!(actual.len(), 2);
+
+    // a frame which doesn't match the type fails without breaking framing
+    let mut buf = BytesMut::from(&[0xc3, 0x82, 0xa2, 0x69, 0x64, 0x03, 0xa4][..]);
+    buf.extend_from_slice(b"body\xa0");
+    assert!(codec.decode(&mut buf).is_err());
+    assert_eq!(codec.decode(&mut buf).unwrap().unwrap().id, 3);
+}
@@ -0,0 +1,38 @@
+use msgpack_serde::ext::Ext;
+use msgpack_serde::Timestamp;
+use serde_json::Value as Json;
+
+#[path = "../../testdata/conformance.rs"]
+mod suite;
+
+use suite::from_hex;
+
+fn check_case(ctx: &str, case: &Json, bytes: &[u8]) {
+    if let Some(v) = case.get("binary") {
+        let expected = from_hex(v.as_str().unwrap());
+        let actual: serde_bytes::ByteBuf = msgpack_serde::unpack(bytes).expect(ctx);
+        assert_eq!(actual.into_vec(), expected, "{}", ctx);
+    } else if let Some(v) = case.get("ext") {
+        let actual: Ext = msgpack_serde::unpack(bytes).expect(ctx);
+        assert_eq!(i64::from(actual.typ()), v[0].as_i64().unwrap(), "{}", ctx);
+        let data = from_hex(v[1].as_str().unwrap());
+        assert_eq!(actual.data(), data.as_slice(), "{}", ctx);
+    } else if let Some(v) = case.get("timestamp") {
+        let actual: Timestamp = msgpack_serde::unpack(bytes).expect(ctx);
+        let nsec = v[1].as_u64().unwrap() as u32;
+        let expected = Timestamp::new(v[0].as_i64().unwrap(), nsec);
+        assert_eq!(actual, expected, "{}", ctx);
+    } else {
+        let expected = ["nil", "bool", "number", "string", "array", "map"]
+            .iter()
+            .find_map(|k| case.get(k))
+            .unwrap();
+        let actual: Json = msgpack_serde::unpack(bytes).expect(ctx);
+        assert_eq!(&actual, expected, "{}", ctx);
+    }
+}
+
+#[test]
+fn conformance() {
+    suite::for_each_case(check_case);
+}
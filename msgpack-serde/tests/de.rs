@@ -1,3 +1,9 @@
+#![allow(
+    clippy::bool_assert_comparison,
+    clippy::single_component_path_imports,
+    clippy::unnecessary_cast
+)]
+
 use msgpack_serde;
 use serde::Deserialize;
 
@@ -82,3 +88,164 @@ fn de_struct() {
         ret
     );
 }
+
+#[test]
+fn de_struct_unknown_fields() {
+    // {"x": [1, {"y": nil}], "h": 1, "z": "zz", "o": "o"}
+    let buf = [
+        0x84, 0xa1, 0x78, 0x92, 0x01, 0x81, 0xa1, 0x79, 0xc0, 0xa1, 0x68, 0x01, 0xa1, 0x7a, 0xa2,
+        0x7a, 0x7a, 0xa1, 0x6f, 0xa1, 0x6f,
+    ];
+    let ret: Point = msgpack_serde::unpack(&buf).unwrap();
+    assert_eq!(
+        Point {
+            o: "o".to_string(),
+            h: 1,
+        },
+        ret
+    );
+}
+
+#[test]
+fn de_any_width_int() {
+    assert_eq!(1_u64, msgpack_serde::unpack(&[0x01]).unwrap());
+    assert_eq!(255_u32, msgpack_serde::unpack(&[0xcc, 0xff]).unwrap());
+    assert_eq!(-1_i64, msgpack_serde::unpack(&[0xff]).unwrap());
+    assert_eq!(127_i16, msgpack_serde::unpack(&[0x7f]).unwrap());
+    assert!(msgpack_serde::unpack::<u8>(&[0xcd, 0x01, 0x00]).is_err());
+    assert!(msgpack_serde::unpack::<u64>(&[0xff]).is_err());
+}
+
+#[test]
+fn de_round_trip_ints() {
+    for v in [0_u64, 1, 127, 128, 255, 256, 65535, 65536, u64::MAX] {
+        let buf = msgpack_serde::pack(&v).unwrap();
+        assert_eq!(v, msgpack_serde::unpack::<u64>(&buf).unwrap());
+    }
+
+    for v in [0_i64, -1, -32, -33, -128, -129, i64::MIN, i64::MAX] {
+        let buf = msgpack_serde::pack(&v).unwrap();
+        assert_eq!(v, msgpack_serde::unpack::<i64>(&buf).unwrap());
+    }
+}
+
+#[test]
+fn de_float_mode() {
+    // 1 and 2^24 + 1 as ints
+    let buf = [0x01, 0xce, 0x01, 0x00, 0x00, 0x01];
+    assert!(msgpack_serde::unpack::<f64>(&buf[..1]).is_err());
+
+    let mode = msgpack::FloatMode {
+        accept_int: true,
+        ..msgpack::FloatMode::default()
+    };
+    let mut de = msgpack_serde::Deserializer::new(&buf[..]).with_float_mode(mode);
+    assert_eq!(f64::deserialize(&mut de).unwrap(), 1.0);
+    assert!(f32::deserialize(&mut de).is_err());
+
+    let mode = msgpack::FloatMode {
+        allow_precision_loss: true,
+        ..mode
+    };
+    let mut de = msgpack_serde::Deserializer::new(&buf[1..]).with_float_mode(mode);
+    assert_eq!(f32::deserialize(&mut de).unwrap(), 16777216.0);
+}
+
+#[derive(Deserialize, Debug)]
+#[allow(dead_code)]
+struct Item {
+    name: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[allow(dead_code)]
+struct Items {
+    items: Vec<Item>,
+}
+
+#[test]
+fn de_error_location() {
+    // {"items": [{"name": "a"}, {"name": 1}]}
+    let buf = [
+        0x81, 0xa5, 0x69, 0x74, 0x65, 0x6d, 0x73, 0x92, 0x81, 0xa4, 0x6e, 0x61, 0x6d, 0x65, 0xa1,
+        0x61, 0x81, 0xa4, 0x6e, 0x61, 0x6d, 0x65, 0x01,
+    ];
+    let err = msgpack_serde::unpack::<Items>(&buf).unwrap_err();
+    assert_eq!(err.path().unwrap(), "$.items[1].name");
+    assert_eq!(err.offset(), Some(22));
+    match err.root() {
+        msgpack_serde::DeError::UnpackError(msgpack::UnpackError::TypeMismatch(..)) => {}
+        e => panic!("unexpected error: {:?}", e),
+    }
+}
+
+#[test]
+fn de_limits() {
+    let limits = msgpack::DecodeLimits {
+        max_depth: 2,
+        max_container_len: 3,
+        max_data_len: 4,
+        ..msgpack::DecodeLimits::default()
+    };
+
+    let v: Vec<Vec<u8>> = msgpack_serde::unpack_with_limits(&[0x91, 0x91, 0x01], limits).unwrap();
+    assert_eq!(v, vec![vec![1]]);
+
+    let err = msgpack_serde::unpack_with_limits::<Vec<Vec<Vec<u8>>>>(&[0x91, 0x91, 0x90], limits)
+        .unwrap_err();
+    assert_eq!(err.path().unwrap(), "$[0][0]");
+    match err.root() {
+        msgpack_serde::DeError::UnpackError(msgpack::UnpackError::DepthExceeded(2)) => {}
+        e => panic!("unexpected error: {:?}", e),
+    }
+
+    let err = msgpack_serde::unpack_with_limits::<Vec<u8>>(&[0x94, 0x01, 0x02, 0x03, 0x04], limits)
+        .unwrap_err();
+    match err.root() {
+        msgpack_serde::DeError::UnpackError(msgpack::UnpackError::LimitExceeded(
+            msgpack::Limit::ContainerLength,
+            4,
+        )) => {}
+        e => panic!("unexpected error: {:?}", e),
+    }
+
+    let err =
+        msgpack_serde::unpack_with_limits::<String>(&[0xa5, 0x61, 0x62, 0x63, 0x64, 0x65], limits)
+            .unwrap_err();
+    match err.root() {
+        msgpack_serde::DeError::UnpackError(msgpack::UnpackError::LimitExceeded(
+            msgpack::Limit::DataLength,
+            5,
+        )) => {}
+        e => panic!("unexpected error: {:?}", e),
+    }
+
+    // a str32 header claiming 4 GiB fails without allocating it
+    let err = msgpack_serde::unpack::<String>(&[0xdb, 0xff, 0xff, 0xff, 0xff]).unwrap_err();
+    match err.root() {
+        msgpack_serde::DeError::UnpackError(msgpack::UnpackError::UnexpectedEof) => {}
+        e => panic!("unexpected error: {:?}", e),
+    }
+}
+
+#[test]
+fn de_deserializer() {
+    let limits = msgpack::DecodeLimits {
+        max_container_len: 1,
+        ..msgpack::DecodeLimits::default()
+    };
+
+    // objects are read one after another
+    let buf = [0x91, 0x01, 0x02, 0x92, 0x01, 0x02];
+    let mut de = msgpack_serde::Deserializer::new(&buf[..]).with_limits(limits);
+    assert_eq!(Vec::<u8>::deserialize(&mut de).unwrap(), vec![1]);
+    assert_eq!(u8::deserialize(&mut de).unwrap(), 2);
+    let err = Vec::<u8>::deserialize(&mut de).unwrap_err();
+    match err.root() {
+        msgpack_serde::DeError::UnpackError(msgpack::UnpackError::LimitExceeded(
+            msgpack::Limit::ContainerLength,
+            2,
+        )) => {}
+        e => panic!("unexpected error: {:?}", e),
+    }
+}
@@ -0,0 +1,153 @@
+use msgpack::CanonicalOptions;
+use msgpack_serde::{DeError, EnumRepr, Serializer};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+enum Shape {
+    Empty,
+    Circle(u8),
+    Line(u8, u8),
+    Rect { w: u8, h: u8 },
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Scene {
+    shapes: Vec<Shape>,
+    last: Option<Shape>,
+}
+
+fn shapes() -> Vec<Shape> {
+    vec![
+        Shape::Empty,
+        Shape::Circle(3),
+        Shape::Line(1, 2),
+        Shape::Rect { w: 4, h: 5 },
+    ]
+}
+
+fn pack_with(v: &Shape, repr: EnumRepr) -> Vec<u8> {
+    let mut ser = Serializer::new(vec![]).with_enum_repr(repr);
+    v.serialize(&mut ser).unwrap();
+    ser.into_inner()
+}
+
+#[test]
+fn by_name() {
+    let bufs: Vec<_> = shapes()
+        .iter()
+        .map(|v| pack_with(v, EnumRepr::Name))
+        .collect();
+    assert_eq!(
+        bufs,
+        [
+            &[0x81, 0xa5, b'E', b'm', b'p', b't', b'y', 0xc0][..],
+            &[0x81, 0xa6, b'C', b'i', b'r', b'c', b'l', b'e', 0x03],
+            &[0x81, 0xa4, b'L', b'i', b'n', b'e', 0x92, 0x01, 0x02],
+            &[0x81, 0xa4, b'R', b'e', b'c', b't', 0x82, 0xa1, b'w', 0x04, 0xa1, b'h', 0x05],
+        ]
+    );
+}
+
+#[test]
+fn by_index() {
+    let bufs: Vec<_> = shapes()
+        .iter()
+        .map(|v| pack_with(v, EnumRepr::Index))
+        .collect();
+    assert_eq!(
+        bufs,
+        [
+            &[0x81, 0x00, 0xc0][..],
+            &[0x81, 0x01, 0x03],
+            &[0x81, 0x02, 0x92, 0x01, 0x02],
+            &[0x81, 0x03, 0x82, 0xa1, b'w', 0x04, 0xa1, b'h', 0x05],
+        ]
+    );
+}
+
+#[test]
+fn as_array() {
+    let bufs: Vec<_> = shapes()
+        .iter()
+        .map(|v| pack_with(v, EnumRepr::Array))
+        .collect();
+    assert_eq!(
+        bufs,
+        [
+            &[0x92, 0x00, 0x90][..],
+            &[0x92, 0x01, 0x91, 0x03],
+            &[0x92, 0x02, 0x92, 0x01, 0x02],
+            &[0x92, 0x03, 0x92, 0x04, 0x05],
+        ]
+    );
+}
+
+#[test]
+fn round_trips() {
+    for repr in [EnumRepr::Name, EnumRepr::Index, EnumRepr::Array] {
+        for v in shapes() {
+            let buf = pack_with(&v, repr);
+            assert_eq!(
+                msgpack_serde::unpack::<Shape>(&buf).unwrap(),
+                v,
+                "{:?}",
+                repr
+            );
+        }
+    }
+}
+
+#[test]
+fn nested() {
+    let v = Scene {
+        shapes: shapes(),
+        last: Some(Shape::Line(7, 8)),
+    };
+    let buf = msgpack_serde::pack(&v).unwrap();
+    assert_eq!(msgpack_serde::unpack::<Scene>(&buf).unwrap(), v);
+
+    let mut ser = Serializer::new(vec![]).with_enum_repr(EnumRepr::Array);
+    v.serialize(&mut ser).unwrap();
+    assert_eq!(
+        msgpack_serde::unpack::<Scene>(&ser.into_inner()).unwrap(),
+        v
+    );
+}
+
+#[test]
+fn canonical_struct_variant() {
+    let v = Shape::Rect { w: 4, h: 5 };
+    let buf = msgpack_serde::pack_canonical(&v, CanonicalOptions::default()).unwrap();
+    assert_eq!(
+        buf,
+        &[0x81, 0xa4, b'R', b'e', b'c', b't', 0x82, 0xa1, b'h', 0x05, 0xa1, b'w', 0x04]
+    );
+    assert_eq!(msgpack_serde::unpack::<Shape>(&buf).unwrap(), v);
+}
+
+#[test]
+fn bare_unit_variant() {
+    let v: Shape = msgpack_serde::unpack(&[0xa5, b'E', b'm', b'p', b't', b'y']).unwrap();
+    assert_eq!(v, Shape::Empty);
+    let v: Shape = msgpack_serde::unpack(&[0x00]).unwrap();
+    assert_eq!(v, Shape::Empty);
+
+    let e = msgpack_serde::unpack::<Shape>(&[0x01]).unwrap_err();
+    assert!(matches!(e.root(), DeError::Custom(_)));
+}
+
+#[test]
+fn unknown_variant() {
+    let e = msgpack_serde::unpack::<Shape>(&[0x81, 0xa3, b'S', b'u', b'n', 0xc0]).unwrap_err();
+    assert!(matches!(e.root(), DeError::Custom(_)));
+    let e = msgpack_serde::unpack::<Shape>(&[0x81, 0x09, 0xc0]).unwrap_err();
+    assert!(matches!(e.root(), DeError::Custom(_)));
+}
+
+#[test]
+fn wrong_size() {
+    let e = msgpack_serde::unpack::<Shape>(&[0x82, 0x00, 0xc0, 0x01, 0x03]).unwrap_err();
+    assert!(matches!(e.root(), DeError::InvalidSize));
+    let e = msgpack_serde::unpack::<Shape>(&[0x92, 0x01, 0x92, 0x03, 0x04]).unwrap_err();
+    assert!(matches!(e.root(), DeError::InvalidSize));
+}
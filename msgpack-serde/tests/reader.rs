@@ -0,0 +1,208 @@
+use msgpack::{DecodeLimits, Limit, UnpackError};
+use msgpack_serde::{DeError, StreamDeserializer};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::io::{self, Read};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Owned {
+    id: u32,
+    name: String,
+    #[serde(with = "serde_bytes")]
+    data: Vec<u8>,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Borrowed<'a> {
+    id: u32,
+    name: &'a str,
+    #[serde(borrow)]
+    data: &'a serde_bytes::Bytes,
+    #[serde(borrow)]
+    cow: Cow<'a, str>,
+}
+
+// hands out one byte per read, like a slow socket
+struct Trickle<'a>(&'a [u8]);
+
+impl Read for Trickle<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.0.is_empty() || buf.is_empty() {
+            return Ok(0);
+        }
+        buf[0] = self.0[0];
+        self.0 = &self.0[1..];
+        Ok(1)
+    }
+}
+
+fn owned(id: u32) -> Owned {
+    Owned {
+        id,
+        name: format!("n{}", id),
+        data: vec![id as u8; 300],
+    }
+}
+
+#[test]
+fn to_writer() {
+    let mut buf = vec![];
+    msgpack_serde::to_writer(&mut buf, &owned(1)).unwrap();
+    assert_eq!(buf, msgpack_serde::pack(&owned(1)).unwrap());
+}
+
+#[test]
+fn from_slice_borrows() {
+    #[derive(Serialize)]
+    struct Out<'a> {
+        id: u32,
+        name: &'a str,
+        #[serde(with = "serde_bytes")]
+        data: &'a [u8],
+        cow: &'a str,
+    }
+    let buf = msgpack_serde::pack(&Out {
+        id: 3,
+        name: "abc",
+        data: &[1, 2, 3],
+        cow: "x",
+    })
+    .unwrap();
+
+    let v: Borrowed = msgpack_serde::from_slice(&buf).unwrap();
+    assert_eq!(v.id, 3);
+    assert_eq!(v.name, "abc");
+    assert_eq!(&v.data[..], &[1, 2, 3]);
+    assert!(matches!(v.cow, Cow::Borrowed("x")));
+
+    let range = buf.as_ptr_range();
+    assert!(range.contains(&v.name.as_ptr()));
+    assert!(range.contains(&v.data.as_ptr()));
+}
+
+#[test]
+fn from_reader() {
+    let mut buf = msgpack_serde::pack(&owned(1)).unwrap();
+    buf.extend(msgpack_serde::pack(&owned(2)).unwrap());
+
+    // only the first object is read
+    let mut rd = Trickle(&buf);
+    assert_eq!(
+        msgpack_serde::from_reader::<_, Owned>(&mut rd).unwrap(),
+        owned(1)
+    );
+    assert_eq!(
+        msgpack_serde::from_reader::<_, Owned>(&mut rd).unwrap(),
+        owned(2)
+    );
+    assert!(rd.0.is_empty());
+}
+
+#[test]
+fn from_reader_truncated() {
+    let buf = msgpack_serde::pack(&owned(1)).unwrap();
+    let e = msgpack_serde::from_reader::<_, Owned>(&buf[..buf.len() - 1]).unwrap_err();
+    assert!(matches!(
+        e.root(),
+        DeError::UnpackError(UnpackError::UnexpectedEof)
+    ));
+}
+
+#[test]
+fn from_reader_limits() {
+    let buf = msgpack_serde::pack(&owned(1)).unwrap();
+    let limits = DecodeLimits {
+        max_data_len: 100,
+        ..DecodeLimits::default()
+    };
+    assert!(msgpack_serde::from_reader_with_limits::<_, Owned>(&buf[..], limits).is_err());
+}
+
+#[test]
+fn from_reader_limits_bound_buffering() {
+    // a str32 header claiming 4 GiB is turned away before any of the body is read
+    let rd = (&[0xdb, 0xff, 0xff, 0xff, 0xff][..]).chain(io::repeat(b'a'));
+    let limits = DecodeLimits {
+        max_data_len: 1024,
+        ..DecodeLimits::default()
+    };
+    let e = msgpack_serde::from_reader_with_limits::<_, String>(rd, limits).unwrap_err();
+    assert!(matches!(
+        e.root(),
+        DeError::UnpackError(UnpackError::LimitExceeded(Limit::DataLength, 0xffff_ffff))
+    ));
+
+    // and an endless array stops once `max_alloc` bytes have been buffered
+    let rd = (&[0xdd, 0xff, 0xff, 0xff, 0xff][..]).chain(io::repeat(0));
+    let limits = DecodeLimits {
+        max_alloc: 4096,
+        ..DecodeLimits::default()
+    };
+    let e = msgpack_serde::from_reader_with_limits::<_, Vec<u8>>(rd, limits).unwrap_err();
+    assert!(matches!(
+        e.root(),
+        DeError::UnpackError(UnpackError::LimitExceeded(Limit::Allocation, 4097))
+    ));
+
+    // the same limits apply to each object of a stream
+    let rd = (&[0x91, 0x01, 0xdd, 0xff, 0xff, 0xff, 0xff][..]).chain(io::repeat(0));
+    let mut stream = StreamDeserializer::<_, Vec<u8>>::new(rd).with_limits(DecodeLimits {
+        max_container_len: 16,
+        ..DecodeLimits::default()
+    });
+    assert_eq!(stream.next().unwrap().unwrap(), vec![1]);
+    let e = stream.next().unwrap().unwrap_err();
+    assert!(matches!(
+        e.root(),
+        DeError::UnpackError(UnpackError::LimitExceeded(Limit::ContainerLength, _))
+    ));
+    assert!(stream.next().is_none());
+}
+
+#[test]
+fn stream() {
+    let mut buf = vec![];
+    for id in 0..5 {
+        msgpack_serde::to_writer(&mut buf, &owned(id)).unwrap();
+    }
+
+    let stream = StreamDeserializer::<_, Owned>::new(Trickle(&buf));
+    let out: Vec<Owned> = stream.collect::<Result<_, _>>().unwrap();
+    assert_eq!(out, (0..5).map(owned).collect::<Vec<_>>());
+}
+
+#[test]
+fn stream_offsets() {
+    let mut buf = vec![];
+    msgpack_serde::to_writer(&mut buf, &1u8).unwrap();
+    msgpack_serde::to_writer(&mut buf, &300u16).unwrap();
+
+    let mut stream = StreamDeserializer::<_, u16>::new(&buf[..]);
+    assert_eq!(stream.byte_offset(), 0);
+    assert_eq!(stream.next().unwrap().unwrap(), 1);
+    assert_eq!(stream.byte_offset(), 1);
+    assert_eq!(stream.next().unwrap().unwrap(), 300);
+    assert_eq!(stream.byte_offset(), 4);
+    assert!(stream.next().is_none());
+}
+
+#[test]
+fn stream_stops_at_error() {
+    let mut buf = vec![];
+    msgpack_serde::to_writer(&mut buf, &owned(1)).unwrap();
+    msgpack_serde::to_writer(&mut buf, &"not a record").unwrap();
+    msgpack_serde::to_writer(&mut buf, &owned(2)).unwrap();
+
+    let mut stream = StreamDeserializer::<_, Owned>::new(&buf[..]);
+    assert_eq!(stream.next().unwrap().unwrap(), owned(1));
+    assert!(stream.next().unwrap().is_err());
+    assert!(stream.next().is_none());
+}
+
+#[test]
+fn stream_truncated() {
+    let buf = msgpack_serde::pack(&owned(1)).unwrap();
+    let mut stream = StreamDeserializer::<_, Owned>::new(&buf[..10]);
+    assert!(stream.next().unwrap().is_err());
+    assert!(stream.next().is_none());
+}
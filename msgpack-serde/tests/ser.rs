@@ -1,3 +1,5 @@
+#![allow(clippy::redundant_field_names, clippy::single_component_path_imports)]
+
 use msgpack_serde;
 use serde::Serialize;
 
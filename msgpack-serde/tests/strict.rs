@@ -0,0 +1,122 @@
+use msgpack::{DecodeLimits, UnpackError};
+use msgpack_serde::DeError;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+const STRICT: DecodeLimits = DecodeLimits {
+    max_depth: 128,
+    max_container_len: usize::MAX,
+    max_data_len: usize::MAX,
+    max_alloc: usize::MAX,
+    strict: true,
+};
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Item {
+    id: u32,
+    name: String,
+}
+
+fn root(err: &DeError) -> &UnpackError {
+    match err.root() {
+        DeError::UnpackError(e) => e.root(),
+        e => panic!("unexpected error: {:?}", e),
+    }
+}
+
+#[test]
+fn non_minimal() {
+    // {"id": 1 as uint16, "name": "a"}
+    let buf = [
+        0x82, 0xa2, 0x69, 0x64, 0xcd, 0x00, 0x01, 0xa4, 0x6e, 0x61, 0x6d, 0x65, 0xa1, 0x61,
+    ];
+    let item = msgpack_serde::unpack::<Item>(&buf).unwrap();
+    assert_eq!(item.id, 1);
+
+    let err = msgpack_serde::unpack_with_limits::<Item>(&buf, STRICT).unwrap_err();
+    assert!(matches!(root(&err), UnpackError::NotMinimal(_)));
+    assert_eq!(err.path().unwrap(), "$.id");
+
+    // "a" as str8
+    let err = msgpack_serde::unpack_with_limits::<String>(&[0xd9, 0x01, 0x61], STRICT).unwrap_err();
+    assert!(matches!(root(&err), UnpackError::NotMinimal(_)));
+    let err =
+        msgpack_serde::unpack_with_limits::<Vec<u8>>(&[0xdc, 0x00, 0x00], STRICT).unwrap_err();
+    assert!(matches!(root(&err), UnpackError::NotMinimal(_)));
+}
+
+#[test]
+fn non_minimal_floats() {
+    let mode = msgpack::FloatMode {
+        accept_int: true,
+        ..msgpack::FloatMode::default()
+    };
+    // 1 as uint8
+    let buf = [0xcc, 0x01];
+    let mut de = msgpack_serde::Deserializer::new(&buf[..]).with_float_mode(mode);
+    assert_eq!(f64::deserialize(&mut de).unwrap(), 1.0);
+
+    let mut de = msgpack_serde::Deserializer::new(&buf[..])
+        .with_limits(STRICT)
+        .with_float_mode(mode);
+    let err = f32::deserialize(&mut de).unwrap_err();
+    assert!(matches!(root(&err), UnpackError::NotMinimal(_)));
+}
+
+#[test]
+fn duplicate_keys() {
+    let err = msgpack_serde::unpack_with_limits::<HashMap<String, u32>>(
+        &[0x82, 0xa1, 0x61, 0x01, 0xa1, 0x61, 0x02],
+        STRICT,
+    )
+    .unwrap_err();
+    assert!(matches!(root(&err), UnpackError::DuplicateMapKey));
+    assert_eq!(err.path().unwrap(), "$.a");
+
+    let v =
+        msgpack_serde::unpack::<HashMap<String, u32>>(&[0x82, 0xa1, 0x61, 0x01, 0xa1, 0x61, 0x02])
+            .unwrap();
+    assert_eq!(v["a"], 2);
+}
+
+#[test]
+fn ignored_values_are_checked() {
+    // {"id": 1, "name": "a", "x": [1 as uint8]}
+    let buf = [
+        0x83, 0xa2, 0x69, 0x64, 0x01, 0xa4, 0x6e, 0x61, 0x6d, 0x65, 0xa1, 0x61, 0xa1, 0x78, 0x91,
+        0xcc, 0x01,
+    ];
+    msgpack_serde::unpack::<Item>(&buf).unwrap();
+    let err = msgpack_serde::unpack_with_limits::<Item>(&buf, STRICT).unwrap_err();
+    assert!(matches!(root(&err), UnpackError::NotMinimal(_)));
+
+    // an ignored ext8 of 4 bytes, which should have been fixext4
+    let buf = [
+        0x83, 0xa2, 0x69, 0x64, 0x01, 0xa4, 0x6e, 0x61, 0x6d, 0x65, 0xa1, 0x61, 0xa1, 0x78, 0xc7,
+        0x04, 0x01, 0x00, 0x00, 0x00, 0x00,
+    ];
+    msgpack_serde::unpack::<Item>(&buf).unwrap();
+    let err = msgpack_serde::unpack_with_limits::<Item>(&buf, STRICT).unwrap_err();
+    assert!(matches!(root(&err), UnpackError::NotMinimal(_)));
+
+    let buf = [
+        0x83, 0xa2, 0x69, 0x64, 0x01, 0xa4, 0x6e, 0x61, 0x6d, 0x65, 0xa1, 0x61, 0xa1, 0x78, 0xd6,
+        0x01, 0x00, 0x00, 0x00, 0x00,
+    ];
+    let item = msgpack_serde::unpack_with_limits::<Item>(&buf, STRICT).unwrap();
+    assert_eq!(
+        item,
+        Item {
+            id: 1,
+            name: "a".to_owned()
+        }
+    );
+}
+
+#[test]
+fn invalid_utf8_and_reserved() {
+    let err = msgpack_serde::unpack_with_limits::<String>(&[0xa2, 0xc3, 0x28], STRICT).unwrap_err();
+    assert!(matches!(root(&err), UnpackError::InvalidUtf8(_)));
+    let err = msgpack_serde::unpack_with_limits::<u8>(&[0xc1], STRICT).unwrap_err();
+    assert!(matches!(root(&err), UnpackError::ReservedCode(0xc1)));
+}
@@ -0,0 +1,116 @@
+use msgpack::CanonicalOptions;
+use msgpack_serde::{DeError, EnumRepr, Serializer, StructRepr};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Record {
+    id: u32,
+    name: String,
+    tags: Vec<String>,
+    origin: Option<Point>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Point {
+    x: i8,
+    y: i8,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+enum Event {
+    Move { to: Point },
+}
+
+// an older consumer which doesn't know about the last field yet
+#[derive(Deserialize, Debug, PartialEq)]
+struct OldRecord {
+    id: u32,
+    name: String,
+}
+
+fn record() -> Record {
+    Record {
+        id: 7,
+        name: "a".into(),
+        tags: vec!["t".into()],
+        origin: Some(Point { x: 1, y: -1 }),
+    }
+}
+
+fn pack_array<T: Serialize>(v: &T) -> Vec<u8> {
+    let mut ser = Serializer::new(vec![]).with_struct_repr(StructRepr::Array);
+    v.serialize(&mut ser).unwrap();
+    ser.into_inner()
+}
+
+#[test]
+fn writes_arrays() {
+    assert_eq!(
+        pack_array(&record()),
+        &[0x94, 0x07, 0xa1, b'a', 0x91, 0xa1, b't', 0x92, 0x01, 0xff]
+    );
+    assert_eq!(
+        pack_array(&Event::Move {
+            to: Point { x: 2, y: 3 }
+        }),
+        &[0x81, 0xa4, b'M', b'o', b'v', b'e', 0x91, 0x92, 0x02, 0x03]
+    );
+}
+
+#[test]
+fn reads_either() {
+    let v = record();
+    let map = msgpack_serde::pack(&v).unwrap();
+    let array = pack_array(&v);
+    assert!(array.len() < map.len());
+    assert_eq!(msgpack_serde::unpack::<Record>(&map).unwrap(), v);
+    assert_eq!(msgpack_serde::unpack::<Record>(&array).unwrap(), v);
+
+    let e = Event::Move {
+        to: Point { x: 2, y: 3 },
+    };
+    assert_eq!(msgpack_serde::unpack::<Event>(&pack_array(&e)).unwrap(), e);
+}
+
+#[test]
+fn with_enum_array() {
+    let e = Event::Move {
+        to: Point { x: 2, y: 3 },
+    };
+    let mut ser = Serializer::new(vec![])
+        .with_struct_repr(StructRepr::Array)
+        .with_enum_repr(EnumRepr::Array);
+    e.serialize(&mut ser).unwrap();
+    let buf = ser.into_inner();
+    assert_eq!(buf, &[0x92, 0x00, 0x91, 0x92, 0x02, 0x03]);
+    assert_eq!(msgpack_serde::unpack::<Event>(&buf).unwrap(), e);
+}
+
+#[test]
+fn canonical_keeps_order() {
+    let mut ser = Serializer::new(vec![])
+        .with_canonical(CanonicalOptions::default())
+        .with_struct_repr(StructRepr::Array);
+    Point { x: 1, y: 2 }.serialize(&mut ser).unwrap();
+    assert_eq!(ser.into_inner(), &[0x92, 0x01, 0x02]);
+}
+
+#[test]
+fn map_size_is_read() {
+    // fields past the ones the consumer knows are skipped
+    let buf = msgpack_serde::pack(&record()).unwrap();
+    let v: OldRecord = msgpack_serde::unpack(&buf).unwrap();
+    assert_eq!(
+        v,
+        OldRecord {
+            id: 7,
+            name: "a".into()
+        }
+    );
+}
+
+#[test]
+fn short_array() {
+    let e = msgpack_serde::unpack::<Record>(&[0x92, 0x07, 0xa1, b'a']).unwrap_err();
+    assert!(matches!(e.root(), DeError::Custom(_)));
+}
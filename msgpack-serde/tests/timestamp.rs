@@ -0,0 +1,52 @@
+#![cfg(any(feature = "chrono", feature = "time"))]
+
+use serde::{Deserialize, Serialize};
+
+// {"at": timestamp64(1569144132, 33554431)}
+const BUF: [u8; 14] = [
+    0x81, 0xa2, 0x61, 0x74, 0xd7, 0xff, 0x07, 0xff, 0xff, 0xfc, 0x5d, 0x87, 0x3d, 0x44,
+];
+
+#[cfg(feature = "chrono")]
+#[test]
+fn timestamp_chrono() {
+    use chrono::{DateTime, TimeZone, Utc};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Event {
+        #[serde(with = "msgpack_serde::ext::chrono_datetime")]
+        at: DateTime<Utc>,
+    }
+
+    let event = Event {
+        at: Utc.timestamp_opt(1569144132, 33554431).unwrap(),
+    };
+    assert_eq!(msgpack_serde::pack(&event).unwrap(), BUF);
+    assert_eq!(msgpack_serde::unpack::<Event>(&BUF).unwrap(), event);
+
+    let t = msgpack_serde::Timestamp::from(event.at);
+    assert_eq!(t, msgpack_serde::Timestamp::new(1569144132, 33554431));
+    assert_eq!(DateTime::<Utc>::try_from(t).unwrap(), event.at);
+}
+
+#[cfg(feature = "time")]
+#[test]
+fn timestamp_time() {
+    use time::OffsetDateTime;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Event {
+        #[serde(with = "msgpack_serde::ext::time_datetime")]
+        at: OffsetDateTime,
+    }
+
+    let event = Event {
+        at: OffsetDateTime::from_unix_timestamp_nanos(1_569_144_132_033_554_431).unwrap(),
+    };
+    assert_eq!(msgpack_serde::pack(&event).unwrap(), BUF);
+    assert_eq!(msgpack_serde::unpack::<Event>(&BUF).unwrap(), event);
+
+    let t = msgpack_serde::Timestamp::from(event.at);
+    assert_eq!(t, msgpack_serde::Timestamp::new(1569144132, 33554431));
+    assert_eq!(OffsetDateTime::try_from(t).unwrap(), event.at);
+}
@@ -0,0 +1,123 @@
+use msgpack::{is_canonical, CanonicalOptions};
+use msgpack_serde::Serializer;
+use serde::{Deserialize, Serialize, Serializer as _};
+use std::collections::BTreeMap;
+use std::io::Cursor;
+
+// a seq whose length serde can't know up front
+struct Evens(u32);
+
+impl Serialize for Evens {
+    fn serialize<S: serde::Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.collect_seq((0..self.0).filter(|i| i.is_multiple_of(2)))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Inner {
+    b: u8,
+    c: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Outer {
+    a: u8,
+    #[serde(flatten)]
+    inner: Inner,
+}
+
+#[test]
+fn buffers_seq() {
+    let buf = msgpack_serde::pack(&Evens(7)).unwrap();
+    assert_eq!(buf, &[0x94, 0x00, 0x02, 0x04, 0x06]);
+
+    let buf = msgpack_serde::pack(&Evens(40)).unwrap();
+    assert_eq!(&buf[..3], &[0xdc, 0x00, 0x14]);
+    let v: Vec<u32> = msgpack_serde::unpack(&buf).unwrap();
+    assert_eq!(v, (0..40).step_by(2).collect::<Vec<_>>());
+}
+
+#[test]
+fn buffers_map() {
+    let mut buf = vec![];
+    (&mut Serializer::new(&mut buf))
+        .collect_map((0u8..5).filter(|i| *i != 2).map(|i| (i, "v")))
+        .unwrap();
+    assert_eq!(buf[0], 0x84);
+    let v: BTreeMap<u8, String> = msgpack_serde::unpack(&buf).unwrap();
+    assert_eq!(v.keys().copied().collect::<Vec<_>>(), [0, 1, 3, 4]);
+}
+
+#[test]
+fn flatten() {
+    let v = Outer {
+        a: 1,
+        inner: Inner {
+            b: 2,
+            c: "x".into(),
+        },
+    };
+    let buf = msgpack_serde::pack(&v).unwrap();
+    assert_eq!(
+        buf,
+        &[0x83, 0xa1, 0x61, 0x01, 0xa1, 0x62, 0x02, 0xa1, 0x63, 0xa1, 0x78]
+    );
+    assert_eq!(msgpack_serde::unpack::<Outer>(&buf).unwrap(), v);
+}
+
+#[test]
+fn nested() {
+    let v = vec![Evens(3), Evens(0), Evens(5)];
+    let buf = msgpack_serde::pack(&v).unwrap();
+    let out: Vec<Vec<u32>> = msgpack_serde::unpack(&buf).unwrap();
+    assert_eq!(out, [vec![0, 2], vec![], vec![0, 2, 4]]);
+}
+
+#[test]
+fn canonical_uses_smallest_header() {
+    let buf = msgpack_serde::pack_canonical(&Evens(7), CanonicalOptions::default()).unwrap();
+    assert_eq!(buf, &[0x94, 0x00, 0x02, 0x04, 0x06]);
+    assert!(is_canonical(&buf));
+}
+
+#[test]
+fn backpatch_vec() {
+    let mut ser = Serializer::new(vec![0xc0]).with_backpatch();
+    Evens(7).serialize(&mut ser).unwrap();
+    assert_eq!(
+        ser.into_inner(),
+        &[0xc0, 0xdd, 0x00, 0x00, 0x00, 0x04, 0x00, 0x02, 0x04, 0x06]
+    );
+}
+
+#[test]
+fn backpatch_cursor() {
+    let mut ser = Serializer::new(Cursor::new(vec![])).with_backpatch();
+    (&mut ser)
+        .collect_map(
+            (0u8..4)
+                .filter(|i| *i != 1)
+                .map(|i| (i, vec![Evens(i.into())])),
+        )
+        .unwrap();
+    let buf = ser.into_inner().into_inner();
+    assert_eq!(&buf[..5], &[0xdf, 0x00, 0x00, 0x00, 0x03]);
+    let v: BTreeMap<u8, Vec<Vec<u32>>> = msgpack_serde::unpack(&buf).unwrap();
+    assert_eq!(v[&3], [vec![0, 2]]);
+}
+
+#[test]
+fn backpatch_flatten() {
+    let v = Outer {
+        a: 1,
+        inner: Inner {
+            b: 2,
+            c: "x".into(),
+        },
+    };
+    let mut ser = Serializer::new(vec![]).with_backpatch();
+    v.serialize(&mut ser).unwrap();
+    let buf = ser.into_inner();
+    assert_eq!(&buf[..5], &[0xdf, 0x00, 0x00, 0x00, 0x03]);
+    assert_eq!(msgpack_serde::unpack::<Outer>(&buf).unwrap(), v);
+}
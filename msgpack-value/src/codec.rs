@@ -0,0 +1,52 @@
+use crate::{pack, unpack, RefValue, Value};
+
+use bytes::Bytes;
+use msgpack::{DecodeLimits, FrameDecode, FrameEncode, PackError, UnpackError};
+use std::io;
+
+impl FrameDecode for Value {
+    type Item = Value;
+    type Error = UnpackError;
+
+    fn decode_frame(frame: Bytes, limits: DecodeLimits) -> Result<Value, UnpackError> {
+        unpack::unpack_value_with_limits(&mut &frame[..], limits)
+    }
+}
+
+impl FrameEncode<Value> for Value {
+    type Error = PackError;
+
+    fn encode_frame<W: io::Write>(item: Value, wr: &mut W) -> Result<(), PackError> {
+        pack::pack_value(wr, item)
+    }
+}
+
+// a decoded frame which hands out RefValues borrowing from it instead of copying.
+// the frame is checked against the codec's limits once, when it is decoded
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RefFrame(Bytes);
+
+impl RefFrame {
+    pub fn value(&self) -> RefValue<'_> {
+        unpack::unpack_value_ref_with_limits(&mut &self.0[..], DecodeLimits::unlimited())
+            .expect("a frame is validated when it is decoded")
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn into_bytes(self) -> Bytes {
+        self.0
+    }
+}
+
+impl FrameDecode for RefFrame {
+    type Item = RefFrame;
+    type Error = UnpackError;
+
+    fn decode_frame(frame: Bytes, limits: DecodeLimits) -> Result<RefFrame, UnpackError> {
+        unpack::unpack_value_ref_with_limits(&mut &frame[..], limits)?;
+        Ok(RefFrame(frame))
+    }
+}
@@ -0,0 +1,752 @@
+use crate::ser;
+use crate::value::{float, integer, Float, Integer, Utf8String, Utf8StringRef};
+use crate::{RefValue, SerdeError, Value};
+use msgpack::{code, pack, unpack, TIMESTAMP_TYPE};
+use serde::de::{self, DeserializeSeed, Error, Unexpected, Visitor};
+use std::borrow::Cow;
+use std::fmt;
+use std::marker::PhantomData;
+
+// msgpack-serde hands an ext to deserialize_any as a newtype around its fields, which no
+// other format does. the same exts come back as plain arrays from formats without them
+impl<'de> de::Deserialize<'de> for Value {
+    fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+// strs, bins and exts borrow from the input, so this only deserializes from formats which
+// can lend it out, like msgpack_serde::from_slice
+impl<'de: 'a, 'a> de::Deserialize<'de> for RefValue<'a> {
+    fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(RefValueVisitor(PhantomData))
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("any MessagePack value")
+    }
+
+    fn visit_bool<E: Error>(self, v: bool) -> Result<Value, E> {
+        Ok(Value::Boolean(v))
+    }
+
+    fn visit_i64<E: Error>(self, v: i64) -> Result<Value, E> {
+        Ok(Value::Integer(Integer::from(v)))
+    }
+
+    fn visit_u64<E: Error>(self, v: u64) -> Result<Value, E> {
+        Ok(Value::Integer(Integer::from(v)))
+    }
+
+    fn visit_f32<E: Error>(self, v: f32) -> Result<Value, E> {
+        Ok(Value::Float(Float::from(v)))
+    }
+
+    fn visit_f64<E: Error>(self, v: f64) -> Result<Value, E> {
+        Ok(Value::Float(Float::from(v)))
+    }
+
+    fn visit_str<E: Error>(self, v: &str) -> Result<Value, E> {
+        Ok(Value::String(Utf8String::from(v)))
+    }
+
+    fn visit_string<E: Error>(self, v: String) -> Result<Value, E> {
+        Ok(Value::String(Utf8String::from(v)))
+    }
+
+    fn visit_bytes<E: Error>(self, v: &[u8]) -> Result<Value, E> {
+        Ok(Value::Binary(v.to_vec()))
+    }
+
+    fn visit_byte_buf<E: Error>(self, v: Vec<u8>) -> Result<Value, E> {
+        Ok(Value::Binary(v))
+    }
+
+    fn visit_unit<E: Error>(self) -> Result<Value, E> {
+        Ok(Value::Nil)
+    }
+
+    fn visit_none<E: Error>(self) -> Result<Value, E> {
+        Ok(Value::Nil)
+    }
+
+    fn visit_some<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        de::Deserialize::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut vs = Vec::with_capacity(unpack::capacity_hint::<Self::Value>(
+            seq.size_hint().unwrap_or(0),
+        ));
+        while let Some(v) = seq.next_element()? {
+            vs.push(v);
+        }
+        Ok(Value::Array(vs))
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut vs = Vec::with_capacity(unpack::capacity_hint::<(Self::Value, Self::Value)>(
+            map.size_hint().unwrap_or(0),
+        ));
+        while let Some(kv) = map.next_entry()? {
+            vs.push(kv);
+        }
+        Ok(Value::Map(vs))
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let (ty, data) = deserializer.deserialize_seq(ExtVisitor)?;
+        if ty == TIMESTAMP_TYPE {
+            return timestamp(&data).map(Value::from);
+        }
+        Ok(Value::Extension(ty, data.into_owned()))
+    }
+}
+
+struct RefValueVisitor<'a>(PhantomData<RefValue<'a>>);
+
+impl<'de: 'a, 'a> Visitor<'de> for RefValueVisitor<'a> {
+    type Value = RefValue<'a>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("any MessagePack value borrowed from the input")
+    }
+
+    fn visit_bool<E: Error>(self, v: bool) -> Result<Self::Value, E> {
+        Ok(RefValue::Boolean(v))
+    }
+
+    fn visit_i64<E: Error>(self, v: i64) -> Result<Self::Value, E> {
+        Ok(RefValue::Integer(Integer::from(v)))
+    }
+
+    fn visit_u64<E: Error>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(RefValue::Integer(Integer::from(v)))
+    }
+
+    fn visit_f32<E: Error>(self, v: f32) -> Result<Self::Value, E> {
+        Ok(RefValue::Float(Float::from(v)))
+    }
+
+    fn visit_f64<E: Error>(self, v: f64) -> Result<Self::Value, E> {
+        Ok(RefValue::Float(Float::from(v)))
+    }
+
+    fn visit_borrowed_str<E: Error>(self, v: &'de str) -> Result<Self::Value, E> {
+        Ok(RefValue::String(Utf8StringRef::from(v)))
+    }
+
+    fn visit_borrowed_bytes<E: Error>(self, v: &'de [u8]) -> Result<Self::Value, E> {
+        Ok(RefValue::Binary(v))
+    }
+
+    fn visit_unit<E: Error>(self) -> Result<Self::Value, E> {
+        Ok(RefValue::Nil)
+    }
+
+    fn visit_none<E: Error>(self) -> Result<Self::Value, E> {
+        Ok(RefValue::Nil)
+    }
+
+    fn visit_some<D: de::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        de::Deserialize::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut vs = Vec::with_capacity(unpack::capacity_hint::<Self::Value>(
+            seq.size_hint().unwrap_or(0),
+        ));
+        while let Some(v) = seq.next_element()? {
+            vs.push(v);
+        }
+        Ok(RefValue::Array(vs))
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut vs = Vec::with_capacity(unpack::capacity_hint::<(Self::Value, Self::Value)>(
+            map.size_hint().unwrap_or(0),
+        ));
+        while let Some(kv) = map.next_entry()? {
+            vs.push(kv);
+        }
+        Ok(RefValue::Map(vs))
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        let (ty, data) = deserializer.deserialize_seq(ExtVisitor)?;
+        if ty == TIMESTAMP_TYPE {
+            return timestamp(&data).map(RefValue::from);
+        }
+        match data {
+            Cow::Borrowed(data) => Ok(RefValue::Extension(ty, data)),
+            Cow::Owned(data) => Err(Error::invalid_type(Unexpected::Bytes(&data), &self)),
+        }
+    }
+}
+
+fn timestamp<E: Error>(data: &[u8]) -> Result<msgpack::Timestamp, E> {
+    unpack::unpack_timestamp_data(&mut &data[..], data.len()).map_err(E::custom)
+}
+
+// the fields msgpack-serde gives an ext: its code, the length for ext8/16/32, its type and
+// its data
+struct ExtVisitor;
+
+impl<'de> Visitor<'de> for ExtVisitor {
+    type Value = (i8, Cow<'de, [u8]>);
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a MessagePack ext")
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let code: u8 = seq
+            .next_element()?
+            .ok_or_else(|| Error::invalid_length(0, &self))?;
+        let len = match code {
+            code::EXT8 => seq.next_element::<u8>()?.map(u32::from),
+            code::EXT16 => seq.next_element::<u16>()?.map(u32::from),
+            code::EXT32 => seq.next_element::<u32>()?,
+            _ => Some(0),
+        };
+        len.ok_or_else(|| Error::invalid_length(1, &self))?;
+
+        let ty: i8 = seq
+            .next_element()?
+            .ok_or_else(|| Error::invalid_length(2, &self))?;
+        let data = seq
+            .next_element_seed(BytesSeed)?
+            .ok_or_else(|| Error::invalid_length(3, &self))?;
+        Ok((ty, data))
+    }
+}
+
+struct BytesSeed;
+
+impl<'de> DeserializeSeed<'de> for BytesSeed {
+    type Value = Cow<'de, [u8]>;
+
+    fn deserialize<D: de::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_bytes(self)
+    }
+}
+
+impl<'de> Visitor<'de> for BytesSeed {
+    type Value = Cow<'de, [u8]>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("bytes")
+    }
+
+    fn visit_borrowed_bytes<E: Error>(self, v: &'de [u8]) -> Result<Self::Value, E> {
+        Ok(Cow::Borrowed(v))
+    }
+
+    fn visit_bytes<E: Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        Ok(Cow::Owned(v.to_vec()))
+    }
+
+    fn visit_byte_buf<E: Error>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+        Ok(Cow::Owned(v))
+    }
+}
+
+// reads a `T` back out of a Value laid out the way msgpack-serde writes it by default, or the
+// way unpack_value reads what it writes in any of its representations
+pub fn from_value<T: de::DeserializeOwned>(value: Value) -> Result<T, SerdeError> {
+    T::deserialize(value)
+}
+
+// like from_value, but strs and bins in `T` can borrow from the value
+pub fn from_ref_value<'de, T: de::Deserialize<'de>>(value: RefValue<'de>) -> Result<T, SerdeError> {
+    T::deserialize(value)
+}
+
+// what the Value and RefValue deserializers have in common
+trait Tree<'de>: de::Deserializer<'de, Error = SerdeError> + Sized {
+    fn is_nil(&self) -> bool;
+
+    // the elements of an array, or the value back when it's something else
+    fn into_array(self) -> Result<Vec<Self>, Self>;
+}
+
+impl<'de> Tree<'de> for Value {
+    fn is_nil(&self) -> bool {
+        matches!(*self, Value::Nil)
+    }
+
+    fn into_array(self) -> Result<Vec<Self>, Self> {
+        match self {
+            Value::Array(vs) => Ok(vs),
+            v => Err(v),
+        }
+    }
+}
+
+impl<'de> Tree<'de> for RefValue<'de> {
+    fn is_nil(&self) -> bool {
+        matches!(*self, RefValue::Nil)
+    }
+
+    fn into_array(self) -> Result<Vec<Self>, Self> {
+        match self {
+            RefValue::Array(vs) => Ok(vs),
+            v => Err(v),
+        }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Value {
+    type Error = SerdeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match self {
+            Value::Nil => visitor.visit_unit(),
+            Value::Boolean(v) => visitor.visit_bool(v),
+            Value::Integer(v) => visit_integer(v, visitor),
+            Value::Float(v) => visit_float(v, visitor),
+            Value::Binary(v) => visitor.visit_byte_buf(v),
+            Value::String(v) => match v.s {
+                Ok(v) => visitor.visit_string(v),
+                Err((v, _)) => visitor.visit_byte_buf(v),
+            },
+            Value::Array(vs) => visit_array(vs, visitor),
+            Value::Map(vs) => visit_map(vs, visitor),
+            Value::Extension(ty, data) => visitor.visit_newtype_struct(ext_fields(ty, data)),
+            Value::Timestamp(sec, nsec) => {
+                visitor.visit_newtype_struct(timestamp_fields(sec, nsec)?)
+            }
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        deserialize_option(self, visitor)
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        deserialize_unit_struct(self, visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        match self {
+            _ if name != ser::EXT_TOKEN => visitor.visit_newtype_struct(self),
+            Value::Extension(ty, data) => ext_fields(ty, data).deserialize_any(visitor),
+            Value::Timestamp(sec, nsec) => timestamp_fields(sec, nsec)?.deserialize_any(visitor),
+            _ => visitor.visit_newtype_struct(self),
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        match self {
+            Value::Map(vs) => visit_tagged(vs, visitor),
+            Value::Array(vs) => visit_indexed(vs, visitor),
+            v => visitor.visit_enum(EnumAccess::new(v, None)),
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_unit()
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+            unit seq tuple tuple_struct map struct identifier
+    }
+}
+
+impl<'de> de::Deserializer<'de> for RefValue<'de> {
+    type Error = SerdeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match self {
+            RefValue::Nil => visitor.visit_unit(),
+            RefValue::Boolean(v) => visitor.visit_bool(v),
+            RefValue::Integer(v) => visit_integer(v, visitor),
+            RefValue::Float(v) => visit_float(v, visitor),
+            RefValue::Binary(v) => visitor.visit_borrowed_bytes(v),
+            RefValue::String(v) => match v.s {
+                Ok(v) => visitor.visit_borrowed_str(v),
+                Err((v, _)) => visitor.visit_borrowed_bytes(v),
+            },
+            RefValue::Array(vs) => visit_array(vs, visitor),
+            RefValue::Map(vs) => visit_map(vs, visitor),
+            RefValue::Extension(ty, data) => visitor.visit_newtype_struct(ref_ext_fields(ty, data)),
+            RefValue::Timestamp(sec, nsec) => {
+                visitor.visit_newtype_struct(timestamp_fields(sec, nsec)?)
+            }
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        deserialize_option(self, visitor)
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        deserialize_unit_struct(self, visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        match self {
+            _ if name != ser::EXT_TOKEN => visitor.visit_newtype_struct(self),
+            RefValue::Extension(ty, data) => ref_ext_fields(ty, data).deserialize_any(visitor),
+            RefValue::Timestamp(sec, nsec) => timestamp_fields(sec, nsec)?.deserialize_any(visitor),
+            _ => visitor.visit_newtype_struct(self),
+        }
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        match self {
+            RefValue::Map(vs) => visit_tagged(vs, visitor),
+            RefValue::Array(vs) => visit_indexed(vs, visitor),
+            v => visitor.visit_enum(EnumAccess::new(v, None)),
+        }
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_unit()
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+            unit seq tuple tuple_struct map struct identifier
+    }
+}
+
+fn visit_integer<'de, V: Visitor<'de>>(v: Integer, visitor: V) -> Result<V::Value, SerdeError> {
+    match v.n {
+        integer::Number::PosInt(v) => visitor.visit_u64(v),
+        integer::Number::NegInt(v) => visitor.visit_i64(v),
+    }
+}
+
+fn visit_float<'de, V: Visitor<'de>>(v: Float, visitor: V) -> Result<V::Value, SerdeError> {
+    match v.n {
+        float::Number::Float32(v) => visitor.visit_f32(v),
+        float::Number::Float64(v) => visitor.visit_f64(v),
+    }
+}
+
+fn visit_array<'de, T, V>(vs: Vec<T>, visitor: V) -> Result<V::Value, SerdeError>
+where
+    T: Tree<'de>,
+    V: Visitor<'de>,
+{
+    let len = vs.len();
+    let mut seq = SeqAccess(vs.into_iter());
+    let v = visitor.visit_seq(&mut seq)?;
+    if seq.0.len() != 0 {
+        return Err(Error::invalid_length(len, &"fewer elements in array"));
+    }
+    Ok(v)
+}
+
+fn visit_map<'de, T, V>(vs: Vec<(T, T)>, visitor: V) -> Result<V::Value, SerdeError>
+where
+    T: Tree<'de>,
+    V: Visitor<'de>,
+{
+    let len = vs.len();
+    let mut map = MapAccess {
+        iter: vs.into_iter(),
+        value: None,
+    };
+    let v = visitor.visit_map(&mut map)?;
+    if map.iter.len() != 0 {
+        return Err(Error::invalid_length(len, &"fewer elements in map"));
+    }
+    Ok(v)
+}
+
+fn deserialize_option<'de, T, V>(v: T, visitor: V) -> Result<V::Value, SerdeError>
+where
+    T: Tree<'de>,
+    V: Visitor<'de>,
+{
+    if v.is_nil() {
+        visitor.visit_none()
+    } else {
+        visitor.visit_some(v)
+    }
+}
+
+// msgpack-serde writes unit structs as empty arrays, but nil reads as one as well
+fn deserialize_unit_struct<'de, T, V>(v: T, visitor: V) -> Result<V::Value, SerdeError>
+where
+    T: Tree<'de>,
+    V: Visitor<'de>,
+{
+    match v.into_array() {
+        Ok(vs) if vs.is_empty() => visitor.visit_unit(),
+        Ok(vs) => Err(Error::invalid_length(vs.len(), &"an empty array")),
+        Err(v) => v.deserialize_any(visitor),
+    }
+}
+
+// a map of one entry from the variant to its payload
+fn visit_tagged<'de, T, V>(vs: Vec<(T, T)>, visitor: V) -> Result<V::Value, SerdeError>
+where
+    T: Tree<'de>,
+    V: Visitor<'de>,
+{
+    if vs.len() != 1 {
+        return Err(Error::invalid_length(vs.len(), &"a map of one variant"));
+    }
+    let (variant, payload) = vs.into_iter().next().unwrap();
+    visitor.visit_enum(EnumAccess::new(variant, Some((EnumForm::Tagged, payload))))
+}
+
+// `[index, [fields..]]`
+fn visit_indexed<'de, T, V>(vs: Vec<T>, visitor: V) -> Result<V::Value, SerdeError>
+where
+    T: Tree<'de>,
+    V: Visitor<'de>,
+{
+    if vs.len() != 2 {
+        return Err(Error::invalid_length(
+            vs.len(),
+            &"an array of a variant and its fields",
+        ));
+    }
+    let mut vs = vs.into_iter();
+    let variant = vs.next().unwrap();
+    let payload = vs.next().unwrap();
+    visitor.visit_enum(EnumAccess::new(variant, Some((EnumForm::Array, payload))))
+}
+
+// the fields msgpack-serde hands to visit_newtype_struct for an ext, as an array
+fn ext_fields(ty: i8, data: Vec<u8>) -> Value {
+    let mut fields = ext_header(ty, data.len())
+        .into_iter()
+        .map(Value::Integer)
+        .collect::<Vec<_>>();
+    fields.push(Value::Binary(data));
+    Value::Array(fields)
+}
+
+fn timestamp_fields(sec: i64, nsec: u32) -> Result<Value, SerdeError> {
+    let mut buf = vec![];
+    pack::pack_timestamp(&mut buf, sec, nsec).map_err(Error::custom)?;
+    let (code, len, ty, data) = ser::split_ext(&buf);
+    let mut fields = vec![Value::from(code)];
+    fields.extend(len.map(Value::from));
+    fields.push(Value::from(ty));
+    fields.push(Value::Binary(data.to_vec()));
+    Ok(Value::Array(fields))
+}
+
+fn ref_ext_fields(ty: i8, data: &[u8]) -> RefValue<'_> {
+    let mut fields = ext_header(ty, data.len())
+        .into_iter()
+        .map(RefValue::Integer)
+        .collect::<Vec<_>>();
+    fields.push(RefValue::Binary(data));
+    RefValue::Array(fields)
+}
+
+// the code, the length for ext8/16/32 and the type of an ext of `len` bytes
+fn ext_header(ty: i8, len: usize) -> Vec<Integer> {
+    let (code, len, ty, _) = ser::split_ext(&ser::ext_header(ty, len));
+    let mut header = vec![Integer::from(code)];
+    header.extend(len.map(Integer::from));
+    header.push(Integer::from(ty));
+    header
+}
+
+struct SeqAccess<I>(I);
+
+impl<'de, T, I> de::SeqAccess<'de> for SeqAccess<I>
+where
+    T: Tree<'de>,
+    I: ExactSizeIterator<Item = T>,
+{
+    type Error = SerdeError;
+
+    fn next_element_seed<S: DeserializeSeed<'de>>(
+        &mut self,
+        seed: S,
+    ) -> Result<Option<S::Value>, SerdeError> {
+        self.0.next().map(|v| seed.deserialize(v)).transpose()
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.0.len())
+    }
+}
+
+struct MapAccess<I, T> {
+    iter: I,
+    value: Option<T>,
+}
+
+impl<'de, T, I> de::MapAccess<'de> for MapAccess<I, T>
+where
+    T: Tree<'de>,
+    I: ExactSizeIterator<Item = (T, T)>,
+{
+    type Error = SerdeError;
+
+    fn next_key_seed<S: DeserializeSeed<'de>>(
+        &mut self,
+        seed: S,
+    ) -> Result<Option<S::Value>, SerdeError> {
+        match self.iter.next() {
+            Some((k, v)) => {
+                self.value = Some(v);
+                seed.deserialize(k).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<S: DeserializeSeed<'de>>(
+        &mut self,
+        seed: S,
+    ) -> Result<S::Value, SerdeError> {
+        let v = self
+            .value
+            .take()
+            .expect("next_value_seed is called after next_key_seed");
+        seed.deserialize(v)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+// how a variant was tagged, see msgpack-serde's deserialize_enum
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum EnumForm {
+    Tagged,
+    Array,
+}
+
+struct EnumAccess<T> {
+    variant: T,
+    // None for just the variant, which is a unit variant
+    payload: Option<(EnumForm, T)>,
+}
+
+impl<T> EnumAccess<T> {
+    fn new(variant: T, payload: Option<(EnumForm, T)>) -> Self {
+        EnumAccess { variant, payload }
+    }
+}
+
+impl<'de, T: Tree<'de>> de::EnumAccess<'de> for EnumAccess<T> {
+    type Error = SerdeError;
+    type Variant = VariantAccess<T>;
+
+    fn variant_seed<S: DeserializeSeed<'de>>(
+        self,
+        seed: S,
+    ) -> Result<(S::Value, VariantAccess<T>), SerdeError> {
+        let variant = seed.deserialize(self.variant)?;
+        Ok((variant, VariantAccess(self.payload)))
+    }
+}
+
+struct VariantAccess<T>(Option<(EnumForm, T)>);
+
+impl<T> VariantAccess<T> {
+    fn payload(self) -> Result<(EnumForm, T), SerdeError> {
+        self.0
+            .ok_or_else(|| Error::invalid_type(Unexpected::UnitVariant, &"a variant with data"))
+    }
+}
+
+// the `[fields..]` of an `[index, [fields..]]` variant
+fn fields<'de, T: Tree<'de>>(v: T, len: usize) -> Result<Vec<T>, SerdeError> {
+    match v.into_array() {
+        Ok(vs) if vs.len() == len => Ok(vs),
+        Ok(vs) => Err(Error::invalid_length(vs.len(), &"the fields of a variant")),
+        Err(_) => Err(Error::invalid_type(
+            Unexpected::Other("not an array"),
+            &"an array of fields",
+        )),
+    }
+}
+
+impl<'de, T: Tree<'de>> de::VariantAccess<'de> for VariantAccess<T> {
+    type Error = SerdeError;
+
+    fn unit_variant(self) -> Result<(), SerdeError> {
+        match self.0 {
+            Some((EnumForm::Tagged, v)) => de::Deserialize::deserialize(v),
+            Some((EnumForm::Array, v)) => fields(v, 0).map(drop),
+            None => Ok(()),
+        }
+    }
+
+    fn newtype_variant_seed<S: DeserializeSeed<'de>>(
+        self,
+        seed: S,
+    ) -> Result<S::Value, SerdeError> {
+        match self.payload()? {
+            (EnumForm::Tagged, v) => seed.deserialize(v),
+            (EnumForm::Array, v) => seed.deserialize(fields(v, 1)?.pop().unwrap()),
+        }
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        let (_, v) = self.payload()?;
+        v.deserialize_tuple(len, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        let (_, v) = self.payload()?;
+        v.deserialize_struct("", fields, visitor)
+    }
+}
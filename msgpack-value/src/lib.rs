@@ -1,44 +1,65 @@
+#[cfg(feature = "codec")]
+mod codec;
+#[cfg(feature = "serde")]
+mod de;
 mod pack;
+#[cfg(feature = "serde")]
+mod ser;
+#[cfg(feature = "serde")]
+mod serde_error;
 mod unpack;
 mod unpacker;
 mod value;
 
-pub use pack::pack_value;
-pub use unpack::{unpack_value, unpack_value_ref};
+#[cfg(feature = "codec")]
+pub use codec::RefFrame;
+#[cfg(feature = "serde")]
+pub use de::{from_ref_value, from_value};
+#[cfg(feature = "async")]
+pub use pack::pack_value_async;
+pub use pack::{pack_value, pack_value_canonical};
+#[cfg(feature = "serde")]
+pub use ser::to_value;
+#[cfg(feature = "serde")]
+pub use serde_error::SerdeError;
+pub use unpack::{
+    unpack_value, unpack_value_ref, unpack_value_ref_with_limits, unpack_value_with_limits,
+};
 pub use unpacker::*;
+pub use value::float::Number as FloatNumber;
+pub use value::index::ValueIndex;
+pub use value::integer::Number as IntegerNumber;
+pub use value::{Float, Integer, Utf8String, Utf8StringRef};
 
-// pub//  mod float;
-// pub mod from;
-// pub mod integer;
-// pub mod utf8_string;
-
-// pub use float::Float;
-// pub use float::Number as FloatNumber;
-// pub use integer::Integer;
-// pub use integer::Number as IntegerNumber;
-// use std::iter::Iterator;
-// pub use utf8_string::{Utf8String, Utf8StringRef};
-
-// use chrono::{self, TimeZone};
-// use std::fmt;
-// pub struct Nil;
-
-use msgpack::PackError;
+use msgpack::{CanonicalOptions, PackError};
 use std::io;
 
 pub struct ValuePacker<W> {
     wr: W,
+    canonical: Option<CanonicalOptions>,
 }
 
 impl<W> ValuePacker<W> {
     pub fn new(wr: W) -> Self {
-        ValuePacker { wr }
+        ValuePacker {
+            wr,
+            canonical: None,
+        }
+    }
+
+    // packs every value with pack_value_canonical
+    pub fn with_canonical(mut self, opts: CanonicalOptions) -> Self {
+        self.canonical = Some(opts);
+        self
     }
 }
 
 impl<W: io::Write> ValuePacker<W> {
     pub fn pack_value(&mut self, v: Value) -> Result<(), PackError> {
-        pack::pack_value(&mut self.wr, v)
+        match self.canonical {
+            Some(opts) => pack::pack_value_canonical(&mut self.wr, v, opts),
+            None => pack::pack_value(&mut self.wr, v),
+        }
     }
 }
 
@@ -75,62 +96,166 @@ pub enum Value {
     Timestamp(i64, u32),
 }
 
-// impl fmt::Display for Value {
-//     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-//         match self {
-//             Value::Nil => write!(f, "null"),
-//             Value::Boolean(val) => write!(f, "{}", if *val { "true" } else { "false" }),
-//             Value::Float(val) => val.fmt(f),
-//             Value::Integer(val) => val.fmt(f),
-//             Value::Binary(ref val) => {
-//                 for v in val {
-//                     write!(f, "{:02X}", v)?;
-//                 }
-//                 Ok(())
-//             }
-//             Value::String(ref val) => val.fmt(f),
-//             Value::Array(ref val) => {
-//                 write!(f, "[")?;
-//                 let mut s = true;
-//                 for v in val {
-//                     if s {
-//                         s = false
-//                     } else {
-//                         write!(f, ", ")?;
-//                     }
-
-//                     v.fmt(f)?;
-//                 }
-//                 write!(f, "]")
-//             }
-//             Value::Map(ref val) => {
-//                 write!(f, "{{")?;
-//                 let mut s = true;
-//                 for v in val {
-//                     if s {
-//                         s = false
-//                     } else {
-//                         write!(f, ", ")?;
-//                     }
-
-//                     write!(f, "{}: {}", v.0, v.1)?;
-//                 }
-//                 write!(f, "}}")
-//             }
-//             Value::Extension(ty, ref buf) => {
-//                 write!(f, "Extension({}, ", ty)?;
-//                 for b in buf {
-//                     write!(f, "{:X}", b)?;
-//                 }
-//                 write!(f, ")")
-//             }
-
-//             Value::Timestamp(sec, nsec) => {
-//                 write!(f, "{}", chrono::Local.timestamp(*sec as i64, *nsec))
-//             }
-//         }
-//     }
-// }
+impl Value {
+    // None unless this is a timestamp with a valid nsec
+    pub fn as_timestamp(&self) -> Option<msgpack::Timestamp> {
+        match *self {
+            Value::Timestamp(sec, nsec) => msgpack::Timestamp::new(sec, nsec),
+            _ => None,
+        }
+    }
+
+    pub fn is_nil(&self) -> bool {
+        matches!(*self, Value::Nil)
+    }
+
+    pub fn is_boolean(&self) -> bool {
+        matches!(*self, Value::Boolean(_))
+    }
+
+    pub fn is_integer(&self) -> bool {
+        matches!(*self, Value::Integer(_))
+    }
+
+    pub fn is_float(&self) -> bool {
+        matches!(*self, Value::Float(_))
+    }
+
+    // an integer or a float
+    pub fn is_number(&self) -> bool {
+        self.is_integer() || self.is_float()
+    }
+
+    pub fn is_u64(&self) -> bool {
+        self.as_u64().is_some()
+    }
+
+    pub fn is_i64(&self) -> bool {
+        self.as_i64().is_some()
+    }
+
+    pub fn is_binary(&self) -> bool {
+        matches!(*self, Value::Binary(_))
+    }
+
+    // true for invalid utf-8 too, which as_str won't return
+    pub fn is_string(&self) -> bool {
+        matches!(*self, Value::String(_))
+    }
+
+    pub fn is_array(&self) -> bool {
+        matches!(*self, Value::Array(_))
+    }
+
+    pub fn is_map(&self) -> bool {
+        matches!(*self, Value::Map(_))
+    }
+
+    pub fn is_extension(&self) -> bool {
+        matches!(*self, Value::Extension(..))
+    }
+
+    pub fn is_timestamp(&self) -> bool {
+        matches!(*self, Value::Timestamp(..))
+    }
+
+    pub fn as_nil(&self) -> Option<()> {
+        match *self {
+            Value::Nil => Some(()),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Value::Boolean(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    // None for negative integers and for floats that aren't a whole u64
+    pub fn as_u64(&self) -> Option<u64> {
+        match *self {
+            Value::Integer(ref v) => v.as_u64(),
+            Value::Float(ref v) => v.as_u64(),
+            _ => None,
+        }
+    }
+
+    // None for integers above i64::MAX and for floats that aren't a whole i64
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            Value::Integer(ref v) => v.as_i64(),
+            Value::Float(ref v) => v.as_i64(),
+            _ => None,
+        }
+    }
+
+    // integers may round to the nearest f64
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Value::Integer(ref v) => Some(v.as_f64()),
+            Value::Float(ref v) => Some(v.as_f64()),
+            _ => None,
+        }
+    }
+
+    pub fn as_binary(&self) -> Option<&[u8]> {
+        match *self {
+            Value::Binary(ref v) => Some(v),
+            _ => None,
+        }
+    }
+
+    // None unless this is a string of valid utf-8
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            Value::String(ref v) => v.as_str(),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<Value>> {
+        match *self {
+            Value::Array(ref v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_array_mut(&mut self) -> Option<&mut Vec<Value>> {
+        match *self {
+            Value::Array(ref mut v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_map(&self) -> Option<&Vec<(Value, Value)>> {
+        match *self {
+            Value::Map(ref v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_map_mut(&mut self) -> Option<&mut Vec<(Value, Value)>> {
+        match *self {
+            Value::Map(ref mut v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_extension(&self) -> Option<(i8, &[u8])> {
+        match *self {
+            Value::Extension(ty, ref v) => Some((ty, v)),
+            _ => None,
+        }
+    }
+
+    // the value of a map entry, or of an array element for integer indices. None for any
+    // other value
+    pub fn get<I: ValueIndex>(&self, index: I) -> Option<&Value> {
+        index.index_into(self)
+    }
+}
 
 #[derive(Debug, PartialEq)]
 pub enum RefValue<'a> {
@@ -165,142 +290,163 @@ pub enum RefValue<'a> {
     Timestamp(i64, u32),
 }
 
-// impl<'a> fmt::Display for RefValue<'a> {
-//     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-//         match self {
-//             RefValue::Nil => write!(f, "null"),
-//             RefValue::Boolean(val) => write!(f, "{}", if *val { "true" } else { "false" }),
-//             RefValue::Float(val) => val.fmt(f),
-//             RefValue::Integer(val) => val.fmt(f),
-//             RefValue::Binary(val) => {
-//                 for v in *val {
-//                     write!(f, "{:02X}", v)?;
-//                 }
-//                 Ok(())
-//             }
-//             RefValue::String(ref val) => val.fmt(f),
-//             RefValue::Array(ref val) => {
-//                 write!(f, "[")?;
-//                 let mut s = true;
-//                 for v in val {
-//                     if s {
-//                         s = false
-//                     } else {
-//                         write!(f, ", ")?;
-//                     }
-
-//                     v.fmt(f)?;
-//                 }
-//                 write!(f, "]")
-//             }
-//             RefValue::Map(ref val) => {
-//                 write!(f, "{{")?;
-//                 let mut s = true;
-//                 for v in val {
-//                     if s {
-//                         s = false
-//                     } else {
-//                         write!(f, ", ")?;
-//                     }
-
-//                     write!(f, "{}: {}", v.0, v.1)?;
-//                 }
-//                 write!(f, "}}")
-//             }
-//             RefValue::Extension(ty, buf) => {
-//                 write!(f, "Extension({}, ", ty)?;
-//                 for b in *buf {
-//                     write!(f, "{:X}", b)?;
-//                 }
-//                 write!(f, ")")
-//             }
-//             RefValue::Timestamp(sec, nsec) => {
-//                 write!(f, "{}", chrono::Local.timestamp(*sec as i64, *nsec))
-//             }
-//         }
-//     }
-// }
-
-// impl Value {
-//     pub fn to_ref(&self) -> RefValue {
-//         match self {
-//             &Value::Nil => RefValue::Nil,
-//             &Value::Boolean(val) => RefValue::Boolean(val),
-//             &Value::Float(v) => RefValue::Float(v),
-//             &Value::Integer(val) => RefValue::Integer(val),
-//             &Value::Binary(ref v) => RefValue::Binary(v.as_slice()),
-//             &Value::String(ref v) => RefValue::String(v.as_ref()), // XXX
-//             &Value::Array(ref v) => RefValue::Array(v.iter().map(|v| v.to_ref()).collect()),
-//             &Value::Map(ref v) => RefValue::Map(
-//                 v.iter()
-//                     .map(|&(ref k, ref v)| (k.to_ref(), v.to_ref()))
-//                     .collect(),
-//             ),
-//             &Value::Extension(ty, ref buf) => RefValue::Extension(ty, buf.as_slice()),
-//             &Value::Timestamp(sec, nsec) => RefValue::Timestamp(sec, nsec),
-//         }
-//     }
-
-//     pub fn to_nil(&self) -> Option<Nil> {
-//         match self {
-//             Value::Nil => Some(Nil),
-//             _ => None,
-//         }
-//     }
-
-//     pub fn to_array(&self) -> Option<&Vec<Value>> {
-//         match self {
-//             Value::Array(v) => Some(v),
-//             _ => None,
-//         }
-//     }
-// }
-
-// impl<'a> RefValue<'a> {
-//     pub fn to_owned(&self) -> Value {
-//         match self {
-//             &RefValue::Nil => Value::Nil,
-//             &RefValue::Boolean(v) => Value::Boolean(v),
-//             &RefValue::Integer(v) => Value::Integer(v),
-//             &RefValue::Float(v) => Value::Float(v),
-//             &RefValue::Binary(v) => Value::Binary(v.into()),
-//             &RefValue::String(v) => Value::String(v.into()), // XXX
-//             &RefValue::Array(ref v) => Value::Array(v.iter().map(|v| v.to_owned()).collect()),
-//             &RefValue::Map(ref v) => Value::Map(
-//                 v.iter()
-//                     .map(|&(ref k, ref v)| (k.to_owned(), v.to_owned()))
-//                     .collect(),
-//             ),
-//             &RefValue::Extension(ty, buf) => Value::Extension(ty, buf.into()),
-//             &RefValue::Timestamp(sec, nsec) => Value::Timestamp(sec, nsec),
-//         }
-//     }
-
-//     pub fn to_nil(&self) -> Option<Nil> {
-//         match self {
-//             RefValue::Nil => Some(Nil),
-//             _ => None,
-//         }
-//     }
-
-//     pub fn to_array(&self) -> Option<&Vec<RefValue>> {
-//         match self {
-//             RefValue::Array(v) => Some(v),
-//             _ => None,
-//         }
-//     }
-
-//     pub fn to_str(&self) -> Option<&str> {
-//         match self {
-//             RefValue::String(v) => v.as_str(),
-//             _ => None,
-//         }
-//     }
-
-//     pub fn to_slice(&self) -> Option<&[u8]> {
-//         match self {
-//             RefValue::String(v) => Some(v.as_slice()),
-//             _ => None,
-//         }
-//     }
-// }
+impl<'a> RefValue<'a> {
+    // None unless this is a timestamp with a valid nsec
+    pub fn as_timestamp(&self) -> Option<msgpack::Timestamp> {
+        match *self {
+            RefValue::Timestamp(sec, nsec) => msgpack::Timestamp::new(sec, nsec),
+            _ => None,
+        }
+    }
+
+    pub fn is_nil(&self) -> bool {
+        matches!(*self, RefValue::Nil)
+    }
+
+    pub fn is_boolean(&self) -> bool {
+        matches!(*self, RefValue::Boolean(_))
+    }
+
+    pub fn is_integer(&self) -> bool {
+        matches!(*self, RefValue::Integer(_))
+    }
+
+    pub fn is_float(&self) -> bool {
+        matches!(*self, RefValue::Float(_))
+    }
+
+    // an integer or a float
+    pub fn is_number(&self) -> bool {
+        self.is_integer() || self.is_float()
+    }
+
+    pub fn is_u64(&self) -> bool {
+        self.as_u64().is_some()
+    }
+
+    pub fn is_i64(&self) -> bool {
+        self.as_i64().is_some()
+    }
+
+    pub fn is_binary(&self) -> bool {
+        matches!(*self, RefValue::Binary(_))
+    }
+
+    // true for invalid utf-8 too, which as_str won't return
+    pub fn is_string(&self) -> bool {
+        matches!(*self, RefValue::String(_))
+    }
+
+    pub fn is_array(&self) -> bool {
+        matches!(*self, RefValue::Array(_))
+    }
+
+    pub fn is_map(&self) -> bool {
+        matches!(*self, RefValue::Map(_))
+    }
+
+    pub fn is_extension(&self) -> bool {
+        matches!(*self, RefValue::Extension(..))
+    }
+
+    pub fn is_timestamp(&self) -> bool {
+        matches!(*self, RefValue::Timestamp(..))
+    }
+
+    pub fn as_nil(&self) -> Option<()> {
+        match *self {
+            RefValue::Nil => Some(()),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            RefValue::Boolean(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    // None for negative integers and for floats that aren't a whole u64
+    pub fn as_u64(&self) -> Option<u64> {
+        match *self {
+            RefValue::Integer(ref v) => v.as_u64(),
+            RefValue::Float(ref v) => v.as_u64(),
+            _ => None,
+        }
+    }
+
+    // None for integers above i64::MAX and for floats that aren't a whole i64
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            RefValue::Integer(ref v) => v.as_i64(),
+            RefValue::Float(ref v) => v.as_i64(),
+            _ => None,
+        }
+    }
+
+    // integers may round to the nearest f64
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            RefValue::Integer(ref v) => Some(v.as_f64()),
+            RefValue::Float(ref v) => Some(v.as_f64()),
+            _ => None,
+        }
+    }
+
+    pub fn as_binary(&self) -> Option<&'a [u8]> {
+        match *self {
+            RefValue::Binary(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    // None unless this is a string of valid utf-8
+    pub fn as_str(&self) -> Option<&'a str> {
+        match *self {
+            RefValue::String(v) => v.s.ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<RefValue<'a>>> {
+        match *self {
+            RefValue::Array(ref v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_array_mut(&mut self) -> Option<&mut Vec<RefValue<'a>>> {
+        match *self {
+            RefValue::Array(ref mut v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_map(&self) -> Option<&Vec<(RefValue<'a>, RefValue<'a>)>> {
+        match *self {
+            RefValue::Map(ref v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_map_mut(&mut self) -> Option<&mut Vec<(RefValue<'a>, RefValue<'a>)>> {
+        match *self {
+            RefValue::Map(ref mut v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_extension(&self) -> Option<(i8, &'a [u8])> {
+        match *self {
+            RefValue::Extension(ty, v) => Some((ty, v)),
+            _ => None,
+        }
+    }
+
+    // the value of a map entry, or of an array element for integer indices. None for any
+    // other value
+    pub fn get<I: ValueIndex>(&self, index: I) -> Option<&RefValue<'a>> {
+        index.index_into_ref(self)
+    }
+}
@@ -1,6 +1,6 @@
 use crate::{value, Value};
 
-use msgpack::{pack, PackError};
+use msgpack::{pack, CanonicalOptions, PackError};
 use std::io;
 
 pub fn pack_value<W: io::Write>(writer: &mut W, val: Value) -> Result<(), PackError> {
@@ -17,7 +17,7 @@ pub fn pack_value<W: io::Write>(writer: &mut W, val: Value) -> Result<(), PackEr
         },
         Value::Binary(v) => pack::pack_bin(writer, &v),
         Value::String(value::utf8_string::Utf8String { ref s }) => match *s {
-            Ok(ref s) => pack::pack_str(writer, &s.as_str()),
+            Ok(ref s) => pack::pack_str(writer, s.as_str()),
             Err((ref s, _)) => pack::pack_str_from_slice(writer, s),
         },
         Value::Array(vs) => {
@@ -42,3 +42,58 @@ pub fn pack_value<W: io::Write>(writer: &mut W, val: Value) -> Result<(), PackEr
         Value::Timestamp(sec, nsec) => pack::pack_timestamp(writer, sec, nsec),
     }
 }
+
+// the same value always gives the same bytes: maps are sorted by their encoded keys, floats
+// are normalized, and a map with two equal keys or a timestamp with nsec past a second is an
+// error
+pub fn pack_value_canonical<W: io::Write>(
+    writer: &mut W,
+    val: Value,
+    opts: CanonicalOptions,
+) -> Result<(), PackError> {
+    match val {
+        Value::Float(value::float::Float { n }) => match n {
+            value::float::Number::Float32(v) => msgpack::pack_f32_canonical(writer, v),
+            value::float::Number::Float64(v) => msgpack::pack_f64_canonical(writer, v, opts),
+        },
+        Value::Array(vs) => {
+            pack::pack_array_header(writer, vs.len())?;
+            for v in vs {
+                pack_value_canonical(writer, v, opts)?;
+            }
+            Ok(())
+        }
+        Value::Map(vs) => {
+            let mut entries = Vec::with_capacity(vs.len());
+            for (k, v) in vs {
+                let (mut kbuf, mut vbuf) = (vec![], vec![]);
+                pack_value_canonical(&mut kbuf, k, opts)?;
+                pack_value_canonical(&mut vbuf, v, opts)?;
+                entries.push((kbuf, vbuf));
+            }
+            msgpack::pack_sorted_map(writer, entries)
+        }
+        Value::Timestamp(sec, nsec) => match msgpack::Timestamp::new(sec, nsec) {
+            Some(_) => pack::pack_timestamp(writer, sec, nsec),
+            None => Err(PackError::WriteError(io::Error::other(
+                msgpack::TimestampRangeError,
+            ))),
+        },
+        // every other encoding is already the smallest one
+        v => pack_value(writer, v),
+    }
+}
+
+// encodes the whole value first so it reaches the writer with one write
+#[cfg(feature = "async")]
+pub async fn pack_value_async<W>(
+    packer: &mut msgpack::AsyncPacker<W>,
+    val: Value,
+) -> Result<(), PackError>
+where
+    W: tokio::io::AsyncWrite + Unpin,
+{
+    let mut buf = vec![];
+    pack_value(&mut buf, val)?;
+    packer.write_raw(&buf).await
+}
@@ -0,0 +1,522 @@
+use crate::value::{float, integer, Utf8String, Utf8StringRef};
+use crate::{RefValue, SerdeError, Value};
+use msgpack::{code, pack, unpack, TIMESTAMP_TYPE};
+use serde::ser::{self, Error, SerializeMap, SerializeSeq, SerializeTupleStruct};
+
+// the tuple struct msgpack-serde writes as an ext in place. it has to match
+// msgpack_serde::ext::EXT_TOKEN
+pub(crate) const EXT_TOKEN: &str = "$serde_json::private::Ext";
+
+// bin and str without valid utf-8 go through serialize_bytes. extensions and timestamps keep
+// their type in msgpack-serde and become an array of their header, type and data elsewhere
+impl ser::Serialize for Value {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match *self {
+            Value::Nil => serializer.serialize_unit(),
+            Value::Boolean(v) => serializer.serialize_bool(v),
+            Value::Integer(v) => serialize_integer(v, serializer),
+            Value::Float(v) => serialize_float(v, serializer),
+            Value::Binary(ref v) => serializer.serialize_bytes(v),
+            Value::String(ref v) => serialize_string(v.as_ref(), serializer),
+            Value::Array(ref vs) => {
+                let mut seq = serializer.serialize_seq(Some(vs.len()))?;
+                for v in vs {
+                    seq.serialize_element(v)?;
+                }
+                seq.end()
+            }
+            Value::Map(ref vs) => {
+                let mut map = serializer.serialize_map(Some(vs.len()))?;
+                for (k, v) in vs {
+                    map.serialize_entry(k, v)?;
+                }
+                map.end()
+            }
+            Value::Extension(ty, ref data) => serialize_ext(ty, data, serializer),
+            Value::Timestamp(sec, nsec) => serialize_timestamp(sec, nsec, serializer),
+        }
+    }
+}
+
+impl ser::Serialize for RefValue<'_> {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match *self {
+            RefValue::Nil => serializer.serialize_unit(),
+            RefValue::Boolean(v) => serializer.serialize_bool(v),
+            RefValue::Integer(v) => serialize_integer(v, serializer),
+            RefValue::Float(v) => serialize_float(v, serializer),
+            RefValue::Binary(v) => serializer.serialize_bytes(v),
+            RefValue::String(v) => serialize_string(v, serializer),
+            RefValue::Array(ref vs) => {
+                let mut seq = serializer.serialize_seq(Some(vs.len()))?;
+                for v in vs {
+                    seq.serialize_element(v)?;
+                }
+                seq.end()
+            }
+            RefValue::Map(ref vs) => {
+                let mut map = serializer.serialize_map(Some(vs.len()))?;
+                for (k, v) in vs {
+                    map.serialize_entry(k, v)?;
+                }
+                map.end()
+            }
+            RefValue::Extension(ty, data) => serialize_ext(ty, data, serializer),
+            RefValue::Timestamp(sec, nsec) => serialize_timestamp(sec, nsec, serializer),
+        }
+    }
+}
+
+fn serialize_integer<S: ser::Serializer>(v: integer::Integer, s: S) -> Result<S::Ok, S::Error> {
+    match v.n {
+        integer::Number::PosInt(v) => s.serialize_u64(v),
+        integer::Number::NegInt(v) => s.serialize_i64(v),
+    }
+}
+
+fn serialize_float<S: ser::Serializer>(v: float::Float, s: S) -> Result<S::Ok, S::Error> {
+    match v.n {
+        float::Number::Float32(v) => s.serialize_f32(v),
+        float::Number::Float64(v) => s.serialize_f64(v),
+    }
+}
+
+fn serialize_string<S: ser::Serializer>(v: Utf8StringRef, s: S) -> Result<S::Ok, S::Error> {
+    match v.s {
+        Ok(v) => s.serialize_str(v),
+        Err((v, _)) => s.serialize_bytes(v),
+    }
+}
+
+fn serialize_ext<S: ser::Serializer>(ty: i8, data: &[u8], s: S) -> Result<S::Ok, S::Error> {
+    let mut buf = ext_header(ty, data.len());
+    buf.extend_from_slice(data);
+    serialize_raw_ext(&buf, s)
+}
+
+// the encoded header of an ext of `len` bytes. unlike pack::pack_ext_header it takes the
+// reserved negative types too, since unpack_value reads them
+pub(crate) fn ext_header(ty: i8, len: usize) -> Vec<u8> {
+    let mut buf = match len {
+        1 => vec![code::FIXEXT1],
+        2 => vec![code::FIXEXT2],
+        4 => vec![code::FIXEXT4],
+        8 => vec![code::FIXEXT8],
+        16 => vec![code::FIXEXT16],
+        0..=0xff => vec![code::EXT8, len as u8],
+        0x100..=0xffff => [&[code::EXT16][..], &(len as u16).to_be_bytes()].concat(),
+        _ => [&[code::EXT32][..], &(len as u32).to_be_bytes()].concat(),
+    };
+    buf.push(ty as u8);
+    buf
+}
+
+fn serialize_timestamp<S: ser::Serializer>(sec: i64, nsec: u32, s: S) -> Result<S::Ok, S::Error> {
+    let mut buf = vec![];
+    pack::pack_timestamp(&mut buf, sec, nsec).map_err(S::Error::custom)?;
+    serialize_raw_ext(&buf, s)
+}
+
+// hands an encoded ext to msgpack-serde field by field: its code, the length for
+// ext8/16/32, its type and its data
+fn serialize_raw_ext<S: ser::Serializer>(buf: &[u8], s: S) -> Result<S::Ok, S::Error> {
+    let (code, len, ty, data) = split_ext(buf);
+    let mut ts = s.serialize_tuple_struct(EXT_TOKEN, if len.is_some() { 4 } else { 3 })?;
+    ts.serialize_field(&code)?;
+    match (code, len) {
+        (code::EXT8, Some(len)) => ts.serialize_field(&(len as u8))?,
+        (code::EXT16, Some(len)) => ts.serialize_field(&(len as u16))?,
+        (_, Some(len)) => ts.serialize_field(&len)?,
+        (_, None) => {}
+    }
+    ts.serialize_field(&ty)?;
+    ts.serialize_field(&Bytes(data))?;
+    ts.end()
+}
+
+// splits an encoded ext into its code, the length for ext8/16/32, its type and its data
+pub(crate) fn split_ext(buf: &[u8]) -> (u8, Option<u32>, i8, &[u8]) {
+    let (len, body) = match buf[0] {
+        code::EXT8 => (Some(u32::from(buf[1])), &buf[2..]),
+        code::EXT16 => (
+            Some(u32::from(u16::from_be_bytes([buf[1], buf[2]]))),
+            &buf[3..],
+        ),
+        code::EXT32 => (
+            Some(u32::from_be_bytes([buf[1], buf[2], buf[3], buf[4]])),
+            &buf[5..],
+        ),
+        _ => (None, &buf[1..]),
+    };
+    (buf[0], len, body[0] as i8, &body[1..])
+}
+
+struct Bytes<'a>(&'a [u8]);
+
+impl ser::Serialize for Bytes<'_> {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+// builds the Value which unpack_value would read from what msgpack-serde writes for `value`
+// by default: structs and enum variants become maps keyed by their names
+pub fn to_value<T: ?Sized + ser::Serialize>(value: &T) -> Result<Value, SerdeError> {
+    value.serialize(ValueSerializer)
+}
+
+struct ValueSerializer;
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = SerdeError;
+
+    type SerializeSeq = SerializeVec;
+    type SerializeTuple = SerializeVec;
+    type SerializeTupleStruct = SerializeVec;
+    type SerializeTupleVariant = SerializeVec;
+    type SerializeMap = SerializeMapValue;
+    type SerializeStruct = SerializeMapValue;
+    type SerializeStructVariant = SerializeMapValue;
+
+    fn serialize_bool(self, v: bool) -> Result<Value, SerdeError> {
+        Ok(Value::Boolean(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Value, SerdeError> {
+        Ok(Value::from(v))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Value, SerdeError> {
+        Ok(Value::from(v))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Value, SerdeError> {
+        Ok(Value::from(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Value, SerdeError> {
+        Ok(Value::from(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Value, SerdeError> {
+        Ok(Value::from(v))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Value, SerdeError> {
+        Ok(Value::from(v))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Value, SerdeError> {
+        Ok(Value::from(v))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Value, SerdeError> {
+        Ok(Value::from(v))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Value, SerdeError> {
+        Ok(Value::from(v))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Value, SerdeError> {
+        Ok(Value::from(v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value, SerdeError> {
+        Ok(Value::String(Utf8String::from(v.to_string())))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value, SerdeError> {
+        Ok(Value::String(Utf8String::from(v)))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value, SerdeError> {
+        Ok(Value::Binary(v.to_vec()))
+    }
+
+    fn serialize_none(self) -> Result<Value, SerdeError> {
+        Ok(Value::Nil)
+    }
+
+    fn serialize_some<T: ?Sized + ser::Serialize>(self, v: &T) -> Result<Value, SerdeError> {
+        v.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value, SerdeError> {
+        Ok(Value::Nil)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value, SerdeError> {
+        Ok(Value::Array(vec![]))
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _idx: u32,
+        variant: &'static str,
+    ) -> Result<Value, SerdeError> {
+        Ok(tagged(variant, Value::Nil))
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + ser::Serialize>(
+        self,
+        _name: &'static str,
+        v: &T,
+    ) -> Result<Value, SerdeError> {
+        v.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + ser::Serialize>(
+        self,
+        _name: &'static str,
+        _idx: u32,
+        variant: &'static str,
+        v: &T,
+    ) -> Result<Value, SerdeError> {
+        Ok(tagged(variant, v.serialize(self)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeVec, SerdeError> {
+        Ok(SerializeVec::new(len.unwrap_or(0), None))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeVec, SerdeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        name: &'static str,
+        len: usize,
+    ) -> Result<SerializeVec, SerdeError> {
+        let mut vec = SerializeVec::new(len, None);
+        vec.ext = name == EXT_TOKEN;
+        Ok(vec)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _idx: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeVec, SerdeError> {
+        Ok(SerializeVec::new(len, Some(variant)))
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<SerializeMapValue, SerdeError> {
+        Ok(SerializeMapValue::new(len.unwrap_or(0), None))
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SerializeMapValue, SerdeError> {
+        Ok(SerializeMapValue::new(len, None))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _idx: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeMapValue, SerdeError> {
+        Ok(SerializeMapValue::new(len, Some(variant)))
+    }
+}
+
+fn tagged(variant: &str, v: Value) -> Value {
+    Value::Map(vec![(Value::String(Utf8String::from(variant)), v)])
+}
+
+struct SerializeVec {
+    vec: Vec<Value>,
+    variant: Option<&'static str>,
+    // the fields of an ext, see serialize_raw_ext
+    ext: bool,
+}
+
+impl SerializeVec {
+    fn new(len: usize, variant: Option<&'static str>) -> Self {
+        SerializeVec {
+            vec: Vec::with_capacity(unpack::capacity_hint::<Value>(len)),
+            variant,
+            ext: false,
+        }
+    }
+
+    fn push<T: ?Sized + ser::Serialize>(&mut self, v: &T) -> Result<(), SerdeError> {
+        self.vec.push(v.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn end(mut self) -> Result<Value, SerdeError> {
+        if self.ext {
+            return ext_value(&mut self.vec);
+        }
+        let v = Value::Array(self.vec);
+        Ok(match self.variant {
+            Some(variant) => tagged(variant, v),
+            None => v,
+        })
+    }
+}
+
+// only the type and data matter, the header fields before them are implied by the data
+fn ext_value(fields: &mut Vec<Value>) -> Result<Value, SerdeError> {
+    let (ty, data) = match (fields.pop(), fields.pop()) {
+        (Some(Value::Binary(data)), Some(Value::Integer(ty))) => (ty, data),
+        _ => return Err(SerdeError::InvalidExt),
+    };
+    let ty = match ty.n {
+        integer::Number::PosInt(v) => i8::try_from(v),
+        integer::Number::NegInt(v) => i8::try_from(v),
+    }
+    .map_err(|_| SerdeError::InvalidExt)?;
+
+    if ty == TIMESTAMP_TYPE {
+        let t = unpack::unpack_timestamp_data(&mut &data[..], data.len())
+            .map_err(|_| SerdeError::InvalidExt)?;
+        return Ok(Value::from(t));
+    }
+    Ok(Value::Extension(ty, data))
+}
+
+impl ser::SerializeSeq for SerializeVec {
+    type Ok = Value;
+    type Error = SerdeError;
+
+    fn serialize_element<T: ?Sized + ser::Serialize>(&mut self, v: &T) -> Result<(), SerdeError> {
+        self.push(v)
+    }
+
+    fn end(self) -> Result<Value, SerdeError> {
+        SerializeVec::end(self)
+    }
+}
+
+impl ser::SerializeTuple for SerializeVec {
+    type Ok = Value;
+    type Error = SerdeError;
+
+    fn serialize_element<T: ?Sized + ser::Serialize>(&mut self, v: &T) -> Result<(), SerdeError> {
+        self.push(v)
+    }
+
+    fn end(self) -> Result<Value, SerdeError> {
+        SerializeVec::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeVec {
+    type Ok = Value;
+    type Error = SerdeError;
+
+    fn serialize_field<T: ?Sized + ser::Serialize>(&mut self, v: &T) -> Result<(), SerdeError> {
+        self.push(v)
+    }
+
+    fn end(self) -> Result<Value, SerdeError> {
+        SerializeVec::end(self)
+    }
+}
+
+impl ser::SerializeTupleVariant for SerializeVec {
+    type Ok = Value;
+    type Error = SerdeError;
+
+    fn serialize_field<T: ?Sized + ser::Serialize>(&mut self, v: &T) -> Result<(), SerdeError> {
+        self.push(v)
+    }
+
+    fn end(self) -> Result<Value, SerdeError> {
+        SerializeVec::end(self)
+    }
+}
+
+struct SerializeMapValue {
+    map: Vec<(Value, Value)>,
+    key: Option<Value>,
+    variant: Option<&'static str>,
+}
+
+impl SerializeMapValue {
+    fn new(len: usize, variant: Option<&'static str>) -> Self {
+        SerializeMapValue {
+            map: Vec::with_capacity(unpack::capacity_hint::<(Value, Value)>(len)),
+            key: None,
+            variant,
+        }
+    }
+
+    fn end(self) -> Result<Value, SerdeError> {
+        let v = Value::Map(self.map);
+        Ok(match self.variant {
+            Some(variant) => tagged(variant, v),
+            None => v,
+        })
+    }
+}
+
+impl ser::SerializeMap for SerializeMapValue {
+    type Ok = Value;
+    type Error = SerdeError;
+
+    fn serialize_key<T: ?Sized + ser::Serialize>(&mut self, key: &T) -> Result<(), SerdeError> {
+        self.key = Some(key.serialize(ValueSerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: ?Sized + ser::Serialize>(&mut self, v: &T) -> Result<(), SerdeError> {
+        let key = self
+            .key
+            .take()
+            .expect("serialize_value is called after serialize_key");
+        self.map.push((key, v.serialize(ValueSerializer)?));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, SerdeError> {
+        SerializeMapValue::end(self)
+    }
+}
+
+impl ser::SerializeStruct for SerializeMapValue {
+    type Ok = Value;
+    type Error = SerdeError;
+
+    fn serialize_field<T: ?Sized + ser::Serialize>(
+        &mut self,
+        key: &'static str,
+        v: &T,
+    ) -> Result<(), SerdeError> {
+        let v = v.serialize(ValueSerializer)?;
+        self.map.push((Value::String(Utf8String::from(key)), v));
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, SerdeError> {
+        SerializeMapValue::end(self)
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeMapValue {
+    type Ok = Value;
+    type Error = SerdeError;
+
+    fn serialize_field<T: ?Sized + ser::Serialize>(
+        &mut self,
+        key: &'static str,
+        v: &T,
+    ) -> Result<(), SerdeError> {
+        ser::SerializeStruct::serialize_field(self, key, v)
+    }
+
+    fn end(self) -> Result<Value, SerdeError> {
+        SerializeMapValue::end(self)
+    }
+}
@@ -0,0 +1,35 @@
+use serde::{de, ser};
+use std::{error, fmt};
+
+// what to_value, from_value and from_ref_value fail with
+#[derive(Debug, PartialEq)]
+pub enum SerdeError {
+    // from a Serialize or Deserialize impl
+    Custom(String),
+    // a tuple struct named like msgpack-serde's ext token which doesn't hold an ext, or a
+    // timestamp ext with a bad body
+    InvalidExt,
+}
+
+impl fmt::Display for SerdeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SerdeError::Custom(ref msg) => f.write_str(msg),
+            SerdeError::InvalidExt => f.write_str("invalid ext"),
+        }
+    }
+}
+
+impl error::Error for SerdeError {}
+
+impl ser::Error for SerdeError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        SerdeError::Custom(msg.to_string())
+    }
+}
+
+impl de::Error for SerdeError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        SerdeError::Custom(msg.to_string())
+    }
+}
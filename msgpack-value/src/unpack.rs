@@ -1,379 +1,534 @@
 use msgpack::unpack::primitive::*;
 use msgpack::unpack::*;
-use msgpack::{code::Code, BufferedRead, UnpackError};
-use std::io;
+use msgpack::{
+    code::Code, BufferedRead, DecodeLimits, LimitTracker, PathSegment, UnpackError, TIMESTAMP_TYPE,
+};
+use std::collections::HashSet;
+use std::{io, mem};
 
 use crate::{value, RefValue, Value};
 
+fn unpack_bin_data<R: io::Read>(
+    reader: &mut R,
+    len: usize,
+    limits: &mut LimitTracker,
+) -> Result<Vec<u8>, UnpackError> {
+    limits.data(len)?;
+    unpack_data(reader, len)
+}
+
+// ext type -1 is always decoded as a timestamp
+fn unpack_ext_data<R: io::Read>(
+    reader: &mut R,
+    len: usize,
+    limits: &mut LimitTracker,
+) -> Result<Value, UnpackError> {
+    let ty = read_data_i8(reader)?;
+    if ty == TIMESTAMP_TYPE {
+        return Ok(Value::from(unpack_timestamp_data(reader, len)?));
+    }
+    Ok(Value::Extension(ty, unpack_bin_data(reader, len, limits)?))
+}
+
 fn unpack_str_data<R: io::Read>(
     reader: &mut R,
     len: usize,
+    limits: &mut LimitTracker,
 ) -> Result<value::Utf8String, UnpackError> {
-    let buf = unpack_data(reader, len)?;
+    let buf = unpack_bin_data(reader, len, limits)?;
+    limits.check_utf8(&buf)?;
     Ok(value::Utf8String::from(buf))
 }
 
 fn unpack_map_data<R: io::Read>(
-    reader: &mut R,
+    reader: &mut CountingReader<R>,
     len: usize,
+    limits: &mut LimitTracker,
 ) -> Result<Vec<(Value, Value)>, UnpackError> {
-    let mut vec = Vec::with_capacity(len);
+    limits.container(len, mem::size_of::<(Value, Value)>())?;
+    limits.enter()?;
+    let mut vec = Vec::with_capacity(capacity_hint::<(Value, Value)>(len));
+    let mut keys = HashSet::new();
     for _ in 0..len {
-        vec.push((unpack_value(reader)?, unpack_value(reader)?));
+        let start = reader.count;
+        let k = if limits.strict() {
+            // a key inside a key is recorded into both
+            let outer = reader.key.replace(vec![]);
+            let k = unpack_item(reader, limits)?;
+            let encoded = mem::replace(&mut reader.key, outer).unwrap_or_default();
+            if let Some(outer) = reader.key.as_mut() {
+                outer.extend_from_slice(&encoded);
+            }
+            if !keys.insert(encoded) {
+                return Err(UnpackError::DuplicateMapKey
+                    .within(key_segment(&k))
+                    .at_offset(start));
+            }
+            k
+        } else {
+            unpack_item(reader, limits)?
+        };
+        let v = unpack_item(reader, limits).map_err(|e| e.within(key_segment(&k)))?;
+        vec.push((k, v));
     }
+    limits.leave();
     Ok(vec)
 }
 
-fn unpack_array_data<R: io::Read>(reader: &mut R, len: usize) -> Result<Vec<Value>, UnpackError> {
-    let mut vec = Vec::with_capacity(len);
-    for _ in 0..len {
-        vec.push(unpack_value(reader)?);
+fn unpack_array_data<R: io::Read>(
+    reader: &mut CountingReader<R>,
+    len: usize,
+    limits: &mut LimitTracker,
+) -> Result<Vec<Value>, UnpackError> {
+    limits.container(len, mem::size_of::<Value>())?;
+    limits.enter()?;
+    let mut vec = Vec::with_capacity(capacity_hint::<Value>(len));
+    for i in 0..len {
+        vec.push(unpack_item(reader, limits).map_err(|e| e.within(PathSegment::Index(i)))?);
     }
-
+    limits.leave();
     Ok(vec)
 }
 
+fn key_segment(key: &Value) -> PathSegment {
+    match key {
+        Value::String(s) => PathSegment::Key(String::from_utf8_lossy(s.as_slice()).into_owned()),
+        Value::Integer(v) => PathSegment::Key(v.to_string()),
+        v => PathSegment::Key(format!("{:?}", v)),
+    }
+}
+
+fn ref_key_segment(key: &RefValue) -> PathSegment {
+    match key {
+        RefValue::String(s) => PathSegment::Key(String::from_utf8_lossy(s.as_slice()).into_owned()),
+        RefValue::Integer(v) => PathSegment::Key(v.to_string()),
+        v => PathSegment::Key(format!("{:?}", v)),
+    }
+}
+
+// in strict mode an integer has to be in its smallest encoding
+fn checked_int<T>(code: &Code, v: T, limits: &LimitTracker) -> Result<T, UnpackError>
+where
+    T: Copy + Into<i128>,
+{
+    limits.check_int(code, v.into())?;
+    Ok(v)
+}
+
+// counts the bytes read so errors can report where they happened. while `key` is set it also
+// copies them out, since strict mode compares map keys by their encoding
+struct CountingReader<'r, R> {
+    inner: &'r mut R,
+    count: u64,
+    key: Option<Vec<u8>>,
+}
+
+impl<'r, R: io::Read> io::Read for CountingReader<'r, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count += n as u64;
+        if let Some(key) = self.key.as_mut() {
+            key.extend_from_slice(&buf[..n]);
+        }
+        Ok(n)
+    }
+}
+
+// decodes one value. errors point at where the innermost value that failed starts
+fn unpack_item<R: io::Read>(
+    reader: &mut CountingReader<R>,
+    limits: &mut LimitTracker,
+) -> Result<Value, UnpackError> {
+    let start = reader.count;
+    unpack_value_inner(reader, limits).map_err(|e| e.at_offset(start))
+}
+
+// errors carry the path to the failing value and the offset of its start, counted from where
+// this call started reading
 pub fn unpack_value<R>(reader: &mut R) -> Result<Value, UnpackError>
 where
     R: io::Read,
 {
-    let val = match read_code(reader)? {
+    unpack_value_with_limits(reader, DecodeLimits::default())
+}
+
+pub fn unpack_value_with_limits<R>(
+    reader: &mut R,
+    limits: DecodeLimits,
+) -> Result<Value, UnpackError>
+where
+    R: io::Read,
+{
+    let mut reader = CountingReader {
+        inner: reader,
+        count: 0,
+        key: None,
+    };
+    let mut limits = LimitTracker::new(limits);
+    unpack_item(&mut reader, &mut limits)
+}
+
+fn unpack_value_inner<R>(
+    reader: &mut CountingReader<R>,
+    limits: &mut LimitTracker,
+) -> Result<Value, UnpackError>
+where
+    R: io::Read,
+{
+    let code = read_code(reader)?;
+    let val = match code {
         Code::Nil => Value::Nil,
         Code::True => Value::from(true),
         Code::False => Value::from(false),
         Code::PosInt(v) => Value::from(v),
-        Code::Uint8 => Value::from(read_data_u8(reader)?),
-        Code::Uint16 => Value::from(read_data_u16(reader)?),
-        Code::Uint32 => Value::from(read_data_u32(reader)?),
-        Code::Uint64 => Value::from(read_data_u64(reader)?),
+        Code::Uint8 => Value::from(checked_int(&code, read_data_u8(reader)?, limits)?),
+        Code::Uint16 => Value::from(checked_int(&code, read_data_u16(reader)?, limits)?),
+        Code::Uint32 => Value::from(checked_int(&code, read_data_u32(reader)?, limits)?),
+        Code::Uint64 => Value::from(checked_int(&code, read_data_u64(reader)?, limits)?),
         Code::NegInt(v) => Value::from(v),
-        Code::Int8 => Value::from(read_data_i8(reader)?),
-        Code::Int16 => Value::from(read_data_i16(reader)?),
-        Code::Int32 => Value::from(read_data_i32(reader)?),
-        Code::Int64 => Value::from(read_data_i64(reader)?),
+        Code::Int8 => Value::from(checked_int(&code, read_data_i8(reader)?, limits)?),
+        Code::Int16 => Value::from(checked_int(&code, read_data_i16(reader)?, limits)?),
+        Code::Int32 => Value::from(checked_int(&code, read_data_i32(reader)?, limits)?),
+        Code::Int64 => Value::from(checked_int(&code, read_data_i64(reader)?, limits)?),
         Code::Float32 => Value::from(read_data_f32(reader)?),
         Code::Float64 => Value::from(read_data_f64(reader)?),
         Code::Bin8 => {
             let len = usize::from(read_data_u8(reader)?);
-            Value::Binary(unpack_data(reader, len)?)
+            limits.check_len(&code, len)?;
+            Value::Binary(unpack_bin_data(reader, len, limits)?)
         }
         Code::Bin16 => {
             let len = usize::from(read_data_u16(reader)?);
-            Value::Binary(unpack_data(reader, len)?)
+            limits.check_len(&code, len)?;
+            Value::Binary(unpack_bin_data(reader, len, limits)?)
         }
         Code::Bin32 => {
-            let len = read_data_u32(reader)? as usize;
-            Value::Binary(unpack_data(reader, len)?)
+            let len = read_data_u32(reader).and_then(len_to_usize)?;
+            limits.check_len(&code, len)?;
+            Value::Binary(unpack_bin_data(reader, len, limits)?)
         }
-        Code::FixStr(len) => Value::String(unpack_str_data(reader, usize::from(len))?),
+        Code::FixStr(len) => Value::String(unpack_str_data(reader, usize::from(len), limits)?),
         Code::Str8 => {
             let len = usize::from(read_data_u8(reader)?);
-            Value::String(unpack_str_data(reader, len)?)
+            limits.check_len(&code, len)?;
+            Value::String(unpack_str_data(reader, len, limits)?)
         }
         Code::Str16 => {
             let len = usize::from(read_data_u16(reader)?);
-            Value::String(unpack_str_data(reader, len)?)
+            limits.check_len(&code, len)?;
+            Value::String(unpack_str_data(reader, len, limits)?)
         }
         Code::Str32 => {
-            let len = read_data_u32(reader)?;
-            Value::String(unpack_str_data(reader, len as usize)?)
+            let len = read_data_u32(reader).and_then(len_to_usize)?;
+            limits.check_len(&code, len)?;
+            Value::String(unpack_str_data(reader, len, limits)?)
         }
-        Code::FixArray(len) => Value::Array(unpack_array_data(reader, len as usize)?),
+        Code::FixArray(len) => Value::Array(unpack_array_data(reader, len as usize, limits)?),
         Code::Array16 => {
             let len = usize::from(read_data_u16(reader)?);
-            Value::Array(unpack_array_data(reader, len)?)
+            limits.check_len(&code, len)?;
+            Value::Array(unpack_array_data(reader, len, limits)?)
         }
         Code::Array32 => {
-            let len = read_data_u32(reader)? as usize;
-            Value::Array(unpack_array_data(reader, len)?)
+            let len = read_data_u32(reader).and_then(len_to_usize)?;
+            limits.check_len(&code, len)?;
+            Value::Array(unpack_array_data(reader, len, limits)?)
         }
-        Code::FixMap(len) => Value::Map(unpack_map_data(reader, len as usize)?),
+        Code::FixMap(len) => Value::Map(unpack_map_data(reader, len as usize, limits)?),
         Code::Map16 => {
             let len = usize::from(read_data_u16(reader)?);
-            Value::Map(unpack_map_data(reader, len)?)
+            limits.check_len(&code, len)?;
+            Value::Map(unpack_map_data(reader, len, limits)?)
         }
         Code::Map32 => {
-            let len = read_data_u16(reader)? as usize;
-            Value::Map(unpack_map_data(reader, len)?)
-        }
-        Code::FixExt1 => {
-            let (ty, vec) = unpack_ext_type_data(reader, 1)?;
-            Value::Extension(ty, vec)
-        }
-        Code::FixExt2 => {
-            let (ty, vec) = unpack_ext_type_data(reader, 2)?;
-            Value::Extension(ty, vec)
-        }
-        Code::FixExt4 => {
-            let (ty, vec) = unpack_ext_type_data(reader, 4)?;
-            Value::Extension(ty, vec)
-        }
-        Code::FixExt8 => {
-            let (ty, vec) = unpack_ext_type_data(reader, 8)?;
-            Value::Extension(ty, vec)
-        }
-        Code::FixExt16 => {
-            let (ty, vec) = unpack_ext_type_data(reader, 16)?;
-            Value::Extension(ty, vec)
-        }
+            let len = read_data_u32(reader).and_then(len_to_usize)?;
+            limits.check_len(&code, len)?;
+            Value::Map(unpack_map_data(reader, len, limits)?)
+        }
+        Code::FixExt1 => unpack_ext_data(reader, 1, limits)?,
+        Code::FixExt2 => unpack_ext_data(reader, 2, limits)?,
+        Code::FixExt4 => unpack_ext_data(reader, 4, limits)?,
+        Code::FixExt8 => unpack_ext_data(reader, 8, limits)?,
+        Code::FixExt16 => unpack_ext_data(reader, 16, limits)?,
         Code::Ext8 => {
             let len = usize::from(read_data_u8(reader)?);
-            let (ty, vec) = unpack_ext_type_data(reader, len)?;
-            Value::Extension(ty, vec)
+            limits.check_len(&code, len)?;
+            unpack_ext_data(reader, len, limits)?
         }
         Code::Ext16 => {
             let len = usize::from(read_data_u16(reader)?);
-            let (ty, vec) = unpack_ext_type_data(reader, len)?;
-            Value::Extension(ty, vec)
+            limits.check_len(&code, len)?;
+            unpack_ext_data(reader, len, limits)?
         }
         Code::Ext32 => {
-            let len = read_data_u32(reader)? as usize;
-            let (ty, vec) = unpack_ext_type_data(reader, len)?;
-            Value::Extension(ty, vec)
+            let len = read_data_u32(reader).and_then(len_to_usize)?;
+            limits.check_len(&code, len)?;
+            unpack_ext_data(reader, len, limits)?
         }
-        Code::Reserved => unreachable!(),
+        Code::Reserved => return Err(UnpackError::ReservedCode(msgpack::code::RESERVED)),
     };
 
     Ok(val)
 }
 
-// use std::io::{self, ErrorKind};
-
 fn unpack_array_data_ref<'a, R>(
-    reader: &mut R,
+    reader: &mut RefReader<R>,
     len: usize,
+    limits: &mut LimitTracker,
 ) -> Result<Vec<RefValue<'a>>, UnpackError>
 where
     R: BufferedRead<'a>,
 {
-    let mut vec = Vec::with_capacity(len);
-    for _ in 0..len {
-        vec.push(unpack_value_ref(reader)?);
+    limits.container(len, mem::size_of::<RefValue>())?;
+    limits.enter()?;
+    let mut vec = Vec::with_capacity(capacity_hint::<RefValue>(len));
+    for i in 0..len {
+        vec.push(unpack_item_ref(reader, limits).map_err(|e| e.within(PathSegment::Index(i)))?);
     }
-
+    limits.leave();
     Ok(vec)
 }
 
-fn unpack_array_ref<'a, R>(reader: &mut R) -> Result<Vec<RefValue<'a>>, UnpackError>
+fn unpack_map_data_ref<'a, R>(
+    reader: &mut RefReader<R>,
+    len: usize,
+    limits: &mut LimitTracker,
+) -> Result<Vec<(RefValue<'a>, RefValue<'a>)>, UnpackError>
 where
     R: BufferedRead<'a>,
 {
-    let len = unpack_array_header(reader)?;
-    unpack_array_data_ref(reader, len)
+    limits.container(len, mem::size_of::<(RefValue, RefValue)>())?;
+    limits.enter()?;
+    let mut vec = Vec::with_capacity(capacity_hint::<(RefValue, RefValue)>(len));
+    let mut keys = HashSet::new();
+    for _ in 0..len {
+        let start = reader.position();
+        let rest = reader.fill_buf()?;
+        let k = unpack_item_ref(reader, limits)?;
+        if limits.strict() {
+            let encoded = &rest[..rest.len() - reader.fill_buf()?.len()];
+            if !keys.insert(encoded) {
+                return Err(UnpackError::DuplicateMapKey
+                    .within(ref_key_segment(&k))
+                    .at_offset(start));
+            }
+        }
+        let v = unpack_item_ref(reader, limits).map_err(|e| e.within(ref_key_segment(&k)))?;
+        vec.push((k, v));
+    }
+    limits.leave();
+    Ok(vec)
 }
 
-fn unpack_map_data_ref<'a, R>(
+fn unpack_bin_data_ref<'a, R>(
     reader: &mut R,
     len: usize,
-) -> Result<Vec<(RefValue<'a>, RefValue<'a>)>, UnpackError>
+    limits: &mut LimitTracker,
+) -> Result<&'a [u8], UnpackError>
 where
     R: BufferedRead<'a>,
 {
-    let mut vec = Vec::with_capacity(len);
-    for _ in 0..len {
-        vec.push((unpack_value_ref(reader)?, unpack_value_ref(reader)?));
-    }
-
-    Ok(vec)
+    limits.data_ref(len)?;
+    unpack_data_ref(reader, len)
 }
 
-fn unpack_map_ref<'a, R>(reader: &mut R) -> Result<Vec<(RefValue<'a>, RefValue<'a>)>, UnpackError>
+fn unpack_ext_data_ref<'a, R>(
+    reader: &mut R,
+    len: usize,
+    limits: &mut LimitTracker,
+) -> Result<RefValue<'a>, UnpackError>
 where
     R: BufferedRead<'a>,
 {
-    let len = unpack_map_header(reader)?;
-    unpack_map_data_ref(reader, len)
+    let ty = read_data_i8(reader)?;
+    if ty == TIMESTAMP_TYPE {
+        return Ok(RefValue::from(unpack_timestamp_data(reader, len)?));
+    }
+    Ok(RefValue::Extension(
+        ty,
+        unpack_bin_data_ref(reader, len, limits)?,
+    ))
 }
 
 fn unpack_str_data_ref<'a, R>(
     reader: &mut R,
     len: usize,
+    limits: &mut LimitTracker,
 ) -> Result<value::Utf8StringRef<'a>, UnpackError>
 where
     R: BufferedRead<'a>,
 {
-    let buf = unpack_data_ref(reader, len)?;
+    let buf = unpack_bin_data_ref(reader, len, limits)?;
+    limits.check_utf8(buf)?;
     Ok(value::Utf8StringRef::from(buf))
 }
 
-// pub fn unpack_str<'a, R>(reader: &mut R) -> Result<value::Utf8StringRef<'a>, UnpackError>
-// where
-//     R: BufferedRead<'a>,
-// {
-//     let len = unpack::unpack_str_header(reader)?;
-//     unpack_str_data(reader, len)
-// }
-
-// #[test]
-// fn test_unpack_str() {
-//     let v = vec![0xa5, 0x68, 0x65, 0x6c, 0x6c, 0x6f];
-//     let mut cur = io::Cursor::new(v.as_ref());
-//     let ret = unpack_str(&mut cur).unwrap();
-//     assert_eq!(*ret, Ok("hello"));
-// }
+// reads through to `inner`, knowing how far it is from where decoding started
+struct RefReader<'r, R> {
+    inner: &'r mut R,
+    start: usize,
+}
 
-// fn unpack_bin_data<'a, R>(reader: &mut R, len: usize) -> Result<&'a [u8], UnpackError>
-// where
-//     R: BufferedRead<'a>,
-// {
-//     let buf = reader.fill_buf().map_err(|e| UnpackError::InvalidData(e))?;
-//     if len > buf.len() {
-//         return Err(UnpackError::InvalidData(io::Error::new(
-//             ErrorKind::UnexpectedEof,
-//             "Unexpected EOF",
-//         )));
-//     }
+impl<'a, 'r, R: BufferedRead<'a>> RefReader<'r, R> {
+    fn position(&self) -> u64 {
+        let rest = self.inner.fill_buf().map_or(0, |buf| buf.len());
+        (self.start - rest) as u64
+    }
+}
 
-//     let buf = &buf[..len];
-//     reader.consume(len);
-//     Ok(buf)
-// }
+impl<'r, R: io::Read> io::Read for RefReader<'r, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
 
-// pub fn unpack_bin<'a, R>(reader: &mut R) -> Result<&'a [u8], UnpackError>
-// where
-//     R: BufferedRead<'a>,
-// {
-//     let len = unpack::unpack_bin_header(reader)?;
-//     unpack_bin_data(reader, len)
-// }
+impl<'a, 'r, R: BufferedRead<'a>> BufferedRead<'a> for RefReader<'r, R> {
+    fn fill_buf(&self) -> io::Result<&'a [u8]> {
+        self.inner.fill_buf()
+    }
 
-// #[test]
-// fn test_unpack_bin() {
-//     let v = vec![0xc4, 0x03, 0x61, 0x61, 0x61];
-//     let mut cur = io::Cursor::new(v.as_ref());
-//     let ret = unpack_bin(&mut cur).unwrap();
-//     assert_eq!(ret, &[0x61, 0x61, 0x61]);
-// }
+    fn consume(&mut self, len: usize) {
+        self.inner.consume(len)
+    }
+}
 
-const TIMESTAMP64_SEC_MASK: u64 = (1 << 35) - 1;
-const TIMESTAMP64_NSEC_MASK: u32 = (1 << 31) - 1;
+fn unpack_item_ref<'a, R: BufferedRead<'a>>(
+    reader: &mut RefReader<R>,
+    limits: &mut LimitTracker,
+) -> Result<RefValue<'a>, UnpackError> {
+    let start = reader.position();
+    unpack_value_ref_inner(reader, limits).map_err(|e| e.at_offset(start))
+}
 
+// errors carry the path to the failing value and the offset of its start, counted from where
+// this call started reading
 pub fn unpack_value_ref<'a, R>(reader: &mut R) -> Result<RefValue<'a>, UnpackError>
 where
     R: BufferedRead<'a>,
 {
-    let val = match read_code(reader)? {
+    unpack_value_ref_with_limits(reader, DecodeLimits::default())
+}
+
+pub fn unpack_value_ref_with_limits<'a, R>(
+    reader: &mut R,
+    limits: DecodeLimits,
+) -> Result<RefValue<'a>, UnpackError>
+where
+    R: BufferedRead<'a>,
+{
+    let start = reader.fill_buf()?.len();
+    let mut limits = LimitTracker::new(limits);
+    unpack_item_ref(
+        &mut RefReader {
+            inner: reader,
+            start,
+        },
+        &mut limits,
+    )
+}
+
+fn unpack_value_ref_inner<'a, R>(
+    reader: &mut RefReader<R>,
+    limits: &mut LimitTracker,
+) -> Result<RefValue<'a>, UnpackError>
+where
+    R: BufferedRead<'a>,
+{
+    let code = read_code(reader)?;
+    let val = match code {
         Code::Nil => RefValue::Nil,
         Code::True => RefValue::from(true),
         Code::False => RefValue::from(false),
         Code::PosInt(v) => RefValue::from(v),
-        Code::Uint8 => RefValue::from(read_data_u8(reader)?),
-        Code::Uint16 => RefValue::from(read_data_u16(reader)?),
-        Code::Uint32 => RefValue::from(read_data_u32(reader)?),
-        Code::Uint64 => RefValue::from(read_data_u64(reader)?),
+        Code::Uint8 => RefValue::from(checked_int(&code, read_data_u8(reader)?, limits)?),
+        Code::Uint16 => RefValue::from(checked_int(&code, read_data_u16(reader)?, limits)?),
+        Code::Uint32 => RefValue::from(checked_int(&code, read_data_u32(reader)?, limits)?),
+        Code::Uint64 => RefValue::from(checked_int(&code, read_data_u64(reader)?, limits)?),
         Code::NegInt(v) => RefValue::from(v),
-        Code::Int8 => RefValue::from(read_data_i8(reader)?),
-        Code::Int16 => RefValue::from(read_data_i16(reader)?),
-        Code::Int32 => RefValue::from(read_data_i32(reader)?),
-        Code::Int64 => RefValue::from(read_data_i64(reader)?),
+        Code::Int8 => RefValue::from(checked_int(&code, read_data_i8(reader)?, limits)?),
+        Code::Int16 => RefValue::from(checked_int(&code, read_data_i16(reader)?, limits)?),
+        Code::Int32 => RefValue::from(checked_int(&code, read_data_i32(reader)?, limits)?),
+        Code::Int64 => RefValue::from(checked_int(&code, read_data_i64(reader)?, limits)?),
         Code::Float32 => RefValue::from(read_data_f32(reader)?),
         Code::Float64 => RefValue::from(read_data_f64(reader)?),
-        Code::FixStr(len) => RefValue::String(unpack_str_data_ref(reader, usize::from(len))?),
+        Code::FixStr(len) => {
+            RefValue::String(unpack_str_data_ref(reader, usize::from(len), limits)?)
+        }
         Code::Str8 => {
             let len = usize::from(read_data_u8(reader)?);
-            RefValue::String(unpack_str_data_ref(reader, len)?)
+            limits.check_len(&code, len)?;
+            RefValue::String(unpack_str_data_ref(reader, len, limits)?)
         }
         Code::Str16 => {
             let len = usize::from(read_data_u16(reader)?);
-            RefValue::String(unpack_str_data_ref(reader, len)?)
+            limits.check_len(&code, len)?;
+            RefValue::String(unpack_str_data_ref(reader, len, limits)?)
         }
         Code::Str32 => {
-            let len = read_data_u32(reader)? as usize;
-            RefValue::String(unpack_str_data_ref(reader, len)?)
+            let len = read_data_u32(reader).and_then(len_to_usize)?;
+            limits.check_len(&code, len)?;
+            RefValue::String(unpack_str_data_ref(reader, len, limits)?)
         }
         Code::Bin8 => {
             let len = usize::from(read_data_u8(reader)?);
-            RefValue::Binary(unpack_data_ref(reader, len)?)
+            limits.check_len(&code, len)?;
+            RefValue::Binary(unpack_bin_data_ref(reader, len, limits)?)
         }
         Code::Bin16 => {
             let len = usize::from(read_data_u16(reader)?);
-            RefValue::Binary(unpack_data_ref(reader, len)?)
+            limits.check_len(&code, len)?;
+            RefValue::Binary(unpack_bin_data_ref(reader, len, limits)?)
         }
         Code::Bin32 => {
-            let len = read_data_u32(reader)? as usize;
-            RefValue::Binary(unpack_data_ref(reader, len)?)
+            let len = read_data_u32(reader).and_then(len_to_usize)?;
+            limits.check_len(&code, len)?;
+            RefValue::Binary(unpack_bin_data_ref(reader, len, limits)?)
+        }
+        Code::FixArray(len) => {
+            RefValue::Array(unpack_array_data_ref(reader, len as usize, limits)?)
         }
-        Code::FixArray(len) => RefValue::Array(unpack_array_data_ref(reader, len as usize)?),
         Code::Array16 => {
             let len = usize::from(read_data_u16(reader)?);
-            RefValue::Array(unpack_array_data_ref(reader, len)?)
+            limits.check_len(&code, len)?;
+            RefValue::Array(unpack_array_data_ref(reader, len, limits)?)
         }
         Code::Array32 => {
-            let len = read_data_u32(reader)? as usize;
-            RefValue::Array(unpack_array_data_ref(reader, len)?)
+            let len = read_data_u32(reader).and_then(len_to_usize)?;
+            limits.check_len(&code, len)?;
+            RefValue::Array(unpack_array_data_ref(reader, len, limits)?)
         }
-        Code::FixMap(len) => RefValue::Map(unpack_map_data_ref(reader, len as usize)?),
+        Code::FixMap(len) => RefValue::Map(unpack_map_data_ref(reader, len as usize, limits)?),
         Code::Map16 => {
-            let len = read_data_u16(reader)? as usize;
-            RefValue::Map(unpack_map_data_ref(reader, len)?)
+            let len = usize::from(read_data_u16(reader)?);
+            limits.check_len(&code, len)?;
+            RefValue::Map(unpack_map_data_ref(reader, len, limits)?)
         }
         Code::Map32 => {
-            let len = read_data_u16(reader)? as usize;
-            RefValue::Map(unpack_map_data_ref(reader, len)?)
-        }
-        Code::FixExt1 => {
-            let (ty, vec) = unpack_ext_type_data_ref(reader, 1)?;
-            RefValue::Extension(ty, vec)
-        }
-        Code::FixExt2 => {
-            let (ty, vec) = unpack_ext_type_data_ref(reader, 2)?;
-            RefValue::Extension(ty, vec)
-        }
-        Code::FixExt4 => {
-            let ty = read_data_i8(reader)?;
-
-            // TODO: refactor
-            if ty == -1 {
-                let v: u32 = read_data_u32(reader)?;
-                RefValue::Timestamp(v as i64, 0)
-            } else {
-                let buf = unpack_data_ref(reader, 4)?;
-                RefValue::Extension(ty, buf)
-            }
-        }
-        Code::FixExt8 => {
-            let ty = read_data_i8(reader)?;
-
-            // TODO: refactor
-            if ty == -1 {
-                let v = read_data_u64(reader)?;
-                let sec = v | TIMESTAMP64_SEC_MASK;
-                let nsec = (v >> 34) as u32 | TIMESTAMP64_NSEC_MASK;
-                RefValue::Timestamp(sec as i64, nsec)
-            } else {
-                let buf = unpack_data_ref(reader, 8)?;
-                RefValue::Extension(ty, buf)
-            }
-        }
-        Code::FixExt16 => {
-            let (ty, vec) = unpack_ext_type_data_ref(reader, 16)?;
-            RefValue::Extension(ty, vec)
-        }
+            let len = read_data_u32(reader).and_then(len_to_usize)?;
+            limits.check_len(&code, len)?;
+            RefValue::Map(unpack_map_data_ref(reader, len, limits)?)
+        }
+        Code::FixExt1 => unpack_ext_data_ref(reader, 1, limits)?,
+        Code::FixExt2 => unpack_ext_data_ref(reader, 2, limits)?,
+        Code::FixExt4 => unpack_ext_data_ref(reader, 4, limits)?,
+        Code::FixExt8 => unpack_ext_data_ref(reader, 8, limits)?,
+        Code::FixExt16 => unpack_ext_data_ref(reader, 16, limits)?,
         Code::Ext8 => {
-            // TODO: refactor
             let len = usize::from(read_data_u8(reader)?);
-            let ty = read_data_i8(reader)?;
-            if len == 12 && ty == -1 {
-                let nsec = read_data_u32(reader)?;
-                let sec = read_data_i64(reader)?;
-                RefValue::Timestamp(sec as i64, nsec)
-            } else {
-                let buf = unpack_data_ref(reader, 4)?;
-                RefValue::Extension(ty, buf)
-            }
+            limits.check_len(&code, len)?;
+            unpack_ext_data_ref(reader, len, limits)?
         }
         Code::Ext16 => {
             let len = usize::from(read_data_u16(reader)?);
-            let (ty, vec) = unpack_ext_type_data_ref(reader, len)?;
-            RefValue::Extension(ty, vec)
+            limits.check_len(&code, len)?;
+            unpack_ext_data_ref(reader, len, limits)?
         }
         Code::Ext32 => {
-            let len = read_data_u32(reader)? as usize;
-            let (ty, vec) = unpack_ext_type_data_ref(reader, len)?;
-            RefValue::Extension(ty, vec)
+            let len = read_data_u32(reader).and_then(len_to_usize)?;
+            limits.check_len(&code, len)?;
+            unpack_ext_data_ref(reader, len, limits)?
         }
-        // Code::Reserved => unreachable!(),
-        _ => unreachable!(),
+        Code::Reserved => return Err(UnpackError::ReservedCode(msgpack::code::RESERVED)),
     };
 
     Ok(val)
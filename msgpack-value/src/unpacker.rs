@@ -1,9 +1,9 @@
 use std::io;
 
-use crate::{unpack, unpack_value_ref, RefValue, Value};
-use msgpack::{BufferedRead, InnerBuf, UnpackError};
+use crate::{unpack, RefValue, Value};
+use msgpack::{BufferedRead, DecodeLimits, InnerBuf, UnpackError};
 
-pub struct RefUnpackFeeder<'a, R>(&'a mut R);
+pub struct RefUnpackFeeder<'a, R>(&'a mut R, DecodeLimits);
 
 impl<'a, R> Iterator for RefUnpackFeeder<'a, R>
 where
@@ -12,7 +12,7 @@ where
     type Item = RefValue<'a>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        RefUnpacker::unpack_ref_value(self.0).ok()
+        RefUnpacker::unpack_ref_value_with_limits(self.0, self.1).ok()
     }
 }
 
@@ -23,28 +23,51 @@ impl RefUnpacker {
     where
         R: BufferedRead<'a>,
     {
-        RefUnpackFeeder(rd)
+        Self::feeder_with_limits(rd, DecodeLimits::default())
+    }
+
+    pub fn feeder_with_limits<'a, R>(rd: &'a mut R, limits: DecodeLimits) -> RefUnpackFeeder<'a, R>
+    where
+        R: BufferedRead<'a>,
+    {
+        RefUnpackFeeder(rd, limits)
     }
 
     pub fn unpack_ref_value<'a, R>(rd: &mut R) -> Result<RefValue<'a>, UnpackError>
     where
         R: BufferedRead<'a>,
     {
-        unpack_value_ref(rd)
+        unpack::unpack_value_ref(rd)
+    }
+
+    pub fn unpack_ref_value_with_limits<'a, R>(
+        rd: &mut R,
+        limits: DecodeLimits,
+    ) -> Result<RefValue<'a>, UnpackError>
+    where
+        R: BufferedRead<'a>,
+    {
+        unpack::unpack_value_ref_with_limits(rd, limits)
     }
 }
 
 #[derive(Debug)]
-pub struct UnpackerIter<R>(R);
+pub struct UnpackerIter<R>(R, DecodeLimits);
 
 impl<R> UnpackerIter<R> {
     pub fn new(inner: R) -> Self {
-        UnpackerIter(inner)
+        UnpackerIter(inner, DecodeLimits::default())
+    }
+
+    pub fn with_limits(mut self, limits: DecodeLimits) -> Self {
+        self.1 = limits;
+        self
     }
 }
 
 pub struct ValueUnpacker<R> {
     inner: R,
+    limits: DecodeLimits,
 }
 
 impl<R> ValueUnpacker<R>
@@ -52,28 +75,43 @@ where
     R: io::Read,
 {
     pub fn from_reader(inner: R) -> Self {
-        ValueUnpacker { inner }
+        ValueUnpacker {
+            inner,
+            limits: DecodeLimits::default(),
+        }
+    }
+
+    pub fn with_limits(mut self, limits: DecodeLimits) -> Self {
+        self.limits = limits;
+        self
     }
 
     pub fn iter(&mut self) -> UnpackerIter<&mut ValueUnpacker<R>> {
-        UnpackerIter::new(self)
+        let limits = self.limits;
+        UnpackerIter::new(self).with_limits(limits)
     }
 }
 
-impl<'a, R> Iterator for UnpackerIter<R>
+impl<R> Iterator for UnpackerIter<R>
 where
     R: io::Read,
 {
     type Item = Value;
 
     fn next(&mut self) -> Option<Self::Item> {
-        unpack::unpack_value(&mut self.0).ok()
+        unpack::unpack_value_with_limits(&mut self.0, self.1).ok()
     }
 }
 
 impl<R: io::Read> ValueUnpacker<R> {
     pub fn unpack_value(&mut self) -> Result<Value, UnpackError> {
-        unpack::unpack_value(&mut self.inner)
+        unpack::unpack_value_with_limits(&mut self.inner, self.limits)
+    }
+}
+
+impl Default for ValueUnpacker<InnerBuf> {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -81,8 +119,26 @@ impl ValueUnpacker<InnerBuf> {
     pub fn new() -> Self {
         ValueUnpacker {
             inner: InnerBuf::new(),
+            limits: DecodeLimits::default(),
         }
     }
+
+    pub fn feed(&mut self, buf: &[u8]) {
+        self.inner.feed(buf)
+    }
+}
+
+// yields a value only once it is fully buffered and None while more bytes are needed, so unlike
+// most iterators it goes on once more bytes are fed. a value is decoded once, however many
+// pieces it arrived in
+impl Iterator for ValueUnpacker<InnerBuf> {
+    type Item = Result<Value, UnpackError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let limits = self.limits;
+        self.inner
+            .try_read_object(|rd| unpack::unpack_value_with_limits(rd, limits))
+    }
 }
 
 impl<T> io::Write for ValueUnpacker<T>
@@ -106,3 +162,49 @@ where
         self.inner.read(buf)
     }
 }
+
+// reads values from an AsyncRead, buffering until each one is complete
+#[cfg(feature = "async")]
+#[derive(Debug)]
+pub struct AsyncValueUnpacker<R> {
+    inner: msgpack::AsyncUnpacker<R>,
+    limits: DecodeLimits,
+}
+
+#[cfg(feature = "async")]
+impl<R> AsyncValueUnpacker<R> {
+    pub fn new(rd: R) -> Self {
+        AsyncValueUnpacker {
+            inner: msgpack::AsyncUnpacker::new(rd),
+            limits: DecodeLimits::default(),
+        }
+    }
+
+    pub fn with_limits(mut self, limits: DecodeLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn into_inner(self) -> msgpack::AsyncUnpacker<R> {
+        self.inner
+    }
+}
+
+#[cfg(feature = "async")]
+impl<R: tokio::io::AsyncRead + Unpin> AsyncValueUnpacker<R> {
+    pub async fn unpack_value(&mut self) -> Result<Value, UnpackError> {
+        let limits = self.limits;
+        self.inner
+            .unpack_with(|rd| unpack::unpack_value_with_limits(rd, limits))
+            .await
+    }
+
+    // None once the reader ends cleanly between values
+    pub async fn next(&mut self) -> Option<Result<Value, UnpackError>> {
+        match self.inner.fill_buf().await {
+            Ok([]) => None,
+            Ok(_) => Some(self.unpack_value().await),
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
@@ -1,5 +1,6 @@
 pub mod float;
 pub mod from;
+pub mod index;
 pub mod integer;
 pub mod utf8_string;
 
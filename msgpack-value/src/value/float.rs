@@ -29,6 +29,38 @@ impl From<f32> for Float {
     }
 }
 
+impl Float {
+    // None unless this is a whole number that fits in a u64
+    pub fn as_u64(&self) -> Option<u64> {
+        let v = self.as_f64();
+        // 2^64 is exact as an f64, unlike u64::MAX
+        if v.fract() == 0.0 && (0.0..18_446_744_073_709_551_616.0).contains(&v) {
+            Some(v as u64)
+        } else {
+            None
+        }
+    }
+
+    // None unless this is a whole number that fits in an i64
+    pub fn as_i64(&self) -> Option<i64> {
+        let v = self.as_f64();
+        if v.fract() == 0.0
+            && (-9_223_372_036_854_775_808.0..9_223_372_036_854_775_808.0).contains(&v)
+        {
+            Some(v as i64)
+        } else {
+            None
+        }
+    }
+
+    pub fn as_f64(&self) -> f64 {
+        match self.n {
+            Number::Float32(v) => v as f64,
+            Number::Float64(v) => v,
+        }
+    }
+}
+
 impl fmt::Display for Float {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self.n {
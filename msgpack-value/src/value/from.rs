@@ -52,6 +52,12 @@ impl From<String> for Value {
     }
 }
 
+impl From<msgpack::Timestamp> for Value {
+    fn from(v: msgpack::Timestamp) -> Self {
+        Value::Timestamp(v.sec(), v.nsec())
+    }
+}
+
 macro_rules! from_pos_value_ref_integer {
     ($($ty:ident)*) => {
         $(
@@ -103,3 +109,37 @@ impl<'a> From<&'a str> for RefValue<'a> {
         RefValue::String(super::utf8_string::Utf8StringRef::new(v))
     }
 }
+
+impl<'a> From<msgpack::Timestamp> for RefValue<'a> {
+    fn from(v: msgpack::Timestamp) -> Self {
+        RefValue::Timestamp(v.sec(), v.nsec())
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<chrono::DateTime<chrono::Utc>> for Value {
+    fn from(v: chrono::DateTime<chrono::Utc>) -> Self {
+        Value::from(msgpack::Timestamp::from(v))
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl<'a> From<chrono::DateTime<chrono::Utc>> for RefValue<'a> {
+    fn from(v: chrono::DateTime<chrono::Utc>) -> Self {
+        RefValue::from(msgpack::Timestamp::from(v))
+    }
+}
+
+#[cfg(feature = "time")]
+impl From<time::OffsetDateTime> for Value {
+    fn from(v: time::OffsetDateTime) -> Self {
+        Value::from(msgpack::Timestamp::from(v))
+    }
+}
+
+#[cfg(feature = "time")]
+impl<'a> From<time::OffsetDateTime> for RefValue<'a> {
+    fn from(v: time::OffsetDateTime) -> Self {
+        RefValue::from(msgpack::Timestamp::from(v))
+    }
+}
@@ -0,0 +1,117 @@
+use super::integer::{Integer, Number};
+use crate::{RefValue, Value};
+use std::ops;
+
+// what Value::get and value[..] accept. strings look up map keys, and integers look up array
+// positions or integer map keys
+pub trait ValueIndex {
+    fn index_into<'v>(&self, v: &'v Value) -> Option<&'v Value>;
+
+    fn index_into_ref<'v, 'a>(&self, v: &'v RefValue<'a>) -> Option<&'v RefValue<'a>>;
+}
+
+impl ValueIndex for str {
+    fn index_into<'v>(&self, v: &'v Value) -> Option<&'v Value> {
+        match *v {
+            Value::Map(ref entries) => entries
+                .iter()
+                .find(|(k, _)| k.as_str() == Some(self))
+                .map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn index_into_ref<'v, 'a>(&self, v: &'v RefValue<'a>) -> Option<&'v RefValue<'a>> {
+        match *v {
+            RefValue::Map(ref entries) => entries
+                .iter()
+                .find(|(k, _)| k.as_str() == Some(self))
+                .map(|(_, v)| v),
+            _ => None,
+        }
+    }
+}
+
+impl ValueIndex for String {
+    fn index_into<'v>(&self, v: &'v Value) -> Option<&'v Value> {
+        self.as_str().index_into(v)
+    }
+
+    fn index_into_ref<'v, 'a>(&self, v: &'v RefValue<'a>) -> Option<&'v RefValue<'a>> {
+        self.as_str().index_into_ref(v)
+    }
+}
+
+impl<T: ValueIndex + ?Sized> ValueIndex for &T {
+    fn index_into<'v>(&self, v: &'v Value) -> Option<&'v Value> {
+        (**self).index_into(v)
+    }
+
+    fn index_into_ref<'v, 'a>(&self, v: &'v RefValue<'a>) -> Option<&'v RefValue<'a>> {
+        (**self).index_into_ref(v)
+    }
+}
+
+// compares by value, so an integer matches however it was stored
+fn widen(i: &Integer) -> i128 {
+    match i.n {
+        Number::PosInt(v) => v as i128,
+        Number::NegInt(v) => v as i128,
+    }
+}
+
+macro_rules! impl_index_int {
+    ($($ty:ty)*) => {
+        $(
+            impl ValueIndex for $ty {
+                fn index_into<'v>(&self, v: &'v Value) -> Option<&'v Value> {
+                    match *v {
+                        Value::Array(ref vs) => usize::try_from(*self).ok().and_then(|i| vs.get(i)),
+                        Value::Map(ref entries) => entries
+                            .iter()
+                            .find(|(k, _)| matches!(k, Value::Integer(i) if widen(i) == *self as i128))
+                            .map(|(_, v)| v),
+                        _ => None,
+                    }
+                }
+
+                fn index_into_ref<'v, 'a>(&self, v: &'v RefValue<'a>) -> Option<&'v RefValue<'a>> {
+                    match *v {
+                        RefValue::Array(ref vs) => {
+                            usize::try_from(*self).ok().and_then(|i| vs.get(i))
+                        }
+                        RefValue::Map(ref entries) => entries
+                            .iter()
+                            .find(|(k, _)| {
+                                matches!(k, RefValue::Integer(i) if widen(i) == *self as i128)
+                            })
+                            .map(|(_, v)| v),
+                        _ => None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_index_int! {u8 u16 u32 u64 usize i8 i16 i32 i64 isize}
+
+// what indexing returns for a missing key, like serde_json
+static NIL: Value = Value::Nil;
+static NIL_REF: RefValue<'static> = RefValue::Nil;
+
+impl<I: ValueIndex> ops::Index<I> for Value {
+    type Output = Value;
+
+    fn index(&self, index: I) -> &Value {
+        index.index_into(self).unwrap_or(&NIL)
+    }
+}
+
+impl<'a, I: ValueIndex> ops::Index<I> for RefValue<'a> {
+    type Output = RefValue<'a>;
+
+    fn index(&self, index: I) -> &RefValue<'a> {
+        index.index_into_ref(self).unwrap_or(&NIL_REF)
+    }
+}
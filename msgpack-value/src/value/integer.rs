@@ -32,7 +32,13 @@ macro_rules! impl_from_neg_integer {
             impl From<$ty> for Integer {
                 #[inline]
                 fn from(u: $ty) -> Self {
-                    Integer { n: { Number::NegInt(u as i64) } }
+                    // non-negative values are always PosInt so equal numbers compare equal
+                    // regardless of how they were encoded
+                    if u < 0 {
+                        Integer { n: { Number::NegInt(u as i64) } }
+                    } else {
+                        Integer { n: { Number::PosInt(u as u64) } }
+                    }
                 }
             }
         )*
@@ -41,6 +47,30 @@ macro_rules! impl_from_neg_integer {
 
 impl_from_neg_integer! {i8 i16 i32 i64 isize}
 
+impl Integer {
+    pub fn as_u64(&self) -> Option<u64> {
+        match self.n {
+            Number::PosInt(v) => Some(v),
+            Number::NegInt(v) => u64::try_from(v).ok(),
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self.n {
+            Number::PosInt(v) => i64::try_from(v).ok(),
+            Number::NegInt(v) => Some(v),
+        }
+    }
+
+    // may round to the nearest f64
+    pub fn as_f64(&self) -> f64 {
+        match self.n {
+            Number::PosInt(v) => v as f64,
+            Number::NegInt(v) => v as f64,
+        }
+    }
+}
+
 impl fmt::Display for Integer {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self.n {
@@ -44,7 +44,7 @@ impl Utf8String {
         }
     }
 
-    pub fn as_ref(&self) -> Utf8StringRef {
+    pub fn as_ref(&self) -> Utf8StringRef<'_> {
         match self.s {
             Ok(ref s) => Utf8StringRef { s: Ok(s.as_str()) },
             Err((ref buf, err)) => Utf8StringRef {
@@ -96,7 +96,7 @@ impl From<String> for Utf8String {
     }
 }
 
-impl<'a> From<&'a str> for Utf8String {
+impl From<&str> for Utf8String {
     fn from(val: &str) -> Self {
         Utf8String { s: Ok(val.into()) }
     }
@@ -121,10 +121,7 @@ impl<'a> Utf8StringRef<'a> {
     }
 
     pub fn as_str(&self) -> Option<&str> {
-        match self.s {
-            Ok(ref s) => Some(s),
-            Err(_) => None,
-        }
+        self.s.ok()
     }
 
     pub fn into_str(self) -> Option<String> {
@@ -183,9 +180,9 @@ impl<'a> From<&'a str> for Utf8StringRef<'a> {
     }
 }
 
-impl<'a> Into<Utf8String> for Utf8StringRef<'a> {
-    fn into(self) -> Utf8String {
-        match self.s {
+impl<'a> From<Utf8StringRef<'a>> for Utf8String {
+    fn from(val: Utf8StringRef<'a>) -> Self {
+        match val.s {
             Ok(s) => Utf8String { s: Ok(s.into()) },
             Err((buf, err)) => Utf8String {
                 s: Err((buf.into(), err)),
@@ -0,0 +1,121 @@
+use msgpack_value::{unpack_value_ref, RefValue, Utf8String, Value};
+
+fn s(v: &str) -> Value {
+    Value::String(Utf8String::from(v))
+}
+
+fn record() -> Value {
+    Value::Map(vec![
+        (s("tag"), s("app.log")),
+        (s("time"), Value::Timestamp(1, 0)),
+        (
+            s("tags"),
+            Value::Array(vec![s("a"), Value::from(-1i8), Value::Nil]),
+        ),
+        (Value::from(7u8), Value::Binary(vec![1, 2])),
+        (Value::from(-2i8), Value::Extension(5, vec![3])),
+    ])
+}
+
+#[test]
+fn kinds() {
+    let v = record();
+    assert!(v.is_map() && !v.is_array());
+    assert!(v["tag"].is_string());
+    assert!(v["time"].is_timestamp());
+    assert!(v["tags"].is_array());
+    assert!(v["tags"][1].is_integer() && v["tags"][1].is_number());
+    assert!(v["tags"][2].is_nil());
+    assert!(v[7].is_binary());
+    assert!(v[-2].is_extension());
+    assert!(Value::from(true).is_boolean());
+    assert!(Value::from(0.5).is_float());
+
+    // invalid utf-8 is still a string, but has no str
+    let bad = Value::String(Utf8String::from(vec![0xff]));
+    assert!(bad.is_string());
+    assert_eq!(bad.as_str(), None);
+}
+
+#[test]
+fn accessors() {
+    let v = record();
+    assert_eq!(v["tag"].as_str(), Some("app.log"));
+    assert_eq!(v[7].as_binary(), Some(&[1, 2][..]));
+    assert_eq!(v[-2].as_extension(), Some((5, &[3][..])));
+    assert_eq!(v["tags"].as_array().map(Vec::len), Some(3));
+    assert_eq!(v.as_map().map(Vec::len), Some(5));
+    assert_eq!(v["tags"][2].as_nil(), Some(()));
+    assert_eq!(Value::from(true).as_bool(), Some(true));
+    assert_eq!(v["tag"].as_bool(), None);
+    assert_eq!(v["time"].as_timestamp().unwrap().sec(), 1);
+}
+
+#[test]
+fn numbers() {
+    assert_eq!(Value::from(u64::MAX).as_u64(), Some(u64::MAX));
+    assert_eq!(Value::from(u64::MAX).as_i64(), None);
+    assert_eq!(Value::from(-1i8).as_u64(), None);
+    assert_eq!(Value::from(-1i8).as_i64(), Some(-1));
+    assert_eq!(Value::from(3u8).as_f64(), Some(3.0));
+
+    // whole floats convert when they fit, and nothing else does
+    assert_eq!(Value::from(3.0f32).as_u64(), Some(3));
+    assert_eq!(Value::from(-3.0).as_i64(), Some(-3));
+    assert_eq!(Value::from(-3.0).as_u64(), None);
+    assert_eq!(Value::from(3.5).as_i64(), None);
+    assert_eq!(Value::from(1e20).as_u64(), None);
+    assert_eq!(Value::from(f64::NAN).as_i64(), None);
+    assert_eq!(Value::from(f64::INFINITY).as_u64(), None);
+    assert_eq!(Value::from(0.25f32).as_f64(), Some(0.25));
+    assert!(Value::from(2.0).is_u64() && !Value::from(2.5).is_i64());
+    assert_eq!(s("1").as_u64(), None);
+}
+
+#[test]
+fn get_and_index() {
+    let v = record();
+    assert_eq!(v.get("tag"), Some(&s("app.log")));
+    assert_eq!(v.get(String::from("tag")), Some(&s("app.log")));
+    assert_eq!(v.get(7u64), Some(&Value::Binary(vec![1, 2])));
+    assert_eq!(v.get("missing"), None);
+    assert_eq!(v.get(0), None);
+    assert_eq!(v["tags"].get(0), Some(&s("a")));
+    assert_eq!(v["tags"].get(3), None);
+    assert_eq!(v["tags"].get(-1), None);
+    assert_eq!(v["tag"].get("tag"), None);
+
+    // misses index as nil, however deep
+    assert_eq!(v["missing"], Value::Nil);
+    assert_eq!(v["tags"][9], Value::Nil);
+    assert_eq!(v["missing"]["deeper"][0], Value::Nil);
+}
+
+#[test]
+fn mutate() {
+    let mut v = record();
+    v.as_map_mut().unwrap().push((s("host"), s("web1")));
+    assert_eq!(v["host"].as_str(), Some("web1"));
+
+    let tags = v.as_map_mut().unwrap()[2].1.as_array_mut().unwrap();
+    tags.truncate(1);
+    assert_eq!(v["tags"], Value::Array(vec![s("a")]));
+    assert!(v["tag"].clone().as_array_mut().is_none());
+}
+
+#[test]
+fn ref_values() {
+    let mut buf = vec![];
+    msgpack_value::pack_value(&mut buf, record()).unwrap();
+    let r = unpack_value_ref(&mut &buf[..]).unwrap();
+
+    // strs and bins borrow the input rather than the value
+    let tag = r["tag"].as_str().unwrap();
+    assert!(buf.as_ptr_range().contains(&tag.as_ptr()));
+    assert_eq!(r[7].as_binary(), Some(&[1, 2][..]));
+    assert_eq!(r[-2].as_extension(), Some((5, &[3][..])));
+    assert_eq!(r["tags"][1].as_i64(), Some(-1));
+    assert_eq!(r["tags"].get(0), Some(&RefValue::from("a")));
+    assert_eq!(r["missing"][0], RefValue::Nil);
+    assert!(r["time"].is_timestamp() && r.is_map());
+}
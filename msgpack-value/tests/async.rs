@@ -0,0 +1,55 @@
+#![cfg(feature = "async")]
+
+use msgpack::{AsyncPacker, DecodeLimits, UnpackError};
+use msgpack_value::{pack_value_async, AsyncValueUnpacker, Value};
+
+#[tokio::test]
+async fn async_values() {
+    let (wr, rd) = tokio::io::duplex(4);
+    let values = vec![
+        Value::from(1u64),
+        Value::Array(vec![
+            Value::from("abc".to_owned()),
+            Value::Nil,
+            Value::from(-2i64),
+        ]),
+        Value::Map(vec![(
+            Value::from("k".to_owned()),
+            Value::Binary(vec![0; 100]),
+        )]),
+    ];
+
+    let expected = values.clone();
+    let write = async move {
+        let mut packer = AsyncPacker::new(wr);
+        for v in values {
+            pack_value_async(&mut packer, v).await.unwrap();
+        }
+    };
+
+    let read = async move {
+        let mut unpacker = AsyncValueUnpacker::new(rd);
+        let mut actual = vec![];
+        while let Some(v) = unpacker.next().await {
+            actual.push(v.unwrap());
+        }
+        assert_eq!(actual, expected);
+    };
+
+    tokio::join!(write, read);
+}
+
+#[tokio::test]
+async fn async_value_limits() {
+    // str32 claiming 4 GiB, rejected before any body is read
+    let buf = [0xdb, 0xff, 0xff, 0xff, 0xff];
+    let limits = DecodeLimits {
+        max_data_len: 1024,
+        ..DecodeLimits::default()
+    };
+    let mut unpacker = AsyncValueUnpacker::new(&buf[..]).with_limits(limits);
+    match unpacker.unpack_value().await.unwrap_err().root() {
+        UnpackError::LimitExceeded(..) => {}
+        e => panic!("unexpected {:?}", e),
+    }
+}
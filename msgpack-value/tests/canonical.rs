@@ -0,0 +1,101 @@
+use msgpack::{is_canonical, is_canonical_with, CanonicalOptions, PackError};
+use msgpack_value::{pack_value, unpack_value, Value, ValuePacker};
+
+fn canonical(v: Value, opts: CanonicalOptions) -> Result<Vec<u8>, PackError> {
+    let mut buf = vec![];
+    ValuePacker::new(&mut buf)
+        .with_canonical(opts)
+        .pack_value(v)?;
+    Ok(buf)
+}
+
+fn s(v: &str) -> Value {
+    Value::from(v.to_owned())
+}
+
+#[test]
+fn sorts_map_keys() {
+    let v = Value::Map(vec![
+        (s("b"), Value::from(2_u8)),
+        (
+            s("a"),
+            Value::Map(vec![(s("y"), Value::Nil), (s("x"), Value::Nil)]),
+        ),
+        (Value::from(300_u16), Value::Nil),
+        (Value::from(1_u8), Value::Nil),
+    ]);
+
+    let buf = canonical(v.clone(), CanonicalOptions::default()).unwrap();
+    assert!(is_canonical(&buf));
+    assert_eq!(
+        buf,
+        &[
+            0x84, 0x01, 0xc0, 0xa1, 0x61, 0x82, 0xa1, 0x78, 0xc0, 0xa1, 0x79, 0xc0, 0xa1, 0x62,
+            0x02, 0xcd, 0x01, 0x2c, 0xc0
+        ]
+    );
+
+    let mut plain = vec![];
+    pack_value(&mut plain, v).unwrap();
+    assert!(!is_canonical(&plain));
+    assert_eq!(buf.len(), plain.len());
+}
+
+#[test]
+fn same_bytes_whatever_the_order() {
+    let a = Value::Map(vec![
+        (s("k1"), Value::from(1_u8)),
+        (s("k2"), Value::from(2_u8)),
+    ]);
+    let b = Value::Map(vec![
+        (s("k2"), Value::from(2_u8)),
+        (s("k1"), Value::from(1_u8)),
+    ]);
+    let opts = CanonicalOptions::default();
+    assert_eq!(canonical(a, opts).unwrap(), canonical(b, opts).unwrap());
+}
+
+#[test]
+fn rejects_duplicate_keys() {
+    let v = Value::Array(vec![Value::Map(vec![
+        (s("a"), Value::from(1_u8)),
+        (s("a"), Value::from(2_u8)),
+    ])]);
+    match canonical(v, CanonicalOptions::default()) {
+        Err(PackError::DuplicateMapKey) => {}
+        v => panic!("unexpected {:?}", v),
+    }
+}
+
+#[test]
+fn shrinks_floats() {
+    let opts = CanonicalOptions {
+        shrink_floats: true,
+    };
+    let v = Value::Array(vec![Value::from(0.5_f64), Value::from(0.1_f64)]);
+    let buf = canonical(v, opts).unwrap();
+    assert!(is_canonical_with(&buf, opts));
+    assert_eq!(&buf[..6], &[0x92, 0xca, 0x3f, 0x00, 0x00, 0x00]);
+    assert_eq!(
+        unpack_value(&mut &buf[..]).unwrap(),
+        Value::Array(vec![Value::from(0.5_f32), Value::from(0.1_f64)])
+    );
+
+    let buf = canonical(Value::from(0.5_f64), CanonicalOptions::default()).unwrap();
+    assert_eq!(buf[0], 0xcb);
+}
+
+#[test]
+fn checks_timestamps() {
+    for (sec, nsec) in [(1, 0), (1, 5), (-1, 999_999_999), (1 << 34, 0)] {
+        let buf = canonical(Value::Timestamp(sec, nsec), CanonicalOptions::default()).unwrap();
+        assert!(is_canonical(&buf), "{} {}", sec, nsec);
+    }
+
+    // nsec fits the 30 bits of a timestamp64 but isn't below a second
+    let err = canonical(
+        Value::Timestamp(1, 1_000_000_000),
+        CanonicalOptions::default(),
+    );
+    assert!(matches!(err, Err(PackError::WriteError(_))));
+}
@@ -0,0 +1,65 @@
+#![cfg(feature = "codec")]
+
+use bytes::BytesMut;
+use msgpack::{DecodeLimits, MsgPackCodec, UnpackError};
+use msgpack_value::{RefFrame, RefValue, Value};
+use tokio_util::codec::{Decoder, Encoder};
+
+#[test]
+fn codec_values() {
+    let values = vec![
+        Value::Array(vec![Value::from(1u64), Value::Nil]),
+        Value::from("abc".to_owned()),
+        Value::Map(vec![(Value::from(-1i64), Value::Binary(vec![0; 300]))]),
+    ];
+
+    let mut codec = MsgPackCodec::<Value>::new();
+    let mut encoded = BytesMut::new();
+    for v in values.clone() {
+        codec.encode(v, &mut encoded).unwrap();
+    }
+
+    // split at an arbitrary point inside the last value
+    let mut buf = encoded.split_to(20);
+    let mut actual = vec![];
+    while let Some(v) = codec.decode(&mut buf).unwrap() {
+        actual.push(v);
+    }
+    buf.unsplit(encoded);
+    while let Some(v) = codec.decode(&mut buf).unwrap() {
+        actual.push(v);
+    }
+    assert_eq!(actual, values);
+}
+
+#[test]
+fn codec_ref_frames() {
+    let mut codec = MsgPackCodec::<RefFrame>::new();
+    let mut buf = BytesMut::from(&[0x92, 0xa1, 0x61, 0xc3, 0x01][..]);
+
+    let frame = codec.decode(&mut buf).unwrap().unwrap();
+    assert_eq!(
+        frame.value(),
+        RefValue::Array(vec![RefValue::from("a"), RefValue::from(true)])
+    );
+    assert_eq!(frame.as_bytes(), &[0x92, 0xa1, 0x61, 0xc3]);
+
+    let frame = codec.decode(&mut buf).unwrap().unwrap();
+    assert_eq!(frame.value(), RefValue::from(1u64));
+    assert!(codec.decode(&mut buf).unwrap().is_none());
+}
+
+#[test]
+fn codec_value_limits() {
+    // the frame is complete, but nested deeper than allowed
+    let limits = DecodeLimits {
+        max_depth: 2,
+        ..DecodeLimits::default()
+    };
+    let mut codec = MsgPackCodec::<RefFrame>::new().with_limits(limits);
+    let mut buf = BytesMut::from(&[0x91, 0x91, 0x91, 0xc0][..]);
+    match codec.decode(&mut buf).unwrap_err().root() {
+        UnpackError::DepthExceeded(2) => {}
+        e => panic!("unexpected {:?}", e),
+    }
+}
@@ -0,0 +1,104 @@
+use msgpack_value::{unpack_value, unpack_value_ref, RefValue, Value};
+use serde_json::Value as Json;
+use std::io;
+
+#[path = "../../testdata/conformance.rs"]
+mod suite;
+
+use suite::from_hex;
+
+// floats are compared at the width they were encoded with
+fn to_value(v: &Json, f32_encoded: bool) -> Value {
+    match v {
+        Json::Null => Value::Nil,
+        Json::Bool(v) => Value::from(*v),
+        Json::Number(v) => {
+            if let Some(v) = v.as_u64() {
+                Value::from(v)
+            } else if let Some(v) = v.as_i64() {
+                Value::from(v)
+            } else if f32_encoded {
+                Value::from(v.as_f64().unwrap() as f32)
+            } else {
+                Value::from(v.as_f64().unwrap())
+            }
+        }
+        Json::String(v) => Value::from(v.clone()),
+        Json::Array(v) => Value::Array(v.iter().map(|v| to_value(v, false)).collect()),
+        Json::Object(v) => Value::Map(
+            v.iter()
+                .map(|(k, v)| (Value::from(k.clone()), to_value(v, false)))
+                .collect(),
+        ),
+    }
+}
+
+fn to_ref_value(v: &Json, f32_encoded: bool) -> RefValue<'_> {
+    match v {
+        Json::Null => RefValue::Nil,
+        Json::Bool(v) => RefValue::from(*v),
+        Json::Number(v) => {
+            if let Some(v) = v.as_u64() {
+                RefValue::from(v)
+            } else if let Some(v) = v.as_i64() {
+                RefValue::from(v)
+            } else if f32_encoded {
+                RefValue::from(v.as_f64().unwrap() as f32)
+            } else {
+                RefValue::from(v.as_f64().unwrap())
+            }
+        }
+        Json::String(v) => RefValue::from(v.as_str()),
+        Json::Array(v) => RefValue::Array(v.iter().map(|v| to_ref_value(v, false)).collect()),
+        Json::Object(v) => RefValue::Map(
+            v.iter()
+                .map(|(k, v)| (RefValue::from(k.as_str()), to_ref_value(v, false)))
+                .collect(),
+        ),
+    }
+}
+
+fn check_case(ctx: &str, case: &Json, bytes: &[u8]) {
+    let f32_encoded = bytes[0] == msgpack::code::FLOAT32;
+
+    let binary = case.get("binary").map(|v| from_hex(v.as_str().unwrap()));
+    let ext = case.get("ext").map(|v| {
+        let ty = v[0].as_i64().unwrap() as i8;
+        (ty, from_hex(v[1].as_str().unwrap()))
+    });
+    let timestamp = case.get("timestamp").map(|v| {
+        let nsec = v[1].as_u64().unwrap() as u32;
+        (v[0].as_i64().unwrap(), nsec)
+    });
+    let json = ["nil", "bool", "number", "string", "array", "map"]
+        .iter()
+        .find_map(|k| case.get(k));
+
+    let (expected, expected_ref) = if let Some(ref data) = binary {
+        (Value::Binary(data.clone()), RefValue::Binary(data))
+    } else if let Some((ty, ref data)) = ext {
+        (
+            Value::Extension(ty, data.clone()),
+            RefValue::Extension(ty, data),
+        )
+    } else if let Some((sec, nsec)) = timestamp {
+        (Value::Timestamp(sec, nsec), RefValue::Timestamp(sec, nsec))
+    } else {
+        let v = json.unwrap();
+        (to_value(v, f32_encoded), to_ref_value(v, f32_encoded))
+    };
+
+    let mut reader = io::Cursor::new(bytes);
+    assert_eq!(unpack_value(&mut reader).expect(ctx), expected, "{}", ctx);
+    assert_eq!(reader.position() as usize, bytes.len(), "{}", ctx);
+
+    let mut reader = bytes;
+    let actual = unpack_value_ref(&mut reader).expect(ctx);
+    assert_eq!(actual, expected_ref, "{}", ctx);
+    assert!(reader.is_empty(), "{}", ctx);
+}
+
+#[test]
+fn conformance() {
+    suite::for_each_case(check_case);
+}
@@ -1,3 +1,6 @@
+#![allow(clippy::unnecessary_cast, clippy::unused_io_amount)]
+
+use msgpack::{DecodeLimits, UnpackError};
 use msgpack_value::{RefUnpacker, RefValue, Value, ValueUnpacker};
 use std::io::{self, Write};
 
@@ -84,3 +87,93 @@ fn feed_ref_slice() {
         RefValue::Map(vec![(RefValue::from(1 as u8), RefValue::from("s"))])
     );
 }
+
+#[test]
+fn unpacker_feed_and_next() {
+    let val = vec![0x92, 0x01, 0xa1, 0x73, 0x81, 0x01, 0xc3, 0xc0];
+    let mut unpacker = ValueUnpacker::new();
+
+    // feed one byte at a time. values come out only once they are complete
+    let mut values = vec![];
+    for b in val {
+        unpacker.feed(&[b]);
+        for v in &mut unpacker {
+            values.push(v.unwrap());
+        }
+    }
+
+    assert_eq!(
+        values,
+        vec![
+            Value::Array(vec![Value::from(1_u8), Value::from("s".to_owned())]),
+            Value::Map(vec![(Value::from(1_u8), Value::Boolean(true))]),
+            Value::Nil,
+        ]
+    );
+    assert!(unpacker.next().is_none());
+}
+
+#[test]
+fn unpackers_with_limits() {
+    let limits = DecodeLimits {
+        max_depth: 1,
+        ..DecodeLimits::default()
+    };
+    let val = vec![0x91, 0x01, 0x91, 0x91, 0x01];
+
+    let mut unpacker = ValueUnpacker::from_reader(io::Cursor::new(val.clone())).with_limits(limits);
+    assert_eq!(
+        unpacker.unpack_value().unwrap(),
+        Value::Array(vec![Value::from(1_u8)])
+    );
+    let err = unpacker.unpack_value().unwrap_err();
+    assert!(matches!(err.root(), UnpackError::DepthExceeded(1)));
+
+    let mut unpacker = ValueUnpacker::new().with_limits(limits);
+    unpacker.feed(&val);
+    assert!(unpacker.next().unwrap().is_ok());
+    assert!(unpacker.next().unwrap().is_err());
+
+    let mut reader = &val[..];
+    assert!(RefUnpacker::unpack_ref_value_with_limits(&mut reader, limits).is_ok());
+    let err = RefUnpacker::unpack_ref_value_with_limits(&mut reader, limits).unwrap_err();
+    assert!(matches!(err.root(), UnpackError::DepthExceeded(1)));
+
+    let mut reader = &val[..];
+    assert_eq!(
+        RefUnpacker::feeder_with_limits(&mut reader, limits).count(),
+        1
+    );
+}
+
+#[cfg(feature = "chrono")]
+#[test]
+fn timestamp_chrono() {
+    use chrono::{DateTime, TimeZone, Utc};
+
+    let time = Utc.timestamp_opt(1569144132, 33554431).unwrap();
+    let v = Value::from(time);
+    assert_eq!(v, Value::Timestamp(1569144132, 33554431));
+    let t = v.as_timestamp().unwrap();
+    assert_eq!(DateTime::<Utc>::try_from(t).unwrap(), time);
+    assert_eq!(
+        RefValue::from(time),
+        RefValue::Timestamp(1569144132, 33554431)
+    );
+}
+
+#[cfg(feature = "time")]
+#[test]
+fn timestamp_time() {
+    use time::OffsetDateTime;
+
+    let time = OffsetDateTime::from_unix_timestamp_nanos(1_569_144_132_033_554_431).unwrap();
+    let v = Value::from(time);
+    assert_eq!(v, Value::Timestamp(1569144132, 33554431));
+    let t = v.as_timestamp().unwrap();
+    assert_eq!(OffsetDateTime::try_from(t).unwrap(), time);
+    assert_eq!(
+        RefValue::from(time),
+        RefValue::Timestamp(1569144132, 33554431)
+    );
+}
@@ -0,0 +1,208 @@
+#![cfg(feature = "serde")]
+
+use msgpack_value::{pack_value, unpack_value, RefValue, Utf8String, Value};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Envelope {
+    id: u32,
+    body: Value,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct RefEnvelope<'a> {
+    id: u32,
+    #[serde(borrow)]
+    body: RefValue<'a>,
+}
+
+fn s(v: &str) -> Value {
+    Value::String(Utf8String::from(v))
+}
+
+fn sample() -> Value {
+    Value::Map(vec![
+        (s("nil"), Value::Nil),
+        (s("bool"), Value::Boolean(true)),
+        (s("pos"), Value::from(300u16)),
+        (s("neg"), Value::from(-5i8)),
+        (s("f32"), Value::from(1.5f32)),
+        (s("f64"), Value::from(0.1f64)),
+        (s("bin"), Value::Binary(vec![0, 1, 2])),
+        (s("str"), s("s")),
+        (s("array"), Value::Array(vec![Value::from(1u8), Value::Nil])),
+        (Value::from(7u8), s("int key")),
+        (s("fixext"), Value::Extension(5, vec![1, 2, 3, 4])),
+        (s("ext8"), Value::Extension(5, vec![9; 3])),
+        (s("ext16"), Value::Extension(5, vec![9; 300])),
+        (s("ts32"), Value::Timestamp(1, 0)),
+        (s("ts64"), Value::Timestamp(1, 2)),
+        (s("ts96"), Value::Timestamp(-1, 2)),
+    ])
+}
+
+fn packed(v: Value) -> Vec<u8> {
+    let mut buf = vec![];
+    pack_value(&mut buf, v).unwrap();
+    buf
+}
+
+#[test]
+fn serializes_like_pack_value() {
+    assert_eq!(msgpack_serde::pack(&sample()).unwrap(), packed(sample()));
+}
+
+#[test]
+fn round_trips() {
+    let buf = msgpack_serde::pack(&sample()).unwrap();
+    assert_eq!(msgpack_serde::unpack::<Value>(&buf).unwrap(), sample());
+}
+
+#[test]
+fn borrowed() {
+    let buf = packed(sample());
+    let v: RefValue = msgpack_serde::from_slice(&buf).unwrap();
+    assert_eq!(v, msgpack_value::unpack_value_ref(&mut &buf[..]).unwrap());
+    assert_eq!(msgpack_serde::pack(&v).unwrap(), buf);
+}
+
+#[test]
+fn embedded() {
+    let v = Envelope {
+        id: 1,
+        body: sample(),
+    };
+    let buf = msgpack_serde::pack(&v).unwrap();
+    assert_eq!(msgpack_serde::unpack::<Envelope>(&buf).unwrap(), v);
+
+    let r: RefEnvelope = msgpack_serde::from_slice(&buf).unwrap();
+    assert_eq!(r.id, 1);
+    assert_eq!(
+        r.body,
+        msgpack_value::unpack_value_ref(&mut &packed(sample())[..]).unwrap()
+    );
+
+    // the body is whatever msgpack-value reads at that spot
+    let mut rd = &buf[..];
+    let whole = unpack_value(&mut rd).unwrap();
+    match whole {
+        Value::Map(ref kvs) => assert_eq!(kvs[1].1, sample()),
+        _ => panic!("{:?}", whole),
+    }
+}
+
+#[test]
+fn json() {
+    let v = Value::Map(vec![
+        (s("a"), Value::Array(vec![Value::from(1u8)])),
+        (s("b"), Value::from(-2i8)),
+        (s("c"), Value::Binary(vec![3])),
+        (s("d"), Value::String(Utf8String::from(vec![0xff, 0x61]))),
+    ]);
+    let json = serde_json::to_string(&v).unwrap();
+    assert_eq!(json, r#"{"a":[1],"b":-2,"c":[3],"d":[255,97]}"#);
+
+    let back: Value = serde_json::from_str(r#"{"a":[1,null,true,0.5],"b":"s"}"#).unwrap();
+    assert_eq!(
+        back,
+        Value::Map(vec![
+            (
+                s("a"),
+                Value::Array(vec![
+                    Value::from(1u8),
+                    Value::Nil,
+                    Value::Boolean(true),
+                    Value::from(0.5f64)
+                ])
+            ),
+            (s("b"), s("s")),
+        ])
+    );
+}
+
+#[test]
+fn json_ext() {
+    // without an ext type, the header, type and data are an array
+    let json = serde_json::to_string(&Value::Extension(5, vec![1, 2])).unwrap();
+    assert_eq!(json, "[213,5,[1,2]]");
+    let json = serde_json::to_string(&Value::Timestamp(1, 0)).unwrap();
+    assert_eq!(json, "[214,-1,[0,0,0,1]]");
+}
+
+#[test]
+fn ref_value_needs_borrowing() {
+    assert!(serde_json::from_str::<RefValue>(r#""escaped\n""#).is_err());
+    let v: RefValue = serde_json::from_str(r#"["plain", 1]"#).unwrap();
+    assert_eq!(
+        v,
+        RefValue::Array(vec![RefValue::from("plain"), RefValue::from(1u8)])
+    );
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Wrapped(Value);
+
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(untagged)]
+enum Stamp {
+    Time(msgpack_serde::Timestamp),
+    Text(String),
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Flattened {
+    id: u32,
+    #[serde(flatten)]
+    inner: Stamped,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Stamped {
+    ts: msgpack_serde::Timestamp,
+}
+
+#[test]
+fn exts_behind_other_types() {
+    // a newtype around a Value sees the ext itself rather than its fields
+    for v in [
+        Value::Extension(5, vec![1, 2, 3]),
+        Value::Extension(7, vec![9; 300]),
+        Value::Timestamp(1, 2),
+    ] {
+        let buf = packed(v.clone());
+        assert_eq!(msgpack_serde::unpack::<Wrapped>(&buf).unwrap(), Wrapped(v));
+    }
+
+    // untagged and flattened types buffer the ext before picking what reads it
+    let buf = packed(Value::Timestamp(1, 2));
+    assert_eq!(
+        msgpack_serde::unpack::<Stamp>(&buf).unwrap(),
+        Stamp::Time(msgpack_serde::Timestamp::new(1, 2))
+    );
+    let buf = msgpack_serde::pack(&"now").unwrap();
+    assert_eq!(
+        msgpack_serde::unpack::<Stamp>(&buf).unwrap(),
+        Stamp::Text("now".to_string())
+    );
+
+    let v = Flattened {
+        id: 1,
+        inner: Stamped {
+            ts: msgpack_serde::Timestamp::new(-1, 2),
+        },
+    };
+    let buf = msgpack_serde::pack(&v).unwrap();
+    assert_eq!(msgpack_serde::unpack::<Flattened>(&buf).unwrap(), v);
+}
+
+#[test]
+fn reserved_ext_types() {
+    // negative types are reserved, but whatever unpack_value reads goes back out as it came in
+    let buf = [0xd4, 0xfb, 0x01];
+    let v = unpack_value(&mut &buf[..]).unwrap();
+    assert_eq!(v, Value::Extension(-5, vec![1]));
+    assert_eq!(msgpack_serde::pack(&v).unwrap(), buf);
+    assert_eq!(msgpack_serde::unpack::<Value>(&buf).unwrap(), v);
+    assert_eq!(msgpack_value::from_value::<Value>(v.clone()).unwrap(), v);
+    assert_eq!(msgpack_value::to_value(&v).unwrap(), v);
+}
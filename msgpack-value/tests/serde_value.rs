@@ -0,0 +1,217 @@
+#![cfg(feature = "serde")]
+
+use msgpack_serde::{EnumRepr, Serializer, StructRepr};
+use msgpack_value::{
+    from_ref_value, from_value, to_value, unpack_value, unpack_value_ref, SerdeError, Utf8String,
+    Value,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+enum Output {
+    Stdout,
+    File(String),
+    Forward { host: String, port: u16 },
+    Pair(u8, u8),
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Unit;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Config {
+    name: String,
+    retries: u8,
+    timeout: Option<f64>,
+    tags: Vec<String>,
+    labels: BTreeMap<String, i32>,
+    outputs: Vec<Output>,
+    #[serde(with = "serde_bytes")]
+    key: Vec<u8>,
+    marker: Unit,
+    extra: Value,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Stamped {
+    ts: msgpack_serde::Timestamp,
+    ext: Value,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Wrapped(Value);
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct Borrowed<'a> {
+    name: &'a str,
+    #[serde(with = "serde_bytes")]
+    key: &'a [u8],
+}
+
+fn s(v: &str) -> Value {
+    Value::String(Utf8String::from(v))
+}
+
+fn config() -> Config {
+    Config {
+        name: "plugin".to_owned(),
+        retries: 3,
+        timeout: Some(1.5),
+        tags: vec!["a".to_owned(), "b".to_owned()],
+        labels: [("x".to_owned(), -1), ("y".to_owned(), 2)].into(),
+        outputs: vec![
+            Output::Stdout,
+            Output::File("out.log".to_owned()),
+            Output::Forward {
+                host: "localhost".to_owned(),
+                port: 24224,
+            },
+            Output::Pair(1, 2),
+        ],
+        key: vec![0, 1, 2],
+        marker: Unit,
+        extra: Value::Array(vec![
+            Value::Timestamp(1, 2),
+            Value::Extension(5, vec![9; 300]),
+        ]),
+    }
+}
+
+fn unpacked(buf: &[u8]) -> Value {
+    unpack_value(&mut &buf[..]).unwrap()
+}
+
+#[test]
+fn to_value_matches_unpack_value() {
+    let buf = msgpack_serde::pack(&config()).unwrap();
+    assert_eq!(to_value(&config()).unwrap(), unpacked(&buf));
+}
+
+#[test]
+fn round_trips() {
+    let v = to_value(&config()).unwrap();
+    assert_eq!(from_value::<Config>(v).unwrap(), config());
+}
+
+#[test]
+fn from_unpacked() {
+    let buf = msgpack_serde::pack(&config()).unwrap();
+    assert_eq!(from_value::<Config>(unpacked(&buf)).unwrap(), config());
+}
+
+#[test]
+fn from_other_reprs() {
+    for (enums, structs) in [
+        (EnumRepr::Index, StructRepr::Map),
+        (EnumRepr::Array, StructRepr::Array),
+    ] {
+        let mut ser = Serializer::new(vec![])
+            .with_enum_repr(enums)
+            .with_struct_repr(structs);
+        config().serialize(&mut ser).unwrap();
+        let v = unpacked(&ser.into_inner());
+        assert_eq!(from_value::<Config>(v).unwrap(), config());
+    }
+}
+
+#[test]
+fn values() {
+    assert_eq!(
+        to_value(&Output::Stdout).unwrap(),
+        Value::Map(vec![(s("Stdout"), Value::Nil)])
+    );
+    assert_eq!(to_value(&Unit).unwrap(), Value::Array(vec![]));
+    assert_eq!(to_value(&None::<u8>).unwrap(), Value::Nil);
+    assert_eq!(to_value(&'c').unwrap(), s("c"));
+    assert_eq!(
+        to_value(&Value::Timestamp(-1, 0)).unwrap(),
+        Value::Timestamp(-1, 0)
+    );
+
+    // a bare variant name reads as a unit variant, and nil as a unit struct
+    assert_eq!(from_value::<Output>(s("Stdout")).unwrap(), Output::Stdout);
+    assert_eq!(from_value::<Unit>(Value::Nil).unwrap(), Unit);
+    assert_eq!(from_value::<Option<u8>>(Value::Nil).unwrap(), None);
+    assert_eq!(
+        from_value::<Value>(Value::Extension(5, vec![1; 3])).unwrap(),
+        Value::Extension(5, vec![1; 3])
+    );
+}
+
+#[test]
+fn errors() {
+    let e = from_value::<Config>(Value::from(1u8)).unwrap_err();
+    assert!(matches!(e, SerdeError::Custom(_)));
+    assert!(from_value::<u8>(Value::from(300u16)).is_err());
+    assert!(from_value::<(u8, u8)>(Value::Array(vec![Value::from(1u8); 3])).is_err());
+    assert!(from_value::<Output>(Value::Map(vec![])).is_err());
+}
+
+#[test]
+fn from_ref() {
+    let v = Value::Map(vec![
+        (s("name"), s("plugin")),
+        (s("key"), Value::Binary(vec![1, 2])),
+    ]);
+    let mut buf = vec![];
+    msgpack_value::pack_value(&mut buf, v).unwrap();
+
+    let r = unpack_value_ref(&mut &buf[..]).unwrap();
+    let b: Borrowed = from_ref_value(r).unwrap();
+    assert_eq!(b.name, "plugin");
+    assert_eq!(b.key, &[1, 2]);
+    assert!(buf.as_ptr_range().contains(&b.name.as_ptr()));
+}
+
+#[test]
+fn from_ref_ext() {
+    let buf = msgpack_serde::pack(&config()).unwrap();
+    let r = unpack_value_ref(&mut &buf[..]).unwrap();
+    assert_eq!(from_ref_value::<Config>(r).unwrap(), config());
+    let r = unpack_value_ref(&mut &buf[..]).unwrap();
+    assert_eq!(from_ref_value::<Value>(r).unwrap(), unpacked(&buf));
+}
+
+#[test]
+fn exts() {
+    let v = Stamped {
+        ts: msgpack_serde::Timestamp::new(-1, 2),
+        ext: Value::Extension(5, vec![1, 2, 3]),
+    };
+    let value = to_value(&v).unwrap();
+    match value {
+        Value::Map(ref entries) => assert_eq!(entries[0].1, Value::Timestamp(-1, 2)),
+        ref v => panic!("unexpected {:?}", v),
+    }
+    assert_eq!(from_value::<Stamped>(value).unwrap(), v);
+
+    let ext = msgpack_serde::ext::Ext::ext8(3, 7, &[1, 2, 3]);
+    let back: msgpack_serde::ext::Ext = from_value(to_value(&ext).unwrap()).unwrap();
+    assert_eq!((back.typ(), back.data()), (7, &[1, 2, 3][..]));
+
+    // other newtypes around an ext get the ext itself
+    let w = Wrapped(Value::Extension(5, vec![1, 2, 3]));
+    assert_eq!(from_value::<Wrapped>(to_value(&w).unwrap()).unwrap(), w);
+    let w = Wrapped(Value::Timestamp(1, 0));
+    assert_eq!(from_value::<Wrapped>(Value::Timestamp(1, 0)).unwrap(), w);
+}
+
+#[test]
+fn error_offsets_agree() {
+    // each error is reported at the start of the innermost value that failed
+    for (buf, offset) in [
+        (&[0xc1][..], 0),
+        (&[0x91, 0xc1], 1),
+        (&[0x92, 0x01, 0xa3, 0x61], 2),
+        (&[0x82, 0xa1, 0x61, 0x01, 0xa1, 0x62, 0xd9, 0x05, 0x61], 6),
+        (&[0x81, 0xa1, 0x61, 0x91, 0x92, 0xc0, 0xc1], 6),
+    ] {
+        let err = unpack_value(&mut &buf[..]).unwrap_err();
+        assert_eq!(err.offset(), Some(offset), "{:x?}", buf);
+        let err = unpack_value_ref(&mut &buf[..]).unwrap_err();
+        assert_eq!(err.offset(), Some(offset), "{:x?}", buf);
+        let err = msgpack_serde::from_slice::<Value>(buf).unwrap_err();
+        assert_eq!(err.offset(), Some(offset), "{:x?}", buf);
+    }
+}
@@ -0,0 +1,108 @@
+use msgpack::{DecodeLimits, UnpackError};
+use msgpack_value::{unpack_value_ref_with_limits, unpack_value_with_limits, Value};
+use std::io;
+
+const STRICT: DecodeLimits = DecodeLimits {
+    max_depth: 128,
+    max_container_len: usize::MAX,
+    max_data_len: usize::MAX,
+    max_alloc: usize::MAX,
+    strict: true,
+};
+
+fn strict_err(buf: &[u8]) -> UnpackError {
+    let err = unpack_value_with_limits(&mut io::Cursor::new(buf), STRICT).unwrap_err();
+    let ref_err = unpack_value_ref_with_limits(&mut &buf[..], STRICT).unwrap_err();
+    assert_eq!(err.to_string(), ref_err.to_string());
+
+    // nothing here is rejected without strict mode, other than bad timestamps
+    if !matches!(err.root(), UnpackError::InvalidExtTimestamp(_)) {
+        unpack_value_with_limits(&mut io::Cursor::new(buf), DecodeLimits::default()).unwrap();
+        unpack_value_ref_with_limits(&mut &buf[..], DecodeLimits::default()).unwrap();
+    }
+    err
+}
+
+#[test]
+fn non_minimal() {
+    assert!(matches!(
+        strict_err(&[0xcd, 0x00, 0x01]).root(),
+        UnpackError::NotMinimal(_)
+    ));
+    assert!(matches!(
+        strict_err(&[0xd2, 0xff, 0xff, 0xff, 0xff]).root(),
+        UnpackError::NotMinimal(_)
+    ));
+    assert!(matches!(
+        strict_err(&[0xda, 0x00, 0x01, 0x61]).root(),
+        UnpackError::NotMinimal(_)
+    ));
+    assert!(matches!(
+        strict_err(&[0xdc, 0x00, 0x00]).root(),
+        UnpackError::NotMinimal(_)
+    ));
+
+    let err = strict_err(&[0x91, 0x81, 0xa1, 0x61, 0xcc, 0x01]);
+    assert!(matches!(err.root(), UnpackError::NotMinimal(_)));
+    assert_eq!(err.path().unwrap(), "$[0].a");
+}
+
+#[test]
+fn duplicate_keys() {
+    // {"a": 1, "b": 2, "a": 3}
+    let buf = [0x83, 0xa1, 0x61, 0x01, 0xa1, 0x62, 0x02, 0xa1, 0x61, 0x03];
+    let err = strict_err(&buf);
+    assert!(matches!(err.root(), UnpackError::DuplicateMapKey));
+    assert_eq!(err.path().unwrap(), "$.a");
+
+    // keys are compared within one map only
+    let buf = [0x92, 0x81, 0x01, 0xc0, 0x81, 0x01, 0xc0];
+    unpack_value_with_limits(&mut io::Cursor::new(&buf[..]), STRICT).unwrap();
+
+    // container keys
+    let buf = [0x82, 0x91, 0x01, 0xc0, 0x91, 0x01, 0xc0];
+    assert!(matches!(
+        strict_err(&buf).root(),
+        UnpackError::DuplicateMapKey
+    ));
+}
+
+#[test]
+fn invalid_utf8_and_timestamps() {
+    assert!(matches!(
+        strict_err(&[0xa2, 0xc3, 0x28]).root(),
+        UnpackError::InvalidUtf8(_)
+    ));
+
+    // timestamp64 with nsec = 1e9
+    let payload = (1_000_000_000_u64 << 34) | 1;
+    let mut buf = vec![0xd7, 0xff];
+    buf.extend_from_slice(&payload.to_be_bytes());
+    assert!(matches!(
+        strict_err(&buf).root(),
+        UnpackError::InvalidExtTimestamp(_)
+    ));
+
+    assert!(matches!(
+        unpack_value_with_limits(&mut io::Cursor::new(&[0xc1][..]), STRICT)
+            .unwrap_err()
+            .root(),
+        UnpackError::ReservedCode(0xc1)
+    ));
+}
+
+#[test]
+fn strict_accepts_minimal_input() {
+    let v = Value::Map(vec![
+        (Value::from("a".to_owned()), Value::from(300_u16)),
+        (Value::from("b".to_owned()), Value::from(-200_i16)),
+        (Value::from(1_u8), Value::Array(vec![Value::Nil; 20])),
+    ]);
+    let mut buf = vec![];
+    msgpack_value::pack_value(&mut buf, v.clone()).unwrap();
+    assert_eq!(
+        unpack_value_with_limits(&mut io::Cursor::new(&buf[..]), STRICT).unwrap(),
+        v
+    );
+    unpack_value_ref_with_limits(&mut &buf[..], STRICT).unwrap();
+}
@@ -1,6 +1,9 @@
+#![allow(clippy::unnecessary_cast)]
+
 use std::io;
 
-use msgpack_value::Value;
+use msgpack::{DecodeLimits, Limit, UnpackError};
+use msgpack_value::{unpack_value_ref_with_limits, unpack_value_with_limits, Value};
 
 #[test]
 fn unpack_value() {
@@ -54,3 +57,158 @@ fn unpack_invalid_str_value() {
         panic!("must be string");
     };
 }
+
+#[test]
+fn unpack_error_location() {
+    // {"items": [1, 2, 3, {"name": 0xc1}]}
+    let buf = [
+        0x81, 0xa5, 0x69, 0x74, 0x65, 0x6d, 0x73, 0x94, 0x01, 0x02, 0x03, 0x81, 0xa4, 0x6e, 0x61,
+        0x6d, 0x65, 0xc1,
+    ];
+
+    let err = msgpack_value::unpack_value(&mut io::Cursor::new(&buf[..])).unwrap_err();
+    assert!(matches!(err.root(), UnpackError::ReservedCode(0xc1)));
+    assert_eq!(err.path().unwrap(), "$.items[3].name");
+    assert_eq!(err.offset(), Some(17));
+
+    let err = msgpack_value::unpack_value_ref(&mut &buf[..]).unwrap_err();
+    assert!(matches!(err.root(), UnpackError::ReservedCode(0xc1)));
+    assert_eq!(err.path().unwrap(), "$.items[3].name");
+    assert_eq!(err.offset(), Some(17));
+
+    // truncated inside the second element
+    let buf = [0x92, 0x01, 0xa3, 0x61];
+    let err = msgpack_value::unpack_value(&mut io::Cursor::new(&buf[..])).unwrap_err();
+    assert!(matches!(err.root(), UnpackError::UnexpectedEof));
+    assert_eq!(err.path().unwrap(), "$[1]");
+    assert_eq!(err.offset(), Some(2));
+}
+
+#[test]
+fn unpack_hostile_headers() {
+    // array32, map32, str32, bin32 and ext32 headers claiming 4 GiB with no body
+    for buf in [
+        &[0xdd, 0xff, 0xff, 0xff, 0xff][..],
+        &[0xdf, 0xff, 0xff, 0xff, 0xff],
+        &[0xdb, 0xff, 0xff, 0xff, 0xff],
+        &[0xc6, 0xff, 0xff, 0xff, 0xff],
+        &[0xc9, 0xff, 0xff, 0xff, 0xff, 0x01],
+    ] {
+        let err = msgpack_value::unpack_value(&mut io::Cursor::new(buf)).unwrap_err();
+        assert!(matches!(err.root(), UnpackError::UnexpectedEof));
+        let err = msgpack_value::unpack_value_ref(&mut &buf[..]).unwrap_err();
+        assert!(matches!(err.root(), UnpackError::UnexpectedEof));
+    }
+}
+
+#[test]
+fn unpack_depth_limit() {
+    let limits = DecodeLimits {
+        max_depth: 3,
+        ..DecodeLimits::default()
+    };
+
+    // [[[]]] is at the limit, [[[[]]]] is one over
+    let buf = [0x91, 0x91, 0x90];
+    let v = unpack_value_with_limits(&mut io::Cursor::new(&buf[..]), limits).unwrap();
+    assert_eq!(
+        v,
+        Value::Array(vec![Value::Array(vec![Value::Array(vec![])])])
+    );
+    unpack_value_ref_with_limits(&mut &buf[..], limits).unwrap();
+
+    let buf = [0x91, 0x91, 0x91, 0x90];
+    let err = unpack_value_with_limits(&mut io::Cursor::new(&buf[..]), limits).unwrap_err();
+    assert!(matches!(err.root(), UnpackError::DepthExceeded(3)));
+    assert_eq!(err.path().unwrap(), "$[0][0][0]");
+    let err = unpack_value_ref_with_limits(&mut &buf[..], limits).unwrap_err();
+    assert!(matches!(err.root(), UnpackError::DepthExceeded(3)));
+
+    // deeply nested input fails cleanly with the default limits
+    let buf = vec![0x91; 100_000];
+    let err = msgpack_value::unpack_value(&mut io::Cursor::new(&buf[..])).unwrap_err();
+    assert!(matches!(err.root(), UnpackError::DepthExceeded(128)));
+}
+
+#[test]
+fn unpack_length_limits() {
+    let limits = DecodeLimits {
+        max_container_len: 2,
+        max_data_len: 3,
+        ..DecodeLimits::default()
+    };
+
+    let buf = [0x93, 0x01, 0x02, 0x03];
+    let err = unpack_value_with_limits(&mut io::Cursor::new(&buf[..]), limits).unwrap_err();
+    assert!(matches!(
+        err.root(),
+        UnpackError::LimitExceeded(Limit::ContainerLength, 3)
+    ));
+
+    let buf = [0x81, 0x01, 0xa4, 0x61, 0x62, 0x63, 0x64];
+    let err = unpack_value_with_limits(&mut io::Cursor::new(&buf[..]), limits).unwrap_err();
+    assert!(matches!(
+        err.root(),
+        UnpackError::LimitExceeded(Limit::DataLength, 4)
+    ));
+    assert_eq!(err.path().unwrap(), "$.1");
+    let err = unpack_value_ref_with_limits(&mut &buf[..], limits).unwrap_err();
+    assert!(matches!(
+        err.root(),
+        UnpackError::LimitExceeded(Limit::DataLength, 4)
+    ));
+
+    // ext bodies count as data too
+    let buf = [0xd6, 0x01, 0x00, 0x00, 0x00, 0x00];
+    let err = unpack_value_with_limits(&mut io::Cursor::new(&buf[..]), limits).unwrap_err();
+    assert!(matches!(
+        err.root(),
+        UnpackError::LimitExceeded(Limit::DataLength, 4)
+    ));
+}
+
+#[test]
+fn unpack_alloc_limit() {
+    // the array storage plus two 3-byte strings
+    let limits = DecodeLimits {
+        max_alloc: 6 + 2 * std::mem::size_of::<Value>(),
+        ..DecodeLimits::unlimited()
+    };
+    let buf = [0x92, 0xa3, 0x61, 0x62, 0x63, 0xa3, 0x64, 0x65, 0x66];
+    unpack_value_with_limits(&mut io::Cursor::new(&buf[..]), limits).unwrap();
+
+    let limits = DecodeLimits {
+        max_alloc: 5 + 2 * std::mem::size_of::<Value>(),
+        ..DecodeLimits::unlimited()
+    };
+    let err = unpack_value_with_limits(&mut io::Cursor::new(&buf[..]), limits).unwrap_err();
+    assert!(matches!(
+        err.root(),
+        UnpackError::LimitExceeded(Limit::Allocation, _)
+    ));
+
+    // borrowed bodies aren't allocated
+    unpack_value_ref_with_limits(&mut &buf[..], limits).unwrap();
+}
+
+#[test]
+fn unpack_timestamp_value() {
+    let buf = [0xd7, 0xff, 0x07, 0xff, 0xff, 0xfc, 0x5d, 0x87, 0x3d, 0x44];
+    assert_eq!(
+        msgpack_value::unpack_value(&mut io::Cursor::new(&buf[..])).unwrap(),
+        Value::Timestamp(1569144132, 33554431)
+    );
+    assert_eq!(
+        msgpack_value::unpack_value_ref(&mut &buf[..]).unwrap(),
+        msgpack_value::RefValue::Timestamp(1569144132, 33554431)
+    );
+
+    // nsec of 1e9 in a timestamp96
+    let buf = [
+        0xc7, 0x0c, 0xff, 0x3b, 0x9a, 0xca, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+    let err = msgpack_value::unpack_value(&mut io::Cursor::new(&buf[..])).unwrap_err();
+    assert!(matches!(err.root(), UnpackError::InvalidExtTimestamp(_)));
+    let err = msgpack_value::unpack_value_ref(&mut &buf[..]).unwrap_err();
+    assert!(matches!(err.root(), UnpackError::InvalidExtTimestamp(_)));
+}
@@ -0,0 +1,24 @@
+// shared by the conformance tests of each crate, which include it with #[path]
+use serde_json::Value as Json;
+
+const SUITE: &str = include_str!("msgpack-test-suite.json");
+
+pub fn from_hex(s: &str) -> Vec<u8> {
+    s.split('-')
+        .filter(|v| !v.is_empty())
+        .map(|v| u8::from_str_radix(v, 16).unwrap())
+        .collect()
+}
+
+// calls `check` with each encoding of each case, and "group: hex" to name it in assert messages
+pub fn for_each_case(mut check: impl FnMut(&str, &Json, &[u8])) {
+    let suite: serde_json::Map<String, Json> = serde_json::from_str(SUITE).unwrap();
+    for (name, cases) in suite {
+        for case in cases.as_array().unwrap() {
+            for hex in case["msgpack"].as_array().unwrap() {
+                let hex = hex.as_str().unwrap();
+                check(&format!("{}: {}", name, hex), case, &from_hex(hex));
+            }
+        }
+    }
+}